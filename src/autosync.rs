@@ -1,11 +1,89 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
 use crate::cli::Command;
 use crate::cli::args::BrewSubcmd;
-use crate::config::core::Config;
-use crate::config::remote::RemoteConfigManager;
+use crate::config::core::{Config, SyncMode};
+use crate::config::path::get_config_path;
+use crate::config::remote::{RemoteLayers, keep_local_threshold};
+use crate::util::notify::{NotifyEvent, notify_event};
 use crate::{log_err, log_info, log_warn};
 
+/// Parses an interval such as "90s", "30m", "6h" or "1d".
+pub fn parse_interval(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (num, unit) = text.split_at(split);
+
+    let num: u64 = num
+        .parse()
+        .with_context(|| format!("Invalid interval: {text}"))?;
+    let unit_secs = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => bail!("Invalid interval unit in {text}; use s, m, h or d."),
+    };
+    let secs = num
+        .checked_mul(unit_secs)
+        .with_context(|| format!("Interval too long: {text}"))?;
+
+    Ok(Duration::from_secs(secs))
+}
+
+/// Returns the path to the file holding the last autosync timestamp.
+pub fn get_last_sync_path(config_path: &Path) -> Result<PathBuf> {
+    let parent = config_path
+        .parent()
+        .context("Could not determine config parent directory")?;
+
+    Ok(parent.join("last_sync"))
+}
+
+/// Helper for: try_auto_sync()
+/// Checks if the last autosync happened less than `every` ago.
+async fn synced_recently(every: Duration) -> bool {
+    let Ok(config_path) = get_config_path().await else {
+        return false;
+    };
+    let Ok(path) = get_last_sync_path(&config_path) else {
+        return false;
+    };
+    let Ok(content) = fs::read_to_string(path).await else {
+        return false;
+    };
+    let Ok(last) = content.trim().parse::<u64>() else {
+        return false;
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    now.saturating_sub(last) < every.as_secs()
+}
+
+/// Helper for: try_auto_sync()
+/// Records the current time as the last autosync.
+async fn record_sync() -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    fs::write(
+        get_last_sync_path(&get_config_path().await?)?,
+        now.to_string(),
+    )
+    .await?;
+
+    Ok(())
+}
+
 /// Perform remote config auto-sync if enabled in [remote] and internet is available.
 /// This should be called early in main().
 pub async fn try_auto_sync(command: &crate::cli::Command, local_config: &mut Config) {
@@ -18,34 +96,129 @@ pub async fn try_auto_sync(command: &crate::cli::Command, local_config: &mut Con
         | Command::CheckUpdate(_)
         | Command::Cookbook(_)
         | Command::Completion(_)
+        | Command::Env(_)
+        | Command::ExitCodes(_)
+        | Command::Prompt(_)
+        | Command::History(_)
         | Command::Reset(_)
+        | Command::Uninstall(_)
         | Command::Init(_)
-        | Command::Config { .. } => {
+        | Command::Bootstrap(_)
+        | Command::Import(_)
+        | Command::Export(_)
+        | Command::Config { .. }
+        | Command::Backup { .. }
+        | Command::External(_) => {
+            return;
+        }
+        // prompts need this to be fast
+        Command::Status(cmd) if cmd.cached => {
             return;
         }
         _ => {}
     }
 
-    if local_config.load(true).await.is_err() {
+    // a config locked by its remote still syncs, so that the remote can lift its lock
+    if local_config.load(false).await.is_err() {
         return;
     };
 
     // start
-    let remote = local_config.remote.clone().unwrap_or_default();
-    let remote_mgr = RemoteConfigManager::new(remote.url);
-
-    if remote.autosync.unwrap_or_default() {
-        match remote_mgr.fetch().await {
-            Ok(()) => {
-                if let Err(e) = remote_mgr.save().await {
-                    log_err!("Failed to save remote config after auto-sync: {e}");
+    let Some(remotes) = local_config.remote.clone() else {
+        log_info!("Skipping auto-sync since disabled in config.",);
+        return;
+    };
+    // autosync settings are read from the first remote
+    let remote = remotes.policy().cloned().unwrap_or_default();
+
+    if !remote.autosync.unwrap_or_default() {
+        log_info!("Skipping auto-sync since disabled in config.",);
+        return;
+    }
+
+    // only sync for the configured subcommands, if any
+    if let Some(commands) = &remote.commands
+        && !commands.iter().any(|c| c.trim() == command.name())
+    {
+        log_info!("Skipping auto-sync for `{}`.", command.name());
+        return;
+    }
+
+    // respect the minimum interval between syncs
+    if let Some(every) = &remote.every {
+        match parse_interval(every) {
+            Ok(every) => {
+                if synced_recently(every).await {
+                    log_info!("Skipping auto-sync since last sync was recent.",);
+                    return;
                 }
             }
-            Err(e) => {
-                log_warn!("Remote config auto-sync failed: {e}",);
+            Err(e) => log_warn!("Ignoring `every` in [remote]: {e}"),
+        }
+    }
+
+    let layers = RemoteLayers::new(&remotes);
+
+    if let Err(e) = layers.fetch().await {
+        log_warn!("Remote config auto-sync failed: {e}",);
+        return;
+    }
+
+    match remote.mode.unwrap_or_default() {
+        SyncMode::FetchOnly => {
+            let local = fs::read_to_string(&local_config.path)
+                .await
+                .unwrap_or_default();
+
+            let merged = layers
+                .merged(&remotes)
+                .and_then(|remote| keep_local_threshold(&remote, &local));
+            if merged.is_ok_and(|remote| remote != local) {
+                log_warn!("Remote config differs from local. Run `cutler fetch` to sync.",);
+                notify_event(
+                    local_config,
+                    NotifyEvent::Drift,
+                    "Remote config differs from local. Run `cutler fetch` to sync.",
+                )
+                .await;
             }
         }
-    } else {
-        log_info!("Skipping auto-sync since disabled in config.",);
+        SyncMode::Apply => {
+            // a remote lock is enforced even if the remote didn't change, e.g. after a local
+            // `cutler unlock`
+            let local = fs::read_to_string(&local_config.path)
+                .await
+                .unwrap_or_default();
+            let merged = layers
+                .merged(&remotes)
+                .and_then(|remote| keep_local_threshold(&remote, &local));
+            let remote_locks = merged
+                .as_ref()
+                .is_ok_and(|remote| RemoteLayers::locks(remote));
+            let relock = merged
+                .as_ref()
+                .is_ok_and(|remote| *remote != local && remote_locks && !local_config.is_locked());
+
+            // a lock made locally keeps the config as it is, unless the remote sets one itself
+            if local_config.is_locked() && !local_config.is_remote_locked() && !remote_locks {
+                log_info!("Skipping auto-sync since the config is locked.");
+            } else if layers.is_unchanged() && !relock {
+                log_info!("Remote config unchanged, nothing to sync.");
+            } else if let Err(e) = layers.save(&remotes).await {
+                log_err!("Failed to save remote config after auto-sync: {e}");
+                return;
+            } else {
+                notify_event(
+                    local_config,
+                    NotifyEvent::Sync,
+                    "Fetched and applied the remote config.",
+                )
+                .await;
+            }
+        }
+    }
+
+    if let Err(e) = record_sync().await {
+        log_warn!("Could not record auto-sync time: {e}");
     }
 }
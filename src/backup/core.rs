@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Read,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the manifest inside a backup bundle.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Names of the files a backup bundle may contain.
+pub const CONFIG_FILE: &str = "config.toml";
+pub const SNAPSHOT_FILE: &str = "snapshot.json";
+pub const BREW_FILE: &str = "brew.json";
+pub const MAS_FILE: &str = "mas.json";
+
+/// Directory inside a backup bundle holding the originals of managed plist files.
+pub const BACKUPS_DIR: &str = "backups";
+
+/// Returns the name of a plist backup inside the bundle.
+pub fn backup_entry(file_name: &str) -> String {
+    format!("{BACKUPS_DIR}/{file_name}")
+}
+
+/// Returns the file name of a plist backup entry, or None if the entry isn't one.
+/// Entries which would escape the backups directory aren't either.
+pub fn backup_file_name(entry: &str) -> Option<&str> {
+    entry
+        .strip_prefix(BACKUPS_DIR)?
+        .strip_prefix('/')
+        .filter(|name| !name.is_empty() && !name.contains('/') && *name != "." && *name != "..")
+}
+
+/// Metadata stored alongside the files of a backup bundle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BundleManifest {
+    /// The cutler version which created the bundle.
+    pub version: String,
+    /// Creation time, in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Names of the files inside the bundle.
+    pub files: Vec<String>,
+}
+
+/// Installed Homebrew software, recorded as full names.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BrewState {
+    pub formulae: Vec<String>,
+    pub casks: Vec<String>,
+    pub taps: Vec<String>,
+}
+
+/// A `.tar.zst` archive of the whole cutler state: config, snapshot along with its plist backups,
+/// and installed software.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub manifest: BundleManifest,
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bundle {
+    pub fn new() -> Self {
+        Self {
+            manifest: BundleManifest {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                created_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                files: Vec::new(),
+            },
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Adds (or replaces) a file in the bundle.
+    pub fn add(&mut self, name: &str, content: Vec<u8>) {
+        if !self.files.contains_key(name) {
+            self.manifest.files.push(name.to_string());
+        }
+        self.files.insert(name.to_string(), content);
+    }
+
+    /// Returns the content of a file in the bundle.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.files.get(name).map(Vec::as_slice)
+    }
+
+    /// Writes the bundle as a zstd-compressed tarball.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+        let encoder = zstd::Encoder::new(file, 0)?.auto_finish();
+        let mut tar = tar::Builder::new(encoder);
+
+        let manifest = serde_json::to_vec_pretty(&self.manifest)?;
+        let entries = std::iter::once((MANIFEST_FILE, manifest.as_slice()))
+            .chain(self.files.iter().map(|(k, v)| (k.as_str(), v.as_slice())));
+
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o600);
+            header.set_mtime(self.manifest.created_at);
+            header.set_cksum();
+            tar.append_data(&mut header, name, content)?;
+        }
+
+        tar.into_inner()?;
+        Ok(())
+    }
+
+    /// Reads a bundle written by `write()`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+        let decoder = zstd::Decoder::new(file)?;
+        let mut tar = tar::Archive::new(decoder);
+
+        let mut manifest = None;
+        let mut files = BTreeMap::new();
+
+        for entry in tar.entries().context("Not a cutler backup bundle")? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+
+            if name == MANIFEST_FILE {
+                manifest = Some(
+                    serde_json::from_slice::<BundleManifest>(&content)
+                        .context("Invalid backup manifest")?,
+                );
+            } else {
+                files.insert(name, content);
+            }
+        }
+
+        let Some(manifest) = manifest else {
+            bail!(
+                "{} is not a cutler backup bundle (no {MANIFEST_FILE}).",
+                path.display()
+            );
+        };
+
+        Ok(Self { manifest, files })
+    }
+}
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Bootstrapping a new Mac in one go.
+//!
+//! `cutler bootstrap` runs every step from fetching the config to the flagged commands in a
+//! fixed order. Finished steps are recorded in a checkpoint next to the config, so that after a
+//! failure, running it again resumes where it stopped.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// A step of the bootstrap, in the order they run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+    /// Fetch the remote config.
+    Fetch,
+    /// Install Xcode Command Line Tools and Homebrew.
+    Homebrew,
+    /// Install everything from [brew].
+    Brew,
+    /// Install everything from [mas].
+    Mas,
+    /// Apply preferences and run regular external commands.
+    Apply,
+    /// Run flagged external commands.
+    Commands,
+}
+
+impl Step {
+    /// Every step, in order.
+    pub const ALL: [Step; 6] = [
+        Step::Fetch,
+        Step::Homebrew,
+        Step::Brew,
+        Step::Mas,
+        Step::Apply,
+        Step::Commands,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Step::Fetch => "fetch",
+            Step::Homebrew => "homebrew",
+            Step::Brew => "brew",
+            Step::Mas => "mas",
+            Step::Apply => "apply",
+            Step::Commands => "commands",
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The steps a bootstrap finished so far.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Checkpoint {
+    pub done: Vec<Step>,
+}
+
+/// Returns the path to the bootstrap checkpoint, next to the config.
+pub fn get_checkpoint_path(config_path: &Path) -> Result<PathBuf> {
+    let parent = config_path
+        .parent()
+        .context("Could not determine config parent directory")?;
+
+    Ok(parent.join("bootstrap.json"))
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint of an unfinished bootstrap, if any.
+    pub async fn load(config_path: &Path) -> Option<Self> {
+        let path = get_checkpoint_path(config_path).ok()?;
+        let content = fs::read_to_string(path).await.ok()?;
+
+        serde_json::from_str(&content).ok()
+    }
+
+    pub async fn save(&self, config_path: &Path) -> Result<()> {
+        let path = get_checkpoint_path(config_path)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(config_path: &Path) -> Result<()> {
+        let path = get_checkpoint_path(config_path)?;
+
+        if fs::try_exists(&path).await? {
+            fs::remove_file(path).await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_done(&self, step: Step) -> bool {
+        self.done.contains(&step)
+    }
+
+    pub fn mark_done(&mut self, step: Step) {
+        if !self.is_done(step) {
+            self.done.push(step);
+        }
+    }
+
+    /// The first step which hasn't finished yet.
+    pub fn next(&self) -> Option<Step> {
+        Step::ALL.into_iter().find(|s| !self.is_done(*s))
+    }
+}
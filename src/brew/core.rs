@@ -1,41 +1,74 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::brew::types::{BrewDiff, BrewListType};
+use crate::brew::types::{BrewDiff, BrewInfo, BrewListType, FormulaInfo, TapDrift};
 use crate::brew::xcode::ensure_xcode_clt;
 use crate::cli::atomic::should_dry_run;
-use crate::config::core::Brew;
+use crate::config::core::{Brew, Formula, Tap};
 use crate::util::io::confirm;
+use crate::util::net::ensure_online;
 use crate::{log_dry, log_info, log_warn};
-use anyhow::{Result, bail};
-use std::{env, path::Path};
+use anyhow::{Context, Result, bail};
+use std::collections::{HashMap, HashSet};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 use tokio::process::Command;
 use tokio::{fs, try_join};
 
-/// Sets the required environment variables for cutler to interact with Homebrew.
-async fn set_homebrew_env_vars() {
-    let existing_path = std::env::var("PATH").unwrap_or_default();
+/// Where Homebrew lives on Apple Silicon, the prefix cutler puts into $PATH.
+pub const HOMEBREW_PREFIX: &str = "/opt/homebrew";
+
+/// Returns the environment cutler runs Homebrew with: its directories in front of $PATH, if
+/// it's installed and they're missing, and variables keeping `brew` from updating or nagging.
+pub fn homebrew_env(path: &str, installed: bool) -> Vec<(String, String)> {
+    let mut env = Vec::new();
+
+    if installed {
+        let mut new_path = path.to_string();
+        for dir in ["bin", "sbin"] {
+            let dir = format!("{HOMEBREW_PREFIX}/{dir}");
+            if !path.split(':').any(|p| p == dir) {
+                new_path = if new_path.is_empty() {
+                    dir
+                } else {
+                    format!("{dir}:{new_path}")
+                };
+            }
+        }
+        if new_path != path {
+            env.push(("PATH".to_string(), new_path));
+        }
+    }
+
+    for var in [
+        "HOMEBREW_NO_AUTO_UPDATE",
+        "HOMEBREW_NO_ANALYTICS",
+        "HOMEBREW_NO_ENV_HINTS",
+    ] {
+        env.push((var.to_string(), "1".to_string()));
+    }
 
-    if fs::try_exists(Path::new("/opt/homebrew/bin/brew"))
+    env
+}
+
+/// Whether Homebrew is installed under `HOMEBREW_PREFIX`.
+pub async fn is_in_prefix() -> bool {
+    fs::try_exists(Path::new(HOMEBREW_PREFIX).join("bin/brew"))
         .await
         .unwrap_or_default()
-    {
-        let bin = "/opt/homebrew/bin";
-        let sbin = "/opt/homebrew/sbin";
-        let mut new_path = existing_path.clone();
-        if !existing_path.split(':').any(|p| p == bin) {
-            new_path = format!("{bin}:{new_path}");
-        }
-        if !existing_path.split(':').any(|p| p == sbin) {
-            new_path = format!("{sbin}:{new_path}");
-        }
-        unsafe { env::set_var("PATH", &new_path) };
-    } else {
+}
+
+/// Sets the required environment variables for cutler to interact with Homebrew.
+async fn set_homebrew_env_vars() {
+    let installed = is_in_prefix().await;
+    if !installed {
         log_warn!("Brew binary not found in standard directories; $PATH not updated.");
     }
 
-    unsafe { env::set_var("HOMEBREW_NO_AUTO_UPDATE", "1") };
-    unsafe { env::set_var("HOMEBREW_NO_ANALYTICS", "1") };
-    unsafe { env::set_var("HOMEBREW_NO_ENV_HINTS", "1") };
+    for (name, value) in homebrew_env(&env::var("PATH").unwrap_or_default(), installed) {
+        unsafe { env::set_var(name, value) };
+    }
 
     log_info!("Homebrew environment has been configured for this process.");
 }
@@ -43,6 +76,8 @@ async fn set_homebrew_env_vars() {
 /// Helper for: ensure_brew()
 /// Installs Homebrew via the official script.
 async fn install_homebrew() -> Result<()> {
+    ensure_online("install Homebrew")?;
+
     let install_command =
         "curl -fsSL https://raw.githubusercontent.com/Homebrew/install/HEAD/install.sh | /bin/bash";
 
@@ -119,43 +154,187 @@ fn flatten_tap_prefix(lines: Vec<String>) -> Vec<String> {
         .collect()
 }
 
-/// Lists Homebrew things (formulae/casks/taps/deps) and separates them based on newline.
-/// Note that `flatten` will be ignored if `list_type` is `BrewListType::Tap`.
-pub async fn brew_list(list_type: BrewListType, flatten: bool) -> Result<Vec<String>> {
-    let args: Vec<String> = match list_type {
-        BrewListType::Tap => vec![list_type.to_string()],
-        _ => {
-            let lt_str = list_type.to_string();
-            vec![
-                "list".to_string(),
-                "--quiet".to_string(),
-                "--full-name".to_string(),
-                "-1".to_string(),
-                lt_str,
-            ]
-        }
-    };
-
-    let output = Command::new("brew").args(&args).output().await?;
-    log_info!("Running {list_type} list command...");
+/// Lists the taps currently added to Homebrew.
+async fn brew_taps() -> Result<Vec<String>> {
+    let output = Command::new("brew").arg("tap").output().await?;
+    log_info!("Running tap list command...");
 
     if !output.status.success() {
-        log_warn!("{list_type} listing failed, will return empty.");
+        log_warn!("tap listing failed, will return empty.");
         return Ok(vec![]);
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut lines: Vec<String> = stdout
+    Ok(stdout
         .lines()
         .map(|l| l.trim().to_string())
         .filter(|l| !l.is_empty())
-        .collect();
+        .collect())
+}
+
+/// Returns the directory of the git checkout behind an installed tap.
+pub async fn tap_dir(name: &str) -> Result<PathBuf> {
+    let output = Command::new("brew")
+        .args(["--repository", name])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        bail!("Could not find the repository of tap {name}");
+    }
+
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+/// Resolves a revision (commit, tag or branch) to a commit inside a tap's checkout.
+/// Returns None if the revision is unknown there, e.g. because it wasn't fetched yet.
+pub async fn rev_parse(dir: &Path, revision: &str) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{revision}^{{commit}}"))
+        .output()
+        .await
+        .ok()
+        .filter(|out| out.status.success())?;
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Helper for: tap_drift(), checkout_tap()
+/// Returns the remote-tracking ref of a revision naming a branch, e.g. `origin/main`, since the
+/// local branch of a tap isn't moved by fetching.
+async fn remote_branch(dir: &Path, revision: &str) -> Option<String> {
+    let branch = format!("origin/{revision}");
+    rev_parse(dir, &format!("refs/remotes/{branch}"))
+        .await
+        .map(|_| branch)
+}
+
+/// Resolves a pinned revision to a commit. Branches resolve to where they are on the remote.
+pub async fn resolve_revision(dir: &Path, revision: &str) -> Option<String> {
+    match remote_branch(dir, revision).await {
+        Some(branch) => rev_parse(dir, &branch).await,
+        None => rev_parse(dir, revision).await,
+    }
+}
+
+/// Returns the drift of an installed tap from the revision pinned in config, if any.
+pub async fn tap_drift(tap: &Tap) -> Option<TapDrift> {
+    let revision = tap.revision()?;
+    let dir = tap_dir(tap.name()).await.ok()?;
+    let current = rev_parse(&dir, "HEAD").await;
+
+    if current.is_some() && current == resolve_revision(&dir, revision).await {
+        return None;
+    }
+
+    Some(TapDrift {
+        name: tap.name().to_string(),
+        revision: revision.to_string(),
+        current,
+    })
+}
+
+/// Checks out the pinned revision of an installed tap, fetching it first if it's not known yet
+/// or a branch, which might have moved on the remote.
+pub async fn checkout_tap(name: &str, revision: &str) -> Result<()> {
+    let dir = tap_dir(name).await?;
+
+    if remote_branch(&dir, revision).await.is_some() || rev_parse(&dir, revision).await.is_none() {
+        log_info!("Fetching {name} for revision {revision}...");
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["fetch", "--quiet", "--tags", "origin"])
+            .status()
+            .await?;
+
+        if !status.success() {
+            bail!("Failed to fetch tap {name}")
+        }
+    }
+
+    let target = remote_branch(&dir, revision)
+        .await
+        .unwrap_or_else(|| revision.to_string());
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["checkout", "--quiet", "--detach", &target])
+        .status()
+        .await?;
+
+    if !status.success() {
+        bail!("Failed to check out {revision} in tap {name}")
+    }
+
+    Ok(())
+}
+
+/// Lists Homebrew things (formulae/casks/taps/deps/pins) by their full names.
+/// Everything except taps is read from a single `brew info --json=v2 --installed` call.
+/// Note that `flatten` will be ignored if `list_type` is `BrewListType::Tap`.
+pub async fn brew_list(list_type: BrewListType, flatten: bool) -> Result<Vec<String>> {
+    if list_type == BrewListType::Tap {
+        return brew_taps().await;
+    }
+
+    let mut names = brew_info().await?.list(list_type);
 
     if flatten {
-        lines = flatten_tap_prefix(lines);
+        names = flatten_tap_prefix(names);
+    }
+
+    Ok(names)
+}
+
+/// Finds the formula among the given ones which is named like a binary, e.g. `jq` for `jq` or
+/// `python@3.12` for `python`. Tap prefixes are ignored.
+pub fn formula_named<'a>(formulae: &'a [Formula], bin: &str) -> Option<&'a Formula> {
+    formulae.iter().find(|f| {
+        let short = f.name().rsplit('/').next().unwrap_or_default();
+        short == bin || short.split('@').next() == Some(bin)
+    })
+}
+
+/// Finds the configured formula which provides a binary, by its name or else by asking
+/// `brew which-formula` (from Homebrew's command-not-found tap) if it's available.
+pub async fn formula_providing(formulae: &[Formula], bin: &str) -> Option<String> {
+    if let Some(formula) = formula_named(formulae, bin) {
+        return Some(formula.name().to_string());
     }
 
-    Ok(lines)
+    let out = Command::new("brew")
+        .args(["which-formula", bin])
+        .output()
+        .await
+        .ok()
+        .filter(|out| out.status.success())?;
+    let provider = String::from_utf8_lossy(&out.stdout).trim().to_string();
+
+    formulae
+        .iter()
+        .find(|f| f.name().rsplit('/').next() == Some(provider.as_str()))
+        .map(|f| f.name().to_string())
+}
+
+/// Installs a single formula.
+pub async fn install_formula(name: &str) -> Result<()> {
+    log_info!("Installing: {name}");
+    let status = Command::new("brew")
+        .args(["install", "--formula", name])
+        .status()
+        .await?;
+
+    if !status.success() {
+        bail!("Failed to install {name}")
+    }
+
+    Ok(())
 }
 
 /// Compare the Brew config struct with the actual Homebrew state.
@@ -163,22 +342,24 @@ pub async fn brew_list(list_type: BrewListType, flatten: bool) -> Result<Vec<Str
 pub async fn diff_brew(brew_cfg: Brew) -> Result<BrewDiff> {
     let no_deps = brew_cfg.no_deps.unwrap_or(false);
 
+    let formulae = brew_cfg.formulae.clone().unwrap_or_default();
     let config_formulae: Vec<String> =
-        flatten_tap_prefix(brew_cfg.formulae.clone().unwrap_or_default());
+        flatten_tap_prefix(formulae.iter().map(|f| f.name().to_string()).collect());
     let config_casks: Vec<String> = flatten_tap_prefix(brew_cfg.casks.clone().unwrap_or_default());
-    let config_taps: Vec<String> = brew_cfg.taps.clone().unwrap_or_default();
+    let taps = brew_cfg.taps.clone().unwrap_or_default();
+    let config_taps: Vec<String> = taps.iter().map(|t| t.name().to_string()).collect();
+
+    // fetch installed state in parallel, everything but taps comes from a single `brew info`
+    let (info, installed_taps) = try_join!(brew_info(), brew_taps())?;
 
-    // fetch installed state in parallel
-    let (mut installed_formulae, installed_casks, installed_taps) = try_join!(
-        brew_list(BrewListType::Formula, true),
-        brew_list(BrewListType::Cask, true),
-        brew_list(BrewListType::Tap, false) // no need for flattening here
-    )?;
+    let mut installed_formulae = flatten_tap_prefix(info.list(BrewListType::Formula));
+    let installed_casks = flatten_tap_prefix(info.list(BrewListType::Cask));
+    let pinned_formulae = flatten_tap_prefix(info.list(BrewListType::Pinned));
 
     // omit installed as dependency
     if no_deps {
         log_info!("--no-deps used, proceeding with checks...");
-        let installed_as_deps = brew_list(BrewListType::Dependency, true).await?;
+        let installed_as_deps = flatten_tap_prefix(info.list(BrewListType::Dependency));
 
         installed_formulae = installed_formulae
             .iter()
@@ -221,6 +402,27 @@ pub async fn diff_brew(brew_cfg: Brew) -> Result<BrewDiff> {
         .cloned()
         .collect();
 
+    let unpinned_formulae: Vec<String> = formulae
+        .iter()
+        .filter(|f| f.is_pinned())
+        .map(|f| f.name().to_string())
+        .filter(|name| {
+            !flatten_tap_prefix(vec![name.clone()])
+                .iter()
+                .any(|n| pinned_formulae.contains(n))
+        })
+        .collect();
+
+    let mut drifted_taps = Vec::new();
+    for tap in taps
+        .iter()
+        .filter(|t| installed_taps.iter().any(|i| i == t.name()))
+    {
+        drifted_taps.extend(tap_drift(tap).await);
+    }
+
+    let versions = info.versions();
+
     Ok(BrewDiff {
         missing_formulae,
         extra_formulae,
@@ -228,5 +430,63 @@ pub async fn diff_brew(brew_cfg: Brew) -> Result<BrewDiff> {
         extra_casks,
         missing_taps,
         extra_taps,
+        unpinned_formulae,
+        drifted_taps,
+        versions,
     })
 }
+
+/// Reads information about all installed formulae using `brew info --json=v2 --installed`.
+pub async fn brew_info() -> Result<BrewInfo> {
+    let output = Command::new("brew")
+        .args(["info", "--json=v2", "--installed"])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        bail!(
+            "`brew info` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    serde_json::from_slice(&output.stdout).context("Failed to parse `brew info` output.")
+}
+
+/// Computes the formulae which are safe to remove: installed as dependencies, but not needed by
+/// any formula installed on request or declared in config (including their dependency closure).
+pub fn autoremovable(info: &BrewInfo, config_formulae: &[String]) -> Vec<String> {
+    // both short and full names point to the same formula
+    let by_name: HashMap<&str, &FormulaInfo> = info
+        .formulae
+        .iter()
+        .flat_map(|f| [(f.name.as_str(), f), (f.full_name.as_str(), f)])
+        .collect();
+
+    // roots: everything installed on request + everything in config
+    let mut stack: Vec<&str> = info
+        .formulae
+        .iter()
+        .filter(|f| !f.is_dependency())
+        .map(|f| f.full_name.as_str())
+        .chain(config_formulae.iter().map(|f| f.as_str()))
+        .collect();
+
+    // walk the dependency graph
+    let mut keep: HashSet<&str> = HashSet::new();
+    while let Some(name) = stack.pop() {
+        let Some(formula) = by_name.get(name) else {
+            continue;
+        };
+
+        if keep.insert(formula.full_name.as_str()) {
+            stack.extend(formula.dependencies.iter().map(|d| d.as_str()));
+        }
+    }
+
+    info.formulae
+        .iter()
+        .filter(|f| f.is_dependency() && !keep.contains(f.full_name.as_str()))
+        .map(|f| f.full_name.clone())
+        .collect()
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::fmt::Display;
 
 /// Represents the type of software to list in Homebrew.
@@ -13,6 +15,8 @@ pub enum BrewListType {
     Dependency,
     /// Lists taps.
     Tap,
+    /// Lists pinned formulae.
+    Pinned,
 }
 
 impl Display for BrewListType {
@@ -22,11 +26,36 @@ impl Display for BrewListType {
             BrewListType::Formula => "--formula",
             BrewListType::Dependency => "--installed-as-dependency",
             BrewListType::Tap => "tap",
+            BrewListType::Pinned => "--pinned",
         };
         write!(f, "{}", flag)
     }
 }
 
+/// A tap checked out at another commit than its `revision` in the config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapDrift {
+    pub name: String,
+    pub revision: String,
+    /// The commit checked out now, if it could be read.
+    pub current: Option<String>,
+}
+
+impl Display for TapDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.current {
+            Some(current) => write!(
+                f,
+                "{} (at {}, pinned to {})",
+                self.name,
+                &current[..current.len().min(7)],
+                self.revision
+            ),
+            None => write!(f, "{} (pinned to {})", self.name, self.revision),
+        }
+    }
+}
+
 /// Struct representing the diff between config and installed Homebrew state.
 #[derive(Debug, Default)]
 pub struct BrewDiff {
@@ -36,4 +65,117 @@ pub struct BrewDiff {
     pub extra_casks: Vec<String>,
     pub missing_taps: Vec<String>,
     pub extra_taps: Vec<String>,
+    /// Formulae marked with `pin = true` which aren't pinned yet.
+    pub unpinned_formulae: Vec<String>,
+    /// Installed taps with a `revision` which is not checked out.
+    pub drifted_taps: Vec<TapDrift>,
+    /// Installed versions of formulae and casks, keyed by both short and full names.
+    pub versions: HashMap<String, String>,
+}
+
+impl BrewDiff {
+    /// Formats a list of names with their installed versions (if known).
+    pub fn with_versions(&self, names: &[String]) -> String {
+        names
+            .iter()
+            .map(|n| match self.versions.get(n) {
+                Some(v) => format!("{n} ({v})"),
+                None => n.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Output of `brew info --json=v2 --installed`.
+#[derive(Deserialize, Debug, Default)]
+pub struct BrewInfo {
+    #[serde(default)]
+    pub formulae: Vec<FormulaInfo>,
+    #[serde(default)]
+    pub casks: Vec<CaskInfo>,
+}
+
+/// A single formula inside [BrewInfo].
+#[derive(Deserialize, Debug, Clone)]
+pub struct FormulaInfo {
+    pub name: String,
+    pub full_name: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub installed: Vec<InstalledInfo>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// A single cask inside [BrewInfo].
+#[derive(Deserialize, Debug, Clone)]
+pub struct CaskInfo {
+    pub token: String,
+    pub full_token: String,
+    pub installed: Option<String>,
+}
+
+/// A single installed keg of a formula.
+#[derive(Deserialize, Debug, Clone)]
+pub struct InstalledInfo {
+    pub version: String,
+    #[serde(default)]
+    pub installed_as_dependency: bool,
+    #[serde(default)]
+    pub installed_on_request: bool,
+}
+
+impl FormulaInfo {
+    /// Whether the formula was only installed to satisfy another formula.
+    pub fn is_dependency(&self) -> bool {
+        self.installed
+            .iter()
+            .all(|i| i.installed_as_dependency && !i.installed_on_request)
+    }
+}
+
+impl BrewInfo {
+    /// Lists the full names of installed software of a given type.
+    /// Taps aren't part of `brew info`, so `BrewListType::Tap` always returns an empty list.
+    pub fn list(&self, list_type: BrewListType) -> Vec<String> {
+        match list_type {
+            BrewListType::Formula => self.formulae.iter().map(|f| f.full_name.clone()).collect(),
+            BrewListType::Cask => self.casks.iter().map(|c| c.full_token.clone()).collect(),
+            BrewListType::Dependency => self
+                .formulae
+                .iter()
+                .filter(|f| f.is_dependency())
+                .map(|f| f.full_name.clone())
+                .collect(),
+            BrewListType::Pinned => self
+                .formulae
+                .iter()
+                .filter(|f| f.pinned)
+                .map(|f| f.full_name.clone())
+                .collect(),
+            BrewListType::Tap => vec![],
+        }
+    }
+
+    /// Maps the short and full names of installed software to their versions.
+    pub fn versions(&self) -> HashMap<String, String> {
+        let mut versions = HashMap::new();
+
+        for f in &self.formulae {
+            if let Some(keg) = f.installed.last() {
+                versions.insert(f.name.clone(), keg.version.clone());
+                versions.insert(f.full_name.clone(), keg.version.clone());
+            }
+        }
+        for c in &self.casks {
+            if let Some(version) = &c.installed {
+                versions.insert(c.token.clone(), version.clone());
+                versions.insert(c.full_token.clone(), version.clone());
+            }
+        }
+
+        versions
+    }
 }
@@ -5,7 +5,11 @@ use std::time::Duration;
 use anyhow::{Result, bail};
 use tokio::process::Command;
 
-use crate::{cli::atomic::should_dry_run, log_cute, log_dry, log_warn, util::io::confirm};
+use crate::{
+    cli::atomic::should_dry_run,
+    log_cute, log_dry, log_warn,
+    util::{io::confirm, net::ensure_online},
+};
 
 /// Checks if Xcode CLT is installed on the device.
 async fn check_installed() -> bool {
@@ -33,6 +37,7 @@ pub async fn ensure_xcode_clt() -> Result<()> {
     }
 
     log_warn!("Xcode CLT is not installed.");
+    ensure_online("install Xcode Command Line Tools")?;
 
     if confirm("Install Xcode Command Line Tools now?") {
         let status = Command::new("xcode-select")
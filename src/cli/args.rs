@@ -1,13 +1,29 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use clap::{Parser, Subcommand};
+use std::env;
+use std::path::PathBuf;
+
+use crate::util::logging::{ColorMode, LogStyle};
 
 use crate::commands::{
-    ApplyCmd, BrewBackupCmd, BrewInstallCmd, CheckUpdateCmd, CompletionCmd, ConfigCmd, CookbookCmd,
-    ExecCmd, FetchCmd, InitCmd, LockCmd, ResetCmd, Runnable, SelfUpdateCmd, StatusCmd, UnapplyCmd,
-    UnlockCmd,
+    ApplyCmd, BackupCreateCmd, BackupRestoreCmd, BootstrapCmd, BrewAutoremoveCmd, BrewBackupCmd,
+    BrewInstallCmd, CheckUpdateCmd, CompletionCmd, ConfigDiffCmd, ConfigEditCmd, ConfigFmtCmd,
+    ConfigGetCmd, ConfigInstallHookCmd, ConfigLintCmd, ConfigLogCmd, ConfigSetCmd, ConfigShowCmd,
+    ConfigUnsetCmd, CookbookCmd, EnvCmd, ExecCmd, ExitCodesCmd, ExportCmd, FetchCmd, HistoryCmd,
+    ImportCmd, InitCmd, LockCmd, PromptCmd, ResetCmd, Runnable, SelfUpdateCmd, StatusCmd,
+    UnapplyCmd, UninstallCmd, UnlockCmd, WatchCmd,
 };
 
+/// Set by dotfile managers and other tools running cutler, e.g. `CUTLER_CALLER=chezmoi`.
+/// Prompts are declined and services aren't restarted while it's set.
+pub const CALLER_ENV: &str = "CUTLER_CALLER";
+
+/// Whether cutler is run by another tool rather than a person (see CALLER_ENV).
+pub fn has_caller() -> bool {
+    env::var(CALLER_ENV).is_ok_and(|v| !v.is_empty())
+}
+
 #[derive(Parser)]
 #[command(
     name = "cutler",
@@ -30,6 +46,10 @@ For more information on a subcommand, run `{name} <subcommand> --help`
     "
 )]
 pub struct Args {
+    /// Use this config file instead of the default one (or $CUTLER_CONFIG).
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     /// Increase output verbosity.
     #[arg(short, long, global = true)]
     pub verbose: bool,
@@ -50,10 +70,34 @@ pub struct Args {
     #[arg(long, global = true)]
     pub dry_run: bool,
 
+    /// Print the end-of-run summary as JSON.
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Maximum amount of concurrent jobs (commands, downloads, reads).
+    #[arg(short, long, global = true, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// When to color output.
+    #[arg(long, global = true, value_enum, default_value_t = ColorMode::Auto, value_name = "WHEN")]
+    pub color: ColorMode,
+
+    /// How to format logs; `github` emits GitHub Actions annotations.
+    #[arg(long, global = true, value_enum, default_value_t = LogStyle::Auto, value_name = "STYLE")]
+    pub log_style: LogStyle,
+
     /// Accepts all interactive prompts.
     #[arg(short = 'y', long, global = true)]
     pub accept_all: bool,
 
+    /// Decline all interactive prompts instead of asking (implied by $CUTLER_CALLER).
+    #[arg(long, global = true)]
+    pub no_prompts: bool,
+
+    /// Fail anything which needs the network instead of trying (implies --no-sync).
+    #[arg(long, global = true)]
+    pub offline: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -71,6 +115,12 @@ pub enum Command {
     Exec(ExecCmd),
     /// Initialize a new config file.
     Init(InitCmd),
+    /// Set up a new Mac in one go: fetch, install software, apply and run flagged commands.
+    Bootstrap(BootstrapCmd),
+    /// Render the config in another format, e.g. a shell script.
+    Export(ExportCmd),
+    /// Convert a `defaults read` dump or exported plist into [set] TOML.
+    Import(ImportCmd),
     /// Lock the config.
     Lock(LockCmd),
     /// Unlock the config.
@@ -80,6 +130,8 @@ pub enum Command {
     Unapply(UnapplyCmd),
     /// WARN: Hard-reset all preferences.
     Reset(ResetCmd),
+    /// Unapply everything and remove cutler's state, e.g. when offboarding a machine.
+    Uninstall(UninstallCmd),
     /// Compare your system against config.
     #[command(visible_alias = "s")]
     Status(StatusCmd),
@@ -88,9 +140,17 @@ pub enum Command {
         #[command(subcommand)]
         command: BrewSubcmd,
     },
-    /// Shows the configuration.
+    /// Bundle the whole cutler state into one archive, or restore it.
+    Backup {
+        #[command(subcommand)]
+        command: BackupSubcmd,
+    },
+    /// Show or edit the configuration (shows by default).
     #[command(visible_alias = "conf")]
-    Config(ConfigCmd),
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigSubcmd>,
+    },
     /// Check for version updates.
     #[command(visible_alias = "cup")]
     CheckUpdate(CheckUpdateCmd),
@@ -100,9 +160,22 @@ pub enum Command {
     /// Generate shell completions.
     #[command(visible_alias = "comp")]
     Completion(CompletionCmd),
+    /// Print the environment cutler runs commands with, for eval in shell rc files.
+    Env(EnvCmd),
+    /// Check for drift periodically, re-applying the config if enabled.
+    Watch(WatchCmd),
+    /// List the exit codes cutler ends with.
+    ExitCodes(ExitCodesCmd),
+    /// Print a short status token for shell prompts, from the last check.
+    Prompt(PromptCmd),
+    /// List past apply, unapply and reset runs, or show what one changed.
+    History(HistoryCmd),
     /// Sync the local config with remote (if any in [remote])
     #[command(visible_alias = "get")]
     Fetch(FetchCmd),
+    /// Anything else is run as a `cutler-<name>` plugin from $PATH.
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
 #[derive(Subcommand, Debug)]
@@ -112,31 +185,156 @@ pub enum BrewSubcmd {
     /// Install formulae/casks/taps from config.
     #[command(visible_alias = "apply")]
     Install(BrewInstallCmd),
+    /// Remove unneeded dependencies not required by config.
+    Autoremove(BrewAutoremoveCmd),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupSubcmd {
+    /// Write the config, snapshot and installed software into a .tar.zst archive.
+    Create(BackupCreateCmd),
+    /// Restore the config and snapshot from an archive.
+    Restore(BackupRestoreCmd),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubcmd {
+    /// Show the configuration.
+    Show(ConfigShowCmd),
+    /// Edit the configuration in $EDITOR, validating it afterwards.
+    Edit(ConfigEditCmd),
+    /// Format the configuration canonically.
+    Fmt(ConfigFmtCmd),
+    /// Print the value at a key path.
+    Get(ConfigGetCmd),
+    /// Write a value at a key path, keeping comments and ordering.
+    Set(ConfigSetCmd),
+    /// Remove the value at a key path.
+    Unset(ConfigUnsetCmd),
+    /// Check the config for invalid values of well-known settings and keys macOS no longer reads.
+    Lint(ConfigLintCmd),
+    /// Show recent git history of the config.
+    Log(ConfigLogCmd),
+    /// Compare two configs key by key.
+    Diff(ConfigDiffCmd),
+    /// Install a git pre-commit hook which validates the config.
+    InstallHook(ConfigInstallHookCmd),
 }
 
 impl Command {
     /// Returns a trait object reference for a given command so that it can
     /// be run using the .run() implementation of that particular command.
-    pub fn as_runnable(&self) -> &dyn Runnable {
-        match self {
+    /// External subcommands return None, since they're run by plugins.
+    pub fn as_runnable(&self) -> Option<&dyn Runnable> {
+        let runnable: &dyn Runnable = match self {
             Command::Apply(cmd) => cmd,
-            Command::Config(cmd) => cmd,
+            Command::Config { command } => match command {
+                None | Some(ConfigSubcmd::Show(_)) => &ConfigShowCmd,
+                Some(ConfigSubcmd::Edit(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Fmt(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Get(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Set(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Unset(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Log(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Diff(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::InstallHook(cmd)) => cmd as &dyn Runnable,
+                Some(ConfigSubcmd::Lint(cmd)) => cmd as &dyn Runnable,
+            },
             Command::Cookbook(cmd) => cmd,
             Command::Exec(cmd) => cmd,
             Command::Fetch(cmd) => cmd,
             Command::Init(cmd) => cmd,
+            Command::Bootstrap(cmd) => cmd,
+            Command::Import(cmd) => cmd,
+            Command::Export(cmd) => cmd,
             Command::Unapply(cmd) => cmd,
             Command::Reset(cmd) => cmd,
+            Command::Uninstall(cmd) => cmd,
             Command::Status(cmd) => cmd,
             Command::Lock(cmd) => cmd,
             Command::Unlock(cmd) => cmd,
             Command::CheckUpdate(cmd) => cmd,
             Command::SelfUpdate(cmd) => cmd,
             Command::Completion(cmd) => cmd,
+            Command::Env(cmd) => cmd,
+            Command::ExitCodes(cmd) => cmd,
+            Command::Prompt(cmd) => cmd,
+            Command::History(cmd) => cmd,
+            Command::Watch(cmd) => cmd,
             Command::Brew { command } => match command {
                 BrewSubcmd::Backup(cmd) => cmd as &dyn Runnable,
                 BrewSubcmd::Install(cmd) => cmd as &dyn Runnable,
+                BrewSubcmd::Autoremove(cmd) => cmd as &dyn Runnable,
+            },
+            Command::Backup { command } => match command {
+                BackupSubcmd::Create(cmd) => cmd as &dyn Runnable,
+                BackupSubcmd::Restore(cmd) => cmd as &dyn Runnable,
+            },
+            Command::External(_) => return None,
+        };
+
+        Some(runnable)
+    }
+
+    /// Whether the command prints machine-readable output which logs must not mix into.
+    pub fn is_porcelain(&self) -> bool {
+        matches!(self, Command::Status(cmd) if cmd.porcelain || cmd.quiet_json)
+            || matches!(self, Command::Env(_))
+    }
+
+    /// Whether stdout carries output for other programs, so that logs have to go to stderr.
+    pub fn owns_stdout(&self) -> bool {
+        self.is_porcelain() || matches!(self, Command::Export(_))
+    }
+
+    /// Returns the subcommand name as typed on the command line, e.g. "brew install".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Apply(_) => "apply",
+            Command::Config { command } => match command {
+                None => "config",
+                Some(ConfigSubcmd::Show(_)) => "config show",
+                Some(ConfigSubcmd::Edit(_)) => "config edit",
+                Some(ConfigSubcmd::Fmt(_)) => "config fmt",
+                Some(ConfigSubcmd::Get(_)) => "config get",
+                Some(ConfigSubcmd::Set(_)) => "config set",
+                Some(ConfigSubcmd::Unset(_)) => "config unset",
+                Some(ConfigSubcmd::Log(_)) => "config log",
+                Some(ConfigSubcmd::Diff(_)) => "config diff",
+                Some(ConfigSubcmd::InstallHook(_)) => "config install-hook",
+                Some(ConfigSubcmd::Lint(_)) => "config lint",
+            },
+            Command::Cookbook(_) => "cookbook",
+            Command::Exec(_) => "exec",
+            Command::Fetch(_) => "fetch",
+            Command::Init(_) => "init",
+            Command::Bootstrap(_) => "bootstrap",
+            Command::Import(_) => "import",
+            Command::Export(_) => "export",
+            Command::Unapply(_) => "unapply",
+            Command::Reset(_) => "reset",
+            Command::Uninstall(_) => "uninstall",
+            Command::Status(_) => "status",
+            Command::Lock(_) => "lock",
+            Command::Unlock(_) => "unlock",
+            Command::CheckUpdate(_) => "check-update",
+            Command::SelfUpdate(_) => "self-update",
+            Command::Completion(_) => "completion",
+            Command::Env(_) => "env",
+            Command::ExitCodes(_) => "exit-codes",
+            Command::Prompt(_) => "prompt",
+            Command::History(_) => "history",
+            Command::Watch(_) => "watch",
+            Command::Brew { command } => match command {
+                BrewSubcmd::Backup(_) => "brew backup",
+                BrewSubcmd::Install(_) => "brew install",
+                BrewSubcmd::Autoremove(_) => "brew autoremove",
+            },
+            Command::Backup { command } => match command {
+                BackupSubcmd::Create(_) => "backup create",
+                BackupSubcmd::Restore(_) => "backup restore",
             },
+            Command::External(_) => "plugin",
         }
     }
 }
@@ -45,6 +45,33 @@ pub fn should_dry_run() -> bool {
     DRY_RUN.load(Ordering::SeqCst)
 }
 
+// --json
+static JSON: AtomicBool = AtomicBool::new(false);
+pub fn set_json(value: bool) {
+    JSON.store(value, Ordering::SeqCst);
+}
+pub fn should_output_json() -> bool {
+    JSON.load(Ordering::SeqCst)
+}
+
+// --no-prompts
+static NO_PROMPTS: AtomicBool = AtomicBool::new(false);
+pub fn set_no_prompts(value: bool) {
+    NO_PROMPTS.store(value, Ordering::SeqCst);
+}
+pub fn should_not_prompt() -> bool {
+    NO_PROMPTS.load(Ordering::SeqCst)
+}
+
+// --offline
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+pub fn set_offline(value: bool) {
+    OFFLINE.store(value, Ordering::SeqCst);
+}
+pub fn should_stay_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
 // --no-restart-services
 static NO_RESTART_SERVICES: AtomicBool = AtomicBool::new(false);
 pub fn set_no_restart_services(value: bool) {
@@ -53,3 +80,21 @@ pub fn set_no_restart_services(value: bool) {
 pub fn should_not_restart_services() -> bool {
     NO_RESTART_SERVICES.load(Ordering::SeqCst)
 }
+
+// --fix-missing (apply, exec)
+static FIX_MISSING: AtomicBool = AtomicBool::new(false);
+pub fn set_fix_missing(value: bool) {
+    FIX_MISSING.store(value, Ordering::SeqCst);
+}
+pub fn should_fix_missing() -> bool {
+    FIX_MISSING.load(Ordering::SeqCst)
+}
+
+// --no-interleave (apply, exec)
+static NO_INTERLEAVE: AtomicBool = AtomicBool::new(false);
+pub fn set_no_interleave(value: bool) {
+    NO_INTERLEAVE.store(value, Ordering::SeqCst);
+}
+pub fn should_not_interleave() -> bool {
+    NO_INTERLEAVE.load(Ordering::SeqCst)
+}
@@ -3,4 +3,4 @@
 pub mod args;
 pub mod atomic;
 
-pub use args::{Args, Command};
+pub use args::{Args, CALLER_ENV, Command, has_caller};
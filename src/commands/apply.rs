@@ -1,57 +1,158 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::{
-    cli::atomic::should_dry_run,
+    autosync::parse_interval,
+    cli::atomic::{set_fix_missing, set_no_interleave, should_dry_run},
     commands::{BrewInstallCmd, Runnable},
+    config::keypath::parse_value,
     config::{core::Config, path::get_config_path, remote::RemoteConfigManager},
     domains::{
-        collector,
-        convert::{prefvalue_to_serializable, toml_to_prefvalue},
+        catalog, collector,
+        convert::{
+            display_prefvalue, prefvalue_to_serializable, serializable_to_prefvalue,
+            toml_edit_to_toml,
+        },
+        files,
+        managed::is_managed,
+        meta::{Requires, SettingMeta, SettingsMeta, not_applicable},
+        plan::{Plan, PlanEntry},
+        safety, screenshots, split_setting_path, spotlight, tcc,
+        text_replacements::merge_system_replacements,
     },
+    ephemeral::{self, EphemeralState},
     exec::core::{self, ExecMode},
-    log_cute, log_dry, log_err, log_info, log_warn,
+    log_cute, log_dry, log_err, log_info, log_warn, plist,
     snapshot::{
         core::{SettingState, Snapshot},
         get_snapshot_path,
     },
     util::{
-        io::{confirm, restart_services},
+        exit::{ExitCode, coded},
+        history,
+        io::{confirm, confirm_always, flush_prefs, restart_services},
+        logging::group,
+        macos::current_version,
         sha::get_digest,
+        status_cache::{StatusCache, format_age},
+        summary,
     },
 };
 use anyhow::{Result, bail};
 use async_trait::async_trait;
 use clap::Args;
 use defaults_rs::{Domain, PrefValue, Preferences};
-use toml::Value;
+use std::io::{self, IsTerminal};
+use std::{collections::HashMap, fmt, time::Duration};
+use tokio::process::Command;
 
 use crate::domains::convert::SerializablePrefValue;
 
-#[derive(Args, Debug)]
+/// Error of an apply declined for changing more than `[safety] confirm_over` settings.
+#[derive(Debug)]
+pub struct TooManyChanges {
+    pub count: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for TooManyChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Aborted: too many changes ({} over the limit of {}).",
+            self.count, self.limit
+        )
+    }
+}
+
+impl std::error::Error for TooManyChanges {}
+
+#[derive(Args, Debug, Default)]
 pub struct ApplyCmd {
     /// The URL to the remote config file.
     #[arg(short, long)]
     url: Option<String>,
 
     /// Skip executing external commands.
-    #[arg(short, long, conflicts_with_all = &["all_cmd", "flagged_cmd"])]
+    #[arg(short, long, conflicts_with_all = &["all_cmd", "flagged_cmd", "exec_tag"])]
     no_cmd: bool,
 
     /// Execute all external commands (even flagged ones).
-    #[arg(short, long, conflicts_with_all = &["no_cmd", "flagged_cmd"])]
+    #[arg(short, long, conflicts_with_all = &["no_cmd", "flagged_cmd", "exec_tag"])]
     all_cmd: bool,
 
     /// Execute flagged external commands only.
-    #[arg(short, long, conflicts_with_all = &["all_cmd", "no_cmd"])]
+    #[arg(short, long, conflicts_with_all = &["all_cmd", "no_cmd", "exec_tag"])]
     flagged_cmd: bool,
 
+    /// Execute external commands carrying the given tag only (repeatable).
+    #[arg(long, value_name = "TAG", conflicts_with_all = &["all_cmd", "no_cmd", "flagged_cmd"])]
+    exec_tag: Vec<String>,
+
+    /// Install binaries missing from the `required` lists of commands through [brew] without
+    /// asking.
+    #[arg(long, conflicts_with = "no_cmd")]
+    fix_missing: bool,
+
+    /// Print the output of concurrent external commands in one block per command instead of
+    /// interleaving their lines.
+    #[arg(long, conflicts_with = "no_cmd")]
+    no_interleave: bool,
+
     /// WARN: Disables domain existence check.
-    #[arg(long)]
+    #[arg(long, conflicts_with = "create_missing")]
     no_dom_check: bool,
 
+    /// Create domains which don't exist yet instead of aborting.
+    #[arg(long)]
+    create_missing: bool,
+
+    /// Allow changing protected domains and settings (see [safety]).
+    #[arg(long)]
+    allow_protected: bool,
+
+    /// Fail on any divergence: missing domains, type changes, failed writes, commands or downloads.
+    #[arg(long, conflicts_with_all = &["no_dom_check", "create_missing"])]
+    strict: bool,
+
     /// Invoke `brew install` after applying preferences.
     #[arg(short, long)]
     brew: bool,
+
+    /// Apply a single setting instead of the config, e.g. `dock.tilesize=48` (repeatable).
+    #[arg(long, value_name = "DOMAIN.KEY=VALUE", conflicts_with_all = &["url", "brew"])]
+    set: Vec<String>,
+
+    /// Skip re-reading written preferences to check that they took effect.
+    #[arg(long)]
+    no_verify: bool,
+
+    /// Restart cfprefsd after writing, so that verification reads the values back from disk.
+    #[arg(long, conflicts_with = "no_verify")]
+    flush_prefs: bool,
+
+    /// Revert the written preferences automatically after a while, e.g. `2h` or `30m`.
+    /// External commands, plist files and Homebrew are skipped.
+    #[arg(long, value_name = "DURATION", value_parser = parse_interval, conflicts_with_all = &["brew", "all_cmd", "flagged_cmd", "exec_tag"])]
+    ephemeral: Option<Duration>,
+
+    /// With --ephemeral, wait in the foreground instead of scheduling a LaunchAgent.
+    /// Ctrl-C reverts right away.
+    #[arg(long, requires = "ephemeral")]
+    foreground: bool,
+
+    /// Only do work when something changed: external commands run only if the config changed
+    /// since the last apply, and services only restart if preferences were written.
+    #[arg(long, conflicts_with = "set")]
+    idempotent: bool,
+
+    /// Open the Full Disk Access settings if some settings couldn't be applied without it.
+    #[arg(long)]
+    open_settings: bool,
+
+    /// Ask before changing more settings than this, even with --accept-all. Overrides
+    /// `[safety] confirm_over`.
+    #[arg(long, value_name = "COUNT")]
+    confirm_over: Option<usize>,
 }
 
 /// Represents a preference modification job.
@@ -59,16 +160,241 @@ pub struct ApplyCmd {
 struct PreferenceJob {
     domain: String,
     key: String,
-    toml_value: Value,
+    desired: PrefValue,
     action: &'static str,
     original: Option<SerializablePrefValue>,
+    /// The value right before this apply, for ephemeral applies.
+    previous: Option<SerializablePrefValue>,
     new_value: String,
+    meta: Option<SettingMeta>,
+}
+
+impl ApplyCmd {
+    /// An apply of the config's settings (and plists) only, skipping external commands.
+    pub fn settings_only() -> Self {
+        ApplyCmd {
+            no_cmd: true,
+            ..Default::default()
+        }
+    }
+
+    /// Decides the external command execution mode from the flags, if commands should run at all.
+    fn exec_mode(&self) -> Option<ExecMode> {
+        if self.no_cmd || self.ephemeral.is_some() {
+            None
+        } else if self.all_cmd {
+            Some(ExecMode::All)
+        } else if !self.exec_tag.is_empty() {
+            Some(ExecMode::Tagged(self.exec_tag.clone()))
+        } else if self.flagged_cmd {
+            Some(ExecMode::Flagged)
+        } else {
+            Some(ExecMode::Regular)
+        }
+    }
+
+    /// Builds a domain → settings map from the `--set` arguments, just like `collect()` would.
+    fn one_off_domains(&self) -> Result<HashMap<String, toml::Table>> {
+        let mut domains: HashMap<String, toml::Table> = HashMap::new();
+
+        for arg in &self.set {
+            let Some((path, raw)) = arg.split_once('=') else {
+                bail!("Invalid --set argument: {arg} (expected DOMAIN.KEY=VALUE)")
+            };
+            let (domain, key) = split_setting_path(path)?;
+            let value = toml_edit_to_toml(&parse_value(raw.trim()))?;
+
+            domains.entry(domain).or_default().insert(key, value);
+        }
+
+        Ok(domains)
+    }
+
+    /// Helper for: run()
+    /// In strict mode, stops the run (with its summary) once anything failed.
+    fn check_strict(&self, stage: &str) -> Result<()> {
+        if self.strict && summary::has_failures() {
+            summary::finish("Apply");
+            return Err(coded(
+                ExitCode::PartialApply,
+                format!("Strict mode: aborting since {stage} failed."),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    /// Stores the values overwritten by an ephemeral apply, then either reverts them
+    /// in the foreground once the time is up or leaves that to a LaunchAgent.
+    async fn schedule_revert(
+        &self,
+        config: &Config,
+        duration: Duration,
+        jobs: &[PreferenceJob],
+    ) -> Result<()> {
+        let settings = jobs
+            .iter()
+            .map(|job| SettingState {
+                domain: job.domain.clone(),
+                key: job.key.clone(),
+                original_value: job.previous.clone(),
+            })
+            .collect();
+        let state = EphemeralState::new(duration, settings);
+        state.save(&config.path).await?;
+
+        let after = format_age(duration.as_secs());
+
+        if !self.foreground {
+            ephemeral::schedule_agent(&config.path).await?;
+            log_cute!("Reverting in {after}. Run `cutler unapply --ephemeral` to revert earlier.");
+            return Ok(());
+        }
+
+        log_cute!("Reverting in {after}. Press Ctrl-C to revert now.");
+        tokio::select! {
+            _ = state.wait() => {}
+            _ = tokio::signal::ctrl_c() => log_warn!("Interrupted, reverting now."),
+        }
+
+        state.revert()?;
+        EphemeralState::delete(&config.path).await?;
+        restart_services().await;
+        log_cute!("Ephemeral settings reverted.");
+
+        Ok(())
+    }
+}
+
+/// Helper for: ApplyCmd::run()
+/// Re-reads every written preference and reports the ones which didn't stick
+/// (e.g. cached by cfprefsd, sandboxed or SIP-protected keys).
+/// Returns the ones which didn't.
+async fn verify_jobs(jobs: &[PreferenceJob]) -> Vec<String> {
+    let mut verified = 0;
+    let mut unverified = Vec::new();
+
+    for job in jobs {
+        let current = collector::read_current(&job.domain, &job.key)
+            .await
+            .ok()
+            .flatten();
+
+        if current.as_ref() == Some(&job.desired) {
+            verified += 1;
+        } else {
+            let now = current
+                .as_ref()
+                .map(display_prefvalue)
+                .unwrap_or_else(|| "Not set".to_string());
+            log_warn!(
+                "{} | {} was written but didn't stick (now: {now})",
+                job.domain,
+                job.key
+            );
+            unverified.push(format!("{} | {}", job.domain, job.key));
+        }
+    }
+
+    summary::add_verified(verified);
+    if verified == jobs.len() {
+        log_info!("All written preferences verified.");
+    }
+    for item in &unverified {
+        summary::add_unverified(item.clone());
+    }
+
+    unverified
+}
+
+/// Helper for: ApplyCmd::run()
+/// Lists the changed settings which only take effect after logging out or restarting, as set
+/// with `requires` or known from the catalog.
+fn session_requirements(jobs: &[PreferenceJob]) -> Vec<(Requires, String)> {
+    jobs.iter()
+        .filter_map(|job| {
+            let requires = job.meta.as_ref().and_then(|m| m.requires).or_else(|| {
+                catalog::by_effective(&job.domain, &job.key).and_then(|e| e.requires)
+            })?;
+            Some((requires, format!("{} | {}", job.domain, job.key)))
+        })
+        .collect()
+}
+
+/// Helper for: ApplyCmd::run()
+/// Tells which settings still need a new session, offering to log out right away.
+async fn report_pending(pending: &[(Requires, String)]) {
+    for (requires, when) in [
+        (Requires::Restart, "restarting your Mac"),
+        (Requires::Logout, "logging out"),
+    ] {
+        let items: Vec<&str> = pending
+            .iter()
+            .filter(|(r, _)| *r == requires)
+            .map(|(_, item)| item.as_str())
+            .collect();
+
+        if !items.is_empty() {
+            log_warn!(
+                "These settings take effect after {when}: {}",
+                items.join(", ")
+            );
+        }
+    }
+
+    // a restart covers logging out, so only offer it when nothing needs more
+    let needs = |requires| pending.iter().any(|(r, _)| *r == requires);
+    if needs(Requires::Logout)
+        && !needs(Requires::Restart)
+        && io::stdin().is_terminal()
+        && confirm_always("Log out now?")
+    {
+        let result = Command::new("osascript")
+            .args(["-e", "tell application \"System Events\" to log out"])
+            .status()
+            .await;
+        if let Err(e) = result {
+            log_err!("Could not log out: {e}");
+        }
+    }
+}
+
+/// Helper for: ApplyCmd::run()
+/// Tells which settings and plist files were left out for lack of Full Disk Access, and how to
+/// grant it.
+async fn report_permissions(deferred: &[String], open_settings: bool) {
+    if deferred.is_empty() {
+        return;
+    }
+
+    log_warn!(
+        "These settings need Full Disk Access and were not applied: {}",
+        deferred.join(", ")
+    );
+    log_warn!(
+        "Allow your terminal in System Settings > Privacy & Security > Full Disk Access, then apply again."
+    );
+
+    if !open_settings {
+        log_warn!("Pass --open-settings to go there right away.");
+    } else if should_dry_run() {
+        log_dry!("Would open the Full Disk Access settings");
+    } else if let Err(e) = Command::new("open").arg(tcc::SETTINGS_URL).status().await {
+        log_err!("Could not open System Settings: {e}");
+    }
 }
 
 #[async_trait]
 impl Runnable for ApplyCmd {
     async fn run(&self, config: &mut Config) -> Result<()> {
         let dry_run = should_dry_run();
+        summary::begin();
+        set_fix_missing(self.fix_missing);
+        set_no_interleave(self.no_interleave);
+
+        // one-off settings from the CLI don't touch the config at all
+        let one_off = !self.set.is_empty();
 
         // remote download logic
         if let Some(url) = &self.url {
@@ -88,12 +414,62 @@ impl Runnable for ApplyCmd {
             );
         }
 
-        // finally either load the preexisting config / the config we just downloaded
-        config.load(true).await?;
+        // finally either load the preexisting config / the config we just downloaded; one-off
+        // applies still honor its lock and [safety], they only leave its [set] alone
+        if !one_off || config.is_loadable() {
+            config.load(true).await?;
+        }
+
+        if self.ephemeral.is_some() && EphemeralState::load(&config.path).await.is_some() {
+            bail!(
+                "An ephemeral apply is still pending. Run `cutler unapply --ephemeral` to revert it first."
+            )
+        }
+
+        // run commands exporting variables first, since [set] values may refer to them
+        let exec_mode = if one_off { None } else { self.exec_mode() };
+        let export_run_count = match &exec_mode {
+            Some(mode) => core::run_exports(config, mode).await?,
+            None => 0,
+        };
+        self.check_strict("variable-exporting commands")?;
+
+        let prefs_group = group("System preferences");
 
         // parse + flatten domains
-        let digest = get_digest(config.path.clone())?;
-        let domains = collector::collect(config).await?;
+        let (digest, (domains, mut meta)) = if one_off {
+            (None, (self.one_off_domains()?, SettingsMeta::new()))
+        } else {
+            let (mut domains, meta) = collector::collect_with_meta(config).await?;
+            merge_system_replacements(config, &mut domains).await?;
+
+            (Some(get_digest(config.path.clone())?), (domains, meta))
+        };
+
+        // settings meant for other macOS versions were left out by the collector
+        for ((eff_dom, eff_key), range) in not_applicable(&meta, current_version()) {
+            log_info!("Skipping {eff_dom} | {eff_key}, only for {range}");
+            summary::add_skipped(1);
+        }
+
+        // validate all domains up front, reporting every missing one at once
+        if !self.no_dom_check {
+            let known: Vec<String> = Preferences::list_domains()?
+                .iter()
+                .map(|f| f.to_string())
+                .collect();
+            let missing = collector::missing_domains(domains.keys(), &known);
+
+            if !missing.is_empty() {
+                if !self.create_missing {
+                    bail!(
+                        "Domains not found: {}. Pass --create-missing to create them.",
+                        missing.join(", ")
+                    )
+                }
+                log_warn!("Creating missing domains: {}", missing.join(", "));
+            }
+        }
 
         // load the old snapshot (if any), otherwise create a new instance
         let snap_path = get_snapshot_path().await?;
@@ -112,8 +488,15 @@ impl Runnable for ApplyCmd {
             Snapshot::new().await
         };
 
+        // one-off applies keep the digest of the last config-driven apply
+        let old_digest = snap.digest.clone();
+        let old_undo = snap.undo.clone();
+        let old_run_count = snap.exec_run_count;
+        let old_brew = snap.brew.clone();
+        let mut plist_states = snap.plists.clone();
+
         // turn the old snapshot into a hashmap for a quick lookup
-        let mut existing: std::collections::HashMap<_, _> = snap
+        let mut existing: HashMap<_, _> = snap
             .settings
             .into_iter()
             .map(|s| ((s.domain.clone(), s.key.clone()), s))
@@ -121,65 +504,140 @@ impl Runnable for ApplyCmd {
 
         let mut jobs: Vec<PreferenceJob> = Vec::new();
 
-        let domains_list: Vec<String> = Preferences::list_domains()?
-            .iter()
-            .map(|f| f.to_string())
-            .collect();
+        // writes without Full Disk Access would fail, so they're reported at the end instead
+        let mut deferred: Vec<String> = Vec::new();
+
+        // keys macOS keeps changing by itself are only written the first time
+        let ignored = collector::ignored_keys(config);
+
+        // compare every setting against the system
+        let plan = Plan::from_domains(domains).await?;
+
+        // changing these by accident can break login or security, so ask for explicit consent
+        if !self.allow_protected {
+            let patterns = safety::protected_patterns(config);
+            let protected: Vec<String> = plan
+                .changes()
+                .filter(|e| safety::is_protected(&patterns, &e.domain, &e.key))
+                .map(|e| format!("{} | {}", e.domain, e.key))
+                .collect();
+
+            if !protected.is_empty() {
+                bail!(
+                    "Refusing to change protected settings: {}. Pass --allow-protected to proceed.",
+                    protected.join(", ")
+                )
+            }
+        }
 
-        for (dom, table) in domains.into_iter() {
-            for (key, toml_value) in table.into_iter() {
-                let (eff_dom, eff_key) = collector::effective(&dom, &key);
+        // a bad remote config shouldn't be able to rewrite a whole machine unnoticed; fetching
+        // keeps the local threshold, so a remote can't lift it
+        let threshold = self
+            .confirm_over
+            .or_else(|| config.safety.as_ref()?.confirm_over);
+        if let Some(threshold) = threshold
+            && !dry_run
+        {
+            let changes = safety::counted_changes(&plan, &ignored);
+
+            if changes.len() > threshold {
+                log_warn!(
+                    "This apply would change {} settings (over the limit of {threshold}):",
+                    changes.len()
+                );
+                for e in &changes {
+                    log_warn!(
+                        "  {} | {}: {} -> {}",
+                        e.domain,
+                        e.key,
+                        e.current
+                            .as_ref()
+                            .map(display_prefvalue)
+                            .unwrap_or_else(|| "(unset)".into()),
+                        display_prefvalue(&e.desired)
+                    );
+                }
 
-                if !self.no_dom_check
-                    && eff_dom != "NSGlobalDomain"
-                    && !domains_list.contains(&eff_dom)
-                {
-                    bail!("Domain \"{}\" not found.", eff_dom)
+                if !confirm_always("Apply all of these changes?") {
+                    return Err(TooManyChanges {
+                        count: changes.len(),
+                        limit: threshold,
+                    }
+                    .into());
                 }
+            }
+        }
 
-                // read the current value from the system
-                // then, check if changed
-                // TODO: could use read_batch from defaults-rs here
-                let current_pref = collector::read_current(&eff_dom, &eff_key).await;
-                let desired_pref = toml_to_prefvalue(&toml_value)?;
+        // a value of another type usually means a typo in the config, or a changed macOS default
+        if self.strict {
+            let type_changes: Vec<String> = plan
+                .entries
+                .iter()
+                .filter(|e| {
+                    e.is_type_change() && !ignored.contains(&(e.domain.clone(), e.key.clone()))
+                })
+                .map(|e| format!("{} | {}", e.domain, e.key))
+                .collect();
+
+            if !type_changes.is_empty() {
+                bail!(
+                    "Strict mode: these settings would change type: {}",
+                    type_changes.join(", ")
+                )
+            }
+        }
 
-                // Compare PrefValues directly instead of strings
-                let changed = match &current_pref {
-                    Some(current) => current != &desired_pref,
-                    None => true, // No current value means it's a new setting
+        for entry in plan.entries {
+            let PlanEntry {
+                domain: eff_dom,
+                key: eff_key,
+                ..
+            } = &entry;
+
+            // grab the old snapshot entry if it exists
+            let id = (eff_dom.clone(), eff_key.clone());
+            let old_entry = existing.get(&id).cloned();
+
+            if entry.is_changed() && old_entry.is_some() && ignored.contains(&id) {
+                log_info!("Skipping ignored {eff_dom} | {eff_key}",);
+                summary::add_skipped(1);
+            } else if entry.is_changed() && is_managed(eff_dom, eff_key) {
+                log_warn!("Skipping {eff_dom} | {eff_key}, managed by a configuration profile");
+                summary::add_skipped(1);
+            } else if entry.is_changed() && tcc::is_blocked(eff_dom) {
+                log_info!("Deferring {eff_dom} | {eff_key}, needs Full Disk Access");
+                deferred.push(format!("{eff_dom} | {eff_key}"));
+                summary::add_skipped(1);
+            } else if entry.is_changed() {
+                existing.remove(&id);
+
+                // Preserve existing non-null original; otherwise, for brand new keys, capture original from system
+                let original = if let Some(e) = &old_entry {
+                    e.original_value.clone()
+                } else {
+                    entry.current.as_ref().map(prefvalue_to_serializable)
                 };
 
-                // grab the old snapshot entry if it exists
-                let old_entry = existing.get(&(eff_dom.clone(), eff_key.clone())).cloned();
-
-                if changed {
-                    existing.remove(&(eff_dom.clone(), eff_key.clone()));
-
-                    // Preserve existing non-null original; otherwise, for brand new keys, capture original from system
-                    let original = if let Some(e) = &old_entry {
-                        e.original_value.clone()
-                    } else {
-                        current_pref.as_ref().map(prefvalue_to_serializable)
-                    };
-
-                    // decide “applying” vs “updating”
-                    let action = if old_entry.is_some() {
-                        "Updating"
-                    } else {
-                        "Applying"
-                    };
-
-                    jobs.push(PreferenceJob {
-                        domain: eff_dom.clone(),
-                        key: eff_key.clone(),
-                        toml_value: toml_value.clone(),
-                        action,
-                        original: if is_bad_snap { None } else { original },
-                        new_value: desired_pref.to_string(),
-                    });
+                // decide “applying” vs “updating”
+                let action = if old_entry.is_some() {
+                    "Updating"
                 } else {
-                    log_info!("Skipping unchanged {eff_dom} | {eff_key}",);
-                }
+                    "Applying"
+                };
+
+                jobs.push(PreferenceJob {
+                    domain: eff_dom.clone(),
+                    key: eff_key.clone(),
+                    action,
+                    original: if is_bad_snap { None } else { original },
+                    previous: entry.current.as_ref().map(prefvalue_to_serializable),
+                    new_value: entry.desired.to_string(),
+                    meta: meta.remove(&id),
+                    desired: entry.desired,
+                });
+            } else {
+                log_info!("Skipping unchanged {eff_dom} | {eff_key}",);
+                summary::add_skipped(1);
             }
         }
 
@@ -188,15 +646,11 @@ impl Runnable for ApplyCmd {
         let mut batch: Vec<(Domain, String, PrefValue)> = Vec::new();
 
         for job in &jobs {
-            let domain_obj = if job.domain == "NSGlobalDomain" {
-                Domain::Global
-            } else {
-                Domain::User(job.domain.clone())
-            };
+            let domain_obj = files::domain_obj(&job.domain)?;
 
             if !dry_run {
                 log_info!(
-                    "{} {} | {} -> {} {}",
+                    "{} {} | {} -> {} {}{}",
                     job.action,
                     job.domain,
                     job.key,
@@ -208,26 +662,92 @@ impl Runnable for ApplyCmd {
                         )
                     } else {
                         "".to_string()
-                    }
+                    },
+                    job.meta
+                        .as_ref()
+                        .map(|m| format!(" # {m}"))
+                        .unwrap_or_default()
                 );
             }
-            let pref_value = toml_to_prefvalue(&job.toml_value)?;
-            batch.push((domain_obj, job.key.clone(), pref_value));
+            batch.push((domain_obj, job.key.clone(), job.desired.clone()));
+        }
+
+        if !one_off {
+            screenshots::ensure_location(config, dry_run).await?;
         }
 
+        // write fully managed plist files (ephemeral and one-off applies only write preferences)
+        if self.ephemeral.is_none() && !one_off {
+            let (blocked, plists): (Vec<_>, Vec<_>) = config
+                .plist
+                .iter()
+                .flatten()
+                .map(|(path, table)| (path.clone(), table.clone()))
+                .partition(|(path, _)| tcc::is_blocked(path));
+
+            for (path, _) in blocked {
+                log_info!("Deferring plist {path}, needs Full Disk Access");
+                deferred.push(path);
+                summary::add_skipped(1);
+            }
+            plist::core::apply_plists(&plists, &mut plist_states, dry_run).await?;
+        }
+
+        // settings which only take effect in a new session, reported at the very end
+        let mut pending = session_requirements(&jobs);
+
         // perform batch write
         if !dry_run {
-            match Preferences::write_batch(batch) {
+            // whatever is still off afterwards, for `status --cached`
+            let drifted = match files::write_batch(batch) {
                 Ok(_) => {
                     log_info!("All preferences applied.");
+                    summary::add_applied(jobs.len());
+                    for job in &jobs {
+                        history::add_change(history::Change {
+                            domain: job.domain.clone(),
+                            key: job.key.clone(),
+                            from: job
+                                .previous
+                                .as_ref()
+                                .map(|p| display_prefvalue(&serializable_to_prefvalue(p))),
+                            to: Some(display_prefvalue(&job.desired)),
+                        });
+                    }
+
+                    if self.flush_prefs && !jobs.is_empty() {
+                        flush_prefs().await;
+                    }
+
+                    if !self.no_verify && !jobs.is_empty() {
+                        verify_jobs(&jobs).await
+                    } else {
+                        Vec::new()
+                    }
                 }
                 Err(e) => {
                     log_err!("Batch write failed: {e}");
+                    pending.clear();
+                    let failed: Vec<String> = jobs
+                        .iter()
+                        .map(|job| format!("{} | {}", job.domain, job.key))
+                        .collect();
+                    for item in &failed {
+                        summary::add_failure(item.clone());
+                    }
+                    failed
                 }
+            };
+
+            if !one_off && self.ephemeral.is_none() {
+                let has_software = config.brew.is_some() || config.mas.is_some();
+                StatusCache::record_prefs(&config.path, drifted, has_software).await;
             }
 
             // restart system services if requested
-            restart_services().await;
+            if !self.idempotent || !jobs.is_empty() {
+                restart_services().await;
+            }
         } else {
             for job in &jobs {
                 log_dry!(
@@ -239,7 +759,37 @@ impl Runnable for ApplyCmd {
             }
         }
 
+        spotlight::reload(jobs.iter().map(|job| job.domain.as_str()), dry_run);
+
+        // ephemeral settings revert on their own, so the snapshot stays as it is
+        if let Some(duration) = self.ephemeral {
+            if dry_run {
+                log_dry!(
+                    "Would revert {} settings after {}",
+                    jobs.len(),
+                    format_age(duration.as_secs())
+                );
+            } else if !jobs.is_empty() {
+                self.schedule_revert(config, duration, &jobs).await?;
+            } else {
+                log_cute!("Nothing changed, so nothing to revert.");
+            }
+
+            report_permissions(&deferred, self.open_settings).await;
+
+            if let Some(summary) = summary::finish("Apply")
+                && !dry_run
+            {
+                history::record(&config.path, "apply", &summary).await;
+            }
+            return Ok(());
+        }
+
         let mut new_snap = Snapshot::new().await;
+        new_snap.plists = plist_states;
+        new_snap.undo = old_undo;
+        new_snap.exec_run_count = old_run_count;
+        new_snap.brew = old_brew;
         for ((_, _), old_entry) in existing.into_iter() {
             new_snap.settings.push(old_entry);
         }
@@ -254,7 +804,8 @@ impl Runnable for ApplyCmd {
         }
 
         // save config digest to snapshot
-        new_snap.digest = digest;
+        let config_unchanged = digest.as_ref() == Some(&old_digest);
+        new_snap.digest = digest.unwrap_or(old_digest);
 
         if !dry_run {
             new_snap.save().await?;
@@ -263,26 +814,45 @@ impl Runnable for ApplyCmd {
             log_dry!("Would save snapshot with system preferences.",);
         }
 
-        // run brew
+        drop(prefs_group);
+        self.check_strict("writing preferences")?;
+
+        // run brew, remembering what got installed for `unapply --brew`
+        if self.brew {
+            let _group = group("Homebrew");
+            let installed = BrewInstallCmd::default().install(config).await?;
+
+            if !dry_run && !installed.is_empty() {
+                new_snap.brew.merge(installed);
+                new_snap.save().await?;
+
+                log_info!("Logged Homebrew installs in snapshot.");
+            }
+        }
+
         if self.brew {
-            BrewInstallCmd.run(config).await?;
+            self.check_strict("installing Homebrew software")?;
         }
 
         // exec external commands
-        if !self.no_cmd {
-            let mode = if self.all_cmd {
-                ExecMode::All
-            } else if self.flagged_cmd {
-                ExecMode::Flagged
-            } else {
-                ExecMode::Regular
-            };
-
-            let exec_run_count = core::run_all(config.clone(), mode).await?;
+        if self.idempotent && config_unchanged && exec_mode.is_some() {
+            log_info!("Config unchanged since the last apply, skipping external commands.");
+        } else if let Some(mode) = exec_mode {
+            let _group = group("External commands");
+            let exec_run_count = export_run_count + core::run_all(config.clone(), mode).await?;
 
             if !dry_run {
                 if exec_run_count > 0 {
-                    new_snap.exec_run_count = exec_run_count;
+                    new_snap.exec_run_count += exec_run_count;
+
+                    // commands which ran again replace their earlier undo scripts, and are
+                    // counted once like them
+                    for undo in core::take_undo_log() {
+                        let before = new_snap.undo.len();
+                        new_snap.undo.retain(|u| u.name != undo.name);
+                        new_snap.exec_run_count -= (before - new_snap.undo.len()) as i32;
+                        new_snap.undo.push(undo);
+                    }
                     new_snap.save().await?;
 
                     log_info!("Logged command execution in snapshot.");
@@ -292,7 +862,34 @@ impl Runnable for ApplyCmd {
             }
         }
 
-        log_cute!("Apply operation complete.");
+        // formulae installed for `required` binaries go away with `unapply --brew` too
+        let installed = core::take_installed_formulae();
+        if !dry_run && !installed.is_empty() {
+            new_snap.brew.merge(installed);
+            new_snap.save().await?;
+
+            log_info!("Logged Homebrew installs in snapshot.");
+        }
+
+        report_permissions(&deferred, self.open_settings).await;
+
+        let summary = summary::finish("Apply");
+
+        if !dry_run {
+            if let Some(summary) = &summary {
+                history::record(&config.path, "apply", summary).await;
+            }
+            report_pending(&pending).await;
+        }
+
+        if summary.is_some_and(|s| s.is_partial()) {
+            let message = if self.strict {
+                "Strict mode: apply did not fully succeed."
+            } else {
+                "Apply did not fully succeed."
+            };
+            return Err(coded(ExitCode::PartialApply, message));
+        }
 
         Ok(())
     }
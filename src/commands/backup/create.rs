@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use tokio::{fs, task, try_join};
+
+use crate::{
+    backup::{
+        Bundle,
+        core::{BREW_FILE, BrewState, CONFIG_FILE, MAS_FILE, SNAPSHOT_FILE, backup_entry},
+    },
+    brew::{
+        core::{brew_is_installed, brew_list},
+        types::BrewListType,
+    },
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::core::Config,
+    log_cute, log_dry, log_info, log_warn,
+    mas::core::{mas_is_installed, mas_list},
+    snapshot::{Snapshot, get_snapshot_path},
+};
+
+#[derive(Debug, Args)]
+pub struct BackupCreateCmd {
+    /// Path of the archive to write, e.g. cutler-backup.tar.zst.
+    file: PathBuf,
+
+    /// Don't record installed Homebrew software and Mac App Store apps.
+    #[arg(long)]
+    no_software: bool,
+}
+
+impl BackupCreateCmd {
+    /// Helper for: run()
+    /// Records the installed Homebrew and Mac App Store software into the bundle.
+    async fn add_software(&self, bundle: &mut Bundle) -> Result<()> {
+        if brew_is_installed().await {
+            let (formulae, casks, taps) = try_join!(
+                brew_list(BrewListType::Formula, false),
+                brew_list(BrewListType::Cask, false),
+                brew_list(BrewListType::Tap, false),
+            )?;
+            let state = BrewState {
+                formulae,
+                casks,
+                taps,
+            };
+            bundle.add(BREW_FILE, serde_json::to_vec_pretty(&state)?);
+        } else {
+            log_info!("Homebrew not available in $PATH, not recording it.");
+        }
+
+        if mas_is_installed().await {
+            // sorted for stable output
+            let apps: BTreeMap<String, String> = mas_list().await?.into_iter().collect();
+            bundle.add(MAS_FILE, serde_json::to_vec_pretty(&apps)?);
+        } else {
+            log_info!("mas not available in $PATH, not recording it.");
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Runnable for BackupCreateCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let mut bundle = Bundle::new();
+
+        if config.is_dir() {
+            log_warn!("Config directories can't be bundled, so it won't be part of the backup.");
+        } else if config.is_loadable() {
+            bundle.add(CONFIG_FILE, fs::read(&config.path).await?);
+        } else {
+            log_warn!("No config found, so it won't be part of the backup.");
+        }
+
+        if Snapshot::is_loadable().await {
+            let snap_path = get_snapshot_path().await?;
+            bundle.add(SNAPSHOT_FILE, fs::read(&snap_path).await?);
+
+            // without the originals of managed plist files, a restored snapshot can't undo them
+            let snapshot = Snapshot::load(&snap_path).await?;
+            for backup in snapshot.plists.iter().filter_map(|s| s.backup.as_ref()) {
+                let backup = Path::new(backup);
+                let Some(name) = backup.file_name() else {
+                    continue;
+                };
+
+                match fs::read(backup).await {
+                    Ok(content) => bundle.add(&backup_entry(&name.to_string_lossy()), content),
+                    Err(e) => log_warn!("Could not back up {}: {e}", backup.display()),
+                }
+            }
+        }
+
+        if !self.no_software {
+            self.add_software(&mut bundle).await?;
+        }
+
+        if bundle.files.is_empty() {
+            bail!("Nothing to back up.");
+        }
+
+        let files = bundle.manifest.files.join(", ");
+
+        if should_dry_run() {
+            log_dry!("Would write {files} to {}", self.file.display());
+            return Ok(());
+        }
+
+        let path = self.file.clone();
+        task::spawn_blocking(move || bundle.write(&path)).await??;
+
+        log_cute!("Backed up {files} to {}", self.file.display());
+        Ok(())
+    }
+}
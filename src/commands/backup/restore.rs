@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str,
+};
+use tokio::{fs, task};
+use toml::Table;
+
+use crate::{
+    backup::{
+        Bundle,
+        core::{
+            BACKUPS_DIR, BREW_FILE, BrewState, CONFIG_FILE, MAS_FILE, SNAPSHOT_FILE, backup_entry,
+            backup_file_name,
+        },
+    },
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::core::Config,
+    log_cute, log_dry, log_info, log_warn,
+    snapshot::{Snapshot, get_snapshot_path},
+    util::io::confirm,
+};
+
+#[derive(Debug, Args)]
+pub struct BackupRestoreCmd {
+    /// Path of the archive written by `cutler backup create`.
+    file: PathBuf,
+}
+
+impl BackupRestoreCmd {
+    /// Helper for: run()
+    /// Parses everything in the bundle before anything gets overwritten. The snapshot is
+    /// returned with its plist backups pointing to where they will be restored.
+    async fn validate(bundle: &Bundle, snap_path: &Path) -> Result<Option<Snapshot>> {
+        if let Some(content) = bundle.get(CONFIG_FILE) {
+            str::from_utf8(content)?
+                .parse::<Table>()
+                .context("Backup holds an invalid config")?;
+        }
+        if let Some(content) = bundle.get(BREW_FILE) {
+            serde_json::from_slice::<BrewState>(content)
+                .context("Backup holds an invalid Homebrew record")?;
+        }
+        if let Some(content) = bundle.get(MAS_FILE) {
+            serde_json::from_slice::<BTreeMap<String, String>>(content)
+                .context("Backup holds an invalid Mac App Store record")?;
+        }
+
+        for name in bundle.files.keys() {
+            if name.starts_with(BACKUPS_DIR) && backup_file_name(name).is_none() {
+                bail!("Backup holds an invalid plist backup: {name}")
+            }
+        }
+
+        let Some(content) = bundle.get(SNAPSHOT_FILE) else {
+            return Ok(None);
+        };
+        let mut snapshot = Snapshot::parse(String::from_utf8(content.to_vec())?, snap_path)
+            .await
+            .context("Backup holds an invalid snapshot")?;
+
+        let backups_dir = snap_path
+            .parent()
+            .context("Could not determine snapshot directory")?
+            .join(BACKUPS_DIR);
+        for state in &mut snapshot.plists {
+            let Some(name) = state
+                .backup
+                .as_ref()
+                .and_then(|b| Path::new(b).file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+
+            if bundle.get(&backup_entry(&name)).is_some() {
+                state.backup = Some(backups_dir.join(name).to_string_lossy().into_owned());
+            } else {
+                log_warn!(
+                    "Backup of {} is missing, so it can't be restored on unapply.",
+                    state.path
+                );
+            }
+        }
+
+        Ok(Some(snapshot))
+    }
+
+    /// Helper for: run()
+    /// Asks before overwriting an existing file. Returns whether to go ahead.
+    async fn may_overwrite(target: &Path, name: &str) -> bool {
+        if should_dry_run()
+            || !fs::try_exists(target).await.unwrap_or_default()
+            || confirm(&format!("Overwrite {}?", target.display()))
+        {
+            true
+        } else {
+            log_warn!("Skipping {name}.");
+            false
+        }
+    }
+}
+
+#[async_trait]
+impl Runnable for BackupRestoreCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let dry_run = should_dry_run();
+
+        let path = self.file.clone();
+        let bundle = task::spawn_blocking(move || Bundle::read(&path)).await??;
+
+        log_info!(
+            "Backup created by cutler {} at {} (Unix time), containing: {}",
+            bundle.manifest.version,
+            bundle.manifest.created_at,
+            bundle.manifest.files.join(", ")
+        );
+        if bundle.manifest.version != env!("CARGO_PKG_VERSION") {
+            log_warn!(
+                "Backup was created by cutler {}; some fields may have changed since.",
+                bundle.manifest.version
+            );
+        }
+
+        let snap_path = get_snapshot_path().await?;
+        let snapshot = Self::validate(&bundle, &snap_path).await?;
+        let mut restored = 0;
+
+        if let Some(content) = bundle.get(CONFIG_FILE) {
+            config.ensure_file()?;
+
+            if dry_run {
+                log_dry!("Would restore {CONFIG_FILE} to {}", config.path.display());
+                restored += 1;
+            } else if Self::may_overwrite(&config.path, CONFIG_FILE).await {
+                if let Some(parent) = config.path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                fs::write(&config.path, content).await?;
+                log_info!("Restored {CONFIG_FILE} to {}", config.path.display());
+                restored += 1;
+            }
+        }
+
+        if let Some(snapshot) = snapshot {
+            let backups: Vec<(&str, &[u8])> = bundle
+                .files
+                .iter()
+                .filter_map(|(name, content)| Some((backup_file_name(name)?, content.as_slice())))
+                .collect();
+
+            if dry_run {
+                log_dry!(
+                    "Would restore {SNAPSHOT_FILE} and {} plist backups to {}",
+                    backups.len(),
+                    snap_path.display()
+                );
+                restored += 1;
+            } else if Self::may_overwrite(&snap_path, SNAPSHOT_FILE).await {
+                // the backups go first, so the snapshot never points to missing ones
+                if let Some(dir) = snap_path.parent() {
+                    let dir = dir.join(BACKUPS_DIR);
+                    fs::create_dir_all(&dir).await?;
+                    for (name, content) in &backups {
+                        fs::write(dir.join(name), content).await?;
+                    }
+                }
+
+                snapshot.save().await?;
+                log_info!("Restored {SNAPSHOT_FILE} to {}", snap_path.display());
+                restored += 1;
+            }
+        }
+
+        // installed software is only a record, the restored config decides what gets installed
+        if let Some(content) = bundle.get(BREW_FILE) {
+            let brew: BrewState = serde_json::from_slice(content)?;
+            log_info!(
+                "Recorded Homebrew state: {} formulae, {} casks, {} taps.",
+                brew.formulae.len(),
+                brew.casks.len(),
+                brew.taps.len()
+            );
+        }
+        if let Some(content) = bundle.get(MAS_FILE) {
+            let apps: BTreeMap<String, String> = serde_json::from_slice(content)?;
+            log_info!("Recorded Mac App Store apps: {}", apps.len());
+        }
+
+        if !dry_run && restored > 0 {
+            log_cute!("Backup restored! Run `cutler apply` to bring the system in line.");
+        }
+
+        Ok(())
+    }
+}
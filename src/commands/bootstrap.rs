@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use tokio::process::Command;
+
+use crate::{
+    bootstrap::{Checkpoint, Step},
+    brew::core::ensure_brew,
+    cli::atomic::should_dry_run,
+    commands::{ApplyCmd, BrewInstallCmd, Runnable},
+    config::{
+        core::Config,
+        remote::{RemoteConfigManager, RemoteLayers},
+    },
+    exec::core::{self, ExecMode, extract_all_cmds},
+    log_cute, log_dry, log_info, log_warn,
+    mas::core::{install_missing, mas_is_installed},
+    snapshot::{Snapshot, get_snapshot_path},
+    util::{
+        exit::{ExitCode, coded},
+        io::confirm,
+        logging::group,
+        net::ensure_online,
+        summary,
+    },
+};
+
+#[derive(Args, Debug)]
+pub struct BootstrapCmd {
+    /// The URL to the remote config file, for machines without a config yet.
+    #[arg(short, long)]
+    url: Option<String>,
+
+    /// Start over instead of resuming an unfinished bootstrap.
+    #[arg(long)]
+    restart: bool,
+
+    /// Leave out a step (repeatable).
+    #[arg(long, value_name = "STEP")]
+    skip: Vec<Step>,
+}
+
+impl BootstrapCmd {
+    /// Helper for: run()
+    /// Fetches the config from --url or [remote], if there's any.
+    async fn fetch(&self, config: &mut Config) -> Result<()> {
+        if let Some(url) = &self.url {
+            if config.is_loadable()
+                && !confirm("Local config exists but a URL was still passed. Overwrite it?")
+            {
+                bail!("Aborted bootstrap: --url is passed despite local config.")
+            }
+
+            let remote_mgr = RemoteConfigManager::new(url.to_owned());
+            remote_mgr.fetch().await?;
+            if should_dry_run() {
+                log_dry!("Would save remote config to {:?}", config.path);
+            } else {
+                remote_mgr.save().await?;
+            }
+
+            return Ok(());
+        }
+
+        if !config.is_loadable() {
+            bail!("No config found. Pass --url to fetch one.")
+        }
+
+        config.load(true).await?;
+        let Some(remotes) = config.remote.clone() else {
+            log_info!("No [remote] in config, using it as is.");
+            return Ok(());
+        };
+
+        let layers = RemoteLayers::new(&remotes);
+        layers.fetch().await?;
+        if should_dry_run() {
+            log_dry!("Would save remote config to {:?}", config.path);
+        } else {
+            layers.save(&remotes).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    /// Installs [brew], remembering what got installed for `unapply --brew`.
+    async fn brew(&self, config: &mut Config) -> Result<()> {
+        // a snapshot which can't be read still holds the undo state, so never replace it, and
+        // find out before installing anything which then couldn't be recorded
+        let mut snap = if Snapshot::is_loadable().await {
+            Snapshot::load(&get_snapshot_path().await?)
+                .await
+                .context("Could not load the snapshot to record Homebrew installs in")?
+        } else {
+            Snapshot::new().await
+        };
+
+        let installed = BrewInstallCmd::default().install(config).await?;
+
+        if !should_dry_run() && !installed.is_empty() {
+            snap.brew.merge(installed);
+            snap.save().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    /// Installs [mas], along with the `mas` CLI itself.
+    async fn mas(&self, config: &Config) -> Result<()> {
+        let Some(mas) = &config.mas else {
+            return Ok(());
+        };
+
+        if !mas_is_installed().await {
+            if should_dry_run() {
+                log_dry!("Would install mas through Homebrew");
+                return Ok(());
+            }
+
+            ensure_online("install mas")?;
+            log_info!("Installing mas...");
+            let status = Command::new("brew")
+                .args(["install", "mas"])
+                .status()
+                .await?;
+            if !status.success() {
+                bail!("Could not install mas through Homebrew.")
+            }
+        }
+
+        summary::begin();
+        install_missing(mas).await?;
+
+        if summary::finish("App Store").is_some_and(|s| s.is_partial()) {
+            return Err(coded(
+                ExitCode::PartialApply,
+                "Not every App Store app could be installed.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    /// Runs the flagged external commands, meant for one-time setup.
+    async fn commands(&self, config: &Config) -> Result<()> {
+        if !extract_all_cmds(config).iter().any(|job| job.is_flagged()) {
+            log_info!("No flagged external commands to run.");
+            return Ok(());
+        }
+
+        summary::begin();
+        core::run_all(config.clone(), ExecMode::Flagged).await?;
+
+        if summary::finish("Flagged commands").is_some_and(|s| s.is_partial()) {
+            return Err(coded(
+                ExitCode::PartialApply,
+                "Not every flagged command succeeded.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    async fn step(&self, step: Step, config: &mut Config) -> Result<()> {
+        if step != Step::Fetch {
+            // a dry run doesn't save the fetched config
+            if should_dry_run() && !config.is_loadable() {
+                log_dry!("Would continue with the fetched config.");
+                return Ok(());
+            }
+            config.load(true).await?;
+        }
+
+        match step {
+            Step::Fetch => self.fetch(config).await,
+            Step::Homebrew if config.brew.is_some() || config.mas.is_some() => ensure_brew().await,
+            Step::Brew if config.brew.is_some() => self.brew(config).await,
+            Step::Mas => self.mas(config).await,
+            Step::Apply => ApplyCmd::default().run(config).await,
+            Step::Commands => self.commands(config).await,
+            Step::Homebrew | Step::Brew => {
+                log_info!("No [brew] in config, nothing to install.");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Runnable for BootstrapCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let dry_run = should_dry_run();
+
+        let mut checkpoint = if self.restart {
+            Checkpoint::default()
+        } else {
+            Checkpoint::load(&config.path).await.unwrap_or_default()
+        };
+
+        if let Some(next) = checkpoint.next()
+            && !checkpoint.done.is_empty()
+        {
+            log_cute!("Resuming bootstrap from `{next}`. Pass --restart to start over.");
+        }
+
+        for step in Step::ALL {
+            if checkpoint.is_done(step) {
+                log_info!("Skipping `{step}`, done in an earlier run.");
+                continue;
+            }
+            if self.skip.contains(&step) {
+                log_warn!("Skipping `{step}` as requested.");
+                continue;
+            }
+
+            let _group = group(&format!("Bootstrap: {step}"));
+            log_cute!("Bootstrap: {step}");
+
+            if let Err(e) = self.step(step, config).await {
+                let message = format!(
+                    "Bootstrap failed at `{step}`: {e}\nFix the problem and run `cutler bootstrap` again to resume from there."
+                );
+                return Err(e.context(message));
+            }
+
+            checkpoint.mark_done(step);
+            if !dry_run {
+                checkpoint.save(&config.path).await?;
+            }
+        }
+
+        if !dry_run {
+            Checkpoint::delete(&config.path).await?;
+        }
+
+        log_cute!("Bootstrap complete.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+use tokio::process::Command;
+
+use crate::{
+    brew::core::{autoremovable, brew_info, ensure_brew},
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::core::Config,
+    log_cute, log_dry, log_err, log_info, log_warn,
+    util::io::confirm,
+};
+
+#[derive(Debug, Args)]
+pub struct BrewAutoremoveCmd;
+
+#[async_trait]
+impl Runnable for BrewAutoremoveCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        config.load(true).await?;
+        ensure_brew().await?;
+
+        autoremove(config).await
+    }
+}
+
+/// Uninstalls formulae which were installed as dependencies and aren't needed anymore.
+/// Formulae declared in config (and everything they depend on) are never removed.
+pub async fn autoremove(config: &Config) -> Result<()> {
+    let config_formulae: Vec<String> = config
+        .brew
+        .as_ref()
+        .and_then(|b| b.formulae.as_ref())
+        .map(|f| f.iter().map(|f| f.name().to_string()).collect())
+        .unwrap_or_default();
+
+    let info = brew_info().await?;
+    let removable = autoremovable(&info, &config_formulae);
+
+    if removable.is_empty() {
+        log_cute!("No unneeded dependencies to remove.");
+        return Ok(());
+    }
+
+    log_warn!("Unneeded dependencies: {}", removable.join(", "));
+
+    if should_dry_run() {
+        log_dry!("Would uninstall {} formulae.", removable.len());
+        return Ok(());
+    }
+
+    if !confirm("Uninstall them?") {
+        log_info!("Skipping dependency cleanup.");
+        return Ok(());
+    }
+
+    let status = Command::new("brew")
+        .arg("uninstall")
+        .arg("--formula")
+        .args(&removable)
+        .status()
+        .await?;
+
+    if status.success() {
+        log_cute!("Removed {} unneeded formulae.", removable.len());
+    } else {
+        log_err!("Failed to uninstall some dependencies.");
+    }
+
+    Ok(())
+}
@@ -3,16 +3,20 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Args;
-use toml_edit::{Array, DocumentMut, Item, Table, value};
+use std::collections::{HashMap, HashSet};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, value};
 
 use crate::{
     brew::{
-        core::{brew_list, ensure_brew},
+        core::{brew_info, brew_list, ensure_brew},
         types::BrewListType,
     },
     cli::atomic::should_dry_run,
     commands::Runnable,
-    config::core::{Config, ConfigCoreMethods},
+    config::{
+        core::{BREW_ARCHS, Config, ConfigCoreMethods},
+        git,
+    },
     log_cute, log_dry, log_info, log_warn,
     util::io::confirm,
 };
@@ -24,6 +28,54 @@ pub struct BrewBackupCmd {
     no_deps: bool,
 }
 
+/// Helper for: run()
+/// Names listed in the per-architecture tables, which stay there instead of moving into the
+/// main lists.
+fn arch_names(brew_tbl: &Table) -> HashSet<String> {
+    let mut names = HashSet::new();
+
+    for arch in BREW_ARCHS {
+        for list in ["formulae", "casks"] {
+            let Some(arr) = brew_tbl
+                .get(arch)
+                .and_then(|t| t.get(list))
+                .and_then(|l| l.as_array())
+            else {
+                continue;
+            };
+
+            for entry in arr {
+                let name = entry
+                    .as_str()
+                    .or_else(|| entry.as_inline_table()?.get("name")?.as_str());
+                if let Some(name) = name {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// Helper for: run()
+/// Detailed entries of a list, e.g. pinned formulae or taps with a remote, keyed by name.
+fn detailed_entries(brew_tbl: &Table, list: &str) -> HashMap<String, InlineTable> {
+    brew_tbl
+        .get(list)
+        .and_then(|l| l.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_inline_table())
+                .filter_map(|t| {
+                    let name = t.get("name")?.as_str()?;
+                    Some((name.to_string(), t.clone()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 #[async_trait]
 impl Runnable for BrewBackupCmd {
     async fn run(&self, conf: &mut Config) -> Result<()> {
@@ -34,6 +86,7 @@ impl Runnable for BrewBackupCmd {
         ensure_brew().await?;
 
         // init config
+        conf.ensure_file()?;
         let mut doc = match conf.load_as_mut(true).await {
             Ok(doc) => doc,
             Err(_) => {
@@ -64,20 +117,47 @@ impl Runnable for BrewBackupCmd {
             brew_tbl["no_deps"] = Item::None;
         }
 
+        // read the installed state once; all names are full names
+        let info = brew_info().await?;
+
         // load deps into memory for comparison
         // this will also be reused for later comparisons
         let deps = if backup_no_deps {
-            brew_list(BrewListType::Dependency, false).await?
+            info.list(BrewListType::Dependency)
         } else {
             vec![]
         };
 
-        // load the formulae, casks and taps list from the `brew` command
+        // load the formulae, casks and taps list
         // flattening is `false` since we want all names to be forced to --full-name
-        let formulas = brew_list(BrewListType::Formula, false).await?;
-        let casks = brew_list(BrewListType::Cask, false).await?;
+        let arch_only = arch_names(brew_tbl);
+        let in_arch_table = |name: &String| {
+            let short = name.rsplit('/').next().unwrap_or(name);
+            arch_only.contains(name) || arch_only.contains(short)
+        };
+        let formulas: Vec<String> = info
+            .list(BrewListType::Formula)
+            .into_iter()
+            .filter(|f| !in_arch_table(f))
+            .collect();
+        let casks: Vec<String> = info
+            .list(BrewListType::Cask)
+            .into_iter()
+            .filter(|c| !in_arch_table(c))
+            .collect();
         let taps = brew_list(BrewListType::Tap, false).await?;
 
+        // detailed formula and tap entries (e.g. pinned ones) are kept as they are
+        let detailed = detailed_entries(brew_tbl, "formulae");
+        let detailed_taps = detailed_entries(brew_tbl, "taps");
+        let formula_entry = |formula: &String| -> toml_edit::Value {
+            let short = formula.rsplit('/').next().unwrap_or(formula);
+            match detailed.get(formula).or_else(|| detailed.get(short)) {
+                Some(tbl) => tbl.clone().into(),
+                None => formula.into(),
+            }
+        };
+
         // build formulae and casks arrays
         let mut formula_arr = Array::new();
         for formula in &formulas {
@@ -87,14 +167,14 @@ impl Runnable for BrewBackupCmd {
                         log_dry!("Would push {formula} as a manually installed formula.",);
                     } else {
                         log_info!("Pushing {formula} as a manually installed formula.",);
-                        formula_arr.push(formula.clone());
+                        formula_arr.push(formula_entry(formula));
                     }
                 }
             } else if dry_run {
                 log_dry!("Would push {formula}");
             } else {
                 log_info!("Pushing {formula}");
-                formula_arr.push(formula.clone());
+                formula_arr.push(formula_entry(formula));
             }
         }
         log_info!("Pushed {} formulae.", formula_arr.len());
@@ -128,7 +208,10 @@ impl Runnable for BrewBackupCmd {
                 log_dry!("Would push {tap} as tap.");
             } else {
                 log_info!("Pushed {tap} as a tap.");
-                taps_arr.push(tap.clone());
+                match detailed_taps.get(tap) {
+                    Some(tbl) => taps_arr.push(tbl.clone()),
+                    None => taps_arr.push(tap.clone()),
+                }
             }
         }
         log_info!("Pushed {} taps.", taps_arr.len());
@@ -139,6 +222,18 @@ impl Runnable for BrewBackupCmd {
             doc.save(&conf.path).await?;
 
             log_cute!("Done!");
+
+            let count = |name: &str| doc["brew"][name].as_array().map_or(0, |a| a.len());
+            let message = format!(
+                "cutler: back up Homebrew ({} formulae, {} casks, {} taps)",
+                count("formulae"),
+                count("casks"),
+                count("taps")
+            );
+            if let Ok(mut updated) = toml::from_str::<Config>(&doc.to_string()) {
+                updated.path = conf.path.clone();
+                git::auto_commit(&updated, &message).await;
+            }
         } else {
             log_info!("Backup would be saved to {:?}", &conf.path);
         }
@@ -3,37 +3,74 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Args;
-use tokio::process::Command;
+use std::sync::Arc;
+use tokio::{process::Command, sync::Semaphore, task};
 
 use crate::{
     brew::{
-        core::{diff_brew, ensure_brew},
+        core::{checkout_tap, diff_brew, ensure_brew},
         types::BrewDiff,
     },
     cli::atomic::{should_be_quiet, should_dry_run},
-    commands::Runnable,
-    config::core::Config,
+    commands::{Runnable, brew::autoremove::autoremove},
+    config::core::{Config, Tap},
     log_cute, log_dry, log_err, log_info, log_warn,
+    snapshot::core::BrewChanges,
+    util::{
+        exit::{ExitCode, coded},
+        limits,
+        net::ensure_online,
+        summary,
+    },
 };
 
-#[derive(Debug, Args)]
-pub struct BrewInstallCmd;
+/// The default amount of concurrent `brew fetch` jobs.
+pub const DEFAULT_BREW_JOBS: usize = 4;
+
+#[derive(Debug, Args, Default)]
+pub struct BrewInstallCmd {
+    /// Remove unneeded dependencies after installing (overrides `autoremove` in [brew]).
+    #[arg(long)]
+    pub autoremove: bool,
+}
 
 #[async_trait]
 impl Runnable for BrewInstallCmd {
     async fn run(&self, config: &mut Config) -> Result<()> {
+        self.install(config).await?;
+        Ok(())
+    }
+}
+
+impl BrewInstallCmd {
+    /// Installs everything missing from [brew].
+    /// Returns what was newly installed, so that `apply --brew` can record it in the snapshot.
+    pub async fn install(&self, config: &mut Config) -> Result<BrewChanges> {
         let dry_run = should_dry_run();
+        let mut changes = BrewChanges::default();
 
         config.load(true).await?;
+        summary::begin();
 
         let brew_cfg = config
             .brew
             .clone()
             .ok_or_else(|| anyhow::anyhow!("No [brew] section found in config"))?;
 
+        // the global --jobs overrides `jobs` in [brew]
+        let jobs = limits::cli_jobs()
+            .or(brew_cfg.jobs)
+            .unwrap_or(DEFAULT_BREW_JOBS)
+            .max(1);
+        let parallel_casks = brew_cfg.parallel_casks.unwrap_or_default();
+        let should_autoremove = self.autoremove || brew_cfg.autoremove.unwrap_or_default();
+
         // ensure homebrew installation
         ensure_brew().await?;
 
+        // keep the tap entries around for their remotes and pinned revisions
+        let taps = brew_cfg.taps.clone().unwrap_or_default();
+
         // check the current brew state, including taps, formulae, and casks
         let brew_diff = match diff_brew(brew_cfg).await {
             Ok(diff) => {
@@ -61,60 +98,148 @@ impl Runnable for BrewInstallCmd {
             }
             Err(e) => {
                 log_err!("Could not check Homebrew status: {e}",);
+                summary::add_failure("Homebrew status");
                 // If we cannot compare the state, treat as if nothing is missing.
-                BrewDiff {
-                    missing_formulae: vec![],
-                    extra_formulae: vec![],
-                    missing_casks: vec![],
-                    extra_casks: vec![],
-                    missing_taps: vec![],
-                    extra_taps: vec![],
-                }
+                BrewDiff::default()
             }
         };
 
+        let needs_network = !brew_diff.missing_taps.is_empty()
+            || !brew_diff.drifted_taps.is_empty()
+            || !brew_diff.missing_formulae.is_empty()
+            || !brew_diff.missing_casks.is_empty();
+        if needs_network && !dry_run {
+            ensure_online("install Homebrew packages")?;
+        }
+
         // tap only the missing taps reported by BrewDiff
-        if !brew_diff.missing_taps.is_empty() {
-            for tap in brew_diff.missing_taps.iter() {
-                if dry_run {
-                    log_dry!("Would tap {tap}");
-                } else {
-                    log_info!("Tapping: {tap}");
-                    let status = Command::new("brew").arg("tap").arg(tap).status().await?;
-
-                    if !status.success() {
-                        log_err!("Failed to tap: {tap}");
+        for name in brew_diff.missing_taps.iter() {
+            let tap = taps
+                .iter()
+                .find(|t| t.name() == name)
+                .cloned()
+                .unwrap_or_else(|| Tap::from(name.as_str()));
+
+            if dry_run {
+                match tap.url() {
+                    Some(url) => log_dry!("Would tap {name} from {url}"),
+                    None => log_dry!("Would tap {name}"),
+                }
+                if let Some(revision) = tap.revision() {
+                    log_dry!("Would check out {name} at {revision}");
+                }
+                continue;
+            }
+
+            log_info!("Tapping: {name}");
+            let status = Command::new("brew")
+                .arg("tap")
+                .arg(name)
+                .args(tap.url())
+                .status()
+                .await?;
+
+            if !status.success() {
+                log_err!("Failed to tap: {name}");
+                summary::add_failure(format!("tap {name}"));
+                continue;
+            }
+
+            changes.taps.push(name.clone());
+            match tap.revision() {
+                Some(revision) => match checkout_tap(name, revision).await {
+                    Ok(()) => summary::add_applied(1),
+                    Err(e) => {
+                        log_err!("{e}");
+                        summary::add_failure(format!("tap {name} at {revision}"));
                     }
+                },
+                None => summary::add_applied(1),
+            }
+        }
+
+        // move taps which drifted off their pinned revision back to it
+        for drift in brew_diff.drifted_taps.iter() {
+            if dry_run {
+                log_dry!("Would check out {} at {}", drift.name, drift.revision);
+                continue;
+            }
+
+            log_info!("Checking out {} at {}", drift.name, drift.revision);
+            match checkout_tap(&drift.name, &drift.revision).await {
+                Ok(()) => summary::add_applied(1),
+                Err(e) => {
+                    log_err!("{e}");
+                    summary::add_failure(format!("tap {} at {}", drift.name, drift.revision));
                 }
             }
         }
 
         if !brew_diff.missing_formulae.is_empty() || !brew_diff.missing_casks.is_empty() {
             log_info!("Pre-downloading all formulae and casks...");
+
+            // handle all of dry-run in this single block
+            if dry_run {
+                brew_diff.missing_formulae.iter().for_each(|formula| {
+                    log_dry!("Would fetch formula: {formula}");
+                });
+                brew_diff.missing_casks.iter().for_each(|cask| {
+                    log_dry!("Would fetch cask: {cask}");
+                });
+            } else {
+                let fetched =
+                    fetch_all(&brew_diff.missing_formulae, &brew_diff.missing_casks, jobs).await;
+
+                // install only the successfully fetched items
+                // formulae are always installed sequentially since they can share dependencies
+                changes.formulae = install_all(fetched.formulae, false, 1).await?;
+                changes.casks =
+                    install_all(fetched.casks, true, if parallel_casks { jobs } else { 1 }).await?;
+            }
         } else {
             log_cute!("No formulae or casks to download/install.");
-            return Ok(());
         }
 
-        // handle all of dry-run in this single block
-        if dry_run {
-            brew_diff.missing_formulae.iter().for_each(|formula| {
-                log_dry!("Would fetch formula: {formula}");
-            });
-            brew_diff.missing_casks.iter().for_each(|cask| {
-                log_dry!("Would fetch cask: {cask}");
-            });
-            return Ok(());
+        // pin formulae marked with `pin = true`
+        pin_all(&brew_diff.unpinned_formulae, dry_run).await?;
+
+        // clean up unneeded dependencies
+        if should_autoremove {
+            autoremove(config).await?;
+        }
+
+        if summary::finish("Brew install").is_some_and(|s| s.is_partial()) {
+            return Err(coded(
+                ExitCode::PartialApply,
+                "Brew install did not fully succeed.",
+            ));
         }
 
-        let fetched = fetch_all(&brew_diff.missing_formulae, &brew_diff.missing_casks).await;
+        Ok(changes)
+    }
+}
+
+/// Pins the given formulae so that `brew upgrade` leaves them alone.
+async fn pin_all(formulae: &[String], dry_run: bool) -> Result<()> {
+    for formula in formulae {
+        if dry_run {
+            log_dry!("Would pin {formula}");
+            continue;
+        }
 
-        // sequentially install only the successfully fetched items
-        install_all(fetched.formulae, false).await?;
-        install_all(fetched.casks, true).await?;
+        log_info!("Pinning: {formula}");
+        let status = Command::new("brew")
+            .arg("pin")
+            .arg(formula)
+            .status()
+            .await?;
 
-        Ok(())
+        if !status.success() {
+            log_err!("Failed to pin: {formula}");
+            summary::add_failure(format!("pin {formula}"));
+        }
     }
+    Ok(())
 }
 
 /// Represents the result of fetching formulae and casks.
@@ -123,10 +248,11 @@ pub struct FetchedThings {
     pub casks: Vec<String>,
 }
 
-/// Downloads all formulae/casks before installation, sequentially.
+/// Downloads all formulae/casks before installation, with at most `jobs` downloads at once.
 /// Returns only the successfully fetched formulae and casks.
-async fn fetch_all(formulae: &[String], casks: &[String]) -> FetchedThings {
+async fn fetch_all(formulae: &[String], casks: &[String], jobs: usize) -> FetchedThings {
     let quiet = should_be_quiet();
+    let semaphore = Arc::new(Semaphore::new(jobs));
 
     // create new vectors
     let mut fetched_formulae = Vec::new();
@@ -134,37 +260,44 @@ async fn fetch_all(formulae: &[String], casks: &[String]) -> FetchedThings {
     let mut failed_formulae = Vec::new();
     let mut failed_casks = Vec::new();
 
-    // fetch formulae sequentially
-    for name in formulae {
-        let mut cmd = Command::new("brew");
-        cmd.arg("fetch").arg(name);
+    let targets = formulae
+        .iter()
+        .map(|f| (f.clone(), false))
+        .chain(casks.iter().map(|c| (c.clone(), true)));
 
-        if !quiet {
-            log_info!("Fetching formula: {name}");
-        } else {
-            cmd.arg("--quiet");
-        }
+    let mut handles = Vec::new();
+    for (name, cask) in targets {
+        let semaphore = semaphore.clone();
 
-        match cmd.status().await {
-            Ok(status) if status.success() => fetched_formulae.push(name.clone()),
-            _ => failed_formulae.push(name.clone()),
-        }
-    }
+        handles.push(task::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let _slot = limits::acquire().await;
 
-    // fetch casks sequentially
-    for name in casks {
-        let mut cmd = Command::new("brew");
-        cmd.arg("fetch").arg("--cask").arg(name);
+            let mut cmd = Command::new("brew");
+            cmd.arg("fetch");
+            if cask {
+                cmd.arg("--cask");
+            }
+            cmd.arg(&name);
 
-        if !quiet {
-            log_info!("Fetching cask: {name}");
-        } else {
-            cmd.arg("--quiet");
-        }
+            if !quiet {
+                log_info!("Fetching {}: {name}", if cask { "cask" } else { "formula" });
+            } else {
+                cmd.arg("--quiet");
+            }
+
+            let ok = matches!(cmd.status().await, Ok(status) if status.success());
+            (name, cask, ok)
+        }));
+    }
 
-        match cmd.status().await {
-            Ok(status) if status.success() => fetched_casks.push(name.clone()),
-            _ => failed_casks.push(name.clone()),
+    for handle in handles {
+        match handle.await {
+            Ok((name, false, true)) => fetched_formulae.push(name),
+            Ok((name, true, true)) => fetched_casks.push(name),
+            Ok((name, false, false)) => failed_formulae.push(name),
+            Ok((name, true, false)) => failed_casks.push(name),
+            Err(e) => log_err!("Fetch task panicked: {e}"),
         }
     }
 
@@ -178,6 +311,9 @@ async fn fetch_all(formulae: &[String], casks: &[String]) -> FetchedThings {
     if !failed_formulae.is_empty() || !failed_casks.is_empty() {
         log_warn!("Some software failed to download and won't be installed.",);
     }
+    for name in failed_formulae.iter().chain(&failed_casks) {
+        summary::add_failure(format!("fetch {name}"));
+    }
 
     FetchedThings {
         formulae: fetched_formulae,
@@ -185,22 +321,45 @@ async fn fetch_all(formulae: &[String], casks: &[String]) -> FetchedThings {
     }
 }
 
-/// Install formulae/casks sequentially.
+/// Install formulae/casks, with at most `jobs` installations at once.
 /// The argument is a vector of argslices, representing the arguments to the `brew install` subcommand.
-async fn install_all(install_tasks: Vec<String>, cask: bool) -> anyhow::Result<()> {
+/// Returns the successfully installed ones.
+async fn install_all(
+    install_tasks: Vec<String>,
+    cask: bool,
+    jobs: usize,
+) -> anyhow::Result<Vec<String>> {
+    let semaphore = Arc::new(Semaphore::new(jobs));
+    let mut handles = Vec::new();
+
     for task in install_tasks {
-        log_info!("Installing: {task}");
+        let semaphore = semaphore.clone();
 
-        let status = Command::new("brew")
-            .arg("install")
-            .arg(if cask { "--cask" } else { "--formula" })
-            .arg(&task)
-            .status()
-            .await?;
+        handles.push(task::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let _slot = limits::acquire().await;
+            log_info!("Installing: {task}");
 
-        if !status.success() {
-            log_err!("Failed to install: {task}");
-        }
+            let status = Command::new("brew")
+                .arg("install")
+                .arg(if cask { "--cask" } else { "--formula" })
+                .arg(&task)
+                .status()
+                .await?;
+
+            if status.success() {
+                summary::add_applied(1);
+            } else {
+                log_err!("Failed to install: {task}");
+                summary::add_failure(format!("install {task}"));
+            }
+            anyhow::Ok(status.success().then_some(task))
+        }));
     }
-    Ok(())
+
+    let mut installed = Vec::new();
+    for handle in handles {
+        installed.extend(handle.await??);
+    }
+    Ok(installed)
 }
@@ -5,7 +5,6 @@ use std::cmp::Ordering;
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use clap::Args;
-use reqwest;
 use semver::Version;
 
 use crate::{
@@ -13,7 +12,11 @@ use crate::{
     commands::Runnable,
     config::core::Config,
     log_cute, log_info,
-    util::logging::{BOLD, RESET},
+    util::{
+        logging::{BOLD, RESET, paint},
+        net::ensure_online,
+        release::{fetch_releases, latest},
+    },
 };
 
 #[derive(Args, Debug)]
@@ -21,45 +24,39 @@ pub struct CheckUpdateCmd;
 
 #[async_trait]
 impl Runnable for CheckUpdateCmd {
-    async fn run(&self, _: &mut Config) -> Result<()> {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        // only needed for [network]; a broken config shouldn't get in the way of updates
+        if config.is_loadable() {
+            let _ = config.load(false).await;
+        }
+
         let current_version = env!("CARGO_PKG_VERSION");
 
         log_info!("Current version: {current_version}",);
+        ensure_online("check for updates")?;
+
+        let channel = config
+            .update
+            .as_ref()
+            .and_then(|u| u.channel)
+            .unwrap_or_default();
+
+        // fetch the latest release on the channel from GitHub API
+        let releases = fetch_releases("cutler-update-check").await?;
+        let latest = latest(&releases, channel)
+            .and_then(|r| r.version())
+            .ok_or_else(|| anyhow!("Could not find a {channel} release on GitHub"))?;
+        let latest_version = latest.to_string();
 
-        // fetch latest release tag from GitHub API
-        let url = "https://api.github.com/repos/machlit/cutler/releases/latest";
-        let client = reqwest::Client::builder()
-            .user_agent("cutler-update-check")
-            .build()
-            .expect("Failed to build request client");
-        let resp = client
-            .get(url)
-            .header("Accept", "application/vnd.github.v3+json")
-            .send()
-            .await
-            .with_context(|| format!("Failed to fetch latest GitHub release: {url}"))?;
-        let body = resp.text().await?;
-        let json: serde_json::Value = serde_json::from_str(&body)
-            .map_err(|e| anyhow!("Failed to parse GitHub API response: {}", e))?;
-
-        // try "tag_name" first, fallback to "name"
-        let latest_version = json
-            .get("tag_name")
-            .and_then(|v| v.as_str())
-            .or_else(|| json.get("name").and_then(|v| v.as_str()))
-            .map(|s| s.trim_start_matches('v').to_string())
-            .ok_or_else(|| anyhow!("Could not find latest version tag in GitHub API response"))?;
-
-        log_info!("Latest version: {latest_version}");
+        log_info!("Latest version: {latest_version} ({channel} channel)");
 
         // let the comparison begin!
         let current = Version::parse(current_version).context("Could not parse current version")?;
-        let latest = Version::parse(&latest_version).context("Could not parse latest version")?;
 
         match current.cmp(&latest) {
             Ordering::Less => {
                 if !should_be_quiet() {
-                    println!(
+                    let notice = format!(
                         r#"
 {BOLD}Update available:{RESET} {current_version} → {latest_version}
 
@@ -73,6 +70,7 @@ To update, run one of the following:
 Or download the latest release from:
   https://github.com/machlit/cutler/releases"#
                     );
+                    println!("{}", paint(&notice));
                 } else {
                     log_cute!("Update available!")
                 }
@@ -1,16 +1,24 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
 use clap::{Args, CommandFactory};
 use clap_complete::{
     generate,
     shells::{Bash, Elvish, Fish, PowerShell, Zsh},
 };
-use std::io;
-use tokio::task;
+use clap_complete_nushell::Nushell;
+use std::{
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tokio::{fs, task};
 
-use crate::{commands::Runnable, config::core::Config};
+use crate::{
+    cli::atomic::should_dry_run, commands::Runnable, config::core::Config, log_cute, log_dry,
+    log_warn,
+};
 
 /// Represents the shell types to generate completions for.
 #[derive(Copy, Clone, PartialEq, Eq, clap::ValueEnum, Debug)]
@@ -20,33 +28,197 @@ pub enum Shell {
     Fish,
     Elvish,
     PowerShell,
+    #[value(name = "nu", alias = "nushell")]
+    Nushell,
+}
+
+impl Shell {
+    /// Tells the shell from its path, e.g. `$SHELL`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Path::new(path).file_name()?.to_str()? {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "elvish" => Some(Shell::Elvish),
+            "pwsh" | "powershell" => Some(Shell::PowerShell),
+            "nu" => Some(Shell::Nushell),
+            _ => None,
+        }
+    }
+}
+
+/// The directories completion files are installed into.
+#[derive(Debug)]
+pub struct InstallDirs {
+    pub home: PathBuf,
+    /// `$XDG_CONFIG_HOME`, or `~/.config`.
+    pub config: PathBuf,
+    /// `$XDG_DATA_HOME`, or `~/.local/share`.
+    pub data: PathBuf,
+    pub brew_prefix: Option<PathBuf>,
+}
+
+impl InstallDirs {
+    pub fn detect() -> Option<Self> {
+        let home = dirs::home_dir()?;
+        let xdg = |var: &str, default: &str| match env::var_os(var) {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home.join(default),
+        };
+
+        let brew_prefix = env::var_os("HOMEBREW_PREFIX")
+            .filter(|prefix| !prefix.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                ["/opt/homebrew", "/usr/local"]
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .find(|prefix| prefix.join("bin/brew").exists())
+            });
+
+        Some(Self {
+            config: xdg("XDG_CONFIG_HOME", ".config"),
+            data: xdg("XDG_DATA_HOME", ".local/share"),
+            home,
+            brew_prefix,
+        })
+    }
+
+    /// Where the shell picks up completions by itself. None for shells which only load them
+    /// from their startup files.
+    pub fn path(&self, shell: Shell) -> Option<PathBuf> {
+        match shell {
+            Shell::Zsh => Some(match &self.brew_prefix {
+                Some(prefix) => prefix.join("share/zsh/site-functions/_cutler"),
+                None => self.home.join(".zfunc/_cutler"),
+            }),
+            Shell::Fish => Some(self.config.join("fish/completions/cutler.fish")),
+            // loaded on demand by bash-completion 2
+            Shell::Bash => Some(self.data.join("bash-completion/completions/cutler")),
+            Shell::Elvish | Shell::PowerShell | Shell::Nushell => None,
+        }
+    }
+
+    /// What to add to `~/.zshrc` for zsh to find the installed completions, given the path of
+    /// the running zsh. Apple's `/bin/zsh` doesn't look in Homebrew's `site-functions`, only the
+    /// zsh installed through Homebrew does.
+    pub fn zsh_fpath_hint(&self, zsh: Option<&str>) -> Option<String> {
+        match &self.brew_prefix {
+            None => Some("~/.zfunc".into()),
+            Some(prefix) if !zsh.is_some_and(|zsh| Path::new(zsh).starts_with(prefix)) => Some(
+                prefix
+                    .join("share/zsh/site-functions")
+                    .display()
+                    .to_string(),
+            ),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Whether the file at `path` was installed by a Homebrew formula, i.e. is a symlink into the
+/// Cellar. Writing to it would change the files of the formula.
+pub fn is_brew_managed(path: &Path) -> bool {
+    path.is_symlink()
+        && std::fs::read_link(path)
+            .is_ok_and(|target| target.components().any(|c| c.as_os_str() == "Cellar"))
 }
 
 #[derive(Args, Debug)]
 pub struct CompletionCmd {
-    /// Your shell type.
+    /// Your shell type. Detected from $SHELL if left out.
     #[arg(value_enum)]
-    shell: Shell,
+    shell: Option<Shell>,
+
+    /// Install the completions where your shell picks them up, instead of printing them.
+    #[arg(long)]
+    install: bool,
+}
+
+/// Helper for: CompletionCmd::run()
+/// Generates the completion script of a shell.
+fn render(shell: Shell) -> Vec<u8> {
+    let mut cmd = crate::cli::Args::command();
+    let name = cmd.get_name().to_string();
+    let mut script = Vec::new();
+
+    match shell {
+        Shell::Bash => generate(Bash, &mut cmd, name, &mut script),
+        Shell::Zsh => generate(Zsh, &mut cmd, name, &mut script),
+        Shell::Fish => generate(Fish, &mut cmd, name, &mut script),
+        Shell::PowerShell => generate(PowerShell, &mut cmd, name, &mut script),
+        Shell::Elvish => generate(Elvish, &mut cmd, name, &mut script),
+        Shell::Nushell => generate(Nushell, &mut cmd, name, &mut script),
+    };
+
+    script
+}
+
+impl CompletionCmd {
+    /// Helper for: run()
+    /// Writes the completion script into the standard location of the shell.
+    async fn install(&self, shell: Shell, script: Vec<u8>) -> Result<()> {
+        let dirs = InstallDirs::detect().context("Could not determine home directory")?;
+        let Some(path) = dirs.path(shell) else {
+            bail!(
+                "{shell:?} has no standard place for completions. Load the output of `cutler completion` from your shell's config instead."
+            )
+        };
+
+        if is_brew_managed(&path) {
+            log_cute!("Homebrew already installed completions to {path:?}, leaving them alone.");
+        } else if should_dry_run() {
+            log_dry!("Would install completions to {path:?}");
+            return Ok(());
+        } else {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .await
+                    .with_context(|| format!("Could not create {dir:?}"))?;
+            }
+            fs::write(&path, script)
+                .await
+                .with_context(|| format!("Could not write {path:?}"))?;
+
+            log_cute!("Installed completions to {path:?}. Restart your shell to use them.");
+        }
+
+        let zsh = env::var("SHELL").ok().filter(|s| s.ends_with("zsh"));
+        match shell {
+            Shell::Zsh => {
+                if let Some(dir) = dirs.zsh_fpath_hint(zsh.as_deref()) {
+                    log_warn!(
+                        "Add `fpath=({dir} $fpath)` before `compinit` in your ~/.zshrc if it's not there yet."
+                    )
+                }
+            }
+            Shell::Bash => log_warn!(
+                "Bash loads them through bash-completion 2, e.g. `brew install bash-completion@2`."
+            ),
+            _ => (),
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl Runnable for CompletionCmd {
     async fn run(&self, _: &mut Config) -> Result<()> {
-        let shell = self.shell;
-        task::spawn_blocking(move || -> Result<()> {
-            let mut cmd = crate::cli::Args::command();
-            let name = cmd.get_name().to_string();
-
-            match shell {
-                Shell::Bash => generate(Bash, &mut cmd, name, &mut io::stdout()),
-                Shell::Zsh => generate(Zsh, &mut cmd, name, &mut io::stdout()),
-                Shell::Fish => generate(Fish, &mut cmd, name, &mut io::stdout()),
-                Shell::PowerShell => generate(PowerShell, &mut cmd, name, &mut io::stdout()),
-                Shell::Elvish => generate(Elvish, &mut cmd, name, &mut io::stdout()),
-            };
+        let Some(shell) = self
+            .shell
+            .or_else(|| Shell::from_path(&env::var("SHELL").ok()?))
+        else {
+            bail!("Could not detect your shell from $SHELL. Pass it, e.g. `cutler completion zsh`.")
+        };
+
+        let script = task::spawn_blocking(move || render(shell)).await?;
+
+        if self.install {
+            self.install(shell, script).await
+        } else {
+            io::stdout().write_all(&script)?;
             Ok(())
-        })
-        .await??;
-        Ok(())
+        }
     }
 }
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::{
+    commands::Runnable,
+    config::{
+        core::Config,
+        diff::{Change, diff_tables},
+    },
+    log_cute,
+    util::logging::{GREEN, RED, RESET, YELLOW, paint},
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigDiffCmd {
+    /// The config to compare from.
+    a: PathBuf,
+
+    /// The config to compare to (defaults to the active config).
+    b: Option<PathBuf>,
+}
+
+/// Helper for: ConfigDiffCmd::run()
+async fn read_table(path: &Path) -> Result<toml::Table> {
+    let text = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Could not read {}", path.display()))?;
+
+    toml::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+#[async_trait]
+impl Runnable for ConfigDiffCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let b = match &self.b {
+            Some(b) => b.clone(),
+            None if config.is_loadable() => config.path.clone(),
+            None => bail!("No active config to compare against; pass a second file."),
+        };
+
+        let changes = diff_tables(&read_table(&self.a).await?, &read_table(&b).await?);
+
+        if changes.is_empty() {
+            log_cute!("No differences found.");
+            return Ok(());
+        }
+
+        for change in &changes {
+            let color = match change {
+                Change::Added { .. } => GREEN,
+                Change::Removed { .. } => RED,
+                Change::Changed { .. } | Change::Items { .. } => YELLOW,
+            };
+            println!("{}", paint(&format!("{color}{change}{RESET}")));
+        }
+
+        Ok(())
+    }
+}
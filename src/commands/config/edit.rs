@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::env;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use tokio::fs;
+
+use crate::{
+    cli::atomic::should_dry_run,
+    commands::{ApplyCmd, Runnable},
+    config::core::Config,
+    log_cute, log_dry, log_err, log_info, log_warn,
+    util::io::{confirm, open_in_editor},
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigEditCmd;
+
+#[async_trait]
+impl Runnable for ConfigEditCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        if !config.is_loadable() {
+            bail!("No config found to edit. Run `cutler init` to create one.")
+        }
+        config.ensure_file()?;
+
+        // respect lock state
+        config.load(true).await?;
+
+        let Ok(editor) = env::var("EDITOR") else {
+            bail!("EDITOR environment variable is not set.")
+        };
+
+        if should_dry_run() {
+            log_dry!("Would open {:?} in {editor}", config.path);
+            return Ok(());
+        }
+
+        let original = fs::read_to_string(&config.path).await?;
+
+        // keep editing until the config parses or the user gives up
+        loop {
+            open_in_editor(&editor, &config.path).await?;
+
+            let edited = fs::read_to_string(&config.path).await?;
+
+            if edited == original {
+                log_info!("No changes made to config.");
+                return Ok(());
+            }
+
+            match toml::from_str::<Config>(&edited) {
+                Ok(_) => break,
+                Err(e) => {
+                    log_err!("Edited config is invalid: {e}");
+
+                    if !confirm("Re-open editor to fix it?") {
+                        fs::write(&config.path, &original).await?;
+                        log_warn!("Restored the previous config.");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        log_cute!("Config saved and validated.");
+
+        if confirm("Run `cutler apply` with the new config?") {
+            ApplyCmd::default().run(config).await?;
+        }
+
+        Ok(())
+    }
+}
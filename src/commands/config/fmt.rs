@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use tokio::fs;
+
+use crate::{
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::{core::Config, fmt::format_str},
+    log_cute, log_dry,
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigFmtCmd {
+    /// Only check whether the config is formatted, erroring out if not (for CI).
+    #[arg(long)]
+    check: bool,
+}
+
+#[async_trait]
+impl Runnable for ConfigFmtCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        if !config.is_loadable() {
+            bail!("Cannot find a configuration to format.")
+        }
+        config.ensure_file()?;
+
+        let original = fs::read_to_string(&config.path).await?;
+        let formatted = format_str(&original)?;
+
+        if formatted == original {
+            log_cute!("Config is already formatted.");
+            return Ok(());
+        }
+
+        if self.check {
+            bail!("Config is not formatted. Run `cutler config fmt` to fix it.")
+        }
+
+        if should_dry_run() {
+            log_dry!("Would format {:?}", config.path);
+            return Ok(());
+        }
+
+        fs::write(&config.path, formatted).await?;
+        log_cute!("Config formatted.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    commands::Runnable,
+    config::{
+        core::Config,
+        keypath::{get_item, parse_key_path},
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigGetCmd {
+    /// Dotted key path, e.g. `set.dock.tilesize` or `set."com.apple.dock".tilesize`.
+    key: String,
+}
+
+#[async_trait]
+impl Runnable for ConfigGetCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let doc = config.load_as_mut(false).await?;
+        let path = parse_key_path(&self.key)?;
+
+        match get_item(&doc, &path) {
+            Some(item) => match item.as_value() {
+                Some(value) => {
+                    let mut value = value.clone();
+                    value.decor_mut().clear();
+                    println!("{value}");
+                }
+                None => println!("{}", item.to_string().trim()),
+            },
+            None => bail!("Key not found in config: {}", self.key),
+        }
+
+        Ok(())
+    }
+}
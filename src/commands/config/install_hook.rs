@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::{core::Config, git},
+    log_cute,
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigInstallHookCmd {
+    /// Also run a dry-run apply of the config in the hook, to catch problems linting can't.
+    #[arg(long)]
+    plan: bool,
+
+    /// Replace an existing pre-commit hook which wasn't written by cutler.
+    #[arg(short, long)]
+    force: bool,
+}
+
+#[async_trait]
+impl Runnable for ConfigInstallHookCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        if !config.is_loadable() {
+            bail!("Config path does not exist!")
+        }
+
+        let hook = git::install_hook(&config.path, self.plan, self.force).await?;
+
+        if !should_dry_run() {
+            log_cute!(
+                "Installed pre-commit hook at {}. Commits changing the config are now validated.",
+                hook.display()
+            );
+        }
+
+        Ok(())
+    }
+}
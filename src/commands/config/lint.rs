@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    commands::Runnable,
+    config::core::Config,
+    domains::{catalog, collect},
+    log_cute, log_info, log_warn,
+    util::{
+        exit::{ExitCode, coded},
+        macos::{MacosVersion, current_version},
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigLintCmd {
+    /// Check for keys ignored by this macOS version (e.g. `15` or `sequoia`) instead of the
+    /// running one. Keys ignored by any version are reported when neither is known.
+    #[arg(long, value_name = "VERSION")]
+    macos: Option<MacosVersion>,
+
+    /// Fail if the config sets keys which macOS no longer reads.
+    #[arg(long)]
+    strict: bool,
+}
+
+#[async_trait]
+impl Runnable for ConfigLintCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        config
+            .load(false)
+            .await
+            .map_err(|e| coded(ExitCode::Config, format!("{e:#}")))?;
+
+        // resolves aliases and checks every cataloged value
+        let domains = collect(config)
+            .await
+            .map_err(|e| coded(ExitCode::Config, format!("{e:#}")))?;
+
+        // point out raw keys which have a readable alias
+        for (domain, settings) in &domains {
+            for key in settings.keys() {
+                if let Some(entry) = catalog::by_key(domain, key) {
+                    log_info!(
+                        "{domain}.{key} can be written as {domain}.{} ({})",
+                        entry.alias,
+                        entry.desc
+                    );
+                }
+            }
+        }
+
+        let target = match self.macos {
+            Some(version) => Some(version),
+            None => current_version(),
+        };
+        let retired = catalog::retirements(&domains, target);
+        let by = target.map_or("newer macOS releases".into(), |version| version.to_string());
+        for line in &retired {
+            log_warn!("{line}");
+        }
+
+        if retired.is_empty() {
+            log_cute!("Config is valid.");
+        } else if self.strict {
+            return Err(coded(
+                ExitCode::Config,
+                format!("{} settings are ignored by {by}", retired.len()),
+            ));
+        } else {
+            log_cute!(
+                "Config is valid, but {} settings are ignored by {by}.",
+                retired.len()
+            );
+        }
+
+        Ok(())
+    }
+}
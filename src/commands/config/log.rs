@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    commands::Runnable,
+    config::{core::Config, git},
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigLogCmd {
+    /// Amount of commits to show.
+    #[arg(short = 'n', long, default_value_t = 10)]
+    count: usize,
+}
+
+#[async_trait]
+impl Runnable for ConfigLogCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        if !config.is_loadable() {
+            bail!("Config path does not exist!")
+        }
+
+        git::log(&config.path, self.count).await
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+pub mod diff;
+pub mod edit;
+pub mod fmt;
+pub mod get;
+pub mod install_hook;
+pub mod lint;
+pub mod log;
+pub mod set;
+pub mod show;
+pub mod unset;
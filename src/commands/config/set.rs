@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::{
+        core::{Config, ConfigCoreMethods},
+        git,
+        keypath::{parse_key_path, parse_value, set_item},
+    },
+    log_cute, log_dry,
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigSetCmd {
+    /// Dotted key path, e.g. `set.dock.tilesize` or `set."com.apple.dock".tilesize`.
+    key: String,
+
+    /// The value to write. Types are inferred (e.g. `48`, `true`, `[1, 2]`); anything else is a string.
+    value: String,
+}
+
+#[async_trait]
+impl Runnable for ConfigSetCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let mut doc = config.load_as_mut(true).await?;
+        let path = parse_key_path(&self.key)?;
+        let value = parse_value(&self.value);
+
+        set_item(&mut doc, &path, value.clone())?;
+
+        // make sure the result is still a valid config
+        let mut updated = toml::from_str::<Config>(&doc.to_string())
+            .with_context(|| format!("Setting {} would make the config invalid", self.key))?;
+
+        if should_dry_run() {
+            log_dry!("Would set {} = {}", self.key, value);
+            return Ok(());
+        }
+
+        doc.save(&config.path).await?;
+        log_cute!("Set {} = {}", self.key, value);
+
+        updated.path = config.path.clone();
+        git::auto_commit(&updated, &format!("cutler: set {} = {}", self.key, value)).await;
+
+        Ok(())
+    }
+}
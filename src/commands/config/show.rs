@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::env;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+use tokio::fs;
+
+use crate::{
+    cli::atomic::{should_be_quiet, should_dry_run},
+    commands::Runnable,
+    config::core::Config,
+    log_cute, log_dry, log_info,
+    util::io::open_in_editor,
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigShowCmd;
+
+#[async_trait]
+impl Runnable for ConfigShowCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        config.ensure_file()?;
+
+        // handle dry‑run
+        if should_dry_run() {
+            log_dry!("Would display config from {:?}", config.path);
+            return Ok(());
+        }
+
+        // show inside editor if available
+        if let Ok(editor) = env::var("EDITOR") {
+            log_cute!("Opening configuration in editor. Close editor to quit.",);
+            open_in_editor(&editor, &config.path).await?;
+            log_info!("Opened configuration file in editor.");
+        } else {
+            if !should_be_quiet() {
+                log_info!("Editor could not be found, opening normally:\n",);
+            }
+
+            // read and print the file
+            let content = fs::read_to_string(&config.path).await?;
+
+            println!("{content}");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::{
+        core::{Config, ConfigCoreMethods},
+        keypath::{parse_key_path, unset_item},
+    },
+    log_cute, log_dry,
+};
+
+#[derive(Debug, Args)]
+pub struct ConfigUnsetCmd {
+    /// Dotted key path, e.g. `set.dock.tilesize` or `set."com.apple.dock".tilesize`.
+    key: String,
+}
+
+#[async_trait]
+impl Runnable for ConfigUnsetCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let mut doc = config.load_as_mut(true).await?;
+        let path = parse_key_path(&self.key)?;
+
+        if unset_item(&mut doc, &path).is_none() {
+            bail!("Key not found in config: {}", self.key);
+        }
+
+        // make sure the result is still a valid config
+        toml::from_str::<Config>(&doc.to_string())
+            .with_context(|| format!("Removing {} would make the config invalid", self.key))?;
+
+        if should_dry_run() {
+            log_dry!("Would remove {}", self.key);
+            return Ok(());
+        }
+
+        doc.save(&config.path).await?;
+        log_cute!("Removed {}", self.key);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use std::env;
+
+use crate::{
+    brew::core::{homebrew_env, is_in_prefix},
+    commands::{Runnable, completion::Shell},
+    config::core::Config,
+};
+
+#[derive(Args, Debug)]
+pub struct EnvCmd {
+    /// The shell to print the exports for. Detected from $SHELL if left out.
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+}
+
+/// Renders a variable as a statement setting it in the given shell.
+pub fn render_export(shell: Shell, name: &str, value: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            format!("export {name}='{}'", value.replace('\'', r"'\''"))
+        }
+        Shell::Fish => {
+            let quote = |v: &str| format!("'{}'", v.replace('\\', r"\\").replace('\'', r"\'"));
+            // fish keeps $PATH as a list
+            let values: Vec<String> = if name.ends_with("PATH") {
+                value.split(':').map(quote).collect()
+            } else {
+                vec![quote(value)]
+            };
+            format!("set -gx {name} {}", values.join(" "))
+        }
+        Shell::Elvish => format!("set-env {name} '{}'", value.replace('\'', "''")),
+        Shell::PowerShell => format!("$env:{name} = '{}'", value.replace('\'', "''")),
+        Shell::Nushell => {
+            let quote = |v: &str| serde_json::to_string(v).unwrap_or_default();
+            if name.ends_with("PATH") {
+                let values: Vec<String> = value.split(':').map(quote).collect();
+                format!("$env.{name} = [{}]", values.join(", "))
+            } else {
+                format!("$env.{name} = {}", quote(value))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Runnable for EnvCmd {
+    async fn run(&self, _: &mut Config) -> Result<()> {
+        let Some(shell) = self
+            .shell
+            .or_else(|| Shell::from_path(&env::var("SHELL").ok()?))
+        else {
+            bail!(
+                "Could not detect your shell from $SHELL. Pass it, e.g. `cutler env --shell zsh`."
+            )
+        };
+
+        let path = env::var("PATH").unwrap_or_default();
+        // the environment cutler runs Homebrew and external commands with
+        for (name, value) in homebrew_env(&path, is_in_prefix().await) {
+            println!("{}", render_export(shell, &name, &value));
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,14 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use crate::cli::atomic::{set_fix_missing, set_no_interleave, should_dry_run, should_output_json};
 use crate::commands::Runnable;
 
 use crate::config::core::Config;
 use crate::exec::core;
 use crate::exec::core::ExecMode;
+use crate::exec::plan;
+use crate::log_info;
+use crate::snapshot::{Snapshot, get_snapshot_path};
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Args;
@@ -12,37 +16,88 @@ use clap::Args;
 #[derive(Args, Debug)]
 pub struct ExecCmd {
     /// The command to execute. Defaults to 'all' if not passed.
-    #[arg(value_name = "NAME")]
+    #[arg(value_name = "NAME", conflicts_with = "group")]
     name: Option<String>,
 
+    /// Execute the commands of a [group.NAME] table, one after another in the listed order.
+    #[arg(short, long, value_name = "NAME", conflicts_with_all = &["all", "flagged", "tag"])]
+    group: Option<String>,
+
     /// Executes all declared commands.
-    #[arg(short, long, conflicts_with = "flagged")]
+    #[arg(short, long, conflicts_with_all = &["flagged", "tag"])]
     all: bool,
 
     /// Execute flagged commands only.
-    #[arg(short, long, conflicts_with = "all")]
+    #[arg(short, long, conflicts_with_all = &["all", "tag"])]
     flagged: bool,
+
+    /// Execute commands carrying the given tag only (repeatable).
+    #[arg(short, long, value_name = "TAG", conflicts_with_all = &["all", "flagged"])]
+    tag: Vec<String>,
+
+    /// Install binaries missing from `required` through [brew] without asking.
+    #[arg(long)]
+    fix_missing: bool,
+
+    /// Print the output of concurrent commands in one block per command instead of interleaving
+    /// their lines.
+    #[arg(long)]
+    no_interleave: bool,
 }
 
 #[async_trait]
 impl Runnable for ExecCmd {
     async fn run(&self, config: &mut Config) -> Result<()> {
         config.load(true).await?;
+        set_fix_missing(self.fix_missing);
+        set_no_interleave(self.no_interleave);
 
         let mode = if self.all {
             ExecMode::All
+        } else if !self.tag.is_empty() {
+            ExecMode::Tagged(self.tag.clone())
         } else if self.flagged {
             ExecMode::Flagged
         } else {
             ExecMode::Regular
         };
 
-        if let Some(cmd_name) = &self.name {
-            core::run_one(config.to_owned(), cmd_name).await?;
+        // a dry-run with --json prints the plan instead of the `Would execute` lines
+        if should_dry_run() && should_output_json() {
+            let plan = if let Some(group) = &self.group {
+                plan::plan_group(config, group)?
+            } else if let Some(cmd_name) = &self.name {
+                plan::plan_one(config, cmd_name)?
+            } else {
+                plan::plan_all(config, &mode)
+            };
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+            return Ok(());
+        }
+
+        let result = if let Some(group) = &self.group {
+            core::run_group(config.to_owned(), group).await
+        } else if let Some(cmd_name) = &self.name {
+            core::run_one(config.to_owned(), cmd_name).await
         } else {
-            core::run_all(config.to_owned(), mode).await?;
+            core::run_all(config.to_owned(), mode).await.map(|_| ())
+        };
+
+        // formulae installed for `required` binaries go away with `unapply --brew` too,
+        // even if a command failed afterwards
+        let installed = core::take_installed_formulae();
+        if !should_dry_run() && !installed.is_empty() {
+            let mut snap = if Snapshot::is_loadable().await {
+                Snapshot::load(&get_snapshot_path().await?).await?
+            } else {
+                Snapshot::new().await
+            };
+            snap.brew.merge(installed);
+            snap.save().await?;
+
+            log_info!("Logged Homebrew installs in snapshot.");
         }
 
-        Ok(())
+        result
     }
 }
@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::json;
+
+use crate::{
+    cli::atomic::should_output_json, commands::Runnable, config::core::Config, util::exit::ExitCode,
+};
+
+#[derive(Args, Debug)]
+pub struct ExitCodesCmd;
+
+#[async_trait]
+impl Runnable for ExitCodesCmd {
+    async fn run(&self, _: &mut Config) -> Result<()> {
+        if should_output_json() {
+            let codes: Vec<_> = ExitCode::ALL
+                .iter()
+                .map(|c| json!({ "code": c.code(), "name": c.name(), "description": c.description() }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&codes)?);
+            return Ok(());
+        }
+
+        for code in ExitCode::ALL {
+            println!(
+                "{:>3}  {:<14} {}",
+                code.code(),
+                code.name(),
+                code.description()
+            );
+        }
+
+        Ok(())
+    }
+}
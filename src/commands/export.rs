@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    commands::Runnable,
+    config::core::Config,
+    domains::collect,
+    export::{PROFILE_IDENTIFIER, render_mobileconfig, render_script},
+};
+
+#[derive(Args, Debug)]
+pub struct ExportCmd {
+    /// Render the config as a shell script of `defaults write`, brew and mas commands.
+    #[arg(long, conflicts_with = "mobileconfig")]
+    script: bool,
+
+    /// Render the [set] settings as a configuration profile for MDM.
+    #[arg(long)]
+    mobileconfig: bool,
+
+    /// The identifier of the profile; payloads append their domain to it.
+    #[arg(long, default_value = PROFILE_IDENTIFIER, requires = "mobileconfig")]
+    identifier: String,
+}
+
+#[async_trait]
+impl Runnable for ExportCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        if !self.script && !self.mobileconfig {
+            bail!("Choose an export format: --script or --mobileconfig.")
+        }
+
+        config.load(false).await?;
+        let domains = collect(config).await?;
+
+        if self.script {
+            print!("{}", render_script(config, &domains)?);
+        } else {
+            print!("{}", render_mobileconfig(&domains, &self.identifier)?);
+        }
+
+        Ok(())
+    }
+}
@@ -3,11 +3,18 @@
 use anyhow::{Result, bail};
 use async_trait::async_trait;
 use clap::Args;
+use tokio::fs;
 
 use crate::{
     cli::atomic::should_dry_run,
     commands::Runnable,
-    config::{core::Config, remote::RemoteConfigManager},
+    config::{
+        core::{Config, locked_error},
+        diff::diff_tables,
+        git,
+        remote::{RemoteLayers, keep_local_threshold},
+    },
+    domains::meta::{SettingMeta, meta_at},
     log_cute, log_dry, log_warn,
     util::{
         io::confirm,
@@ -22,60 +29,74 @@ pub struct FetchCmd {
     force: bool,
 }
 
+/// Helper for: FetchCmd::run()
+/// Finds the metadata of the [set] entry a change path points into.
+fn describe(path: &str, config: &toml::Table) -> Option<SettingMeta> {
+    let set = config.get("set")?.as_table()?;
+    meta_at(set, path.strip_prefix("set.")?).filter(|m| !m.is_empty())
+}
+
 #[async_trait]
 impl Runnable for FetchCmd {
     async fn run(&self, local_config: &mut Config) -> Result<()> {
         let dry_run = should_dry_run();
 
         // prepare local config for comparison
-        local_config.load(true).await?;
+        local_config.ensure_file()?;
+        // a config locked by its remote can still be fetched, so that the remote can lift its lock
+        local_config.load(false).await?;
 
         // parse [remote] section
-        let remote_mgr = if let Some(ref remote) = local_config.remote {
-            RemoteConfigManager::new(remote.clone().url)
-        } else {
+        let Some(remotes) = local_config.remote.clone() else {
             bail!("No URL found in [remote] of config. Add one to use remote sync.")
         };
+        let layers = RemoteLayers::new(&remotes);
+
+        // fetch remote config(s)
+        layers.fetch().await?;
+        let local_text = fs::read_to_string(&local_config.path).await?;
+        let merged = keep_local_threshold(&layers.merged(&remotes)?, &local_text)?;
+
+        // a lock made locally keeps the config as it is, unless the remote sets a lock itself
+        if local_config.is_locked()
+            && !local_config.is_remote_locked()
+            && !RemoteLayers::locks(&merged)
+        {
+            return Err(locked_error(local_config));
+        }
 
-        // fetch remote config
-        remote_mgr.fetch().await?;
+        // nothing to do if the remote hasn't changed and the local config is still identical
+        if !self.force && layers.is_unchanged() && merged == local_text {
+            log_cute!("Remote config hasn't changed since last fetch. Use -f to fetch forcefully.",);
+            return Ok(());
+        }
 
         if !self.force {
-            let remote_config = remote_mgr.get_parsed()?;
-
             // comparison begins
-            let mut changes = Vec::new();
-
-            // Compare fields between local_config and remote_config
-            // Example: compare brew, remote, vars, etc.
-            if local_config.brew.as_ref() != remote_config.brew.as_ref() {
-                changes.push(format!("{BOLD}brew{RESET}: (changed)"));
-            }
-            if local_config.remote.as_ref() != remote_config.remote.as_ref() {
-                changes.push(format!("{BOLD}remote{RESET}: (changed)"));
-            }
-            if local_config.vars.as_ref() != remote_config.vars.as_ref() {
-                changes.push(format!("{BOLD}vars{RESET}: (changed)"));
-            }
-            // Add more comparisons as needed for your config structure
-
-            if changes.is_empty() {
-                log_cute!("No changes found so skipping. Use -f to fetch forcefully.",);
-                return Ok(());
-            } else {
-                log_warn!("Differences between local and remote config:",);
-                for line in &changes {
-                    log_warn!("  {line}");
-                }
-            }
+            let (local_table, remote_table): (toml::Table, toml::Table) =
+                (toml::from_str(&local_text)?, toml::from_str(&merged)?);
+            let changes = diff_tables(&local_table, &remote_table);
 
             if changes.is_empty() {
                 log_cute!("No changes found so skipping. Use -f to fetch forcefully.",);
                 return Ok(());
             } else {
                 log_warn!("Differences between local and remote config:",);
-                for line in &changes {
-                    log_warn!("  {line}");
+                for change in &changes {
+                    // show where the change comes from when layering multiple remotes
+                    match layers.origin(change.path()) {
+                        Some(url) if layers.len() > 1 => {
+                            log_warn!("  {change} {BOLD}(from {url}){RESET}")
+                        }
+                        _ => log_warn!("  {change}"),
+                    }
+
+                    // explain [set] changes with their inline documentation
+                    if let Some(meta) = describe(change.path(), &remote_table)
+                        .or_else(|| describe(change.path(), &local_table))
+                    {
+                        log_warn!("      # {meta}");
+                    }
                 }
             }
 
@@ -92,9 +113,16 @@ impl Runnable for FetchCmd {
                 local_config.path
             );
         } else {
-            remote_mgr.save().await?;
+            layers.save(&remotes).await?;
 
             log_cute!("Local config updated from remote!");
+
+            let urls: Vec<&str> = remotes.layers().iter().map(|r| r.url.as_str()).collect();
+            git::auto_commit(
+                local_config,
+                &format!("cutler: fetch config from {}", urls.join(", ")),
+            )
+            .await;
         }
 
         Ok(())
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::{Args, Subcommand};
+
+use crate::{
+    cli::atomic::should_output_json,
+    commands::Runnable,
+    config::core::Config,
+    log_cute,
+    util::{
+        history::{self, Run, format_timestamp},
+        logging::{BOLD, RESET},
+    },
+};
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct HistoryCmd {
+    #[command(subcommand)]
+    command: Option<HistorySubcmd>,
+
+    /// Amount of runs to list.
+    #[arg(short = 'n', long, default_value_t = 20)]
+    count: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistorySubcmd {
+    /// Show what exactly changed in a past run.
+    Show {
+        /// The run, as listed by `cutler history`.
+        id: u64,
+    },
+}
+
+/// Helper for: HistoryCmd::run()
+/// One line describing a run, e.g. `  12  2025-03-04 13:37 UTC  apply    5 changed (1.2s)`.
+fn describe(run: &Run) -> String {
+    let failed = if run.failed > 0 {
+        format!(", {} failed", run.failed)
+    } else {
+        String::new()
+    };
+    let caller = run
+        .caller
+        .as_ref()
+        .map(|c| format!(" via {c}"))
+        .unwrap_or_default();
+
+    format!(
+        "{:>4}  {}  {:<8} {} changed{failed} ({:.1}s){caller}",
+        run.id,
+        format_timestamp(run.timestamp),
+        run.command,
+        run.changed,
+        run.elapsed_secs
+    )
+}
+
+impl HistoryCmd {
+    /// Helper for: run()
+    fn show(&self, runs: &[Run], id: u64) -> Result<()> {
+        let Some(run) = runs.iter().find(|r| r.id == id) else {
+            bail!("No run #{id} in the history. Run `cutler history` to list them.")
+        };
+
+        if should_output_json() {
+            println!("{}", serde_json::to_string_pretty(run)?);
+            return Ok(());
+        }
+
+        println!("{BOLD}{}{RESET}", describe(run));
+        println!("      {}", run.args);
+
+        if run.changes.is_empty() {
+            println!("      (no settings changed)");
+        }
+        for change in &run.changes {
+            println!(
+                "      {} | {}: {} -> {}",
+                change.domain,
+                change.key,
+                change.from.as_deref().unwrap_or("(unset)"),
+                change.to.as_deref().unwrap_or("(unset)")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Runnable for HistoryCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let runs = history::load(&config.path).await;
+
+        if let Some(HistorySubcmd::Show { id }) = &self.command {
+            return self.show(&runs, *id);
+        }
+
+        // newest first
+        let latest: Vec<&Run> = runs.iter().rev().take(self.count).collect();
+
+        if should_output_json() {
+            println!("{}", serde_json::to_string_pretty(&latest)?);
+            return Ok(());
+        }
+
+        if latest.is_empty() {
+            log_cute!("No runs recorded yet.");
+            return Ok(());
+        }
+
+        for run in latest {
+            println!("{}", describe(run));
+        }
+
+        Ok(())
+    }
+}
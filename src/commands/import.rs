@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use std::path::PathBuf;
+use tokio::fs;
+
+use crate::{
+    commands::Runnable,
+    config::{core::Config, fmt::format_str},
+    domains::import::{parse_defaults_dump, parse_plist},
+    log_warn,
+};
+
+#[derive(Args, Debug)]
+pub struct ImportCmd {
+    /// Text printed by `defaults read` (all domains) or `defaults read <domain>`.
+    #[arg(long, value_name = "FILE", conflicts_with = "plist")]
+    defaults_dump: Option<PathBuf>,
+
+    /// Plist written by `defaults export <domain> <file>`.
+    #[arg(long, value_name = "FILE")]
+    plist: Option<PathBuf>,
+
+    /// The domain of a single-domain dump; plists default to their file name.
+    #[arg(long)]
+    domain: Option<String>,
+}
+
+#[async_trait]
+impl Runnable for ImportCmd {
+    async fn run(&self, _: &mut Config) -> Result<()> {
+        let import = if let Some(path) = &self.defaults_dump {
+            let text = fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Could not read {}", path.display()))?;
+
+            log_warn!("Types are guessed from text dumps: booleans come out as 1/0.");
+            log_warn!("Use `defaults export` and --plist to keep exact types.");
+
+            parse_defaults_dump(&text, self.domain.as_deref())?
+        } else if let Some(path) = &self.plist {
+            let bytes = fs::read(path)
+                .await
+                .with_context(|| format!("Could not read {}", path.display()))?;
+
+            // e.g. com.apple.dock.plist
+            let domain = match &self.domain {
+                Some(domain) => domain.clone(),
+                None => path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .context("Could not infer the domain; pass --domain")?,
+            };
+
+            parse_plist(&bytes, &domain)?
+        } else {
+            bail!("Nothing to import. Pass --defaults-dump or --plist.")
+        };
+
+        for item in &import.skipped {
+            log_warn!("Skipped {item}");
+        }
+
+        if import.set.is_empty() {
+            bail!("No importable settings found.")
+        }
+
+        // only the TOML goes to stdout, so it can be redirected into a file
+        print!("{}", format_str(&import.to_toml()?)?);
+
+        Ok(())
+    }
+}
@@ -2,18 +2,151 @@
 
 use async_trait::async_trait;
 use clap::Args;
+use nix::unistd::{Uid, User};
+use std::env;
+use toml::value::Datetime;
+use toml_edit::{DocumentMut, InlineTable};
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 
 use crate::{
+    autosync::parse_interval,
     cli::atomic::should_dry_run,
     commands::Runnable,
-    config::core::{Config, ConfigCoreMethods},
-    log_dry,
+    config::{
+        core::{Config, ConfigCoreMethods, Lock, now},
+        remote::RemoteLayers,
+    },
+    domains::convert::{datetime_to_unix, unix_to_datetime},
+    log_cute, log_dry, log_warn,
+    util::history::format_timestamp,
 };
 
 #[derive(Debug, Args)]
-pub struct LockCmd;
+pub struct LockCmd {
+    /// Fetch the remote config and lock or unlock the local one to match it.
+    #[arg(long, conflicts_with_all = ["until", "duration", "reason"])]
+    remote: bool,
+
+    /// Lift the lock at the given date or time (UTC unless an offset is given), e.g.
+    /// `2025-01-01` or `2025-01-01T18:00:00+02:00`.
+    #[arg(long, conflicts_with = "duration")]
+    until: Option<String>,
+
+    /// Lift the lock after the given duration, e.g. `30m`, `2h` or `3d`.
+    #[arg(long = "for", value_name = "DURATION")]
+    duration: Option<String>,
+
+    /// Why the config is locked, shown to anyone running into the lock.
+    #[arg(long)]
+    reason: Option<String>,
+}
+
+/// The last moment a TOML datetime can hold, 9999-12-31T23:59:59Z.
+const MAX_UNTIL: i64 = 253_402_300_799;
+
+impl LockCmd {
+    /// Helper for: run()
+    /// Returns when the lock should expire, from `--until` or `--for`.
+    fn expiry(&self, now: i64) -> Result<Option<Datetime>> {
+        let until = if let Some(date) = &self.until {
+            let date: Datetime = date.parse().map_err(|_| {
+                anyhow!("Invalid date `{date}`; use e.g. 2025-01-01 or 2025-01-01T18:00:00Z.")
+            })?;
+            datetime_to_unix(&date)?
+        } else if let Some(duration) = &self.duration {
+            i64::try_from(parse_interval(duration)?.as_secs())
+                .ok()
+                .and_then(|secs| now.checked_add(secs))
+                .unwrap_or(i64::MAX)
+        } else {
+            return Ok(None);
+        };
+
+        if until > MAX_UNTIL {
+            bail!("The lock would expire too far in the future; pick a date before the year 10000.")
+        }
+
+        if until <= now {
+            bail!(
+                "The lock would have expired already ({}).",
+                format_timestamp(until.max(0) as u64)
+            )
+        }
+
+        Ok(Some(unix_to_datetime(until)))
+    }
+
+    /// Helper for: run()
+    /// Builds the lock written into the config, recording who locked it and when.
+    fn lock(&self, now: i64) -> Result<InlineTable> {
+        let mut lock = InlineTable::new();
+
+        // lock runs through sudo, so the user who called it is in SUDO_USER
+        let user = env::var("SUDO_USER")
+            .ok()
+            .or_else(|| Some(User::from_uid(Uid::current()).ok()??.name));
+        if let Some(user) = user {
+            lock.insert("by", user.into());
+        }
+        if let Some(reason) = &self.reason {
+            lock.insert("reason", reason.as_str().into());
+        }
+        lock.insert("since", unix_to_datetime(now).into());
+        if let Some(until) = self.expiry(now)? {
+            lock.insert("until", until.into());
+        }
+
+        Ok(lock)
+    }
+
+    /// Helper for: run()
+    /// Takes over the lock of the remote config, leaving the rest of the local config alone.
+    async fn reconcile(&self, config: &mut Config) -> Result<()> {
+        config.load(false).await?;
+        let Some(remotes) = config.remote.clone() else {
+            bail!("No URL found in [remote] of config. Add one to use remote sync.")
+        };
+
+        let layers = RemoteLayers::new(&remotes);
+        layers.fetch().await?;
+        let merged = layers.merged(&remotes)?;
+        let locked = RemoteLayers::locks(&merged);
+
+        let remote_lock = Lock::read(&merged).filter(Lock::is_active_now);
+        if remote_lock == config.lock.clone().filter(Lock::is_active_now) {
+            log_cute!(
+                "Config is already {} like the remote config.",
+                if locked { "locked" } else { "unlocked" }
+            );
+            return Ok(());
+        }
+
+        let mut document = config.load_as_mut(false).await?;
+        if should_dry_run() {
+            log_dry!(
+                "Would {} config file like the remote config.",
+                if locked { "lock" } else { "unlock" }
+            );
+            return Ok(());
+        }
+
+        match merged.parse::<DocumentMut>()?.remove("lock") {
+            Some(lock) if locked => document["lock"] = lock,
+            _ => {
+                document.remove("lock");
+            }
+        }
+        document.save(&config.path).await?;
+
+        log_cute!(
+            "Config {} like the remote config.",
+            if locked { "locked" } else { "unlocked" }
+        );
+
+        Ok(())
+    }
+}
 
 #[async_trait]
 impl Runnable for LockCmd {
@@ -22,23 +155,36 @@ impl Runnable for LockCmd {
             bail!("Cannot find a configuration to lock in the first place.")
         }
 
+        if self.remote {
+            return self.reconcile(config).await;
+        }
+
         let mut document = config.load_as_mut(false).await?;
         let dry_run = should_dry_run();
+        let now = now();
+
+        if let Some(lock) = Lock::read(&document.to_string()).filter(|lock| lock.is_active(now)) {
+            match lock.describe() {
+                Some(details) => bail!("Already locked ({details})."),
+                None => bail!("Already locked."),
+            }
+        }
 
-        if document
-            .get("lock")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false)
-        {
-            bail!("Already locked.");
-        } else if dry_run {
+        let lock = self.lock(now)?;
+        if dry_run {
             log_dry!("Would lock config file.");
             return Ok(());
         }
 
-        document["lock"] = toml_edit::value(true);
+        document["lock"] = toml_edit::value(lock);
         document.save(&config.path).await?;
 
+        if document.contains_key("remote") {
+            log_warn!(
+                "This config syncs from a remote. Until it's unlocked, `cutler fetch` and autosync leave it alone unless the remote config sets a lock itself."
+            );
+        }
+
         Ok(())
     }
 }
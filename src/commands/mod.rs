@@ -4,36 +4,60 @@ use anyhow::Result;
 use async_trait::async_trait;
 
 pub mod apply;
+pub mod backup;
+pub mod bootstrap;
 pub mod brew;
 pub mod check_update;
 pub mod completion;
 pub mod config;
 pub mod cookbook;
+pub mod env;
 pub mod exec;
+pub mod exit_codes;
+pub mod export;
 pub mod fetch;
+pub mod history;
+pub mod import;
 pub mod init;
 pub mod lock;
+pub mod prompt;
 pub mod reset;
 pub mod self_update;
 pub mod status;
 pub mod unapply;
+pub mod uninstall;
 pub mod unlock;
+pub mod watch;
 
 pub use apply::ApplyCmd;
-pub use brew::{backup::BrewBackupCmd, install::BrewInstallCmd};
+pub use backup::{create::BackupCreateCmd, restore::BackupRestoreCmd};
+pub use bootstrap::BootstrapCmd;
+pub use brew::{autoremove::BrewAutoremoveCmd, backup::BrewBackupCmd, install::BrewInstallCmd};
 pub use check_update::CheckUpdateCmd;
 pub use completion::CompletionCmd;
-pub use config::ConfigCmd;
+pub use config::{
+    diff::ConfigDiffCmd, edit::ConfigEditCmd, fmt::ConfigFmtCmd, get::ConfigGetCmd,
+    install_hook::ConfigInstallHookCmd, lint::ConfigLintCmd, log::ConfigLogCmd, set::ConfigSetCmd,
+    show::ConfigShowCmd, unset::ConfigUnsetCmd,
+};
 pub use cookbook::CookbookCmd;
+pub use env::EnvCmd;
 pub use exec::ExecCmd;
+pub use exit_codes::ExitCodesCmd;
+pub use export::ExportCmd;
 pub use fetch::FetchCmd;
+pub use history::HistoryCmd;
+pub use import::ImportCmd;
 pub use init::InitCmd;
 pub use lock::LockCmd;
+pub use prompt::PromptCmd;
 pub use reset::ResetCmd;
 pub use self_update::SelfUpdateCmd;
 pub use status::StatusCmd;
 pub use unapply::UnapplyCmd;
+pub use uninstall::UninstallCmd;
 pub use unlock::UnlockCmd;
+pub use watch::WatchCmd;
 
 use crate::config::core::Config;
 
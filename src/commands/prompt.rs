@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{
+    commands::Runnable,
+    config::core::Config,
+    util::status_cache::{StatusCache, render_prompt},
+};
+
+#[derive(Args, Debug)]
+pub struct PromptCmd {
+    /// Output format, e.g. "cutler {symbol} ({age})" (overrides [prompt] format).
+    #[arg(long)]
+    format: Option<String>,
+}
+
+#[async_trait]
+impl Runnable for PromptCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        // a prompt must never break, so anything going wrong just prints nothing
+        if !config.is_loadable() || config.load(false).await.is_err() {
+            return Ok(());
+        }
+
+        let mut prompt = config.prompt.clone().unwrap_or_default();
+        if let Some(format) = &self.format {
+            prompt.format = Some(format.clone());
+        }
+
+        let cache = StatusCache::load(&config.path).await;
+        let stale = cache.as_ref().is_none_or(|c| c.is_stale(&config.path));
+
+        let token = render_prompt(cache.as_ref(), stale, &prompt);
+        if !token.is_empty() {
+            println!("{token}");
+        }
+
+        Ok(())
+    }
+}
@@ -1,23 +1,35 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use async_trait::async_trait;
 use clap::Args;
-use defaults_rs::{Domain, Preferences};
 use tokio::fs;
 
 use crate::{
     cli::atomic::should_dry_run,
     commands::Runnable,
     config::core::Config,
-    domains::{collect, effective, read_current},
+    domains::{
+        collect,
+        convert::display_prefvalue,
+        effective, files, read_current,
+        safety::{is_protected, protected_patterns},
+    },
     log_cute, log_dry, log_err, log_info, log_warn,
     snapshot::{Snapshot, get_snapshot_path},
-    util::io::{confirm, restart_services},
+    util::{
+        history::{self, Change},
+        io::{confirm, restart_services},
+        summary,
+    },
 };
 
-#[derive(Args, Debug)]
-pub struct ResetCmd;
+#[derive(Args, Debug, Default)]
+pub struct ResetCmd {
+    /// Also reset protected domains and settings (see [safety]).
+    #[arg(long)]
+    allow_protected: bool,
+}
 
 #[async_trait]
 impl Runnable for ResetCmd {
@@ -26,6 +38,27 @@ impl Runnable for ResetCmd {
 
         config.load(true).await?;
 
+        let domains = collect(config).await?;
+
+        // refuse before deleting anything
+        if !self.allow_protected {
+            let patterns = protected_patterns(config);
+            let mut protected: Vec<String> = domains
+                .iter()
+                .flat_map(|(domain, table)| table.keys().map(|key| effective(domain, key)))
+                .filter(|(dom, key)| is_protected(&patterns, dom, key))
+                .map(|(dom, key)| format!("{dom} | {key}"))
+                .collect();
+            protected.sort();
+
+            if !protected.is_empty() {
+                bail!(
+                    "Refusing to reset protected settings: {}. Pass --allow-protected to proceed.",
+                    protected.join(", ")
+                )
+            }
+        }
+
         log_warn!("This will DELETE all settings defined in your config file.",);
         log_warn!("Settings will be reset to macOS defaults, not to their previous values.",);
 
@@ -33,31 +66,42 @@ impl Runnable for ResetCmd {
             return Ok(());
         }
 
-        let domains = collect(config).await?;
+        // tallied for the history only, reset prints no summary
+        summary::begin();
 
         for (domain, table) in domains {
             for (key, _) in table {
                 let (eff_dom, eff_key) = effective(&domain, &key);
 
                 // only delete it if currently set
-                if read_current(&eff_dom, &eff_key).await.is_some() {
-                    let domain_obj = if eff_dom == "NSGlobalDomain" {
-                        Domain::Global
-                    } else if let Some(rest) = eff_dom.strip_prefix("com.apple.") {
-                        Domain::User(format!("com.apple.{rest}"))
-                    } else {
-                        Domain::User(eff_dom.clone())
-                    };
+                let current = match read_current(&eff_dom, &eff_key).await {
+                    Ok(current) => current,
+                    Err(e) => {
+                        log_err!("Failed to read {eff_dom}.{eff_key}: {e}");
+                        summary::add_failure(format!("{eff_dom} | {eff_key}"));
+                        continue;
+                    }
+                };
+                if let Some(current) = current {
+                    let domain_obj = files::domain_obj(&eff_dom)?;
 
                     if dry_run {
                         log_dry!("Would reset {eff_dom}.{eff_key} to system default",);
                     } else {
-                        match Preferences::delete(domain_obj, &eff_key) {
+                        match files::delete_batch(vec![(domain_obj, eff_key.clone())]) {
                             Ok(_) => {
                                 log_info!("Reset {eff_dom}.{eff_key} to system default",);
+                                summary::add_applied(1);
+                                history::add_change(Change {
+                                    domain: eff_dom.clone(),
+                                    key: eff_key.clone(),
+                                    from: Some(display_prefvalue(&current)),
+                                    to: None,
+                                });
                             }
                             Err(e) => {
                                 log_err!("Failed to reset {eff_dom}.{eff_key}: {e}",);
+                                summary::add_failure(format!("{eff_dom} | {eff_key}"));
                             }
                         }
                     }
@@ -84,6 +128,12 @@ impl Runnable for ResetCmd {
         // restart system services if requested
         restart_services().await;
 
+        if let Some(summary) = summary::end()
+            && !dry_run
+        {
+            history::record(&config.path, "reset", &summary).await;
+        }
+
         log_cute!("Reset operation complete.");
 
         Ok(())
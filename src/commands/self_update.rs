@@ -1,100 +1,311 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
 use clap::Args;
-use self_update::{backends::github::Update, cargo_crate_version};
-use std::env;
-use tokio::fs;
+use reqwest::header::ACCEPT;
+use self_update::{
+    Download, Extract, TempDir, backends::github::Update, cargo_crate_version,
+    errors::Error as UpdateError, self_replace,
+};
+use semver::Version;
+use std::{env, fs::File, io::Write, path::Path};
+use tokio::{fs, process::Command};
 
-use crate::{commands::Runnable, config::core::Config, log_cute, log_warn};
+use crate::{
+    cli::atomic::should_dry_run,
+    commands::Runnable,
+    config::core::Config,
+    log_cute, log_dry, log_info, log_warn,
+    util::{
+        io::confirm,
+        net::{client, ensure_online, is_transient, is_transient_message, proxy, retry, send},
+        release::{
+            checksum_asset, fetch_releases, find, latest, parse_checksum, signature_asset,
+            verify_checksum, verify_signature,
+        },
+    },
+};
+
+/// Path of the binary inside release archives.
+const BIN_IN_ARCHIVE: &str = "bin/cutler";
+
+/// Where the manpage is installed to, next to manually installed binaries.
+pub const MANPAGE_PATH: &str = "/usr/local/share/man/man1/cutler.1";
 
 #[derive(Args, Debug)]
 pub struct SelfUpdateCmd {
     /// Do not install/update manpage during the update procedure.
     #[arg(long)]
     no_man: bool,
+
+    /// Install this release instead of the latest one, e.g. to roll back.
+    #[arg(long, value_name = "X.Y.Z")]
+    version: Option<Version>,
 }
 
-#[async_trait]
-impl Runnable for SelfUpdateCmd {
-    async fn run(&self, _: &mut Config) -> Result<()> {
-        // get the path to the current executable
-        let exe_path = env::current_exe()?;
-        let exe_path_str = exe_path.to_string_lossy();
+/// A package manager which cutler was installed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Installer {
+    Homebrew,
+    Cargo,
+    Mise,
+}
+
+impl Installer {
+    /// Tells the package manager from the path of the cutler binary, given where
+    /// `cargo install` puts binaries (e.g. `~/.cargo/bin/cutler`).
+    pub fn from_exe(exe: &str, cargo_bin: &str) -> Option<Self> {
+        if exe == "/opt/homebrew/bin/cutler" || exe.starts_with("/opt/homebrew/Cellar/cutler/") {
+            Some(Installer::Homebrew)
+        } else if exe.contains(".local/share/mise/installs/cargo-cutler") {
+            Some(Installer::Mise)
+        } else if !cargo_bin.is_empty() && exe == cargo_bin {
+            Some(Installer::Cargo)
+        } else {
+            None
+        }
+    }
 
-        // check for homebrew install
-        let is_homebrew = exe_path_str == "/opt/homebrew/bin/cutler";
+    /// Detects the package manager of the running binary, if any.
+    pub fn detect() -> Option<Self> {
+        let exe = env::current_exe().ok()?;
 
-        // check for cargo install (e.g., ~/.cargo/bin/cutler or $CARGO_HOME/bin/cutler)
-        let cargo_bin_path = if let Ok(cargo_home) = std::env::var("CARGO_HOME") {
+        // e.g. ~/.cargo/bin/cutler or $CARGO_HOME/bin/cutler
+        let cargo_bin = if let Ok(cargo_home) = env::var("CARGO_HOME") {
             format!("{cargo_home}/bin/cutler")
-        } else if let Ok(home) = std::env::var("HOME") {
-            format!("{home}/.cargo/bin/cutler")
-        } else if let Some(home_dir) = dirs::home_dir() {
-            format!("{}/.cargo/bin/cutler", home_dir.to_string_lossy())
+        } else if let Some(home) = dirs::home_dir() {
+            format!("{}/.cargo/bin/cutler", home.to_string_lossy())
         } else {
             String::new()
         };
-        let is_cargo = exe_path_str == cargo_bin_path;
 
-        // check for mise install
-        let is_mise = exe_path_str.contains(".local/share/mise/installs/cargo-cutler");
+        Self::from_exe(&exe.to_string_lossy(), &cargo_bin)
+    }
 
-        if is_homebrew || is_cargo || is_mise {
-            log_warn!(
-                "cutler was installed using a package manager, so cannot install updates manually.",
-            );
+    pub fn name(self) -> &'static str {
+        match self {
+            Installer::Homebrew => "Homebrew",
+            Installer::Cargo => "cargo",
+            Installer::Mise => "mise",
+        }
+    }
+
+    /// The command upgrading cutler through the package manager.
+    pub fn upgrade_command(self) -> &'static [&'static str] {
+        match self {
+            Installer::Homebrew => &["brew", "upgrade", "cutler"],
+            Installer::Cargo => &["cargo", "install", "--force", "cutler"],
+            Installer::Mise => &["mise", "upgrade", "cargo:cutler"],
+        }
+    }
+
+    /// The command removing cutler through the package manager.
+    pub fn uninstall_command(self) -> &'static [&'static str] {
+        match self {
+            Installer::Homebrew => &["brew", "uninstall", "cutler"],
+            Installer::Cargo => &["cargo", "uninstall", "cutler"],
+            Installer::Mise => &["mise", "unuse", "-g", "cargo:cutler"],
+        }
+    }
+
+    /// Upgrades cutler through the package manager, after asking.
+    pub async fn upgrade(self) -> Result<()> {
+        let Some((program, args)) = self.upgrade_command().split_first() else {
+            bail!("No upgrade command known for {}", self.name())
+        };
+        let command = self.upgrade_command().join(" ");
+
+        if should_dry_run() {
+            log_dry!("Would run: {command}");
+            return Ok(());
+        }
+
+        if !confirm(&format!(
+            "cutler was installed with {}. Run `{command}` instead?",
+            self.name()
+        )) {
+            log_warn!("Not updating. Run `{command}` to update cutler.");
+            return Ok(());
+        }
+
+        let status = Command::new(program).args(args).status().await?;
+        if !status.success() {
+            bail!("`{command}` exited with {status}")
+        }
+
+        log_cute!("cutler updated through {}.", self.name());
+
+        Ok(())
+    }
+}
+
+/// Helper for: SelfUpdateCmd::run()
+fn is_transient_update(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<UpdateError>() {
+        Some(UpdateError::Network(message)) => is_transient_message(message),
+        Some(UpdateError::Reqwest(e)) => is_transient(e),
+        _ => false,
+    }
+}
+
+/// Helper for: install_latest()
+fn download(url: &str, dest: impl Write, progress: bool) -> Result<()> {
+    Download::from_url(url)
+        .set_header(ACCEPT, "application/octet-stream".parse()?)
+        .show_progress(progress)
+        .download_to(dest)?;
+
+    Ok(())
+}
+
+/// Helper for: SelfUpdateCmd::run()
+/// Downloads the release with the given tag, verifies it and replaces the running binary with it.
+fn install_release(tag: &str, public_key: Option<&str>) -> Result<()> {
+    let updater = Update::configure()
+        .repo_owner("machlit")
+        .repo_name("cutler")
+        .target("aarch64-apple-darwin")
+        .bin_name("cutler")
+        .current_version(cargo_crate_version!())
+        .build()?;
+
+    let release = updater.get_release_version(tag)?;
+
+    let asset = release
+        .asset_for(&updater.target(), None)
+        .with_context(|| format!("No release asset found for {}", updater.target()))?;
+    let Some(sums) = checksum_asset(&release, &asset) else {
+        bail!(
+            "v{} publishes no SHA256 checksum for {}, refusing to install it.",
+            release.version,
+            asset.name
+        )
+    };
+
+    let dir = TempDir::new()?;
+    let archive = dir.path().join(&asset.name);
+    download(&asset.download_url, File::create(&archive)?, true)?;
+
+    let mut text = Vec::new();
+    download(&sums.download_url, &mut text, false)?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&text), &asset.name)
+        .with_context(|| format!("{} has no checksum for {}", sums.name, asset.name))?;
+    verify_checksum(&archive, &expected)?;
+    log_info!("Checksum verified: {expected}");
+
+    if let Some(key) = public_key {
+        let sig = signature_asset(&release, &asset).with_context(|| {
+            format!(
+                "v{} publishes no signature for {}, refusing to install it.",
+                release.version, asset.name
+            )
+        })?;
+        let sig_path = dir.path().join(&sig.name);
+        download(&sig.download_url, File::create(&sig_path)?, false)?;
+        verify_signature(&archive, &sig_path, key)?;
+        log_info!("Signature verified.");
+    }
+
+    Extract::from_source(&archive).extract_file(dir.path(), BIN_IN_ARCHIVE)?;
+    self_replace::self_replace(dir.path().join(BIN_IN_ARCHIVE))?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl Runnable for SelfUpdateCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        ensure_online("update cutler")?;
+
+        // only needed for [network]; a broken config shouldn't get in the way of updates
+        if config.is_loadable() {
+            let _ = config.load(false).await;
+        }
+
+        if let Some(installer) = Installer::detect() {
+            if self.version.is_some() {
+                bail!(
+                    "--version is only supported for manual installs; use {} to pick a version.",
+                    installer.name()
+                )
+            }
+            return installer.upgrade().await;
+        }
+
+        let update = config.update.clone().unwrap_or_default();
+        let channel = update.channel.unwrap_or_default();
+        let current = Version::parse(cargo_crate_version!())?;
+
+        let releases = fetch_releases("cutler-self-update").await?;
+        let release = match &self.version {
+            Some(version) => find(&releases, version)
+                .with_context(|| format!("No release found for v{version}"))?,
+            None => latest(&releases, channel)
+                .with_context(|| format!("Could not find a {channel} release on GitHub"))?,
+        };
+        let Some(version) = release.version() else {
+            bail!("Invalid release tag: {}", release.tag_name)
+        };
+
+        let up_to_date = match &self.version {
+            Some(_) => version == current,
+            None => version <= current,
+        };
+        if up_to_date {
+            log_cute!("cutler is already up to date (v{current}).");
             return Ok(());
         }
 
         // finally, check if cutler is where it is supposed to be
-        if exe_path_str != "/usr/local/bin/cutler" {
+        if env::current_exe()? != Path::new("/usr/local/bin/cutler") {
             log_warn!(
-                "cutler is currently installed in a custom path. Please note that the manpage will still be installed in: /usr/local/share/man/man1/cutler.1",
+                "cutler is currently installed in a custom path. Please note that the manpage will still be installed in: {MANPAGE_PATH}",
             );
             log_warn!("If you wish to skip this behavior, use: cutler self-update --no-man",);
         }
 
-        // run the self_update updater in a blocking thread to avoid dropping a runtime in async context
-        let status = tokio::task::spawn_blocking(move || {
-            Update::configure()
-                .repo_owner("machlit")
-                .repo_name("cutler")
-                .target("aarch64-apple-darwin")
-                .bin_name("cutler")
-                .bin_path_in_archive("bin/cutler")
-                .show_download_progress(true)
-                .current_version(cargo_crate_version!())
-                .build()?
-                .update()
+        // the updater builds its own client, which only reads the proxy from the environment
+        if let Some(proxy) = proxy() {
+            unsafe { env::set_var("HTTPS_PROXY", &proxy) };
+            unsafe { env::set_var("HTTP_PROXY", &proxy) };
+        }
+
+        // asked once up front, not again on every retry of the download
+        if !confirm(&format!(
+            "Install cutler v{version} (currently v{current})?"
+        )) {
+            bail!("Aborted update.")
+        }
+
+        // run the updater in a blocking thread to avoid dropping a runtime in async context
+        retry("download the update", is_transient_update, || async {
+            let tag = release.tag_name.clone();
+            let public_key = update.public_key.clone();
+            tokio::task::spawn_blocking(move || install_release(&tag, public_key.as_deref()))
+                .await?
         })
-        .await??;
-
-        if status.updated() {
-            if !self.no_man {
-                println!("Binary updated, updating manpage...");
-
-                let manpage_url = "https://raw.githubusercontent.com/machlit/cutler/refs/heads/master/man/man1/cutler.1".to_string();
-                let client = reqwest::Client::builder()
-                    .user_agent("cutler-self-update")
-                    .build()?;
-                let resp = client
-                    .get(&manpage_url)
-                    .send()
-                    .await
-                    .map_err(|e| anyhow::anyhow!("Failed to fetch manpage: {}", e))?;
-                let manpage_content = resp.text().await?;
-
-                fs::create_dir_all("/usr/local/share/man/man1").await?;
-                fs::write("/usr/local/share/man/man1/cutler.1", manpage_content).await?;
+        .await?;
+
+        if !self.no_man {
+            println!("Binary updated, updating manpage...");
+
+            // the manpage of the installed release, not of the development branch
+            let manpage_url = format!(
+                "https://raw.githubusercontent.com/machlit/cutler/refs/tags/{}/man/man1/cutler.1",
+                release.tag_name
+            );
+            let req = client("cutler-self-update")?.get(&manpage_url);
+            let resp = send("fetch the manpage", req).await?;
+            let manpage_content = resp.text().await?;
+
+            if let Some(dir) = Path::new(MANPAGE_PATH).parent() {
+                fs::create_dir_all(dir).await?;
             }
-        } else {
-            log_cute!("cutler is already up to date.");
-            return Ok(());
+            fs::write(MANPAGE_PATH, manpage_content).await?;
         }
 
-        log_cute!("cutler updated to: {}", status.version());
+        log_cute!("cutler updated to: {version}");
 
         Ok(())
     }
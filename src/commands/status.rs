@@ -5,29 +5,188 @@ use crate::{
         core::{brew_is_installed, diff_brew},
         types::BrewDiff,
     },
+    cli::atomic::{should_be_verbose, should_output_json},
     commands::Runnable,
-    config::core::Config,
-    domains::{collect, effective, read_current},
+    config::{core::Config, path::get_overlay_path},
+    domains::{
+        catalog, collect_with_meta,
+        collector::missing_domains,
+        convert::{display_prefvalue, toml_to_prefvalue},
+        effective, ignored_keys,
+        managed::is_managed,
+        meta::not_applicable,
+        overlay_settings, read_current,
+        text_replacements::merge_system_replacements,
+    },
     log_cute, log_err, log_info, log_warn,
-    util::logging::{BOLD, GREEN, RED, RESET},
+    mas::core::{diff_mas, mas_is_installed, mas_list},
+    plist::core::{expand_path, is_in_sync},
+    util::{
+        exit::{ExitCode, coded},
+        io::{terminal_width, truncate},
+        logging::{BOLD, GREEN, RED, RESET, group, set_silent},
+        macos::current_version,
+        notify::{NotifyEvent, notify_event},
+        porcelain::{State, record},
+        status_cache::StatusCache,
+    },
 };
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Args;
-use std::collections::{HashMap, HashSet};
+use defaults_rs::Preferences;
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
 
 #[derive(Args, Debug)]
 pub struct StatusCmd {
     // Disables Homebrew state check.
     #[arg(long)]
     no_brew: bool,
+
+    /// Print preferences in a stable, tab-separated format for scripts.
+    #[arg(long)]
+    pub porcelain: bool,
+
+    /// With --porcelain, terminate fields with NUL instead of escaping them.
+    #[arg(short = 'z', requires = "porcelain")]
+    nul: bool,
+
+    /// Print the result of the last full check instead of checking again (for shell prompts).
+    #[arg(long, conflicts_with_all = &["porcelain", "no_brew"])]
+    pub cached: bool,
+
+    /// Print nothing but a JSON object telling whether the system is in sync (for scripts and
+    /// dotfile managers).
+    #[arg(long, conflicts_with_all = &["porcelain", "cached"])]
+    pub quiet_json: bool,
+
+    /// Exit with code 5 if the system drifted from the config.
+    #[arg(long, conflicts_with = "cached")]
+    exit_code: bool,
+
+    /// Only show what differs from the config.
+    #[arg(long, conflicts_with = "cached")]
+    only_diff: bool,
+
+    /// Only check preferences in domains matching the pattern, e.g. `dock` or `com.apple.*`.
+    #[arg(long, value_name = "PATTERN", conflicts_with_all = &["cached", "brew_only"])]
+    domain: Option<String>,
+
+    /// Only check Homebrew.
+    #[arg(long, conflicts_with_all = &["cached", "prefs_only", "no_brew", "porcelain"])]
+    brew_only: bool,
+
+    /// Only check preferences and plist files.
+    #[arg(long, conflicts_with = "cached")]
+    prefs_only: bool,
+
+    /// Print long values in full instead of cutting them off at the terminal's width.
+    #[arg(long, conflicts_with_all = &["cached", "porcelain"])]
+    full_values: bool,
+}
+
+/// Width of the log tag and tree branch in front of a setting, e.g. `WARN    ├─ `.
+const LINE_START: usize = 11;
+
+/// Values are never cut shorter than this, even in narrow terminals.
+const MIN_VALUE_WIDTH: usize = 16;
+
+/// Helper for: StatusCmd::run()
+/// Cuts a value down to its share of the terminal width, next to `used` other characters
+/// on the line and `shares - 1` other values. Without a width, the value is left as is.
+fn fit(value: &str, width: Option<usize>, used: usize, shares: usize) -> String {
+    match width {
+        Some(width) => {
+            let max = (width.saturating_sub(used) / shares).max(MIN_VALUE_WIDTH);
+            truncate(value, max)
+        }
+        None => value.to_string(),
+    }
+}
+
+/// Helper for: StatusCmd::run()
+/// Returns the domains which can't be read since they don't exist. If the existing domains
+/// can't be listed, none are reported.
+fn unreadable_domains<'a>(domains: impl IntoIterator<Item = &'a String>) -> HashSet<String> {
+    let Ok(known) = Preferences::list_domains() else {
+        return HashSet::new();
+    };
+    let known: Vec<String> = known.iter().map(|d| d.to_string()).collect();
+
+    missing_domains(domains, &known).into_iter().collect()
+}
+
+/// Whether an effective domain matches a `--domain` pattern, where `*` matches anything.
+/// Like in the config, the `com.apple.` prefix is optional, so `dock` matches `com.apple.dock`.
+pub fn domain_matches(pattern: &str, eff_domain: &str) -> bool {
+    let glob = |pattern: &str| {
+        let re = format!("^{}$", regex::escape(pattern).replace(r"\*", ".*"));
+        Regex::new(&re).is_ok_and(|re| re.is_match(eff_domain))
+    };
+
+    glob(pattern) || glob(&format!("com.apple.{pattern}"))
+}
+
+/// Helper for: StatusCmd::run()
+fn drift_error() -> anyhow::Error {
+    coded(ExitCode::Drift, "System drifted from the config.")
+}
+
+impl StatusCmd {
+    /// Helper for: run()
+    /// Whether the check covers everything, so that its result can be cached.
+    fn is_full_check(&self) -> bool {
+        !self.brew_only && !self.prefs_only && self.domain.is_none()
+    }
+
+    /// Helper for: run()
+    /// Whether a preference domain is part of the check.
+    fn checks_domain(&self, eff_domain: &str) -> bool {
+        !self.brew_only
+            && self
+                .domain
+                .as_ref()
+                .is_none_or(|pattern| domain_matches(pattern, eff_domain))
+    }
+
+    /// Helper for: run()
+    /// Prints the stored result of the last check.
+    async fn print_cached(&self, config: &Config) -> Result<()> {
+        let Some(cache) = StatusCache::load(&config.path).await else {
+            println!("unknown (never checked)");
+            return Ok(());
+        };
+        let stale = cache.is_stale(&config.path);
+
+        if should_output_json() {
+            let mut json = serde_json::to_value(&cache)?;
+            json["stale"] = stale.into();
+            println!("{json}");
+        } else {
+            println!("{}", cache.describe(stale));
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl Runnable for StatusCmd {
     async fn run(&self, config: &mut Config) -> Result<()> {
+        if self.cached {
+            return self.print_cached(config).await;
+        }
+
+        // nothing but the JSON object, so callers can parse stdout and show stderr as is
+        if self.quiet_json {
+            set_silent(true);
+        }
+
         config.load(false).await?;
-        let domains = collect(config).await?;
+        let (mut domains, meta) = collect_with_meta(config).await?;
+        merge_system_replacements(config, &mut domains).await?;
 
         // flatten all settings into a list
         let entries: Vec<(String, String, toml::Value)> = domains
@@ -39,84 +198,268 @@ impl Runnable for StatusCmd {
             })
             .collect();
 
+        // keys macOS keeps changing by itself
+        let ignored = ignored_keys(config);
+
+        // verbose output tells which settings come from the local overlay
+        let local = if should_be_verbose() {
+            overlay_settings(config).await?
+        } else {
+            HashSet::new()
+        };
+        let overlay_name = get_overlay_path(&config.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // everything which diverged, for notifications and the status cache
+        let mut drifted: Vec<String> = Vec::new();
+
         // preference check
-        {
+        if !self.brew_only {
+            let _group = group("System preferences");
             let mut outcomes = Vec::with_capacity(entries.len());
-            let mut domain_has_diff = HashMap::new();
+            let mut records = Vec::new();
 
             // let the checks begin!
             for (domain, key, value) in entries.iter() {
                 let (eff_dom, eff_key) = effective(domain, key);
 
-                let current_pref = read_current(&eff_dom, &eff_key).await;
-                let desired_pref = crate::domains::convert::toml_to_prefvalue(value)?;
+                if ignored.contains(&(eff_dom.clone(), eff_key.clone()))
+                    || !self.checks_domain(&eff_dom)
+                {
+                    continue;
+                }
+
+                // a value which can't be read can't be told to be in sync
+                let current_pref = match read_current(&eff_dom, &eff_key).await {
+                    Ok(current) => current,
+                    Err(e) => {
+                        log_err!("{eff_dom} | {eff_key}: {e}");
+                        drifted.push(format!("{eff_dom} | {eff_key}"));
+                        continue;
+                    }
+                };
+                let desired_pref = toml_to_prefvalue(value)?;
 
                 let (current_str, is_diff) = match &current_pref {
                     Some(current) => {
                         let diff = current != &desired_pref;
-                        (current.to_string(), diff)
+                        (display_prefvalue(current), diff)
                     }
                     None => ("Not set".to_string(), true),
                 };
-                let desired_str = desired_pref.to_string();
+                let desired_str = display_prefvalue(&desired_pref);
+
+                // values forced by a configuration profile can't be changed, so they aren't drift
+                let managed = is_diff && is_managed(&eff_dom, &eff_key);
+
+                if self.porcelain {
+                    let (state, current) = match &current_pref {
+                        _ if managed => (State::Managed, current_str.clone()),
+                        None => (State::Missing, String::new()),
+                        Some(_) if is_diff => (State::Drift, current_str.clone()),
+                        Some(_) => (State::Match, current_str.clone()),
+                    };
+                    if !(self.only_diff && state == State::Match) {
+                        records.push((eff_dom, eff_key, state, current, desired_str));
+                    }
+                    continue;
+                }
 
                 outcomes.push((
                     eff_dom.clone(),
                     eff_key,
                     desired_str.clone(),
                     current_str.clone(),
-                    is_diff,
+                    is_diff && !managed,
+                    managed,
                 ));
+            }
 
-                // set to false only if it hasn't been set to true once
-                // we use it later for LogLevel::Warning over domains which have at least one diff
-                if is_diff {
-                    domain_has_diff.insert(eff_dom.clone(), true);
-                } else {
-                    domain_has_diff.entry(eff_dom.clone()).or_insert(false);
+            if let Some(pattern) = &self.domain
+                && outcomes.is_empty()
+                && records.is_empty()
+            {
+                log_warn!("No preferences found in domains matching {pattern}.");
+            }
+
+            // scripts only get the stable records, nothing else
+            if self.porcelain {
+                records.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+
+                let drifted = records
+                    .iter()
+                    .any(|r| matches!(r.2, State::Drift | State::Missing));
+
+                let mut out = io::stdout().lock();
+                for (domain, key, state, current, desired) in records {
+                    out.write_all(
+                        record(&domain, &key, state, &current, &desired, self.nul).as_bytes(),
+                    )?;
                 }
+
+                if drifted && self.exit_code {
+                    return Err(drift_error());
+                }
+                return Ok(());
             }
 
-            // keep track of printed domains so that they're only printed once
-            // the iterable keeps the domain key-value pairs sequentially so this is a plus
-            let mut printed_domains = HashSet::new();
-            let mut any_diff = false;
+            // group the settings by domain, for the tree below
+            let mut tree: BTreeMap<String, Vec<(String, String, String, bool, bool)>> =
+                BTreeMap::new();
+            let mut managed = Vec::new();
+            for (eff_dom, eff_key, desired, current, is_diff, is_managed) in outcomes {
+                if is_managed {
+                    managed.push(format!("{eff_dom} | {eff_key}"));
+                }
+                tree.entry(eff_dom)
+                    .or_default()
+                    .push((eff_key, desired, current, is_diff, is_managed));
+            }
+
+            let unreadable = unreadable_domains(tree.keys());
+            let diverged = tree
+                .iter()
+                .filter(|(dom, settings)| {
+                    !unreadable.contains(*dom) && settings.iter().any(|s| s.3)
+                })
+                .count();
+            let clean = tree.len() - diverged - unreadable.len();
+
+            let summary = format!(
+                "{clean} domains clean, {diverged} diverged, {} unreadable",
+                unreadable.len()
+            );
+            if diverged > 0 || !unreadable.is_empty() {
+                log_warn!("{summary}");
+            } else {
+                log_cute!("{summary}");
+            }
+
+            let width = if self.full_values {
+                None
+            } else {
+                terminal_width()
+            };
+
+            for (eff_dom, settings) in &tree {
+                let diffs = settings.iter().filter(|s| s.3).count();
+                let total = settings.len();
+
+                if unreadable.contains(eff_dom) {
+                    log_warn!("{BOLD}{eff_dom}{RESET} (unreadable, {total} settings)");
+                } else if diffs > 0 {
+                    log_warn!("{BOLD}{eff_dom}{RESET} ({diffs} of {total} diverged)");
+                } else if !self.only_diff {
+                    log_info!("{BOLD}{eff_dom}{RESET} ({total} matched)");
+                }
+
+                // matched settings are only printed in verbose mode, so the branches are drawn
+                // for the printed ones
+                let shown: Vec<_> = settings
+                    .iter()
+                    .filter(|s| s.3 || (should_be_verbose() && !self.only_diff))
+                    .collect();
+
+                for (i, (eff_key, desired, current, is_diff, is_managed)) in
+                    shown.iter().enumerate()
+                {
+                    let branch = if i + 1 == shown.len() {
+                        "└─"
+                    } else {
+                        "├─"
+                    };
+
+                    // inline documentation of the setting, if any
+                    let id = (eff_dom.clone(), eff_key.clone());
+                    let mut note = meta
+                        .get(&id)
+                        .map(|m| m.to_string())
+                        .or_else(|| {
+                            catalog::by_effective(eff_dom, eff_key).map(|e| e.desc.to_string())
+                        })
+                        .map(|m| format!(" # {m}"))
+                        .unwrap_or_default();
+                    if local.contains(&id) {
+                        note = format!(" (from {overlay_name}){note}");
+                    }
 
-            for (eff_dom, eff_key, desired, current, is_diff) in outcomes {
-                if !printed_domains.contains(&eff_dom) {
-                    if *domain_has_diff.get(&eff_dom).unwrap_or(&false) {
-                        log_warn!("{BOLD}{eff_dom}{RESET}");
+                    if *is_diff {
+                        drifted.push(format!("{eff_dom} | {eff_key}"));
+
+                        let used = LINE_START + eff_key.len() + ": should be  (now: )".len();
+                        let desired = fit(desired, width, used, 2);
+                        let current = fit(current, width, used, 2);
+                        log_warn!(
+                            "  {branch} {eff_key}: should be {RED}{desired}{RESET} (now: {RED}{current}{RESET}){note}",
+                        );
+                    } else if *is_managed {
+                        let used = LINE_START + "[Managed by profile] ".len() + eff_key.len() + 2;
+                        let current = fit(current, width, used, 1);
+                        log_info!("  {branch} [Managed by profile] {eff_key}: {current}{note}",);
                     } else {
-                        log_info!("{BOLD}{eff_dom}{RESET}");
+                        let used = LINE_START + "[Matched] ".len() + eff_key.len() + 2;
+                        let current = fit(current, width, used, 1);
+                        log_info!("  {branch} {GREEN}[Matched]{RESET} {eff_key}: {current}{note}",);
                     }
-                    printed_domains.insert(eff_dom.clone());
                 }
+            }
+
+            // settings meant for other macOS versions aren't drift
+            for ((eff_dom, eff_key), range) in not_applicable(&meta, current_version()) {
+                if self.only_diff || !self.checks_domain(eff_dom) {
+                    continue;
+                }
+                log_info!("  [Not applicable on this OS] {eff_dom} | {eff_key}: only for {range}");
+            }
+
+            if !managed.is_empty() {
+                log_warn!(
+                    "Managed by a configuration profile, so left as is: {}",
+                    managed.join(", ")
+                );
+            }
+
+            if diverged > 0 || !unreadable.is_empty() {
+                log_warn!("Preferences diverged. Run `cutler apply` to apply changes.",);
+            }
+        }
+
+        // plist status check
+        if !self.brew_only
+            && self.domain.is_none()
+            && let Some(plists) = &config.plist
+        {
+            let _group = group("Plist files");
+            let mut any_diff = false;
 
-                if is_diff {
-                    if !any_diff {
-                        any_diff = true
+            for (raw_path, table) in plists {
+                if is_in_sync(&expand_path(raw_path), table).await {
+                    if !self.only_diff {
+                        log_info!("  {GREEN}[Matched]{RESET} {raw_path}");
                     }
-                    log_warn!(
-                        "  {eff_key}: should be {RED}{desired}{RESET} (now: {RED}{current}{RESET})",
-                    );
                 } else {
-                    log_info!("  {GREEN}[Matched]{RESET} {eff_key}: {current}",);
+                    any_diff = true;
+                    drifted.push(raw_path.clone());
+                    log_warn!("  {BOLD}{raw_path}{RESET}: contents differ from config");
                 }
             }
 
             if any_diff {
-                log_warn!("Preferences diverged. Run `cutler apply` to apply changes.",);
+                log_warn!("Plist files diverged. Run `cutler apply` to write them.",);
             } else {
-                log_cute!("System preferences are on sync.");
+                log_cute!("Plist files are on sync.");
             }
         }
 
         // brew status check
         {
             let toml_brew = config.clone();
-            let no_brew = self.no_brew;
+            let no_brew = self.no_brew || self.prefs_only || self.domain.is_some();
 
             if !no_brew && let Some(brew_val) = toml_brew.brew {
+                let _group = group("Homebrew");
                 log_info!("Homebrew status:");
 
                 // ensure homebrew is installed (skip if not)
@@ -124,34 +467,54 @@ impl Runnable for StatusCmd {
                     log_warn!("Homebrew not available in $PATH, skipping status check for it.",);
                 } else {
                     match diff_brew(brew_val).await {
-                        Ok(BrewDiff {
-                            missing_formulae,
-                            extra_formulae,
-                            missing_casks,
-                            extra_casks,
-                            missing_taps,
-                            extra_taps,
-                        }) => {
+                        Ok(diff) => {
+                            let BrewDiff {
+                                missing_formulae,
+                                extra_formulae,
+                                missing_casks,
+                                extra_casks,
+                                missing_taps,
+                                extra_taps,
+                                unpinned_formulae,
+                                drifted_taps,
+                                ..
+                            } = &diff;
+
                             let mut any_diff = false;
 
                             // Use a single array of tuples to reduce repeated code
                             let brew_checks = [
-                                ("Formulae missing", &missing_formulae),
-                                ("Extra formulae installed", &extra_formulae),
-                                ("Casks missing", &missing_casks),
-                                ("Extra casks installed", &extra_casks),
-                                ("Missing taps", &missing_taps),
-                                ("Extra taps", &extra_taps),
+                                ("Formulae missing", missing_formulae),
+                                ("Extra formulae installed", extra_formulae),
+                                ("Casks missing", missing_casks),
+                                ("Extra casks installed", extra_casks),
+                                ("Missing taps", missing_taps),
+                                ("Extra taps", extra_taps),
+                                ("Formulae not pinned", unpinned_formulae),
                             ];
 
                             for (label, items) in brew_checks.iter() {
                                 if !items.is_empty() {
                                     any_diff = true;
-                                    log_warn!("{BOLD}{label}:{RESET} {}", items.join(", "));
+                                    log_warn!(
+                                        "{BOLD}{label}:{RESET} {}",
+                                        diff.with_versions(items)
+                                    );
                                 }
                             }
 
+                            if !drifted_taps.is_empty() {
+                                any_diff = true;
+                                let taps: Vec<String> =
+                                    drifted_taps.iter().map(|t| t.to_string()).collect();
+                                log_warn!(
+                                    "{BOLD}Taps off their revision:{RESET} {}",
+                                    taps.join(", ")
+                                );
+                            }
+
                             if any_diff {
+                                drifted.push("brew".to_string());
                                 log_warn!("Homebrew diverged.",);
 
                                 if !missing_casks.is_empty()
@@ -162,6 +525,14 @@ impl Runnable for StatusCmd {
                                         "Run `cutler brew install` to install missing software."
                                     )
                                 }
+                                if !unpinned_formulae.is_empty() {
+                                    log_warn!("Run `cutler brew install` to pin formulae.")
+                                }
+                                if !drifted_taps.is_empty() {
+                                    log_warn!(
+                                        "Run `cutler brew install` to check out the pinned revisions."
+                                    )
+                                }
                                 if !extra_casks.is_empty()
                                     || !extra_formulae.is_empty()
                                     || !extra_taps.is_empty()
@@ -180,6 +551,75 @@ impl Runnable for StatusCmd {
             }
         }
 
+        // mas status check
+        if self.is_full_check()
+            && let Some(mas) = &config.mas
+        {
+            let _group = group("Mac App Store");
+            log_info!("Mac App Store status:");
+
+            if !mas_is_installed().await {
+                log_warn!("mas not available in $PATH, skipping status check for it.",);
+            } else {
+                match mas_list().await {
+                    Ok(installed) => {
+                        let diff = diff_mas(mas, &installed);
+
+                        let mas_checks = [
+                            ("Apps missing", &diff.missing),
+                            ("Extra apps installed", &diff.extra),
+                            ("Name mismatches", &diff.mismatched),
+                        ];
+
+                        let mut any_diff = false;
+
+                        for (label, items) in mas_checks.iter() {
+                            if !items.is_empty() {
+                                any_diff = true;
+                                log_warn!("{BOLD}{label}:{RESET} {}", items.join(", "));
+                            }
+                        }
+
+                        if any_diff {
+                            drifted.push("mas".to_string());
+                            log_warn!("Mac App Store apps diverged.",);
+                        } else {
+                            log_cute!("Mac App Store apps on sync.");
+                        }
+                    }
+                    Err(e) => {
+                        log_err!("Could not check Mac App Store status: {e}",);
+                    }
+                }
+            }
+        }
+
+        // a partial check says nothing about the rest, so it isn't cached
+        if self.is_full_check() && !self.no_brew {
+            StatusCache::record(&config.path, drifted.clone()).await;
+        }
+
+        if self.quiet_json {
+            let json = serde_json::json!({
+                "in_sync": drifted.is_empty(),
+                "drifted": drifted,
+            });
+            println!("{json}");
+        }
+
+        if !drifted.is_empty() {
+            notify_event(
+                config,
+                NotifyEvent::Drift,
+                "Your system drifted from the config. Run `cutler status` for details.",
+            )
+            .await;
+
+            if self.exit_code {
+                return Err(drift_error());
+            }
+        }
+
         Ok(())
     }
 }
@@ -3,45 +3,214 @@
 use anyhow::{Result, bail};
 use async_trait::async_trait;
 use clap::Args;
-use defaults_rs::{Domain, Preferences};
+use defaults_rs::Domain;
 use std::collections::HashMap;
+use tokio::process::Command;
 
 use crate::{
+    brew::core::brew_is_installed,
     cli::atomic::should_dry_run,
     commands::{ResetCmd, Runnable},
     config::core::Config,
-    domains::convert::serializable_to_prefvalue,
-    log_cute, log_dry, log_err, log_info, log_warn,
-    snapshot::{core::Snapshot, get_snapshot_path},
+    domains::{
+        convert::{display_prefvalue, serializable_to_prefvalue},
+        effective, files, read_current, split_setting_path, spotlight,
+    },
+    ephemeral::{self, EphemeralState},
+    exec::core,
+    log_dry, log_err, log_info, log_warn, plist,
+    snapshot::{
+        core::{BrewChanges, SettingState, Snapshot},
+        get_snapshot_path,
+    },
     util::{
+        exit::{ExitCode, coded},
+        history::{self, Change},
         io::{confirm, restart_services},
         sha::get_digest,
+        summary,
     },
 };
 
-#[derive(Args, Debug)]
-pub struct UnapplyCmd;
+#[derive(Args, Debug, Default)]
+pub struct UnapplyCmd {
+    /// Only unapply a single setting, e.g. `dock.tilesize` (repeatable).
+    #[arg(
+        long,
+        visible_alias = "only",
+        value_name = "DOMAIN.KEY",
+        conflicts_with = "ephemeral"
+    )]
+    key: Vec<String>,
+
+    /// Revert the pending `apply --ephemeral` now, leaving the snapshot alone.
+    #[arg(long)]
+    ephemeral: bool,
+
+    /// With --ephemeral, wait until the revert is due (used by the LaunchAgent).
+    #[arg(long, requires = "ephemeral")]
+    wait: bool,
+
+    /// Also uninstall the formulae/casks and untap the taps installed by `apply --brew`.
+    #[arg(long, conflicts_with_all = &["key", "ephemeral"])]
+    brew: bool,
+}
+
+/// Helper for: UnapplyCmd::run()
+/// Uninstalls software recorded by `apply --brew`, casks and formulae first, then taps.
+async fn revert_brew(changes: &BrewChanges, dry_run: bool) -> Result<()> {
+    let steps = [
+        (&changes.casks, vec!["uninstall", "--cask"]),
+        (&changes.formulae, vec!["uninstall", "--formula"]),
+        (&changes.taps, vec!["untap"]),
+    ];
+
+    for (names, args) in steps {
+        for name in names {
+            let cmdline = format!("brew {} {name}", args.join(" "));
+
+            if dry_run {
+                log_dry!("Would run: {cmdline}");
+                continue;
+            }
+
+            log_info!("Running: {cmdline}");
+            let status = Command::new("brew").args(&args).arg(name).status().await?;
+
+            if status.success() {
+                summary::add_applied(1);
+            } else {
+                log_err!("Failed: {cmdline}");
+                summary::add_failure(cmdline);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Helper for: UnapplyCmd::run(), UnapplyCmd::revert_ephemeral()
+/// Describes reverting a setting for the history, reading its value from right before.
+async fn revert_change(s: &SettingState) -> Change {
+    Change {
+        domain: s.domain.clone(),
+        key: s.key.clone(),
+        from: read_current(&s.domain, &s.key)
+            .await
+            .ok()
+            .flatten()
+            .map(|v| display_prefvalue(&v)),
+        to: s
+            .original_value
+            .as_ref()
+            .map(|v| display_prefvalue(&serializable_to_prefvalue(v))),
+    }
+}
+
+impl UnapplyCmd {
+    /// Unapplies the whole snapshot, like `cutler unapply [--brew]`.
+    pub fn everything(brew: bool) -> Self {
+        UnapplyCmd {
+            brew,
+            ..Default::default()
+        }
+    }
+
+    /// Reverts the pending ephemeral apply right away, like `cutler unapply --ephemeral`.
+    pub fn pending_ephemeral() -> Self {
+        UnapplyCmd {
+            ephemeral: true,
+            ..Default::default()
+        }
+    }
+
+    /// Helper for: run()
+    /// Reverts the pending ephemeral apply.
+    async fn revert_ephemeral(&self, config: &Config) -> Result<()> {
+        let Some(state) = EphemeralState::load(&config.path).await else {
+            // nothing left for the agent to do either
+            if self.wait {
+                ephemeral::remove_agent().await;
+            }
+            bail!("No ephemeral apply is pending.")
+        };
+
+        if self.wait {
+            state.wait().await;
+        }
+
+        if should_dry_run() {
+            for s in &state.settings {
+                log_dry!("Would revert: {} | {}", s.domain, s.key);
+            }
+            return Ok(());
+        }
+
+        let mut changes = Vec::with_capacity(state.settings.len());
+        for s in &state.settings {
+            changes.push(revert_change(s).await);
+        }
+
+        summary::begin();
+        match state.revert() {
+            Ok(_) => {
+                summary::add_applied(state.settings.len());
+                changes.into_iter().for_each(history::add_change);
+            }
+            Err(e) => {
+                log_err!("Revert failed: {e}");
+                for s in &state.settings {
+                    summary::add_failure(format!("{} | {}", s.domain, s.key));
+                }
+            }
+        }
+        EphemeralState::delete(&config.path).await?;
+        restart_services().await;
+        if let Some(summary) = summary::finish("Unapply") {
+            history::record(&config.path, "unapply", &summary).await;
+        }
+
+        // last, since launchd stops the agent's own process here
+        ephemeral::remove_agent().await;
+
+        Ok(())
+    }
+}
 
 #[async_trait]
 impl Runnable for UnapplyCmd {
     async fn run(&self, config: &mut Config) -> Result<()> {
-        config.load(true).await?;
+        if self.ephemeral {
+            return self.revert_ephemeral(config).await;
+        }
+
+        // one-off unapplies only touch the snapshot, but still honor the lock of the config
+        let one_off = !self.key.is_empty();
+
+        if !one_off || config.is_loadable() {
+            config.load(true).await?;
+        }
 
         if !Snapshot::is_loadable().await {
+            if one_off {
+                bail!("No snapshot found to revert from.")
+            }
+
             log_warn!("No snapshot found to revert.");
 
             if confirm("Reset all System Settings instead?") {
-                return ResetCmd.run(config).await;
+                return ResetCmd::default().run(config).await;
             } else {
                 bail!("Abort operation.")
             }
         }
 
         let dry_run = should_dry_run();
+        summary::begin();
 
         // load snapshot from disk
         let snap_path = get_snapshot_path().await?;
-        let snapshot = match Snapshot::load(&snap_path).await {
+        let mut snapshot = match Snapshot::load(&snap_path).await {
             Ok(snap) => snap,
             Err(_) => {
                 bail!(
@@ -51,23 +220,55 @@ impl Runnable for UnapplyCmd {
             }
         };
 
-        if snapshot.digest != get_digest(config.path.clone())? {
-            log_warn!("Config has been modified since last application.",);
-            log_warn!("Please note that only the applied modifications will be unapplied.",);
-        }
+        // split off the selected settings, keeping the rest in the snapshot
+        let settings = if one_off {
+            let mut selected = Vec::new();
+
+            for path in &self.key {
+                let (domain, key) = split_setting_path(path)?;
+                let (eff_dom, eff_key) = effective(&domain, &key);
+
+                match snapshot
+                    .settings
+                    .iter()
+                    .position(|s| s.domain == eff_dom && s.key == eff_key)
+                {
+                    Some(idx) => selected.push(snapshot.settings.remove(idx)),
+                    None => bail!("{eff_dom} | {eff_key} was not applied by cutler."),
+                }
+            }
+
+            selected
+        } else {
+            if snapshot.digest != get_digest(config.path.clone())? {
+                log_warn!("Config has been modified since last application.",);
+                log_warn!("Please note that only the applied modifications will be unapplied.",);
+            }
+
+            snapshot.settings.clone()
+        };
 
         // prepare undo operations, grouping by domain for efficiency
         let mut batch_restores: HashMap<Domain, Vec<(String, defaults_rs::PrefValue)>> =
             HashMap::new();
         let mut batch_deletes: HashMap<Domain, Vec<String>> = HashMap::new();
+        let mut restore_changes = Vec::new();
+        let mut delete_changes = Vec::new();
+
+        let touched: Vec<String> = settings.iter().map(|s| s.domain.clone()).collect();
 
         // reverse order to undo in correct sequence
-        for s in snapshot.settings.clone().into_iter().rev() {
-            let domain_obj = if s.domain == "NSGlobalDomain" {
-                Domain::Global
-            } else {
-                Domain::User(s.domain.clone())
-            };
+        for s in settings.into_iter().rev() {
+            if !dry_run {
+                let change = revert_change(&s).await;
+                if s.original_value.is_some() {
+                    restore_changes.push(change);
+                } else {
+                    delete_changes.push(change);
+                }
+            }
+
+            let domain_obj = files::domain_obj(&s.domain)?;
             if let Some(orig) = s.original_value {
                 let pref_value = serializable_to_prefvalue(&orig);
                 batch_restores
@@ -79,6 +280,11 @@ impl Runnable for UnapplyCmd {
             }
         }
 
+        // restore fully managed plist files (not for one-off unapplies)
+        if !one_off {
+            plist::core::restore_plists(&snapshot.plists, dry_run).await?;
+        }
+
         // in dry-run mode, just print what would be done
         if dry_run {
             for (domain, restores) in &batch_restores {
@@ -101,8 +307,17 @@ impl Runnable for UnapplyCmd {
                         batch_vec.push((domain.clone(), key, value));
                     }
                 }
-                if let Err(e) = Preferences::write_batch(batch_vec.clone()) {
-                    log_err!("Batch restore failed: {e}");
+                match files::write_batch(batch_vec.clone()) {
+                    Ok(_) => {
+                        summary::add_applied(batch_vec.len());
+                        restore_changes.into_iter().for_each(history::add_change);
+                    }
+                    Err(e) => {
+                        log_err!("Batch restore failed: {e}");
+                        for (domain, key, _) in &batch_vec {
+                            summary::add_failure(format!("{domain} | {key}"));
+                        }
+                    }
                 }
             }
 
@@ -115,17 +330,85 @@ impl Runnable for UnapplyCmd {
                         delete_vec.push((domain.clone(), key));
                     }
                 }
-                if let Err(e) = Preferences::delete_batch(delete_vec.clone()) {
-                    log_err!("Batch delete failed: {e}");
+                match files::delete_batch(delete_vec.clone()) {
+                    Ok(_) => {
+                        summary::add_applied(delete_vec.len());
+                        delete_changes.into_iter().for_each(history::add_change);
+                    }
+                    Err(e) => {
+                        log_err!("Batch delete failed: {e}");
+                        for (domain, key) in &delete_vec {
+                            summary::add_failure(format!("{domain} | {key}"));
+                        }
+                    }
                 }
             }
         }
 
-        // warn about external command execution
-        if snapshot.exec_run_count > 0 {
+        spotlight::reload(touched.iter().map(String::as_str), dry_run);
+
+        // keep the snapshot around for the remaining settings
+        if one_off {
+            if dry_run {
+                log_dry!("Would update snapshot file at {snap_path:?}",);
+            } else {
+                snapshot.save().await?;
+                log_info!("Updated snapshot file at {snap_path:?}",);
+            }
+
+            restart_services().await;
+
+            return finish(config, dry_run).await;
+        }
+
+        // uninstall software installed by `apply --brew`
+        if !snapshot.brew.is_empty() {
+            let BrewChanges {
+                formulae,
+                casks,
+                taps,
+            } = &snapshot.brew;
+            let listing = format!(
+                "{} formulae, {} casks and {} taps",
+                formulae.len(),
+                casks.len(),
+                taps.len()
+            );
+
+            if !self.brew {
+                log_warn!("Keeping {listing} installed by cutler. Pass --brew to remove them.");
+            } else if !brew_is_installed().await {
+                log_warn!("Homebrew not available in $PATH, cannot remove {listing}.");
+            } else if dry_run || confirm(&format!("Uninstall {listing} installed by cutler?")) {
+                revert_brew(&snapshot.brew, dry_run).await?;
+            } else {
+                log_warn!("Skipped uninstalling Homebrew software.");
+            }
+        } else if self.brew {
+            log_info!("No Homebrew software was installed by cutler.");
+        }
+
+        // revert external commands which know how to
+        if !snapshot.undo.is_empty() {
+            let names: Vec<&str> = snapshot.undo.iter().map(|u| u.name.as_str()).collect();
+
+            if dry_run || confirm(&format!("Run undo scripts of: {}?", names.join(", "))) {
+                let (successes, failures) = core::run_undo(&snapshot.undo).await;
+                summary::add_commands(successes as usize);
+
+                if failures > 0 {
+                    log_warn!("{failures} undo scripts failed",);
+                }
+            } else {
+                log_warn!("Skipped undo scripts; revert those commands manually.");
+            }
+        }
+
+        // warn about the rest of external command execution
+        let manual = snapshot.exec_run_count - snapshot.undo.len() as i32;
+        if manual > 0 {
             log_warn!(
-                "{} commands were executed previously; revert them manually.",
-                snapshot.exec_run_count
+                "{manual} commands without `undo` were executed previously; revert them manually.",
             );
         }
 
@@ -140,8 +423,27 @@ impl Runnable for UnapplyCmd {
         // Restart system services if requested
         restart_services().await;
 
-        log_cute!("Unapply operation complete.");
+        finish(config, dry_run).await
+    }
+}
+
+/// Helper for: run()
+/// Ends the run, recording it in the history, and errors out if it only partially succeeded.
+async fn finish(config: &Config, dry_run: bool) -> Result<()> {
+    let summary = summary::finish("Unapply");
 
-        Ok(())
+    if let Some(summary) = &summary
+        && !dry_run
+    {
+        history::record(&config.path, "unapply", summary).await;
     }
+
+    if summary.is_some_and(|s| s.is_partial()) {
+        return Err(coded(
+            ExitCode::PartialApply,
+            "Unapply did not fully succeed.",
+        ));
+    }
+
+    Ok(())
 }
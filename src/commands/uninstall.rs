@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::Args;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+use tokio::{fs, process::Command};
+
+use crate::{
+    autosync::get_last_sync_path,
+    bootstrap::get_checkpoint_path,
+    cli::atomic::should_dry_run,
+    commands::{
+        Runnable, UnapplyCmd,
+        self_update::{Installer, MANPAGE_PATH},
+    },
+    config::{core::Config, remote::get_remote_cache_path},
+    ephemeral::{self, EphemeralState, get_ephemeral_path},
+    exec::logs::get_log_dir,
+    log_cute, log_dry, log_info, log_warn,
+    snapshot::{Snapshot, crypt, get_snapshot_path},
+    util::{history::get_history_path, io::confirm, status_cache::get_status_cache_path},
+};
+
+#[derive(Args, Debug)]
+pub struct UninstallCmd {
+    /// Leave the applied settings in place instead of unapplying them.
+    #[arg(long)]
+    keep_settings: bool,
+
+    /// Also uninstall the formulae/casks and untap the taps installed by `apply --brew`.
+    #[arg(long, conflicts_with = "keep_settings")]
+    brew: bool,
+
+    /// Also remove the LaunchAgents left behind by cutler.
+    #[arg(long)]
+    agents: bool,
+
+    /// Remove the cutler binary and manpage too, instead of printing how to.
+    #[arg(long)]
+    remove_binary: bool,
+}
+
+/// Lists the state and cache files cutler keeps for the given config and snapshot.
+/// The config itself is not part of it, and neither are the plist backups, which are only known
+/// through the snapshot.
+pub fn state_files(config_path: &Path, snapshot_path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(vec![
+        snapshot_path.to_path_buf(),
+        get_ephemeral_path(config_path)?,
+        get_checkpoint_path(config_path)?,
+        get_status_cache_path(config_path)?,
+        get_history_path(config_path)?,
+        get_remote_cache_path(config_path)?,
+        get_last_sync_path(config_path)?,
+        get_log_dir()?,
+    ])
+}
+
+impl UninstallCmd {
+    /// Helper for: run()
+    /// Reverts the pending ephemeral apply and the snapshot.
+    async fn unapply(&self, config: &mut Config) -> Result<()> {
+        if EphemeralState::load(&config.path).await.is_some() {
+            UnapplyCmd::pending_ephemeral().run(config).await?;
+        }
+
+        if Snapshot::is_loadable().await {
+            UnapplyCmd::everything(self.brew).run(config).await
+        } else {
+            log_info!("No snapshot found, nothing to unapply.");
+            Ok(())
+        }
+    }
+
+    /// Helper for: remove_state()
+    /// Removes the plist backups recorded in the snapshot, which outlive it with --keep-settings.
+    /// The `backups` directory itself only goes if nothing else is left in it.
+    async fn remove_backups(&self, snapshot_path: &PathBuf) -> Result<()> {
+        if !fs::try_exists(snapshot_path).await.unwrap_or_default() {
+            return Ok(());
+        }
+
+        let snapshot = Snapshot::load(snapshot_path).await?;
+        let backups: Vec<PathBuf> = snapshot
+            .plists
+            .iter()
+            .filter_map(|state| state.backup.as_ref().map(PathBuf::from))
+            .collect();
+
+        for backup in &backups {
+            if !fs::try_exists(backup).await.unwrap_or_default() {
+                continue;
+            }
+
+            if should_dry_run() {
+                log_dry!("Would remove {backup:?}");
+            } else {
+                fs::remove_file(backup).await?;
+                log_info!("Removed {backup:?}");
+            }
+        }
+
+        if !should_dry_run()
+            && let Some(dir) = snapshot_path.parent()
+        {
+            fs::remove_dir(dir.join("backups")).await.ok();
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    async fn remove_state(&self, config: &Config) -> Result<()> {
+        let dry_run = should_dry_run();
+        let snapshot_path = get_snapshot_path().await?;
+
+        // the snapshot lists the backups and may need the Keychain key to be read
+        self.remove_backups(&snapshot_path).await?;
+
+        if dry_run {
+            log_dry!("Would remove the snapshot key from the Keychain, if any");
+        } else if crypt::delete_key().await? {
+            log_info!("Removed the snapshot key from the Keychain");
+        }
+
+        for path in state_files(&config.path, &snapshot_path)? {
+            let Ok(meta) = fs::metadata(&path).await else {
+                continue;
+            };
+
+            if dry_run {
+                log_dry!("Would remove {path:?}");
+            } else if meta.is_dir() {
+                fs::remove_dir_all(&path).await?;
+                log_info!("Removed {path:?}");
+            } else {
+                fs::remove_file(&path).await?;
+                log_info!("Removed {path:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Helper for: run()
+    /// Removes the binary and manpage through the package manager, or by hand.
+    async fn remove_binary(&self) -> Result<()> {
+        let exe = env::current_exe()?.to_string_lossy().into_owned();
+        let argv = match Installer::detect() {
+            Some(installer) => installer.uninstall_command().to_vec(),
+            None => vec!["sudo", "rm", "-f", &exe, MANPAGE_PATH],
+        };
+        let command = argv.join(" ");
+
+        if !self.remove_binary {
+            log_cute!("To remove cutler itself, run: {command}");
+            return Ok(());
+        }
+
+        if should_dry_run() {
+            log_dry!("Would run: {command}");
+            return Ok(());
+        }
+
+        let Some((program, args)) = argv.split_first() else {
+            bail!("Don't know how to remove cutler from {exe}; remove it by hand.")
+        };
+
+        log_info!("Running: {command}");
+        let status = Command::new(program).args(args).status().await?;
+        if !status.success() {
+            bail!("`{command}` exited with {status}")
+        }
+
+        log_cute!("Removed cutler from this machine.");
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Runnable for UninstallCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        let dry_run = should_dry_run();
+
+        if !dry_run
+            && !confirm(if self.keep_settings {
+                "Remove cutler's snapshot and state, keeping the applied settings?"
+            } else {
+                "Unapply everything and remove cutler's snapshot and state?"
+            })
+        {
+            bail!("Aborted uninstall.")
+        }
+
+        if self.keep_settings {
+            log_warn!(
+                "Keeping applied settings; they can't be unapplied once the snapshot is gone."
+            );
+        } else {
+            self.unapply(config).await?;
+        }
+
+        let agent = ephemeral::get_agent_path()?;
+        if fs::try_exists(&agent).await.unwrap_or_default() {
+            if !self.agents {
+                log_warn!("Keeping LaunchAgent at {agent:?}. Pass --agents to remove it.");
+            } else if dry_run {
+                log_dry!("Would remove LaunchAgent at {agent:?}");
+            } else {
+                ephemeral::remove_agent().await;
+                log_info!("Removed LaunchAgent at {agent:?}");
+            }
+        }
+
+        self.remove_state(config).await?;
+
+        if config.is_loadable() {
+            log_info!(
+                "Kept config at {:?}; delete it yourself if you no longer need it.",
+                config.path
+            );
+        }
+
+        self.remove_binary().await
+    }
+}
@@ -2,19 +2,64 @@
 
 use async_trait::async_trait;
 use clap::Args;
+use tokio::process::Command;
 
 use anyhow::{Result, bail};
 
 use crate::{
     cli::atomic::should_dry_run,
     commands::Runnable,
-    config::core::{Config, ConfigCoreMethods},
-    log_dry,
+    config::core::{Config, ConfigCoreMethods, Lock},
+    log_cute, log_dry, log_warn,
+    util::io::ask,
 };
 
+/// Keychain item holding the passphrase `cutler unlock` asks for, if set up.
+pub const UNLOCK_SERVICE: &str = "cutler-unlock";
+
+/// What has to be typed to lift a remote lock when there's no passphrase.
+pub const CONFIRM_PHRASE: &str = "unlock anyway";
+
 #[derive(Debug, Args)]
 pub struct UnlockCmd;
 
+/// Helper for: UnlockCmd::run()
+/// Reads the unlock passphrase from the Keychain.
+async fn stored_passphrase() -> Option<String> {
+    let out = Command::new("security")
+        .args(["find-generic-password", "-s", UNLOCK_SERVICE, "-w"])
+        .output()
+        .await
+        .ok()?;
+
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim_end().to_string())
+}
+
+/// Helper for: UnlockCmd::run()
+/// Makes sure the user may lift the lock: with a passphrase in the Keychain, it has to be
+/// typed in, and a remote lock has to be confirmed with `CONFIRM_PHRASE`.
+async fn authorize(remote_locked: bool) -> Result<()> {
+    if let Some(passphrase) = stored_passphrase().await {
+        let Some(answer) = ask("Passphrase to unlock the config", true) else {
+            bail!("Unlocking needs the passphrase stored in the Keychain ({UNLOCK_SERVICE}).")
+        };
+        if answer != passphrase {
+            bail!("Wrong passphrase, the config stays locked.")
+        }
+    } else if remote_locked {
+        let Some(answer) = ask(&format!("Type `{CONFIRM_PHRASE}` to continue"), false) else {
+            bail!("Unlocking a remote lock has to be confirmed interactively.")
+        };
+        if answer.trim() != CONFIRM_PHRASE {
+            bail!("Aborted unlock.")
+        }
+    }
+
+    Ok(())
+}
+
 #[async_trait]
 impl Runnable for UnlockCmd {
     async fn run(&self, config: &mut Config) -> Result<()> {
@@ -25,17 +70,40 @@ impl Runnable for UnlockCmd {
         let mut document = config.load_as_mut(false).await?;
         let dry_run = should_dry_run();
 
-        if !document
-            .get("lock")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false)
-        {
+        let Some(lock) =
+            Lock::read(&document.to_string()).filter(|lock| *lock != Lock::Plain(false))
+        else {
             bail!("Already unlocked.")
-        } else if dry_run {
+        };
+
+        // an expired lock is no longer in effect, so it's just cleaned up
+        if !lock.is_active_now() {
+            if dry_run {
+                log_dry!("Would remove the expired lock from config file.");
+            } else {
+                document.remove("lock");
+                document.save(&config.path).await?;
+                log_cute!("The lock had expired already, removed it from the config.");
+            }
+            return Ok(());
+        }
+
+        // only a lock the remote config sets is the remote's
+        config.load(false).await?;
+        let remote_locked = config.is_remote_locked();
+        if remote_locked {
+            log_warn!(
+                "This config is locked by its remote config. Unless the lock is lifted there, the next sync locks it again."
+            );
+        }
+
+        if dry_run {
             log_dry!("Would unlock config file.");
             return Ok(());
         }
 
+        authorize(remote_locked).await?;
+
         document.remove("lock");
         document.save(&config.path).await?;
 
@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::time::{MissedTickBehavior, sleep};
+
+use crate::{
+    autosync::parse_interval,
+    cli::atomic::{set_no_prompts, should_not_prompt},
+    commands::{ApplyCmd, Runnable, apply::TooManyChanges},
+    config::{core::Config, path::get_overlay_path},
+    domains::{ignored_keys, plan::apply_plan},
+    log_cute, log_err, log_info, log_warn,
+    util::notify::{NotifyEvent, notify_event},
+};
+
+/// How often the system is checked for drift, unless configured.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Minimum time between two heals, unless configured.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+/// How long the config must be left alone before a change is applied, since editors often
+/// write a file several times while saving.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Args, Debug)]
+pub struct WatchCmd {
+    /// How often to check for drift, e.g. "30s" or "5m" (overrides [daemon] interval).
+    #[arg(long, value_name = "DURATION", value_parser = parse_interval)]
+    interval: Option<Duration>,
+
+    /// Re-apply the config when the system drifted, like [daemon] auto_heal = true.
+    #[arg(long)]
+    heal: bool,
+
+    /// Also run external commands when applying a changed config.
+    #[arg(long)]
+    exec: bool,
+}
+
+/// The files making up a config, with symlinks resolved, so that saves can be told apart from
+/// other changes next to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigFiles {
+    /// The config directory, or the config file and its local overlay.
+    paths: Vec<PathBuf>,
+    is_dir: bool,
+}
+
+/// Helper for: ConfigFiles::new()
+/// Resolves symlinks, including for files which don't exist yet (like a missing overlay).
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = std::fs::canonicalize(path) {
+        return path;
+    }
+
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => std::fs::canonicalize(parent)
+            .map(|p| p.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl ConfigFiles {
+    pub fn new(config_path: &Path) -> Self {
+        if config_path.is_dir() {
+            ConfigFiles {
+                paths: vec![resolve(config_path)],
+                is_dir: true,
+            }
+        } else {
+            ConfigFiles {
+                paths: vec![
+                    resolve(config_path),
+                    resolve(&get_overlay_path(config_path)),
+                ],
+                is_dir: false,
+            }
+        }
+    }
+
+    /// The directories to watch. Files are never watched directly, since editors usually save
+    /// by replacing them.
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        if self.is_dir {
+            return self.paths.clone();
+        }
+
+        let mut dirs: Vec<PathBuf> = self
+            .paths
+            .iter()
+            .filter_map(|p| p.parent().map(Path::to_path_buf))
+            .collect();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Whether a changed path belongs to the config.
+    pub fn contains(&self, changed: &Path) -> bool {
+        if self.is_dir {
+            changed.parent() == Some(self.paths[0].as_path())
+                && changed.extension().is_some_and(|ext| ext == "toml")
+        } else {
+            self.paths.iter().any(|p| p == changed)
+        }
+    }
+
+    /// Helper for: WatchCmd::run()
+    fn touched_by(&self, event: &Event) -> bool {
+        !matches!(event.kind, EventKind::Access(_)) && event.paths.iter().any(|p| self.contains(p))
+    }
+}
+
+/// Helper for: WatchCmd::run()
+/// Starts watching the config files, sending every event to the returned channel.
+/// The watcher stops once dropped.
+fn watch_files(files: &ConfigFiles) -> Result<(RecommendedWatcher, UnboundedReceiver<Event>)> {
+    let (tx, rx) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    })?;
+
+    for dir in files.dirs() {
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok((watcher, rx))
+}
+
+/// Helper for: WatchCmd::run()
+/// Waits until the config was left alone for DEBOUNCE.
+async fn settle(events: &mut UnboundedReceiver<Event>) {
+    loop {
+        tokio::select! {
+            Some(_) = events.recv() => {}
+            _ = sleep(DEBOUNCE) => break,
+        }
+    }
+}
+
+/// Helper for: WatchCmd::run()
+/// Lists the settings which differ from the config, leaving out ignored keys.
+async fn drifted(config: &Config) -> Result<Vec<String>> {
+    let plan = apply_plan(config).await?;
+    let ignored = ignored_keys(config);
+
+    let mut drift: Vec<String> = plan
+        .changes()
+        .filter(|e| !ignored.contains(&(e.domain.clone(), e.key.clone())))
+        .map(|e| format!("{} | {}", e.domain, e.key))
+        .collect();
+    drift.sort();
+
+    Ok(drift)
+}
+
+impl WatchCmd {
+    /// Helper for: run()
+    /// Applies the config after it was saved.
+    async fn reapply(&self, config: &mut Config) -> Result<()> {
+        let apply = if self.exec {
+            ApplyCmd::default()
+        } else {
+            ApplyCmd::settings_only()
+        };
+
+        apply.run(config).await
+    }
+
+    /// Helper for: run()
+    /// Checks once, healing if enabled. Returns the drifted settings.
+    async fn check(
+        &self,
+        config: &mut Config,
+        last_heal: &mut Option<Instant>,
+        reported: &[String],
+    ) -> Result<Vec<String>> {
+        config.load(false).await?;
+        let daemon = config.daemon.clone().unwrap_or_default();
+
+        let drift = drifted(config).await?;
+        if drift.is_empty() {
+            log_info!("No drift detected.");
+            return Ok(drift);
+        }
+
+        log_warn!("System drifted: {}", drift.join(", "));
+
+        if !self.heal && !daemon.auto_heal.unwrap_or_default() {
+            // only notify once for the same drift
+            if drift != reported {
+                notify_event(
+                    config,
+                    NotifyEvent::Drift,
+                    "Your system drifted from the config. Run `cutler status` for details.",
+                )
+                .await;
+            }
+            return Ok(drift);
+        }
+
+        let cooldown = match &daemon.cooldown {
+            Some(text) => parse_interval(text)?,
+            None => DEFAULT_COOLDOWN,
+        };
+        if let Some(last) = last_heal
+            && last.elapsed() < cooldown
+        {
+            log_warn!("Healed less than {}s ago, waiting.", cooldown.as_secs());
+            return Ok(drift);
+        }
+        *last_heal = Some(Instant::now());
+
+        // settings only, external commands aren't meant to run over and over, and without
+        // prompts since nobody is around to answer them
+        let prompts_off = should_not_prompt();
+        set_no_prompts(true);
+        let healed = ApplyCmd::settings_only().run(config).await;
+        set_no_prompts(prompts_off);
+
+        match healed {
+            Ok(()) => {}
+            Err(e) if e.downcast_ref::<TooManyChanges>().is_some() => {
+                log_warn!(
+                    "Skipped healing, too many settings drifted. Run `cutler apply` to confirm."
+                );
+                if drift != reported {
+                    notify_event(
+                        config,
+                        NotifyEvent::Drift,
+                        "Too many settings drifted to heal unattended. Run `cutler apply` to confirm.",
+                    )
+                    .await;
+                }
+                return Ok(drift);
+            }
+            Err(e) => return Err(e),
+        }
+        notify_event(
+            config,
+            NotifyEvent::Heal,
+            &format!("Re-applied {} drifted settings.", drift.len()),
+        )
+        .await;
+
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl Runnable for WatchCmd {
+    async fn run(&self, config: &mut Config) -> Result<()> {
+        config.load(false).await?;
+        let daemon = config.daemon.clone().unwrap_or_default();
+
+        let interval = match (self.interval, &daemon.interval) {
+            (Some(interval), _) => interval,
+            (None, Some(text)) => parse_interval(text)?,
+            (None, None) => DEFAULT_INTERVAL,
+        };
+
+        let files = ConfigFiles::new(&config.path);
+        let (_watcher, mut events) = match watch_files(&files) {
+            Ok((watcher, events)) => (Some(watcher), events),
+            Err(e) => {
+                log_warn!("Could not watch the config for changes: {e}");
+                (None, unbounded_channel().1)
+            }
+        };
+
+        log_cute!(
+            "Watching the config, and for drift every {}s. Press Ctrl-C to stop.",
+            interval.as_secs()
+        );
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut last_heal = None;
+        let mut reported = Vec::new();
+
+        loop {
+            tokio::select! {
+                Some(event) = events.recv() => {
+                    if !files.touched_by(&event) {
+                        continue;
+                    }
+                    settle(&mut events).await;
+
+                    // a broken config mid-edit shouldn't end the watch either
+                    log_info!("Config changed, applying.");
+                    if let Err(e) = self.reapply(config).await {
+                        log_err!("{e}");
+                    }
+                }
+                _ = ticker.tick() => {
+                    // a failed check (e.g. a broken config mid-edit) shouldn't end the watch
+                    match self.check(config, &mut last_heal, &reported).await {
+                        Ok(drift) => reported = drift,
+                        Err(e) => log_err!("{e}"),
+                    }
+                }
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        log_cute!("Stopped watching.");
+
+        Ok(())
+    }
+}
@@ -1,16 +1,31 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    env::consts::ARCH,
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result, bail};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, de};
 use tokio::fs;
 use toml::Value;
+use toml::value::Datetime;
 use toml_edit::DocumentMut;
 
+use crate::config::path::get_overlay_path;
+use crate::config::remote::{cached_remote_lock, deep_merge};
+use crate::domains::convert::datetime_to_unix;
+use crate::log_info;
+use crate::snapshot::crypt::set_encryption;
+use crate::util::exit::{ExitCode, coded};
+use crate::util::history::format_timestamp;
+use crate::util::limits::set_config_jobs;
+use crate::util::logging::set_theme;
+use crate::util::macos::MacosVersion;
+use crate::util::net::set_network;
+
 /// Struct representing a cutler configuration.
 ///
 /// This is a fully serde-compatible struct primarily meant to be used within cutler's source code
@@ -18,15 +33,253 @@ use toml_edit::DocumentMut;
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
-    pub lock: Option<bool>,
+    pub lock: Option<Lock>,
+    /// Default for `--jobs`, the maximum amount of concurrent jobs.
+    pub jobs: Option<usize>,
     pub set: Option<HashMap<String, HashMap<String, Value>>>,
-    pub vars: Option<HashMap<String, String>>,
+    pub plist: Option<HashMap<String, toml::Table>>,
+    /// Per-app menu shortcuts: app domain → menu title → shortcut, e.g. "cmd+shift+k".
+    pub app_shortcuts: Option<HashMap<String, HashMap<String, String>>>,
+    pub hot_corners: Option<HotCorners>,
+    pub screenshots: Option<Screenshots>,
+    pub trackpad: Option<Trackpad>,
+    pub spotlight: Option<Spotlight>,
+    pub softwareupdate: Option<SoftwareUpdate>,
+    /// Menu bar items by name → whether they're shown, e.g. "bluetooth" = true.
+    pub menubar: Option<BTreeMap<String, bool>>,
+    /// System-wide text replacements: shortcut → phrase, e.g. "omw" = "On my way!".
+    pub text_replacements: Option<BTreeMap<String, String>>,
+    pub vars: Option<HashMap<String, Var>>,
     pub command: Option<HashMap<String, Command>>,
+    pub group: Option<HashMap<String, Group>>,
+    pub exec: Option<Exec>,
     pub brew: Option<Brew>,
     pub mas: Option<Mas>,
-    pub remote: Option<Remote>,
+    pub remote: Option<Remotes>,
+    pub notify: Option<Notify>,
+    pub daemon: Option<Daemon>,
+    pub status: Option<Status>,
+    pub prompt: Option<Prompt>,
+    pub snapshot: Option<SnapshotConfig>,
+    pub theme: Option<Theme>,
+    pub safety: Option<Safety>,
+    pub git: Option<Git>,
+    pub network: Option<Network>,
+    pub update: Option<UpdateConfig>,
+    /// Tables owned by `cutler-<name>` plugins, passed to them untouched.
+    pub plugin: Option<HashMap<String, toml::Table>>,
     #[serde(skip)]
     pub path: PathBuf,
+    /// The lock of the remote config as of its last fetch, read from the remote cache.
+    #[serde(skip)]
+    pub remote_lock: Option<Lock>,
+}
+
+/// Represents the [hot_corners] table.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HotCorners {
+    pub top_left: Option<HotCorner>,
+    pub top_right: Option<HotCorner>,
+    pub bottom_left: Option<HotCorner>,
+    pub bottom_right: Option<HotCorner>,
+}
+
+impl HotCorners {
+    /// All corners along with the abbreviation the Dock uses in their keys, e.g. `tl`.
+    pub fn corners(&self) -> [(&'static str, &Option<HotCorner>); 4] {
+        [
+            ("tl", &self.top_left),
+            ("tr", &self.top_right),
+            ("bl", &self.bottom_left),
+            ("br", &self.bottom_right),
+        ]
+    }
+}
+
+/// The `lock` of a config: `lock = true`, or a table telling who locked it, why and until when.
+#[derive(Deserialize, PartialEq, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Lock {
+    Plain(bool),
+    Detailed(LockInfo),
+}
+
+/// Represents `lock = { by = "...", reason = "...", since = ..., until = ... }`, as written by
+/// `cutler lock`.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LockInfo {
+    /// The user who locked the config.
+    pub by: Option<String>,
+    pub reason: Option<String>,
+    #[serde(default, deserialize_with = "lenient_datetime")]
+    pub since: Option<Datetime>,
+    /// When the lock expires. Without it, the config stays locked until it's unlocked.
+    #[serde(default, deserialize_with = "lenient_datetime")]
+    pub until: Option<Datetime>,
+}
+
+/// Helper for: LockInfo
+/// Reads a datetime, which merging the config layers turns into a string.
+fn lenient_datetime<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Datetime>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Datetime(dt) => Ok(Some(dt)),
+        Value::String(text) => text.parse().map(Some).map_err(de::Error::custom),
+        other => Err(de::Error::custom(format!(
+            "expected a datetime, found {}",
+            other.type_str()
+        ))),
+    }
+}
+
+impl Lock {
+    /// Reads the lock of a config file's contents. Broken configs have none.
+    pub fn read(config: &str) -> Option<Lock> {
+        toml::from_str::<toml::Table>(config)
+            .ok()?
+            .remove("lock")?
+            .try_into()
+            .ok()
+    }
+
+    /// Whether the lock is in effect at the given unix time.
+    pub fn is_active(&self, now: i64) -> bool {
+        match self {
+            Lock::Plain(locked) => *locked,
+            Lock::Detailed(info) => !info.is_expired(now),
+        }
+    }
+
+    pub fn is_active_now(&self) -> bool {
+        self.is_active(now())
+    }
+
+    /// Whether the lock has run out, as opposed to never having been in effect.
+    pub fn is_expired(&self, now: i64) -> bool {
+        matches!(self, Lock::Detailed(info) if info.is_expired(now))
+    }
+
+    /// Describes why, by whom and until when the config is locked, e.g.
+    /// `mid-migration, by alice, since 2025-01-01 09:00 UTC`. None for `lock = true`.
+    pub fn describe(&self) -> Option<String> {
+        let Lock::Detailed(info) = self else {
+            return None;
+        };
+        let time = |dt: &Option<Datetime>| {
+            dt.as_ref()
+                .and_then(|dt| datetime_to_unix(dt).ok())
+                .map(|secs| format_timestamp(secs.max(0) as u64))
+        };
+
+        let parts: Vec<String> = [
+            info.reason.clone(),
+            info.by.as_ref().map(|by| format!("by {by}")),
+            time(&info.since).map(|since| format!("since {since}")),
+            time(&info.until).map(|until| format!("until {until}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+impl LockInfo {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.until
+            .as_ref()
+            .and_then(|until| datetime_to_unix(until).ok())
+            .is_some_and(|until| until <= now)
+    }
+}
+
+/// Returns the current Unix time in seconds.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// A hot corner, either just its action (`"lock-screen"`) or with a modifier.
+#[derive(Deserialize, PartialEq, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum HotCorner {
+    Action(String),
+    Detailed(HotCornerAction),
+}
+
+/// Represents `{ action = "...", modifier = "cmd+opt" }` in [hot_corners].
+#[derive(Deserialize, PartialEq, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HotCornerAction {
+    pub action: String,
+    pub modifier: Option<String>,
+}
+
+/// Represents the [screenshots] table.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Screenshots {
+    /// Folder to save screenshots to; created on apply if missing.
+    pub location: Option<String>,
+    /// Image format, e.g. "png" or "jpg".
+    pub format: Option<String>,
+    /// Whether window screenshots get a drop shadow.
+    pub shadow: Option<bool>,
+    /// Whether the floating thumbnail is shown after taking a screenshot.
+    pub thumbnail: Option<bool>,
+}
+
+/// Represents the [trackpad] table.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Trackpad {
+    pub tap_to_click: Option<bool>,
+    pub three_finger_drag: Option<bool>,
+    /// Whether content follows finger movement when scrolling.
+    pub natural_scrolling: Option<bool>,
+    /// Whether swiping left or right with two fingers navigates between pages.
+    pub swipe_between_pages: Option<bool>,
+    pub force_click: Option<bool>,
+    /// Click pressure: "light", "medium" or "firm".
+    pub haptic_strength: Option<String>,
+    /// Whether clicking makes no sound.
+    pub silent_clicking: Option<bool>,
+    /// Tracking speed from 0 (slow) to 3 (fast).
+    pub tracking_speed: Option<f64>,
+}
+
+/// Represents the [spotlight] table.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Spotlight {
+    /// Folders kept out of the index, like in the Privacy tab of Spotlight's settings.
+    pub exclude: Option<Vec<String>>,
+    /// Result categories hidden from search, e.g. ["FONTS", "MESSAGES"].
+    pub disabled_categories: Option<Vec<String>>,
+}
+
+/// Represents the [softwareupdate] table.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SoftwareUpdate {
+    /// Whether macOS checks for updates in the background.
+    pub automatic_check: Option<bool>,
+    /// Whether new updates are downloaded once found.
+    pub automatic_download: Option<bool>,
+    /// Whether macOS updates are installed on their own.
+    pub install_macos_updates: Option<bool>,
+    /// Whether Security Responses and system files are installed on their own.
+    pub critical_updates: Option<bool>,
+    /// Whether XProtect and other configuration data is installed on their own.
+    pub config_data: Option<bool>,
+    /// Days between automatic checks.
+    pub schedule_days: Option<u32>,
 }
 
 /// Represents the [remote] table.
@@ -35,100 +288,823 @@ pub struct Config {
 pub struct Remote {
     pub url: String,
     pub autosync: Option<bool>,
+    /// Minimum interval between autosyncs, e.g. "30m", "6h" or "1d".
+    pub every: Option<String>,
+    /// Subcommands which trigger autosync, e.g. ["apply", "brew install"].
+    pub commands: Option<Vec<String>>,
+    pub mode: Option<SyncMode>,
+}
+
+/// Represents either a single [remote] table or a list of [[remote]] layers.
+#[derive(Deserialize, PartialEq, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Remotes {
+    Single(Remote),
+    /// Remotes fetched in order and deep-merged, later ones taking precedence.
+    Layered(Vec<Remote>),
+}
+
+impl Remotes {
+    /// All remotes, in merge order.
+    pub fn layers(&self) -> &[Remote] {
+        match self {
+            Remotes::Single(remote) => std::slice::from_ref(remote),
+            Remotes::Layered(remotes) => remotes,
+        }
+    }
+
+    /// The remote whose autosync settings apply (the first one).
+    pub fn policy(&self) -> Option<&Remote> {
+        self.layers().first()
+    }
+}
+
+/// What autosync does with the fetched remote config.
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncMode {
+    /// Only fetch and report whether the remote config differs from the local one.
+    FetchOnly,
+    /// Overwrite the local config with the remote one.
+    #[default]
+    Apply,
+}
+
+/// Represents a value inside the [vars] table.
+#[derive(Serialize, PartialEq, Clone, Debug)]
+#[serde(untagged)]
+pub enum Var {
+    /// A plain string value.
+    Static(String),
+    /// A value marked with `secret = true`, given as `value` or `from_command`. It's masked
+    /// wherever cutler prints it.
+    Secret {
+        secret: bool,
+        value: Option<String>,
+        from_command: Option<String>,
+    },
+    /// A value computed from the output of a shell command, once per run.
+    Dynamic { from_command: String },
+}
+
+impl<'de> Deserialize<'de> for Var {
+    /// Validates after reading the table, since an untagged enum would swallow the reason
+    /// behind a var that matches no variant.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Static(String),
+            Table {
+                secret: Option<bool>,
+                value: Option<String>,
+                from_command: Option<String>,
+            },
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Static(value) => Ok(Var::Static(value)),
+            Raw::Table {
+                secret: Some(secret),
+                value,
+                from_command,
+            } => {
+                if value.is_none() && from_command.is_none() {
+                    return Err(de::Error::custom(
+                        "a secret var needs a `value` or `from_command`",
+                    ));
+                }
+                Ok(Var::Secret {
+                    secret,
+                    value,
+                    from_command,
+                })
+            }
+            Raw::Table {
+                secret: None,
+                from_command: Some(from_command),
+                ..
+            } => Ok(Var::Dynamic { from_command }),
+            Raw::Table { secret: None, .. } => Err(de::Error::custom(
+                "a var needs a `from_command`, or `secret = true` along with a `value`",
+            )),
+        }
+    }
+}
+
+impl From<&str> for Var {
+    fn from(value: &str) -> Self {
+        Var::Static(value.to_string())
+    }
+}
+
+impl From<String> for Var {
+    fn from(value: String) -> Self {
+        Var::Static(value)
+    }
+}
+
+/// Represents the `run` field of a command.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[serde(untagged)]
+pub enum Script {
+    /// A single script string, run as-is.
+    Text(String),
+    /// Lines of a script, stopping at the first failing one.
+    Lines(Vec<String>),
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Script::Text(String::new())
+    }
+}
+
+impl From<&str> for Script {
+    fn from(value: &str) -> Self {
+        Script::Text(value.to_string())
+    }
+}
+
+impl From<String> for Script {
+    fn from(value: String) -> Self {
+        Script::Text(value)
+    }
+}
+
+impl Script {
+    /// Renders the script for the given shell.
+    /// Lines are joined so that the script exits as soon as one of them fails.
+    pub fn render(&self, shell: Shell) -> String {
+        match self {
+            Script::Text(text) => text.clone(),
+            Script::Lines(lines) if shell == Shell::Fish => lines
+                .iter()
+                .map(|line| format!("{line}\nor exit $status"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Script::Lines(lines) => format!("set -e\n{}", lines.join("\n")),
+        }
+    }
+}
+
+/// The shell a command runs in.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    #[default]
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The binary of the shell.
+    pub fn bin(&self) -> &'static str {
+        match self {
+            Shell::Sh => "sh",
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
 }
 
 /// Represents [command.***] tables.
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Command {
-    pub run: String,
+    pub run: Script,
+    pub shell: Option<Shell>,
+    /// Reverts what `run` did, executed by `cutler unapply`.
+    pub undo: Option<Script>,
     pub ensure_first: Option<bool>,
     pub required: Option<Vec<String>>,
+    /// Deprecated: use `tags = ["flagged"]` instead.
     pub flag: Option<bool>,
+    pub tags: Option<Vec<String>>,
     pub sudo: Option<bool>,
+    pub export_var: Option<String>,
+    pub interactive: Option<bool>,
+    /// Oldest macOS version to run the command on, e.g. `14` or `"sonoma"`.
+    pub min_macos: Option<MacosVersion>,
+    /// Newest macOS version to run the command on.
+    pub max_macos: Option<MacosVersion>,
+    /// Only runs the command if this script exits successfully, e.g. `test -d ~/.ssh`.
+    pub only_if: Option<String>,
+    /// Skips the command if this script exits successfully.
+    pub skip_if: Option<String>,
+}
+
+/// Represents [group.***] tables.
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    /// Names of the commands to run, in order.
+    pub commands: Vec<String>,
+}
+
+/// Represents the [exec] table.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Exec {
+    /// Write the output of every command to a log file in `~/.local/state/cutler/cmd-logs`.
+    pub logs: Option<bool>,
+}
+
+/// Represents a single app inside the [mas] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum MasApp {
+    /// A bare app id, as a string.
+    Id(String),
+    /// A bare app id, as a number.
+    Number(u64),
+    /// An app id with a human-readable name.
+    Detailed { id: u64, name: Option<String> },
+}
+
+impl MasApp {
+    /// The App Store id of the app.
+    pub fn id(&self) -> String {
+        match self {
+            MasApp::Id(id) => id.clone(),
+            MasApp::Number(id) | MasApp::Detailed { id, .. } => id.to_string(),
+        }
+    }
+
+    /// The configured name of the app, if any.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            MasApp::Detailed { name, .. } => name.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Represents the [notify] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Notify {
+    /// Notify when the system or the remote config drifted from the local config.
+    pub drift: Option<bool>,
+    /// Notify when autosync fetched and applied a remote config.
+    pub sync: Option<bool>,
+    /// Notify when `cutler watch` re-applied the config after drift.
+    pub heal: Option<bool>,
+}
+
+/// Represents the [daemon] table, configuring `cutler watch`.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Daemon {
+    /// Re-apply the config whenever the system drifted from it.
+    pub auto_heal: Option<bool>,
+    /// How often to check for drift, e.g. "5m".
+    pub interval: Option<String>,
+    /// Minimum time between two heals, e.g. "15m".
+    pub cooldown: Option<String>,
+}
+
+/// Represents the [prompt] table, formatting the output of `cutler prompt`.
+/// Every string may use the `{count}` and `{age}` placeholders.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Prompt {
+    /// The whole output, with `{symbol}` standing for one of the symbols below.
+    pub format: Option<String>,
+    pub clean: Option<String>,
+    pub drifted: Option<String>,
+    /// Shown when the system was never checked, or the config changed since.
+    pub unknown: Option<String>,
+}
+
+/// Represents the [snapshot] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// Where to keep the snapshot, e.g. in the repo of the config. Relative paths are taken
+    /// from the directory of the config file.
+    pub path: Option<String>,
+    /// Encrypt the snapshot at rest, with a key kept in the login Keychain.
+    pub encrypt: Option<bool>,
+}
+
+/// Represents the [theme] table, overriding the colors of log tags.
+/// Colors are names like "red" or "orange", or 256-color numbers like "208".
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub error: Option<String>,
+    pub warning: Option<String>,
+    pub info: Option<String>,
+    pub exec: Option<String>,
+    pub prompt: Option<String>,
+    pub dry: Option<String>,
+}
+
+/// Represents the [safety] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Safety {
+    /// Extra domains or `domain.key` settings which need `--allow-protected` to be changed.
+    pub protected: Option<Vec<String>>,
+    /// Applies changing more settings than this ask for confirmation, even with `--accept-all`.
+    pub confirm_over: Option<usize>,
+}
+
+/// Represents the [git] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Git {
+    /// Commit the config to its enclosing repository whenever cutler rewrites it.
+    pub auto_commit: Option<bool>,
+}
+
+/// Represents the [network] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Network {
+    /// Proxy for every HTTP request, e.g. "http://proxy.corp:8080".
+    /// Without it, $HTTPS_PROXY, $HTTP_PROXY and $ALL_PROXY are respected.
+    pub proxy: Option<String>,
+    /// How often failed requests are retried (default: 3).
+    pub retries: Option<u32>,
+    /// Time limit of a single request, e.g. "30s" (default) or "2m".
+    pub timeout: Option<String>,
+}
+
+/// Release channel of [update].
+#[derive(Deserialize, PartialEq, Serialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channel {
+    /// Only full releases.
+    #[default]
+    Stable,
+    /// Release candidates and other pre-releases as well.
+    Prerelease,
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Channel::Stable => write!(f, "stable"),
+            Channel::Prerelease => write!(f, "prerelease"),
+        }
+    }
+}
+
+/// Represents the [update] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateConfig {
+    /// Which releases `check-update` and `self-update` pick.
+    pub channel: Option<Channel>,
+    /// minisign public key which release archives must be signed with, e.g. "RWQ...".
+    pub public_key: Option<String>,
+}
+
+/// Represents the [status] table.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Status {
+    /// Settings macOS keeps changing by itself, e.g. "dock.mod-count".
+    /// These never count as drift and aren't rewritten once applied.
+    pub ignore: Option<Vec<String>>,
 }
 
 /// Represents the [mas] table.
-#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Mas {
-    pub ids: Vec<String>,
+    pub ids: Vec<MasApp>,
+}
+
+/// Represents a single entry of `formulae` in the [brew] table.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[serde(untagged)]
+pub enum Formula {
+    /// A bare formula name.
+    Name(String),
+    /// A formula with extra options.
+    Detailed { name: String, pin: Option<bool> },
+}
+
+impl Formula {
+    /// The name of the formula.
+    pub fn name(&self) -> &str {
+        match self {
+            Formula::Name(name) | Formula::Detailed { name, .. } => name,
+        }
+    }
+
+    /// Whether the formula should be pinned (`brew pin`).
+    pub fn is_pinned(&self) -> bool {
+        matches!(
+            self,
+            Formula::Detailed {
+                pin: Some(true),
+                ..
+            }
+        )
+    }
+}
+
+impl From<&str> for Formula {
+    fn from(value: &str) -> Self {
+        Formula::Name(value.to_string())
+    }
+}
+
+/// Represents a single entry of `taps` in the [brew] table.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[serde(untagged)]
+pub enum Tap {
+    /// A bare tap name, e.g. "org/tap".
+    Name(String),
+    /// A tap with a custom remote and/or a pinned revision (commit, tag or branch).
+    Detailed {
+        name: String,
+        url: Option<String>,
+        revision: Option<String>,
+    },
+}
+
+impl Tap {
+    /// The name of the tap.
+    pub fn name(&self) -> &str {
+        match self {
+            Tap::Name(name) | Tap::Detailed { name, .. } => name,
+        }
+    }
+
+    /// The remote to tap from, instead of GitHub's `org/homebrew-tap`.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Tap::Detailed { url, .. } => url.as_deref(),
+            Tap::Name(_) => None,
+        }
+    }
+
+    /// The revision the tap should be checked out at.
+    pub fn revision(&self) -> Option<&str> {
+        match self {
+            Tap::Detailed { revision, .. } => revision.as_deref(),
+            Tap::Name(_) => None,
+        }
+    }
+}
+
+impl From<&str> for Tap {
+    fn from(value: &str) -> Self {
+        Tap::Name(value.to_string())
+    }
 }
 
 /// Represents the [brew] table.
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Brew {
-    pub formulae: Option<Vec<String>>,
+    pub formulae: Option<Vec<Formula>>,
     pub casks: Option<Vec<String>>,
-    pub taps: Option<Vec<String>>,
+    pub taps: Option<Vec<Tap>>,
     pub no_deps: Option<bool>,
+    pub jobs: Option<usize>,
+    pub parallel_casks: Option<bool>,
+    pub autoremove: Option<bool>,
+    /// Only installed on Apple Silicon.
+    pub arm64: Option<BrewArch>,
+    /// Only installed on Intel, or under Rosetta.
+    pub x86_64: Option<BrewArch>,
+}
+
+/// Represents the [brew.arm64] and [brew.x86_64] tables.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BrewArch {
+    pub formulae: Option<Vec<Formula>>,
+    pub casks: Option<Vec<String>>,
+}
+
+/// Names of the per-architecture tables inside [brew].
+pub const BREW_ARCHS: [&str; 2] = ["arm64", "x86_64"];
+
+impl Brew {
+    /// Merges the lists of the given architecture (as in `std::env::consts::ARCH`) into the
+    /// main ones, dropping the per-architecture tables.
+    pub fn for_arch(mut self, arch: &str) -> Self {
+        let (arm64, x86_64) = (self.arm64.take(), self.x86_64.take());
+        let Some(extra) = (match arch {
+            "aarch64" | "arm64" => arm64,
+            "x86_64" => x86_64,
+            _ => None,
+        }) else {
+            return self;
+        };
+
+        if let Some(extra) = extra.formulae {
+            let formulae = self.formulae.get_or_insert_default();
+            for formula in extra {
+                if !formulae.iter().any(|f| f.name() == formula.name()) {
+                    formulae.push(formula);
+                }
+            }
+        }
+        if let Some(extra) = extra.casks {
+            let casks = self.casks.get_or_insert_default();
+            for cask in extra {
+                if !casks.contains(&cask) {
+                    casks.push(cask);
+                }
+            }
+        }
+
+        self
+    }
 }
 
 impl Config {
     pub fn new(path: PathBuf) -> Self {
         Config {
             lock: None,
+            jobs: None,
             set: None,
+            plist: None,
+            app_shortcuts: None,
+            hot_corners: None,
+            screenshots: None,
+            trackpad: None,
+            spotlight: None,
+            softwareupdate: None,
+            menubar: None,
+            text_replacements: None,
             vars: None,
             command: None,
+            group: None,
+            exec: None,
             brew: None,
             mas: None,
             remote: None,
+            notify: None,
+            daemon: None,
+            status: None,
+            prompt: None,
+            snapshot: None,
+            theme: None,
+            safety: None,
+            git: None,
+            network: None,
+            update: None,
+            plugin: None,
             path,
+            remote_lock: None,
         }
     }
 
+    /// Whether the config is locked by its remote, i.e. the remote config sets a lock in effect
+    /// as of its last fetch. Such a lock can only be lifted by the remote config; a lock made
+    /// locally is not the remote's.
+    pub fn is_remote_locked(&self) -> bool {
+        self.is_locked() && self.remote_lock.as_ref().is_some_and(Lock::is_active_now)
+    }
+
+    /// Helper for: load(), load_as_mut()
+    /// Looks up the lock of the remote config in the remote cache next to `path`.
+    async fn read_remote_lock(&mut self, path: &Path) {
+        if let Some(remotes) = &self.remote {
+            self.remote_lock = cached_remote_lock(path, remotes).await;
+        }
+    }
+
+    /// Whether the config is locked. Expired locks are ignored.
+    pub fn is_locked(&self) -> bool {
+        self.lock.as_ref().is_some_and(Lock::is_active_now)
+    }
+
     pub fn is_loadable(&self) -> bool {
         !self.path.as_os_str().is_empty() && self.path.try_exists().unwrap_or(false)
     }
 
-    /// Loads the configuration. Errors out if the configuration is not loadable
-    /// (decided by `.is_loadable()`).
+    /// Loads the configuration, deep-merging all of its layers (see read_layers()): tables are
+    /// merged key by key, anything else is replaced. Errors out if the configuration is not
+    /// loadable (decided by `.is_loadable()`).
     pub async fn load(&mut self, not_if_locked: bool) -> Result<()> {
         if self.is_loadable() {
-            let data = fs::read_to_string(&self.path).await?;
-            let config: Config =
-                toml::from_str(&data).context("Failed to parse config data from valid TOML.")?;
+            let config = self.read_merged().await?;
 
-            if config.lock.unwrap_or_default() && not_if_locked {
-                bail!("Config is locked. Run `cutler unlock` to unlock.")
+            if config.is_locked() && not_if_locked {
+                return Err(locked_error(&config));
+            }
+            if not_if_locked
+                && config
+                    .lock
+                    .as_ref()
+                    .is_some_and(|lock| lock.is_expired(now()))
+            {
+                log_info!("The lock of the config has expired, ignoring it.");
             }
 
             self.lock = config.lock;
+            self.remote_lock = config.remote_lock;
+            self.jobs = config.jobs;
+            set_config_jobs(config.jobs);
             self.set = config.set;
+            self.plist = config.plist;
+            self.app_shortcuts = config.app_shortcuts;
+            self.hot_corners = config.hot_corners;
+            self.screenshots = config.screenshots;
+            self.trackpad = config.trackpad;
+            self.spotlight = config.spotlight;
+            self.softwareupdate = config.softwareupdate;
+            self.menubar = config.menubar;
+            self.text_replacements = config.text_replacements;
             self.vars = config.vars;
             self.command = config.command;
-            self.brew = config.brew;
+            self.group = config.group;
+            self.exec = config.exec;
+            self.brew = config.brew.map(|brew| brew.for_arch(ARCH));
             self.mas = config.mas;
             self.remote = config.remote;
+            self.notify = config.notify;
+            self.daemon = config.daemon;
+            self.status = config.status;
+            self.prompt = config.prompt;
+            set_encryption(
+                config
+                    .snapshot
+                    .as_ref()
+                    .and_then(|s| s.encrypt)
+                    .unwrap_or_default(),
+            );
+            self.snapshot = config.snapshot;
+            set_theme(config.theme.clone());
+            self.theme = config.theme;
+            self.safety = config.safety;
+            self.git = config.git;
+            set_network(config.network.clone());
+            self.network = config.network;
+            self.update = config.update;
+            self.plugin = config.plugin;
 
             Ok(())
         } else {
-            bail!("Config path does not exist!")
+            Err(coded(ExitCode::Config, "Config path does not exist!"))
+        }
+    }
+
+    /// Helper for: load(), load_as_mut()
+    /// Deep-merges all layers of the config into one, later layers winning over earlier ones,
+    /// along with the lock of its remote config.
+    async fn read_merged(&self) -> Result<Config> {
+        let mut table = toml::Table::new();
+        for (path, data) in self.read_layers().await? {
+            let layer: toml::Table = toml::from_str(&data)
+                .with_context(|| format!("Failed to parse {path:?} from valid TOML."))?;
+            deep_merge(&mut table, layer);
+        }
+
+        let mut config: Config = Value::Table(table)
+            .try_into()
+            .context("Failed to parse config data from valid TOML.")?;
+        config.read_remote_lock(&self.path).await;
+
+        Ok(config)
+    }
+
+    /// Whether the config is a directory of files (e.g. `conf.d`) rather than a single file.
+    pub fn is_dir(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    /// Errors out for config directories, which commands writing the config can't handle.
+    pub fn ensure_file(&self) -> Result<()> {
+        if self.is_dir() {
+            bail!(
+                "{:?} is a config directory; edit the files inside it directly.",
+                self.path
+            )
+        }
+
+        Ok(())
+    }
+
+    /// Reads every file making up the config, in merge order: all `*.toml` files of a config
+    /// directory in lexical order, or the config file followed by its local overlay.
+    pub async fn read_layers(&self) -> Result<Vec<(PathBuf, String)>> {
+        let paths = if self.is_dir() {
+            let mut paths = Vec::new();
+            let mut entries = fs::read_dir(&self.path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "toml") && path.is_file() {
+                    paths.push(path);
+                }
+            }
+            paths.sort();
+            paths
+        } else {
+            let overlay = get_overlay_path(&self.path);
+            let has_overlay = fs::try_exists(&overlay).await.unwrap_or_default();
+
+            std::iter::once(self.path.clone())
+                .chain(has_overlay.then_some(overlay))
+                .collect()
+        };
+
+        let mut layers = Vec::with_capacity(paths.len());
+        for path in paths {
+            let data = fs::read_to_string(&path).await?;
+            layers.push((path, data));
+        }
+
+        Ok(layers)
+    }
+
+    /// Helper for: load_overlay_as_mut()
+    async fn read_overlay(&self) -> Result<Option<String>> {
+        let path = get_overlay_path(&self.path);
+
+        if !self.is_dir() && fs::try_exists(&path).await.unwrap_or_default() {
+            Ok(Some(fs::read_to_string(&path).await?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Loads every layer of the config as DocumentMut, in merge order (see read_layers()).
+    pub async fn load_docs(&self) -> Result<Vec<DocumentMut>> {
+        if !self.is_loadable() {
+            return Err(coded(ExitCode::Config, "Config path does not exist!"));
+        }
+
+        self.read_layers()
+            .await?
+            .into_iter()
+            .map(|(path, data)| {
+                data.parse::<DocumentMut>()
+                    .with_context(|| format!("Failed to parse {path:?}"))
+            })
+            .collect()
+    }
+
+    /// Loads the local overlay (e.g. `config.local.toml`) as DocumentMut, if there is one.
+    pub async fn load_overlay_as_mut(&self) -> Result<Option<DocumentMut>> {
+        match self.read_overlay().await? {
+            Some(data) => Ok(Some(data.parse::<DocumentMut>().with_context(|| {
+                format!("Failed to parse {:?}", get_overlay_path(&self.path))
+            })?)),
+            None => Ok(None),
         }
     }
 
     /// Loads config as mutable DocumentMut. Useful for in-place editing of values.
+    /// Only the main config file is read, so config directories are refused, but a lock in the
+    /// local overlay counts just like in `load()`.
     pub async fn load_as_mut(&self, not_if_locked: bool) -> Result<DocumentMut> {
-        if self.is_loadable() {
-            let data = fs::read_to_string(&self.path).await?;
-            let config: Config =
-                toml::from_str(&data).context("Failed to parse config data from valid TOML.")?;
+        self.ensure_file()?;
 
-            if config.lock.unwrap_or_default() && not_if_locked {
-                bail!("Config is locked. Run `cutler unlock` to unlock.")
+        if self.is_loadable() {
+            let config = self.read_merged().await?;
+            if config.is_locked() && not_if_locked {
+                return Err(locked_error(&config));
             }
 
+            let data = fs::read_to_string(&self.path).await?;
+
             let doc = data.parse::<DocumentMut>()?;
 
             Ok(doc)
         } else {
-            bail!("Config path does not exist!")
+            Err(coded(ExitCode::Config, "Config path does not exist!"))
         }
     }
 }
 
+/// The error of commands refusing to work on a locked config, telling how to lift the lock.
+pub fn locked_error(config: &Config) -> anyhow::Error {
+    let details = config
+        .lock
+        .as_ref()
+        .and_then(Lock::describe)
+        .map(|details| format!(" ({details})"))
+        .unwrap_or_default();
+
+    if config.is_remote_locked() {
+        coded(
+            ExitCode::Locked,
+            format!(
+                "Config is locked by its remote config{details}. Changes have to be made there; run `cutler fetch` to pick them up."
+            ),
+        )
+    } else {
+        coded(
+            ExitCode::Locked,
+            format!("Config is locked{details}. Run `cutler unlock` to unlock."),
+        )
+    }
+}
+
 /// Trait for implementing core Config struct methods for other types.
 ///
 /// Purely convenience.
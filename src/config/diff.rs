@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::collections::BTreeSet;
+use std::fmt;
+use toml::{Table, Value};
+
+/// A single difference between two configs, addressed by a dotted key path.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+    /// Items added to or removed from an array, e.g. formulae.
+    Items {
+        path: String,
+        added: Vec<Value>,
+        removed: Vec<Value>,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. }
+            | Change::Items { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {path} = {value}"),
+            Change::Removed { path, value } => write!(f, "- {path} = {value}"),
+            Change::Changed { path, old, new } => write!(f, "~ {path}: {old} -> {new}"),
+            Change::Items {
+                path,
+                added,
+                removed,
+            } => {
+                let items: Vec<String> = added
+                    .iter()
+                    .map(|v| format!("+{v}"))
+                    .chain(removed.iter().map(|v| format!("-{v}")))
+                    .collect();
+                write!(f, "~ {path}: {}", items.join(", "))
+            }
+        }
+    }
+}
+
+/// Recursively compares two TOML tables, returning the changes needed to go from `old` to `new`.
+pub fn diff_tables(old: &Table, new: &Table) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_into("", old, new, &mut changes);
+    changes
+}
+
+/// Helper for: diff_tables()
+fn diff_into(prefix: &str, old: &Table, new: &Table, changes: &mut Vec<Change>) {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    for key in keys {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match (old.get(key), new.get(key)) {
+            (Some(Value::Table(o)), Some(Value::Table(n))) => diff_into(&path, o, n, changes),
+            (Some(Value::Array(o)), Some(Value::Array(n))) if o != n => {
+                let added: Vec<Value> = n.iter().filter(|v| !o.contains(v)).cloned().collect();
+                let removed: Vec<Value> = o.iter().filter(|v| !n.contains(v)).cloned().collect();
+
+                // only the order changed
+                if added.is_empty() && removed.is_empty() {
+                    changes.push(Change::Changed {
+                        path,
+                        old: Value::Array(o.clone()),
+                        new: Value::Array(n.clone()),
+                    });
+                } else {
+                    changes.push(Change::Items {
+                        path,
+                        added,
+                        removed,
+                    });
+                }
+            }
+            (Some(o), Some(n)) if o != n => changes.push(Change::Changed {
+                path,
+                old: o.clone(),
+                new: n.clone(),
+            }),
+            (None, Some(n)) => changes.push(Change::Added {
+                path,
+                value: n.clone(),
+            }),
+            (Some(o), None) => changes.push(Change::Removed {
+                path,
+                value: o.clone(),
+            }),
+            _ => {}
+        }
+    }
+}
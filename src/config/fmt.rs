@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use std::cmp::Ordering;
+use toml_edit::{Array, Decor, DocumentMut, Item, Table, Value};
+
+/// Arrays longer than this (when written on one line) are split into one item per line.
+const MAX_ARRAY_WIDTH: usize = 80;
+
+/// Helper for: has_comment(), tidy()
+fn decor_str(decor: &Decor) -> (&str, &str) {
+    let prefix = decor.prefix().and_then(|p| p.as_str()).unwrap_or("");
+    let suffix = decor.suffix().and_then(|s| s.as_str()).unwrap_or("");
+    (prefix, suffix)
+}
+
+/// Helper for: format_array()
+fn has_comment(array: &Array) -> bool {
+    let trailing = array.trailing().as_str().unwrap_or("");
+
+    trailing.contains('#')
+        || array.iter().any(|v| {
+            let (prefix, suffix) = decor_str(v.decor());
+            prefix.contains('#') || suffix.contains('#')
+        })
+}
+
+/// Replaces whitespace-only decor with the given defaults. Comments are left alone.
+fn tidy(decor: &mut Decor, prefix: &str, suffix: &str) {
+    let (old_prefix, old_suffix) = decor_str(decor);
+    let (old_prefix, old_suffix) = (old_prefix.to_string(), old_suffix.to_string());
+
+    if !old_prefix.contains('#') {
+        decor.set_prefix(prefix);
+    }
+    if !old_suffix.contains('#') {
+        decor.set_suffix(suffix);
+    }
+}
+
+/// Helper for: format_value()
+/// Arrays are kept on a single line unless they get too long or contain comments.
+fn format_array(array: &mut Array) {
+    for value in array.iter_mut() {
+        format_value(value);
+    }
+
+    if has_comment(array) {
+        return;
+    }
+
+    array.fmt();
+
+    if array.to_string().trim().len() > MAX_ARRAY_WIDTH {
+        for value in array.iter_mut() {
+            value.decor_mut().set_prefix("\n    ");
+            value.decor_mut().set_suffix("");
+        }
+        array.set_trailing_comma(true);
+        array.set_trailing("\n");
+    }
+}
+
+/// Helper for: format_table()
+fn format_value(value: &mut Value) {
+    match value {
+        Value::Array(array) => format_array(array),
+        Value::InlineTable(table) => {
+            for (_, value) in table.iter_mut() {
+                format_value(value);
+            }
+            table.fmt();
+        }
+        _ => {}
+    }
+}
+
+/// Helper for: format_document()
+/// Normalizes `key = value` spacing and values, recursing into subtables.
+/// Subtables of the root [set] table (domains) are sorted by name.
+fn format_table(table: &mut Table, sort_tables: bool, root: bool) {
+    if sort_tables {
+        // key-values stay in place, subtables get sorted by name
+        table.sort_values_by(|k1, v1, k2, v2| match (v1.is_table(), v2.is_table()) {
+            (true, true) => k1.get().cmp(k2.get()),
+            (false, false) => Ordering::Equal,
+            (false, true) => Ordering::Less,
+            (true, false) => Ordering::Greater,
+        });
+    }
+
+    for (mut key, item) in table.iter_mut() {
+        match item {
+            Item::Value(value) => {
+                // keep blank lines and comments above the key, drop indentation
+                let (prefix, _) = decor_str(key.leaf_decor());
+                let prefix = if prefix.contains('#') {
+                    prefix.to_string()
+                } else {
+                    prefix.replace([' ', '\t'], "")
+                };
+                key.leaf_decor_mut().set_prefix(prefix);
+                key.leaf_decor_mut().set_suffix(" ");
+
+                format_value(value);
+                tidy(value.decor_mut(), " ", "");
+            }
+            Item::Table(sub) => {
+                let sort = sort_tables || (root && key.get() == "set");
+                format_table(sub, sort, false);
+            }
+            Item::ArrayOfTables(array) => {
+                for sub in array.iter_mut() {
+                    format_table(sub, false, false);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+/// Helper for: format_document()
+/// Assigns positions in item order, so that tables are written out in that order.
+fn renumber(table: &mut Table, next: &mut isize) {
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(sub) => {
+                if !sub.is_dotted() {
+                    sub.set_position(*next);
+                    *next += 1;
+                }
+                renumber(sub, next);
+            }
+            Item::ArrayOfTables(array) => {
+                for sub in array.iter_mut() {
+                    sub.set_position(*next);
+                    *next += 1;
+                    renumber(sub, next);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Formats a config document in place: domains inside [set] are sorted, arrays and inline
+/// tables are normalized, and `key = value` spacing is made consistent. Comments are kept.
+pub fn format_document(doc: &mut DocumentMut) {
+    format_table(doc.as_table_mut(), false, true);
+
+    let mut next = 1;
+    renumber(doc.as_table_mut(), &mut next);
+}
+
+/// Formats config text, making sure that the result still holds the same data.
+pub fn format_str(text: &str) -> Result<String> {
+    let mut doc = text.parse::<DocumentMut>()?;
+    format_document(&mut doc);
+    let formatted = doc.to_string();
+
+    if toml::from_str::<toml::Table>(text)? != toml::from_str::<toml::Table>(&formatted)? {
+        bail!("Formatting would change the config's contents; aborting.");
+    }
+
+    Ok(formatted)
+}
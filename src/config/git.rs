@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Git integration for configs living in a (dotfiles) repository.
+//!
+//! With `[git] auto_commit = true`, commands which rewrite the config on their own
+//! commit the result to the enclosing repository. `cutler config install-hook` adds a
+//! pre-commit hook validating the config before it's committed.
+
+use anyhow::{Context, Result, bail};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use tokio::{fs, process::Command};
+
+use crate::{cli::atomic::should_dry_run, config::core::Config, log_dry, log_info, log_warn};
+
+/// Marks pre-commit hooks written by cutler, so that they can be replaced without --force.
+const HOOK_MARKER: &str = "# cutler: validates the config before every commit.";
+
+/// Helper for: auto_commit(), log()
+/// Resolves symlinks, since configs are often linked into place from a dotfiles repo.
+async fn locate(config_path: &Path) -> Result<(PathBuf, PathBuf)> {
+    let path = fs::canonicalize(config_path)
+        .await
+        .with_context(|| format!("Could not resolve {}", config_path.display()))?;
+    let dir = path
+        .parent()
+        .context("Could not determine config parent directory")?
+        .to_path_buf();
+
+    Ok((dir, path))
+}
+
+/// Helper for: commit(), log()
+async fn git(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("Could not run git")
+}
+
+/// Helper for: commit(), log()
+async fn is_repo(dir: &Path) -> bool {
+    git(dir, &["rev-parse", "--is-inside-work-tree"])
+        .await
+        .is_ok_and(|out| out.status.success())
+}
+
+/// Helper for: auto_commit()
+async fn commit(config_path: &Path, message: &str) -> Result<()> {
+    let (dir, path) = locate(config_path).await?;
+    let file = path.to_string_lossy();
+
+    if !is_repo(&dir).await {
+        log_info!("Config is not inside a git repository, not committing.");
+        return Ok(());
+    }
+
+    let add = git(&dir, &["add", "--", &file]).await?;
+    if !add.status.success() {
+        bail!("{}", String::from_utf8_lossy(&add.stderr).trim())
+    }
+
+    // nothing to commit if the file ended up unchanged
+    let diff = git(&dir, &["diff", "--cached", "--quiet", "--", &file]).await?;
+    if diff.status.success() {
+        return Ok(());
+    }
+
+    // only commit the config, leaving anything else staged alone
+    let out = git(&dir, &["commit", "-m", message, "--", &file]).await?;
+    if !out.status.success() {
+        bail!("{}", String::from_utf8_lossy(&out.stderr).trim())
+    }
+
+    log_info!("Committed config: {message}");
+
+    Ok(())
+}
+
+/// Commits the config to its enclosing git repository if `[git] auto_commit` is enabled.
+/// Failures are only warned about, since the config itself was written already.
+pub async fn auto_commit(config: &Config, message: &str) {
+    let enabled = config
+        .git
+        .as_ref()
+        .and_then(|g| g.auto_commit)
+        .unwrap_or_default();
+
+    if !enabled || should_dry_run() {
+        return;
+    }
+
+    if let Err(e) = commit(&config.path, message).await {
+        log_warn!("Could not commit config change: {e}");
+    }
+}
+
+/// Prints the most recent commits touching the config.
+pub async fn log(config_path: &Path, count: usize) -> Result<()> {
+    let (dir, path) = locate(config_path).await?;
+
+    if !is_repo(&dir).await {
+        bail!("Config is not inside a git repository.")
+    }
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["log", "--format=%h %ad %s", "--date=short"])
+        .arg(format!("--max-count={count}"))
+        .arg("--")
+        .arg(&path)
+        .stdout(Stdio::inherit())
+        .status()
+        .await
+        .context("Could not run git")?;
+
+    if !status.success() {
+        bail!("git log exited with {status}")
+    }
+
+    Ok(())
+}
+
+/// Helper for: install_hook()
+fn stdout_of(out: &Output) -> Result<String> {
+    if !out.status.success() {
+        bail!("{}", String::from_utf8_lossy(&out.stderr).trim())
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Renders the pre-commit hook for the config at `config_path`, relative to the repository
+/// root (where git runs hooks from). With `plan`, a dry-run apply is run after linting.
+pub fn hook_script(config_path: &str, plan: bool) -> String {
+    let path = shell_words::quote(config_path);
+    let mut script = format!(
+        "#!/bin/sh
+{HOOK_MARKER}
+# Installed by `cutler config install-hook`; delete this file to disable it.
+
+# nothing to check if the config isn't part of the commit
+git diff --cached --quiet -- {path} && exit 0
+
+if ! command -v cutler >/dev/null 2>&1; then
+    echo \"cutler not found in \\$PATH, skipping config validation.\" >&2
+    exit 0
+fi
+
+cutler --no-sync --config {path} config lint || exit 1
+"
+    );
+
+    if plan {
+        script.push_str(&format!(
+            "cutler --no-sync --dry-run --config {path} apply --no-cmd --no-dom-check || exit 1\n"
+        ));
+    }
+
+    script
+}
+
+/// Writes a pre-commit hook validating the config into its enclosing repository.
+/// An existing hook not written by cutler is only replaced with `force`.
+pub async fn install_hook(config_path: &Path, plan: bool, force: bool) -> Result<PathBuf> {
+    let (dir, path) = locate(config_path).await?;
+
+    if !is_repo(&dir).await {
+        bail!("Config is not inside a git repository.")
+    }
+
+    let root = PathBuf::from(stdout_of(
+        &git(&dir, &["rev-parse", "--show-toplevel"]).await?,
+    )?);
+    let relative = path
+        .strip_prefix(&root)
+        .with_context(|| format!("{} is outside of {}", path.display(), root.display()))?;
+
+    // respects core.hooksPath and worktrees
+    let hooks = dir.join(stdout_of(
+        &git(&dir, &["rev-parse", "--git-path", "hooks"]).await?,
+    )?);
+    let hook = hooks.join("pre-commit");
+
+    if let Ok(existing) = fs::read_to_string(&hook).await
+        && !existing.contains(HOOK_MARKER)
+        && !force
+    {
+        bail!(
+            "{} already exists. Pass --force to replace it.",
+            hook.display()
+        )
+    }
+
+    let script = hook_script(&relative.to_string_lossy(), plan);
+
+    if should_dry_run() {
+        log_dry!("Would write pre-commit hook to {}", hook.display());
+        return Ok(hook);
+    }
+
+    fs::create_dir_all(&hooks).await?;
+    fs::write(&hook, script).await?;
+    fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).await?;
+
+    Ok(hook)
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value};
+
+/// Splits a dotted key path into its segments.
+/// Segments containing dots can be quoted, e.g. `set."com.apple.dock".tilesize`.
+pub fn parse_key_path(path: &str) -> Result<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in path.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+
+    if quoted {
+        bail!("Unterminated quote in key path: {path}");
+    }
+    if segments.iter().any(|s| s.is_empty()) {
+        bail!("Invalid key path: {path}");
+    }
+
+    Ok(segments)
+}
+
+/// Parses a value from the command line, inferring its type.
+/// Anything that isn't a valid TOML value (e.g. `hello`) is taken as a plain string.
+pub fn parse_value(raw: &str) -> Value {
+    raw.parse::<Value>()
+        .unwrap_or_else(|_| Value::from(raw.to_string()))
+}
+
+/// Helper for: get_item(), set_item(), unset_item()
+/// Walks down to the table holding the last segment.
+fn parent<'a>(doc: &'a DocumentMut, segments: &[String]) -> Option<&'a dyn TableLike> {
+    let mut table: &dyn TableLike = doc.as_table();
+
+    for seg in segments {
+        table = table.get(seg)?.as_table_like()?;
+    }
+
+    Some(table)
+}
+
+/// Returns the item at the given key path, if any.
+pub fn get_item<'a>(doc: &'a DocumentMut, path: &[String]) -> Option<&'a Item> {
+    let (last, rest) = path.split_last()?;
+    parent(doc, rest)?.get(last)
+}
+
+/// Sets the value at the given key path, creating tables along the way.
+pub fn set_item(doc: &mut DocumentMut, path: &[String], value: Value) -> Result<()> {
+    let Some((last, rest)) = path.split_last() else {
+        bail!("Empty key path.");
+    };
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+
+    for seg in rest {
+        table = table
+            .entry(seg)
+            .or_insert_with(|| {
+                let mut new = Table::new();
+                new.set_implicit(true);
+                Item::Table(new)
+            })
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow::anyhow!("`{seg}` is not a table."))?;
+    }
+
+    // keep the formatting of the existing value, if any
+    match table.get_mut(last).and_then(|item| item.as_value_mut()) {
+        Some(existing) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        None => {
+            table.insert(last, Item::Value(value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the item at the given key path, returning it if it existed.
+pub fn unset_item(doc: &mut DocumentMut, path: &[String]) -> Option<Item> {
+    let (last, rest) = path.split_last()?;
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+
+    for seg in rest {
+        table = table.get_mut(seg)?.as_table_like_mut()?;
+    }
+
+    table.remove(last)
+}
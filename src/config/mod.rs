@@ -1,5 +1,10 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 pub mod core;
+pub mod diff;
+pub mod fmt;
+pub mod git;
+pub mod keypath;
 pub mod path;
 pub mod remote;
+pub mod vars;
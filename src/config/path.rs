@@ -2,18 +2,46 @@
 
 use anyhow::{Result, bail};
 use std::sync::OnceLock;
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{self, Path, PathBuf},
+};
 use tokio::fs;
 
 /// The configuration path decided for the current process.
 pub static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
 
-/// Returns the path to the configuration file by checking several candidate locations.
+/// Environment variable pointing to the configuration file, like `--config`.
+pub const CONFIG_ENV: &str = "CUTLER_CONFIG";
+
+/// Uses the given configuration file (`--config`) for the rest of the process.
+/// Must be called before the first get_config_path().
+pub fn set_config_path(path: PathBuf) {
+    CONFIG_PATH.set(path::absolute(&path).unwrap_or(path)).ok();
+}
+
+/// Returns the path to the configuration file: `--config`, then $CUTLER_CONFIG, then the
+/// default candidate locations.
 pub async fn get_config_path() -> Result<PathBuf> {
     if let Some(path) = CONFIG_PATH.get().cloned() {
         return Ok(path);
     }
 
+    let chosen = match env::var_os(CONFIG_ENV).filter(|p| !p.is_empty()) {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            path::absolute(&path).unwrap_or(path)
+        }
+        None => get_default_config_path().await?,
+    };
+
+    CONFIG_PATH.set(chosen.clone()).ok();
+    Ok(chosen)
+}
+
+/// Returns the first existing of several candidate locations, or the preferred one if none exist.
+/// Unlike get_config_path(), overrides aren't considered.
+pub async fn get_default_config_path() -> Result<PathBuf> {
     let home = env::var_os("HOME");
     let xdg = env::var_os("XDG_CONFIG_HOME");
 
@@ -27,6 +55,13 @@ pub async fn get_config_path() -> Result<PathBuf> {
                 .join("cutler")
                 .join("config.toml"),
         );
+        // $HOME/.config/cutler/conf.d/*.toml
+        candidates.push(
+            PathBuf::from(home)
+                .join(".config")
+                .join("cutler")
+                .join("conf.d"),
+        );
         // $HOME/.config/cutler.toml
         candidates.push(PathBuf::from(home).join(".config").join("cutler.toml"));
     }
@@ -34,6 +69,8 @@ pub async fn get_config_path() -> Result<PathBuf> {
     if let Some(ref xdg) = xdg {
         // $XDG_CONFIG_HOME/cutler/config.toml
         candidates.push(PathBuf::from(xdg).join("cutler").join("config.toml"));
+        // $XDG_CONFIG_HOME/cutler/conf.d/*.toml
+        candidates.push(PathBuf::from(xdg).join("cutler").join("conf.d"));
         // $XDG_CONFIG_HOME/cutler.toml
         candidates.push(PathBuf::from(xdg).join("cutler.toml"));
     }
@@ -56,10 +93,20 @@ pub async fn get_config_path() -> Result<PathBuf> {
         None
     };
 
-    if let Some(ref path) = chosen {
-        CONFIG_PATH.set(path.clone()).ok();
-        Ok(path.clone())
+    if let Some(path) = chosen {
+        Ok(path)
     } else {
         bail!("Could not load configuration since cannot be assigned.")
     }
 }
+
+/// Returns the path of the local overlay merged on top of a config, e.g. `config.local.toml`
+/// for `config.toml`. Machine-specific tweaks go there, since fetch and backup never touch it.
+pub fn get_overlay_path(config_path: &Path) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "config".into());
+
+    config_path.with_file_name(format!("{stem}.local.toml"))
+}
@@ -1,19 +1,116 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use anyhow::{Context, Result, bail};
-use reqwest::Client;
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::sync::OnceCell;
+use toml_edit::DocumentMut;
 
-use crate::config::core::Config;
+use crate::config::core::{Config, Lock, Remotes};
 use crate::config::path::get_config_path;
-use crate::log_info;
+use crate::util::exit::{ExitCode, coded};
+use crate::util::net::{client, ensure_online, send};
+use crate::{log_info, log_warn};
+
+/// The last successful response for a remote URL, used for conditional requests.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteCacheEntry {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+/// The cached responses of every remote layer, keyed by URL.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RemoteCache {
+    #[serde(default)]
+    pub entries: BTreeMap<String, RemoteCacheEntry>,
+}
+
+/// Returns the path to the remote config cache, next to the config.
+pub fn get_remote_cache_path(config_path: &Path) -> Result<PathBuf> {
+    let parent = config_path
+        .parent()
+        .context("Could not determine config parent directory")?;
+
+    Ok(parent.join("remote_cache.json"))
+}
+
+impl RemoteCache {
+    /// Helper for: load(), store()
+    async fn path() -> Result<PathBuf> {
+        get_remote_cache_path(&get_config_path().await?)
+    }
+
+    /// Reads the cache from a file. A missing or unreadable cache (e.g. one written by an
+    /// older version) is treated as empty.
+    pub async fn read(path: &Path) -> Self {
+        match fs::read_to_string(path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Writes the cache into a file.
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, serde_json::to_string(self)?).await?;
+        Ok(())
+    }
+
+    /// Loads the cached response of the given URL.
+    async fn load(url: &str) -> Option<RemoteCacheEntry> {
+        Self::read(&Self::path().await.ok()?)
+            .await
+            .entries
+            .remove(url)
+    }
+
+    /// Stores the response of a URL in the cache file, keeping the ones of other layers.
+    pub async fn store_at(path: &Path, url: &str, entry: RemoteCacheEntry) -> Result<()> {
+        let mut cache = Self::read(path).await;
+
+        cache.entries.insert(url.to_string(), entry);
+        cache.write(path).await
+    }
+
+    /// Helper for: RemoteConfigManager::fetch()
+    async fn store(url: &str, entry: RemoteCacheEntry) -> Result<()> {
+        Self::store_at(&Self::path().await?, url, entry).await
+    }
+}
+
+/// The lock the remote config set as of its last fetch, read from the cached responses of its
+/// layers (the last layer setting `lock` wins, like when merging). None if it was never fetched.
+pub async fn cached_remote_lock(config_path: &Path, remotes: &Remotes) -> Option<Lock> {
+    let cache = RemoteCache::read(&get_remote_cache_path(config_path).ok()?).await;
+
+    remotes
+        .layers()
+        .iter()
+        .filter_map(|remote| cache.entries.get(&remote.url))
+        .filter_map(|entry| Lock::read(&entry.body))
+        .next_back()
+}
+
+/// The fetched remote config along with whether it changed since the last fetch.
+#[derive(Debug, Clone)]
+struct Fetched {
+    text: String,
+    unchanged: bool,
+}
 
 /// Manages fetching and storing the remote config.
 #[derive(Debug, Clone)]
 pub struct RemoteConfigManager {
     url: String,
-    config: OnceCell<String>,
+    config: OnceCell<Fetched>,
 }
 
 impl RemoteConfigManager {
@@ -26,42 +123,92 @@ impl RemoteConfigManager {
     }
 
     /// Fetch the remote config file as TOML, only once per instance.
+    /// A conditional request is sent if the URL was fetched before, so that unchanged configs
+    /// aren't downloaded again.
     pub async fn fetch(&self) -> Result<()> {
         self.config
             .get_or_try_init(|| async {
+                ensure_online("fetch the remote config")?;
                 log_info!("Fetching remote config from {}", self.url);
-                let client = Client::builder()
-                    .user_agent("cutler-remote-config")
-                    .build()?;
-                let resp =
-                    client.get(&self.url).send().await.with_context(|| {
-                        format!("Failed to fetch remote config from {}", self.url)
-                    })?;
+                let client = client("cutler-remote-config")?;
+
+                let cache = RemoteCache::load(&self.url).await;
+                let mut req = client.get(&self.url);
+
+                if let Some(cache) = &cache {
+                    if let Some(etag) = &cache.etag {
+                        req = req.header(IF_NONE_MATCH, etag);
+                    }
+                    if let Some(last_modified) = &cache.last_modified {
+                        req = req.header(IF_MODIFIED_SINCE, last_modified);
+                    }
+                }
+
+                let resp = send(&format!("fetch remote config from {}", self.url), req).await?;
+
+                if resp.status() == StatusCode::NOT_MODIFIED
+                    && let Some(cache) = cache
+                {
+                    log_info!("Remote config not modified since last fetch.");
+                    return Ok(Fetched {
+                        text: cache.body,
+                        unchanged: true,
+                    });
+                }
 
                 if !resp.status().is_success() {
-                    bail!("Failed to fetch remote config: HTTP {}", resp.status());
+                    return Err(coded(
+                        ExitCode::Network,
+                        format!("Failed to fetch remote config: HTTP {}", resp.status()),
+                    ));
                 }
 
+                let header = |name| {
+                    resp.headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                let etag = header(ETAG);
+                let last_modified = header(LAST_MODIFIED);
+
                 let text = resp.text().await?;
 
                 toml::from_str::<Config>(&text)
                     .with_context(|| format!("Invalid TOML config fetched from {}", self.url))?;
 
-                Ok(text)
+                // kept even without validators, since the lock of the remote is read from it
+                let entry = RemoteCacheEntry {
+                    etag,
+                    last_modified,
+                    body: text.clone(),
+                };
+                if let Err(e) = RemoteCache::store(&self.url, entry).await {
+                    log_warn!("Could not cache remote config: {e}");
+                }
+
+                Ok(Fetched {
+                    text,
+                    unchanged: false,
+                })
             })
             .await?;
         Ok(())
     }
 
+    /// Checks if the remote reported no changes since the last fetch.
+    pub fn is_unchanged(&self) -> bool {
+        self.config.get().is_some_and(|f| f.unchanged)
+    }
+
     /// Save the fetched remote config to the given path.
     pub async fn save(&self) -> Result<()> {
-        let config = self.get()?;
-        let config_path = get_config_path().await?;
+        write_config(self.get()?).await
+    }
 
-        fs::create_dir_all(config_path.parent().unwrap()).await?;
-        fs::write(config_path, config).await?;
-        log_info!("Successfully saved remote config to destination.");
-        Ok(())
+    /// The URL this manager fetches from.
+    pub fn url(&self) -> &str {
+        &self.url
     }
 
     /// Get a reference to the fetched remote config, if available.
@@ -71,7 +218,7 @@ impl RemoteConfigManager {
             .get()
             .ok_or_else(|| anyhow::anyhow!("Remote config not fetched yet"))?;
 
-        Ok(config)
+        Ok(&config.text)
     }
 
     /// Get a parsed version of the output of .get() as serde-based Config.
@@ -81,3 +228,173 @@ impl RemoteConfigManager {
         Ok(config)
     }
 }
+
+/// Helper for: RemoteConfigManager::save(), RemoteLayers::save()
+async fn write_config(config: &str) -> Result<()> {
+    let config_path = get_config_path().await?;
+    let local = fs::read_to_string(&config_path).await.unwrap_or_default();
+    let config = keep_local_threshold(config, &local)?;
+
+    fs::create_dir_all(config_path.parent().unwrap()).await?;
+    fs::write(config_path, config).await?;
+    log_info!("Successfully saved remote config to destination.");
+    Ok(())
+}
+
+/// Carries `[safety] confirm_over` of the local config over into a remote one, so that a remote
+/// can neither raise nor drop the change threshold guarding `cutler apply`.
+pub fn keep_local_threshold(remote: &str, local: &str) -> Result<String> {
+    let threshold = |doc: &DocumentMut| doc.get("safety")?.get("confirm_over")?.as_integer();
+
+    let local = local.parse::<DocumentMut>().ok();
+    let local = local.as_ref().and_then(threshold);
+    let mut doc = remote
+        .parse::<DocumentMut>()
+        .context("Remote config is invalid")?;
+    if threshold(&doc) == local {
+        return Ok(remote.to_string());
+    }
+
+    if let Some(safety) = doc.get_mut("safety").and_then(|s| s.as_table_like_mut()) {
+        safety.remove("confirm_over");
+    }
+    if let Some(local) = local {
+        doc["safety"]["confirm_over"] = toml_edit::value(local);
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Deep-merges `overlay` into `base`. Tables are merged recursively, everything else is replaced.
+pub fn deep_merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_tbl)), toml::Value::Table(overlay_tbl)) => {
+                deep_merge(base_tbl, overlay_tbl)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Flattens a table into dotted key paths mapped to their (non-table) values.
+pub fn flatten(table: &toml::Table) -> BTreeMap<String, toml::Value> {
+    fn walk(prefix: &str, table: &toml::Table, out: &mut BTreeMap<String, toml::Value>) {
+        for (key, value) in table {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{prefix}.{key}")
+            };
+
+            match value {
+                toml::Value::Table(inner) => walk(&path, inner, out),
+                _ => {
+                    out.insert(path, value.clone());
+                }
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    walk("", table, &mut out);
+    out
+}
+
+/// Fetches all remotes of a config in order and merges them into one.
+#[derive(Debug, Clone)]
+pub struct RemoteLayers {
+    layers: Vec<RemoteConfigManager>,
+}
+
+impl RemoteLayers {
+    pub fn new(remotes: &Remotes) -> Self {
+        Self {
+            layers: remotes
+                .layers()
+                .iter()
+                .map(|r| RemoteConfigManager::new(r.url.clone()))
+                .collect(),
+        }
+    }
+
+    /// Number of layers.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Fetch every layer, in order.
+    pub async fn fetch(&self) -> Result<()> {
+        for layer in &self.layers {
+            layer.fetch().await?;
+        }
+        Ok(())
+    }
+
+    /// Checks if none of the layers changed since the last fetch.
+    pub fn is_unchanged(&self) -> bool {
+        self.layers.iter().all(|l| l.is_unchanged())
+    }
+
+    /// Helper for: merged(), origin()
+    fn tables(&self) -> Result<Vec<toml::Table>> {
+        self.layers
+            .iter()
+            .map(|l| Ok(toml::from_str::<toml::Table>(l.get()?)?))
+            .collect()
+    }
+
+    /// Returns the merged config as TOML.
+    ///
+    /// A single remote is returned verbatim. Otherwise the layers are deep-merged and the
+    /// given `remotes` are kept as the [remote] value, so that the layering survives a sync.
+    pub fn merged(&self, remotes: &Remotes) -> Result<String> {
+        if let [single] = self.layers.as_slice() {
+            return Ok(single.get()?.clone());
+        }
+
+        let mut merged = toml::Table::new();
+        for table in self.tables()? {
+            deep_merge(&mut merged, table);
+        }
+        merged.insert("remote".to_string(), toml::Value::try_from(remotes)?);
+
+        let text = toml::to_string(&merged)?;
+        toml::from_str::<Config>(&text).context("Merged remote config is invalid")?;
+
+        Ok(text)
+    }
+
+    /// Returns the URL of the last layer defining the given dotted key path (or anything below it).
+    pub fn origin(&self, key: &str) -> Option<&str> {
+        let tables = self.tables().ok()?;
+
+        self.layers
+            .iter()
+            .zip(tables)
+            .rev()
+            .find(|(_, table)| {
+                let prefix = format!("{key}.");
+                flatten(table)
+                    .keys()
+                    .any(|k| k == key || k.starts_with(&prefix))
+            })
+            .map(|(layer, _)| layer.url())
+    }
+
+    /// Whether a (merged) remote config is locked, with a lock which hasn't expired.
+    pub fn locks(config: &str) -> bool {
+        Lock::read(config).is_some_and(|lock| lock.is_active_now())
+    }
+
+    /// Save the merged config as the local config.
+    pub async fn save(&self, remotes: &Remotes) -> Result<()> {
+        write_config(&self.merged(remotes)?).await
+    }
+}
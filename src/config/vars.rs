@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::env;
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+use crate::config::core::{Config, Var};
+use crate::util::logging::{MASK, add_secret};
+use crate::{log_info, log_warn};
+
+/// Regex to match $var or ${var}.
+/// note: $ followed by [A-Za-z_][A-Za-z0-9_]* or ${...}
+static VAR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$([A-Za-z_][A-Za-z0-9_]*)|\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap()
+});
+
+/// Variables exported by commands (`export_var`) during the current process.
+static EXPORTED: LazyLock<Mutex<HashMap<String, String>>> = LazyLock::new(Default::default);
+
+/// Outputs of `from_command` variables, keyed by the command, cached for the run.
+static DYNAMIC: LazyLock<Mutex<HashMap<String, Option<String>>>> = LazyLock::new(Default::default);
+
+/// Helper for: resolve()
+/// Runs the command behind a dynamic variable and returns its trimmed stdout.
+fn run_from_command(cmd: &str) -> Option<String> {
+    log_info!("Resolving variable from command: {cmd}");
+
+    match Command::new("sh").arg("-c").arg(cmd).output() {
+        Ok(out) if out.status.success() => {
+            Some(String::from_utf8_lossy(&out.stdout).trim().to_string())
+        }
+        Ok(out) => {
+            log_warn!("Variable command `{cmd}` exited with {}", out.status);
+            None
+        }
+        Err(e) => {
+            log_warn!("Could not run variable command `{cmd}`: {e}");
+            None
+        }
+    }
+}
+
+/// Helper for: resolve()
+/// Runs the command of a dynamic value only once per process; later calls hit the cache.
+fn resolve_command(cmd: &str) -> Option<String> {
+    DYNAMIC
+        .lock()
+        .unwrap()
+        .entry(cmd.to_string())
+        .or_insert_with(|| run_from_command(cmd))
+        .clone()
+}
+
+/// Resolves a single [vars] value.
+pub fn resolve(var: &Var) -> Option<String> {
+    match var {
+        Var::Static(value) => Some(value.clone()),
+        Var::Dynamic { from_command } => resolve_command(from_command),
+        Var::Secret {
+            value: Some(value), ..
+        } => Some(value.clone()),
+        Var::Secret {
+            from_command: Some(cmd),
+            ..
+        } => resolve_command(cmd),
+        Var::Secret { .. } => None,
+    }
+}
+
+/// Name endings of variables which are treated as secrets without `secret = true`.
+const SECRET_NAMES: [&str; 7] = [
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+];
+
+/// Whether a variable's name looks like it holds a secret, e.g. `GITHUB_TOKEN`.
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_uppercase();
+    SECRET_NAMES
+        .iter()
+        .any(|s| name == *s || name.ends_with(&format!("_{s}")))
+}
+
+/// Whether a [vars] value is a secret: marked with `secret = true`, read from the Keychain, or
+/// named like one.
+pub fn is_secret(name: &str, var: &Var) -> bool {
+    match var {
+        Var::Secret { secret, .. } => *secret || is_secret_name(name),
+        Var::Dynamic { from_command } => {
+            from_command.contains("security find-") || is_secret_name(name)
+        }
+        Var::Static(_) => is_secret_name(name),
+    }
+}
+
+/// Records the value of an exported variable for the rest of the run.
+pub fn set_exported(name: &str, value: String) {
+    if is_secret_name(name) {
+        add_secret(&value);
+    }
+    EXPORTED.lock().unwrap().insert(name.to_string(), value);
+}
+
+/// Checks if a variable has already been exported during this run.
+pub fn is_exported(name: &str) -> bool {
+    EXPORTED.lock().unwrap().contains_key(name)
+}
+
+/// Returns all variables exported so far.
+pub fn exported_vars() -> HashMap<String, String> {
+    EXPORTED.lock().unwrap().clone()
+}
+
+/// Looks up a variable visible to substitution: an exported one, or else one of `[vars]`.
+/// `from_command` values are resolved here, so only variables which are actually referenced
+/// run their commands.
+pub fn lookup_var(config: &Config, name: &str) -> Option<String> {
+    if let Some(value) = EXPORTED.lock().unwrap().get(name) {
+        return Some(value.clone());
+    }
+
+    let var = config.vars.as_ref()?.get(name)?;
+    let value = resolve(var)?;
+    if is_secret(name, var) {
+        add_secret(&value);
+    }
+    Some(value)
+}
+
+/// Every variable visible to substitution, but with secrets and the values of `from_command` variables masked,
+/// since these usually come from secret stores like the Keychain. Their commands aren't run.
+pub fn masked_vars(config: &Config) -> HashMap<String, String> {
+    let mut vars: HashMap<String, String> = config
+        .vars
+        .iter()
+        .flatten()
+        .map(|(name, var)| {
+            let value = match var {
+                Var::Static(value) if !is_secret(name, var) => value.clone(),
+                _ => MASK.to_string(),
+            };
+            (name.clone(), value)
+        })
+        .collect();
+    vars.extend(
+        exported_vars()
+            .into_keys()
+            .map(|name| (name, MASK.to_string())),
+    );
+    vars
+}
+
+/// Helper for: substitute(), substitute_known()
+fn var_name<'a>(caps: &'a Captures) -> &'a str {
+    // caps[1] is for $var, caps[2] is for ${var}
+    caps.get(1)
+        .or_else(|| caps.get(2))
+        .map(|m| m.as_str())
+        .unwrap_or("")
+}
+
+/// Perform variable substitution (vars + env) in a command text.
+/// Unresolved variables are normalized to `${var}` and left for the shell.
+pub fn substitute(text: &str, vars: &HashMap<String, String>) -> String {
+    substitute_with(text, |name| vars.get(name).cloned())
+}
+
+/// Like `substitute()`, but variables are looked up one by one as they're hit, e.g. through
+/// `lookup_var()`.
+pub fn substitute_with(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    VAR_RE
+        .replace_all(text, |caps: &Captures| {
+            let name = var_name(caps);
+            lookup(name)
+                .or_else(|| {
+                    let value = env::var(name).ok()?;
+                    if is_secret_name(name) {
+                        add_secret(&value);
+                    }
+                    Some(value)
+                })
+                .unwrap_or_else(|| format!("${{{}}}", name))
+        })
+        .into_owned()
+}
+
+/// Substitute only the variables found in `vars`, leaving everything else untouched.
+pub fn substitute_known(text: &str, vars: &HashMap<String, String>) -> String {
+    substitute_known_with(text, |name| vars.get(name).cloned())
+}
+
+/// Like `substitute_known()`, but variables are looked up one by one as they're hit.
+/// This is what `[set]` string values go through.
+pub fn substitute_known_with(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    VAR_RE
+        .replace_all(text, |caps: &Captures| {
+            lookup(var_name(caps)).unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
@@ -0,0 +1,545 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A curated catalog of well-known defaults keys.
+//!
+//! Each entry gives a key a readable alias which can be used in `[set]` instead of the raw key
+//! (`icon_size` for the Dock's `tilesize`), along with the type and range of values macOS
+//! accepts for it. Values of cataloged keys are checked before anything is written, whether
+//! they're set through the alias or the raw key.
+
+use std::fmt;
+use toml::{Table, Value};
+
+use crate::domains::collector::effective;
+use crate::domains::meta::Requires;
+use crate::util::macos::MacosVersion;
+
+/// The values a cataloged key accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Bool,
+    Int {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    /// A string, restricted to the given choices unless empty.
+    String(&'static [&'static str]),
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Bool => write!(f, "a boolean"),
+            Kind::Int { min, max } => write!(f, "an integer from {min} to {max}"),
+            Kind::Float { min, max } => write!(f, "a number from {min} to {max}"),
+            Kind::String([]) => write!(f, "a string"),
+            Kind::String(choices) => write!(f, "one of {}", choices.join(", ")),
+        }
+    }
+}
+
+/// A cataloged key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    /// Domain as written in `[set]`, e.g. `dock`.
+    pub domain: &'static str,
+    pub alias: &'static str,
+    pub key: &'static str,
+    pub kind: Kind,
+    pub desc: &'static str,
+    /// What the setting needs before it takes effect, if anything.
+    pub requires: Option<Requires>,
+}
+
+impl Entry {
+    /// Checks a value against the kind of the key, explaining what's wrong with it.
+    /// Like macOS itself, numbers and booleans are also accepted in string form, and booleans
+    /// as 0 or 1.
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let ok = match (self.kind, value) {
+            (Kind::Bool, Value::Boolean(_)) => true,
+            (Kind::Bool, Value::Integer(i)) => *i == 0 || *i == 1,
+            (Kind::Bool, Value::String(s)) => {
+                ["yes", "no", "true", "false", "1", "0"].contains(&s.to_lowercase().as_str())
+            }
+            (Kind::Int { min, max }, Value::Integer(i)) => (min..=max).contains(i),
+            (Kind::Int { min, max }, Value::String(s)) => s
+                .trim()
+                .parse()
+                .is_ok_and(|i: i64| (min..=max).contains(&i)),
+            (Kind::Float { min, max }, Value::Integer(i)) => (min..=max).contains(&(*i as f64)),
+            (Kind::Float { min, max }, Value::Float(f)) => (min..=max).contains(f),
+            (Kind::Float { min, max }, Value::String(s)) => s
+                .trim()
+                .parse()
+                .is_ok_and(|f: f64| (min..=max).contains(&f)),
+            (Kind::String(choices), Value::String(s)) => {
+                choices.is_empty() || choices.contains(&s.as_str())
+            }
+            _ => false,
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(format!("expected {}, got {value}", self.kind))
+        }
+    }
+}
+
+/// Helper for: CATALOG
+const fn entry(
+    domain: &'static str,
+    alias: &'static str,
+    key: &'static str,
+    kind: Kind,
+    desc: &'static str,
+) -> Entry {
+    Entry {
+        domain,
+        alias,
+        key,
+        kind,
+        desc,
+        requires: None,
+    }
+}
+
+impl Entry {
+    /// Helper for: CATALOG
+    const fn needs(mut self, requires: Requires) -> Self {
+        self.requires = Some(requires);
+        self
+    }
+}
+
+const TRACKPAD: &str = "driver.AppleBluetoothMultitouch.trackpad";
+
+/// Every cataloged key.
+pub const CATALOG: &[Entry] = &[
+    // Dock
+    entry(
+        "dock",
+        "icon_size",
+        "tilesize",
+        Kind::Int { min: 16, max: 128 },
+        "Size of Dock icons in pixels",
+    ),
+    entry(
+        "dock",
+        "auto_hide",
+        "autohide",
+        Kind::Bool,
+        "Hide the Dock when not in use",
+    ),
+    entry(
+        "dock",
+        "auto_hide_delay",
+        "autohide-delay",
+        Kind::Float {
+            min: 0.0,
+            max: 10.0,
+        },
+        "Seconds before the hidden Dock appears",
+    ),
+    entry(
+        "dock",
+        "auto_hide_duration",
+        "autohide-time-modifier",
+        Kind::Float {
+            min: 0.0,
+            max: 10.0,
+        },
+        "Seconds the Dock takes to show or hide",
+    ),
+    entry(
+        "dock",
+        "position",
+        "orientation",
+        Kind::String(&["left", "bottom", "right"]),
+        "Position of the Dock on screen",
+    ),
+    entry(
+        "dock",
+        "magnify",
+        "magnification",
+        Kind::Bool,
+        "Magnify icons on hover",
+    ),
+    entry(
+        "dock",
+        "magnified_size",
+        "largesize",
+        Kind::Int { min: 16, max: 128 },
+        "Size of magnified Dock icons in pixels",
+    ),
+    entry(
+        "dock",
+        "minimize_effect",
+        "mineffect",
+        Kind::String(&["genie", "scale", "suck"]),
+        "Animation for minimizing windows",
+    ),
+    entry(
+        "dock",
+        "minimize_to_app",
+        "minimize-to-application",
+        Kind::Bool,
+        "Minimize windows into their app icon",
+    ),
+    entry(
+        "dock",
+        "show_recents",
+        "show-recents",
+        Kind::Bool,
+        "Show recent apps in the Dock",
+    ),
+    entry(
+        "dock",
+        "launch_animation",
+        "launchanim",
+        Kind::Bool,
+        "Animate opening apps",
+    ),
+    // Finder
+    entry(
+        "finder",
+        "show_path_bar",
+        "ShowPathbar",
+        Kind::Bool,
+        "Show the path bar in Finder windows",
+    ),
+    entry(
+        "finder",
+        "show_status_bar",
+        "ShowStatusBar",
+        Kind::Bool,
+        "Show the status bar in Finder windows",
+    ),
+    entry(
+        "finder",
+        "show_hidden_files",
+        "AppleShowAllFiles",
+        Kind::Bool,
+        "Show hidden files in Finder",
+    ),
+    entry(
+        "finder",
+        "default_view",
+        "FXPreferredViewStyle",
+        Kind::String(&["icnv", "Nlsv", "clmv", "Flwv"]),
+        "Default view: icons, list, columns or gallery",
+    ),
+    entry(
+        "finder",
+        "search_scope",
+        "FXDefaultSearchScope",
+        Kind::String(&["SCcf", "SCsp", "SCev"]),
+        "Search the current folder, previous scope or whole Mac",
+    ),
+    entry(
+        "finder",
+        "extension_change_warning",
+        "FXEnableExtensionChangeWarning",
+        Kind::Bool,
+        "Warn before changing a file extension",
+    ),
+    entry(
+        "finder",
+        "folders_first",
+        "_FXSortFoldersFirst",
+        Kind::Bool,
+        "Keep folders on top when sorting by name",
+    ),
+    // global
+    entry(
+        "NSGlobalDomain",
+        "show_all_extensions",
+        "AppleShowAllExtensions",
+        Kind::Bool,
+        "Show all filename extensions",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "interface_style",
+        "AppleInterfaceStyle",
+        Kind::String(&["Dark"]),
+        "Dark mode (remove the key for light mode)",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "scroll_bars",
+        "AppleShowScrollBars",
+        Kind::String(&["WhenScrolling", "Automatic", "Always"]),
+        "When to show scroll bars",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "key_repeat",
+        "KeyRepeat",
+        Kind::Int { min: 1, max: 120 },
+        "Key repeat interval (lower is faster)",
+    )
+    .needs(Requires::Logout),
+    entry(
+        "NSGlobalDomain",
+        "initial_key_repeat",
+        "InitialKeyRepeat",
+        Kind::Int { min: 10, max: 120 },
+        "Delay until key repeat (lower is shorter)",
+    )
+    .needs(Requires::Logout),
+    entry(
+        "NSGlobalDomain",
+        "press_and_hold",
+        "ApplePressAndHoldEnabled",
+        Kind::Bool,
+        "Show accents when holding a key instead of repeating it",
+    )
+    .needs(Requires::Logout),
+    entry(
+        "NSGlobalDomain",
+        "natural_scrolling",
+        "com.apple.swipescrolldirection",
+        Kind::Bool,
+        "Content follows finger movement",
+    )
+    .needs(Requires::Logout),
+    entry(
+        "NSGlobalDomain",
+        "auto_correct",
+        "NSAutomaticSpellingCorrectionEnabled",
+        Kind::Bool,
+        "Correct spelling automatically",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "auto_capitalize",
+        "NSAutomaticCapitalizationEnabled",
+        Kind::Bool,
+        "Capitalize words automatically",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "smart_quotes",
+        "NSAutomaticQuoteSubstitutionEnabled",
+        Kind::Bool,
+        "Use smart quotes",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "smart_dashes",
+        "NSAutomaticDashSubstitutionEnabled",
+        Kind::Bool,
+        "Use smart dashes",
+    ),
+    entry(
+        "NSGlobalDomain",
+        "period_with_double_space",
+        "NSAutomaticPeriodSubstitutionEnabled",
+        Kind::Bool,
+        "Add a period with a double space",
+    ),
+    // trackpad
+    entry(
+        TRACKPAD,
+        "tap_to_click",
+        "Clicking",
+        Kind::Bool,
+        "Tap to click",
+    )
+    .needs(Requires::Logout),
+    entry(
+        TRACKPAD,
+        "three_finger_drag",
+        "TrackpadThreeFingerDrag",
+        Kind::Bool,
+        "Drag with three fingers",
+    )
+    .needs(Requires::Logout),
+];
+
+/// Looks up a cataloged key by its alias in the given `[set]` domain, however the domain is
+/// spelled (`dock` or `com.apple.dock`).
+pub fn by_alias(domain: &str, alias: &str) -> Option<&'static Entry> {
+    let wanted = effective(domain, alias);
+    CATALOG
+        .iter()
+        .find(|e| effective(e.domain, e.alias) == wanted)
+}
+
+/// Looks up a cataloged key by its raw key in the given `[set]` domain, however the domain is
+/// spelled (`dock` or `com.apple.dock`).
+pub fn by_key(domain: &str, key: &str) -> Option<&'static Entry> {
+    let wanted = effective(domain, key);
+    CATALOG
+        .iter()
+        .find(|e| effective(e.domain, e.key) == wanted)
+}
+
+/// Looks up a cataloged key by its effective domain and key, e.g. `com.apple.dock` and
+/// `tilesize`.
+pub fn by_effective(eff_domain: &str, eff_key: &str) -> Option<&'static Entry> {
+    CATALOG
+        .iter()
+        .find(|e| effective(e.domain, e.key) == (eff_domain.to_string(), eff_key.to_string()))
+}
+
+/// Replaces aliases in the settings of a `[set]` domain with their raw keys.
+/// Errors out if both the alias and the raw key are set.
+pub fn resolve_aliases(domain: &str, settings: &mut Table) -> Result<(), String> {
+    let aliases: Vec<String> = settings
+        .keys()
+        .filter(|k| by_alias(domain, k).is_some())
+        .cloned()
+        .collect();
+
+    for alias in aliases {
+        let Some(entry) = by_alias(domain, &alias) else {
+            continue;
+        };
+        if settings.contains_key(entry.key) {
+            return Err(format!(
+                "{domain}: `{alias}` and `{}` set the same key",
+                entry.key
+            ));
+        }
+        if let Some(value) = settings.remove(&alias) {
+            settings.insert(entry.key.to_string(), value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every cataloged key in the collected settings, returning one line per invalid value.
+pub fn problems<'a>(domains: impl IntoIterator<Item = (&'a String, &'a Table)>) -> Vec<String> {
+    let mut out = Vec::new();
+
+    for (domain, settings) in domains {
+        for (key, value) in settings {
+            if let Some(entry) = by_key(domain, key)
+                && let Err(reason) = entry.check(value)
+            {
+                out.push(format!("{domain}.{} ({key}): {reason}", entry.alias));
+            }
+        }
+    }
+
+    out.sort();
+    out
+}
+
+/// A key (or a whole domain) which macOS stopped reading in some release.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retired {
+    /// Effective domain, e.g. `com.apple.dock`.
+    pub domain: &'static str,
+    /// None if the whole domain is gone.
+    pub key: Option<&'static str>,
+    /// First macOS version which ignores the key.
+    pub since: MacosVersion,
+    /// What to use instead, if anything.
+    pub instead: Option<&'static str>,
+}
+
+/// Helper for: RETIRED
+const fn retired(
+    domain: &'static str,
+    key: Option<&'static str>,
+    since: MacosVersion,
+    instead: Option<&'static str>,
+) -> Retired {
+    Retired {
+        domain,
+        key,
+        since,
+        instead,
+    }
+}
+
+/// Keys and domains known to be removed or renamed in newer macOS releases.
+pub const RETIRED: &[Retired] = &[
+    retired(
+        "com.apple.finder",
+        Some("QLEnableTextSelection"),
+        MacosVersion::new(10, 11),
+        None,
+    ),
+    retired("com.apple.dashboard", None, MacosVersion::new(10, 15), None),
+    retired(
+        "com.apple.dock",
+        Some("dashboard-in-overlay"),
+        MacosVersion::new(10, 15),
+        None,
+    ),
+    retired(
+        "com.apple.iTunes",
+        None,
+        MacosVersion::new(10, 15),
+        Some("com.apple.Music, com.apple.TV or com.apple.Podcasts"),
+    ),
+    retired(
+        "com.apple.menuextra.clock",
+        Some("DateFormat"),
+        MacosVersion::new(11, 0),
+        Some("ShowSeconds, ShowDayOfWeek, ShowDate and ShowAMPM"),
+    ),
+    retired(
+        "com.apple.systemuiserver",
+        Some("menuExtras"),
+        MacosVersion::new(11, 0),
+        Some("[menubar]"),
+    ),
+    retired(
+        "NSGlobalDomain",
+        Some("CGFontRenderingFontSmoothingDisabled"),
+        MacosVersion::new(11, 0),
+        None,
+    ),
+];
+
+/// Looks up a retired key by its effective domain and key. With a target version, only keys
+/// retired in or before it are returned.
+pub fn retired_key(
+    eff_domain: &str,
+    eff_key: &str,
+    target: Option<MacosVersion>,
+) -> Option<&'static Retired> {
+    RETIRED.iter().find(|r| {
+        r.domain == eff_domain
+            && r.key.is_none_or(|key| key == eff_key)
+            && target.is_none_or(|target| target >= r.since)
+    })
+}
+
+/// Checks the collected settings for keys which the target macOS version (or any version, if
+/// None) no longer reads, returning one line per key.
+pub fn retirements<'a>(
+    domains: impl IntoIterator<Item = (&'a String, &'a Table)>,
+    target: Option<MacosVersion>,
+) -> Vec<String> {
+    let mut out = Vec::new();
+
+    for (domain, settings) in domains {
+        for key in settings.keys() {
+            let (eff_domain, eff_key) = effective(domain, key);
+            let Some(retired) = retired_key(&eff_domain, &eff_key, target) else {
+                continue;
+            };
+
+            let mut line = format!("{domain}.{key} is ignored since {}", retired.since);
+            if let Some(instead) = retired.instead {
+                line.push_str(&format!("; use {instead} instead"));
+            }
+            out.push(line);
+        }
+    }
+
+    out.sort();
+    out
+}
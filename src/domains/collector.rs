@@ -1,48 +1,53 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use anyhow::Result;
-use defaults_rs::{Domain, PrefValue, Preferences};
-use std::collections::HashMap;
+use anyhow::{Result, anyhow, bail};
+use defaults_rs::PrefValue;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use toml::Table;
-use toml_edit::Item;
+use toml_edit::{DocumentMut, Item};
 
 use crate::config::core::Config;
+use crate::config::vars::{lookup_var, substitute_known_with};
+use crate::domains::catalog;
 use crate::domains::convert::toml_edit_to_toml;
+use crate::domains::files::{self, domain_obj, is_missing, is_path_domain};
+use crate::domains::hot_corners::hot_corner_domains;
+use crate::domains::menubar::{menubar_domains, resolve_host};
+use crate::domains::meta::{SettingsMeta, unwrap_setting};
+use crate::domains::screenshots::screenshot_domains;
+use crate::domains::shortcuts::shortcut_domains;
+use crate::domains::software_update::software_update_domains;
+use crate::domains::spotlight::spotlight_domains;
+use crate::domains::text_replacements::replacement_domains;
+use crate::domains::trackpad::trackpad_domains;
+use crate::log_warn;
+use crate::plist::core::expand_path;
+use crate::util::macos::current_version;
 
 /// Collect all tables in `[set]`, parse with toml_edit to properly handle inline tables,
 /// and return a map domain → settings.
 pub async fn collect(config: &Config) -> Result<HashMap<String, Table>> {
+    Ok(collect_with_meta(config).await?.0)
+}
+
+/// Like collect(), but also returns the `desc`/`url` metadata of settings written in the
+/// extended `{ value = ..., desc = "..." }` form.
+pub async fn collect_with_meta(config: &Config) -> Result<(HashMap<String, Table>, SettingsMeta)> {
     let mut out = HashMap::new();
 
     // If we have the config path, read the raw file to parse with toml_edit
     // This allows us to distinguish inline tables from nested tables
-    if let Ok(doc) = config.load_as_mut(false).await {
-        if let Some(Item::Table(set_table)) = doc.get("set") {
-            for (domain_key, item) in set_table.iter() {
-                if let Item::Table(domain_table) = item {
-                    // Now process the domain_table, checking if values are inline tables
-                    let mut settings = Table::new();
-
-                    for (key, value) in domain_table.iter() {
-                        match value {
-                            Item::Value(v) => {
-                                // This could be a scalar value or an inline table
-                                settings.insert(key.to_string(), toml_edit_to_toml(v)?);
-                            }
-                            Item::Table(nested_table) => {
-                                // This is a nested table header [set.domain.nested]
-                                // Recursively process it with the prefixed domain name
-                                let nested_domain = format!("{}.{}", domain_key, key);
-                                collect_nested_table(&nested_domain, nested_table, &mut out)?;
-                            }
-                            _ => {}
-                        }
-                    }
+    if let Ok(docs) = config.load_docs().await {
+        // settings in later layers replace whole values, even dictionaries
+        for doc in docs {
+            let mut layer = HashMap::new();
+            collect_set(&doc, &mut layer)?;
 
-                    if !settings.is_empty() {
-                        out.insert(domain_key.to_string(), settings);
-                    }
-                }
+            for (domain, mut settings) in layer {
+                catalog::resolve_aliases(&domain, &mut settings).map_err(|e| anyhow!(e))?;
+                out.entry(domain)
+                    .or_insert_with(Table::new)
+                    .extend(settings);
             }
         }
     } else {
@@ -54,6 +59,7 @@ pub async fn collect(config: &Config) -> Result<HashMap<String, Table>> {
                 for (k, v) in domain_val {
                     settings.insert(k.clone(), v.clone());
                 }
+                catalog::resolve_aliases(domain_key, &mut settings).map_err(|e| anyhow!(e))?;
                 if !settings.is_empty() {
                     out.insert(domain_key.clone(), settings);
                 }
@@ -61,7 +67,136 @@ pub async fn collect(config: &Config) -> Result<HashMap<String, Table>> {
         }
     }
 
-    Ok(out)
+    // strip extended-form settings down to their values, leaving out the ones meant for other
+    // macOS versions; their metadata is kept so that status can list them
+    let mut meta = SettingsMeta::new();
+    let mut invalid = Vec::new();
+    let version = current_version();
+    out.retain(|domain, table| {
+        let len = table.len();
+        table.retain(|key, value| {
+            let m = match unwrap_setting(value) {
+                Ok(Some(m)) if !m.is_empty() => m,
+                Ok(_) => return true,
+                Err(e) => {
+                    invalid.push(format!("{domain}.{key}: {e}"));
+                    return true;
+                }
+            };
+            let applies = m.macos.contains(version);
+            meta.insert(effective(domain, key), m);
+            applies
+        });
+        len == table.len() || !table.is_empty()
+    });
+    if !invalid.is_empty() {
+        invalid.sort();
+        bail!("Invalid settings:\n  {}", invalid.join("\n  "));
+    }
+
+    // resolve variables inside string values
+    let lookup = |name: &str| lookup_var(config, name);
+    for table in out.values_mut() {
+        for (_, value) in table.iter_mut() {
+            substitute_value(value, &lookup);
+        }
+    }
+
+    // catch invalid values of well-known keys before anything is written
+    let problems = catalog::problems(&out);
+    if !problems.is_empty() {
+        bail!("Invalid settings:\n  {}", problems.join("\n  "));
+    }
+
+    // compile [app_shortcuts] into NSUserKeyEquivalents, [hot_corners] into wvous-* keys,
+    // [screenshots] into screencapture keys, [trackpad] into the keys of both trackpad kinds,
+    // [spotlight] into exclusions and categories, [softwareupdate] into the update policy,
+    // [menubar] into Control Center's visibility keys and [text_replacements] into the
+    // replacement arrays after substitution, since encoded shortcuts may contain `$`
+    let sugar = [
+        ("app_shortcuts", shortcut_domains(config)?),
+        ("hot_corners", hot_corner_domains(config)?),
+        ("screenshots", screenshot_domains(config)?),
+        ("trackpad", trackpad_domains(config)?),
+        ("spotlight", spotlight_domains(config)?),
+        ("softwareupdate", software_update_domains(config)?),
+        ("menubar", menubar_domains(config)?),
+        ("text_replacements", replacement_domains(config)?),
+    ];
+    for (origin, domains) in sugar {
+        for (domain, settings) in domains {
+            let table = out.entry(domain.clone()).or_default();
+            for (key, value) in settings {
+                if table.contains_key(&key) {
+                    bail!("{domain}.{key} is set in both [set] and [{origin}]");
+                }
+                table.insert(key, value);
+            }
+        }
+    }
+
+    Ok((out, meta))
+}
+
+/// Helper for: collect()
+/// Recursively substitutes known variables inside string values.
+fn substitute_value(value: &mut toml::Value, lookup: &impl Fn(&str) -> Option<String>) {
+    match value {
+        toml::Value::String(s) => *s = substitute_known_with(s, lookup),
+        toml::Value::Array(arr) => arr.iter_mut().for_each(|v| substitute_value(v, lookup)),
+        toml::Value::Table(tbl) => tbl
+            .iter_mut()
+            .for_each(|(_, v)| substitute_value(v, lookup)),
+        _ => {}
+    }
+}
+
+/// Helper for: collect_with_meta(), overlay_settings()
+/// Collects the [set] tables of a raw config document.
+fn collect_set(doc: &DocumentMut, out: &mut HashMap<String, Table>) -> Result<()> {
+    if let Some(Item::Table(set_table)) = doc.get("set") {
+        for (domain_key, item) in set_table.iter() {
+            if let Item::Table(domain_table) = item {
+                // Now process the domain_table, checking if values are inline tables
+                let mut settings = Table::new();
+
+                for (key, value) in domain_table.iter() {
+                    match value {
+                        Item::Value(v) => {
+                            // This could be a scalar value or an inline table
+                            settings.insert(key.to_string(), toml_edit_to_toml(v)?);
+                        }
+                        Item::Table(nested_table) => {
+                            // This is a nested table header [set.domain.nested]
+                            // Recursively process it with the prefixed domain name
+                            let nested_domain = format!("{}.{}", domain_key, key);
+                            collect_nested_table(&nested_domain, nested_table, out)?;
+                        }
+                        _ => {}
+                    }
+                }
+
+                if !settings.is_empty() {
+                    out.insert(domain_key.to_string(), settings);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the effective domain-key pairs set by the local overlay, e.g. `config.local.toml`.
+pub async fn overlay_settings(config: &Config) -> Result<HashSet<(String, String)>> {
+    let mut local = HashMap::new();
+    if let Some(overlay) = config.load_overlay_as_mut().await? {
+        collect_set(&overlay, &mut local)?;
+    }
+
+    Ok(local
+        .iter()
+        .flat_map(|(domain, table)| table.keys().map(|key| effective(domain, key)))
+        .collect())
 }
 
 /// Helper to recursively process nested tables
@@ -96,17 +231,45 @@ fn collect_nested_table(
     Ok(())
 }
 
+/// Marks a config domain which is used as is, without the `com.apple.` prefix.
+pub const RAW_PREFIX: char = '@';
+
+/// First labels of reverse-DNS domains, e.g. `org` in `org.hammerspoon.Hammerspoon`.
+const REVERSE_DNS_TLDS: &[&str] = &[
+    "com", "org", "net", "io", "dev", "app", "co", "me", "de", "uk", "fr", "nl", "ch", "se", "jp",
+    "cn", "us", "ca", "au",
+];
+
+/// Whether a config domain is already a fully qualified reverse-DNS name, like
+/// `com.apple.dock` or `org.hammerspoon.Hammerspoon`, which is used without a prefix.
+pub fn is_qualified(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+
+    labels.len() >= 3
+        && REVERSE_DNS_TLDS.contains(&labels[0])
+        && labels.iter().all(|l| !l.is_empty())
+}
+
 /// Helper for: effective()
 /// Turn a config‐domain into the real defaults domain.
-///   finder            -> com.apple.finder
-///   NSGlobalDomain    -> NSGlobalDomain
-///   NSGlobalDomain.bar-> NSGlobalDomain
+///   finder                      -> com.apple.finder
+///   NSGlobalDomain              -> NSGlobalDomain
+///   NSGlobalDomain.bar          -> NSGlobalDomain
+///   /path/to/x.plist            -> /path/to/x.plist
+///   @MyDomain                   -> MyDomain
+///   org.hammerspoon.Hammerspoon -> org.hammerspoon.Hammerspoon
 fn get_defaults_domain(domain: &str) -> String {
-    if domain.strip_prefix("NSGlobalDomain.").is_some() {
+    if is_path_domain(domain) {
+        domain.into()
+    } else if domain.strip_prefix("NSGlobalDomain.").is_some() {
         // NSGlobalDomain.foo -> NSGlobalDomain
         "NSGlobalDomain".into()
     } else if domain == "NSGlobalDomain" {
         domain.into()
+    } else if let Some(raw) = domain.strip_prefix(RAW_PREFIX) {
+        raw.into()
+    } else if is_qualified(domain) {
+        domain.into()
     } else {
         // anything else gets com.apple.
         format!("com.apple.{domain}")
@@ -126,15 +289,73 @@ pub fn effective(domain: &str, key: &str) -> (String, String) {
     (dom, k)
 }
 
-/// Read the current value of a defaults key, if any.
-pub async fn read_current(eff_domain: &str, eff_key: &str) -> Option<PrefValue> {
-    let domain_obj = if eff_domain == "NSGlobalDomain" {
-        Domain::Global
-    } else if let Some(rest) = eff_domain.strip_prefix("com.apple.") {
-        Domain::User(format!("com.apple.{rest}"))
-    } else {
-        Domain::User(eff_domain.to_string())
-    };
+/// Turns an effective domain back into the shortest config domain addressing it, e.g.
+/// `com.apple.dock` into `dock` and `MyDomain` into `@MyDomain`.
+pub fn config_domain(eff_domain: &str) -> String {
+    match eff_domain.strip_prefix("com.apple.") {
+        Some(rest) if !rest.is_empty() && get_defaults_domain(rest) == eff_domain => rest.into(),
+        _ if get_defaults_domain(eff_domain) == eff_domain => eff_domain.into(),
+        _ => format!("{RAW_PREFIX}{eff_domain}"),
+    }
+}
+
+/// Returns the effective domains of the given config domains which aren't in `known`,
+/// sorted and deduplicated. NSGlobalDomain always exists, and path domains exist as files.
+pub fn missing_domains<'a>(
+    config_domains: impl IntoIterator<Item = &'a String>,
+    known: &[String],
+) -> Vec<String> {
+    let known: HashSet<&String> = known.iter().collect();
+
+    let missing: BTreeSet<String> = config_domains
+        .into_iter()
+        .map(|dom| effective(dom, "").0)
+        .filter(|dom| dom != "NSGlobalDomain" && !known.contains(dom))
+        .filter(|dom| {
+            !is_path_domain(dom)
+                || is_missing(&expand_path(
+                    &resolve_host(dom).unwrap_or_else(|_| dom.clone()),
+                ))
+        })
+        .collect();
+
+    missing.into_iter().collect()
+}
+
+/// Splits a `domain.key` path given on the command line into a config domain and key.
+/// The `com.apple.` prefix is optional, so `dock.tilesize` and `com.apple.dock.tilesize`
+/// both refer to the same setting, and other reverse-DNS domains are kept as they are.
+/// Pass the result through `effective()` as usual.
+pub fn split_setting_path(path: &str) -> Result<(String, String)> {
+    let path = path.trim();
+    let path = path.strip_prefix("com.apple.").unwrap_or(path);
+
+    match path.rsplit_once('.') {
+        Some((domain, key)) if !domain.is_empty() && !key.is_empty() => {
+            Ok((domain.to_string(), key.to_string()))
+        }
+        _ => bail!("Invalid setting path: {path} (expected DOMAIN.KEY)"),
+    }
+}
+
+/// Returns the effective domain-key pairs listed in `[status] ignore`.
+pub fn ignored_keys(config: &Config) -> HashSet<(String, String)> {
+    config
+        .status
+        .iter()
+        .flat_map(|status| status.ignore.iter().flatten())
+        .filter_map(|path| match split_setting_path(path) {
+            Ok((domain, key)) => Some(effective(&domain, &key)),
+            Err(e) => {
+                log_warn!("Ignoring bad [status] ignore entry: {e}");
+                None
+            }
+        })
+        .collect()
+}
 
-    (Preferences::read(domain_obj, eff_key)).ok()
+/// Read the current value of a defaults key, if any. Errors out if it can't be told, e.g.
+/// for a plist file only root can read.
+pub async fn read_current(eff_domain: &str, eff_key: &str) -> Result<Option<PrefValue>> {
+    files::read(domain_obj(eff_domain)?, eff_key)
 }
@@ -1,12 +1,21 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use anyhow::{Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 use defaults_rs::PrefValue;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use toml::Value;
+use toml::value::{Date, Datetime, Offset, Time};
 use toml_edit::Value as EditValue;
 
+/// Key of the `{ base64 = "..." }` wrapper used to spell plist Data blobs in TOML.
+pub const DATA_KEY: &str = "base64";
+
+/// Seconds between the Unix epoch and the plist reference date (2001-01-01T00:00:00Z).
+/// PrefValue::Date counts from the latter, like CFAbsoluteTime.
+const REFERENCE_DATE_OFFSET: f64 = 978_307_200.0;
+
 /// Serializable representation of a preference value.
 /// This mirrors the structure of defaults_rs::PrefValue but implements Serialize/Deserialize.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -17,9 +26,137 @@ pub enum SerializablePrefValue {
     Float(f64),
     Boolean(bool),
     Array(Vec<SerializablePrefValue>),
+    Data {
+        #[serde(rename = "$data")]
+        base64: String,
+    },
+    Date {
+        #[serde(rename = "$date")]
+        secs: f64,
+    },
     Dictionary(HashMap<String, SerializablePrefValue>),
 }
 
+/// Helper for: datetime_to_secs()
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of days_from_civil(): the (year, month, day) of a day since 1970-01-01.
+pub fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (
+        if m <= 2 {
+            yoe + era * 400 + 1
+        } else {
+            yoe + era * 400
+        },
+        m,
+        d,
+    )
+}
+
+/// Converts a TOML datetime into seconds since the plist reference date.
+/// Times without an offset are treated as UTC; a bare time (no date) is rejected.
+pub fn datetime_to_secs(dt: &Datetime) -> Result<f64> {
+    let Some(date) = dt.date else {
+        bail!("Datetime `{dt}` has no date; plist dates need one");
+    };
+    let time = dt.time.unwrap_or(Time {
+        hour: 0,
+        minute: 0,
+        second: 0,
+        nanosecond: 0,
+    });
+    let offset_secs = match dt.offset {
+        Some(Offset::Custom { minutes }) => i64::from(minutes) * 60,
+        _ => 0,
+    };
+
+    let days = days_from_civil(
+        i64::from(date.year),
+        i64::from(date.month),
+        i64::from(date.day),
+    );
+    let unix = days * 86_400
+        + i64::from(time.hour) * 3600
+        + i64::from(time.minute) * 60
+        + i64::from(time.second)
+        - offset_secs;
+
+    Ok(unix as f64 - REFERENCE_DATE_OFFSET + f64::from(time.nanosecond) / 1e9)
+}
+
+/// Converts seconds since the plist reference date into a UTC TOML datetime.
+pub fn secs_to_datetime(secs: f64) -> Datetime {
+    let unix = secs + REFERENCE_DATE_OFFSET;
+    let whole = unix.floor();
+    let nanosecond = (((unix - whole) * 1e9).round() as u32).min(999_999_999);
+    let whole = whole as i64;
+
+    let (year, month, day) = civil_from_days(whole.div_euclid(86_400));
+    let rem = whole.rem_euclid(86_400);
+
+    Datetime {
+        date: Some(Date {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }),
+        time: Some(Time {
+            hour: (rem / 3600) as u8,
+            minute: (rem % 3600 / 60) as u8,
+            second: (rem % 60) as u8,
+            nanosecond,
+        }),
+        offset: Some(Offset::Z),
+    }
+}
+
+/// Converts a TOML datetime into a unix timestamp, like datetime_to_secs().
+pub fn datetime_to_unix(dt: &Datetime) -> Result<i64> {
+    Ok((datetime_to_secs(dt)? + REFERENCE_DATE_OFFSET).floor() as i64)
+}
+
+/// Converts a unix timestamp into a UTC TOML datetime.
+pub fn unix_to_datetime(secs: i64) -> Datetime {
+    secs_to_datetime(secs as f64 - REFERENCE_DATE_OFFSET)
+}
+
+/// Returns the decoded bytes if the table is a `{ base64 = "..." }` Data wrapper.
+pub fn data_from_table(tbl: &toml::Table) -> Option<Result<Vec<u8>>> {
+    if tbl.len() != 1 {
+        return None;
+    }
+    let encoded = tbl.get(DATA_KEY)?.as_str()?;
+
+    Some(
+        BASE64
+            .decode(encoded.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid base64 data `{encoded}`: {e}")),
+    )
+}
+
+/// Wraps raw bytes into a `{ base64 = "..." }` TOML table.
+pub fn data_to_table(bytes: &[u8]) -> Value {
+    let mut tbl = toml::Table::new();
+    tbl.insert(DATA_KEY.to_string(), Value::String(BASE64.encode(bytes)));
+    Value::Table(tbl)
+}
+
 /// Turns a toml::Value into its defaults_rs::PrefValue counterpart.
 pub fn toml_to_prefvalue(val: &Value) -> anyhow::Result<PrefValue> {
     Ok(match val {
@@ -32,12 +169,15 @@ pub fn toml_to_prefvalue(val: &Value) -> anyhow::Result<PrefValue> {
                 .map(toml_to_prefvalue)
                 .collect::<Result<Vec<_>>>()?,
         ),
-        Value::Table(tbl) => PrefValue::Dictionary(
-            tbl.iter()
-                .map(|(k, v)| Ok((k.clone(), toml_to_prefvalue(v)?)))
-                .collect::<Result<HashMap<_, _>>>()?,
-        ),
-        _ => bail!("Unsupported TOML value for PrefValue"),
+        Value::Datetime(dt) => PrefValue::Date(datetime_to_secs(dt)?),
+        Value::Table(tbl) => match data_from_table(tbl) {
+            Some(bytes) => PrefValue::Data(bytes?),
+            None => PrefValue::Dictionary(
+                tbl.iter()
+                    .map(|(k, v)| Ok((k.clone(), toml_to_prefvalue(v)?)))
+                    .collect::<Result<HashMap<_, _>>>()?,
+            ),
+        },
     })
 }
 
@@ -56,7 +196,8 @@ pub fn prefvalue_to_toml(val: &PrefValue) -> Value {
                 .collect();
             Value::Table(map)
         }
-        _ => unreachable!(),
+        PrefValue::Data(bytes) => data_to_table(bytes),
+        PrefValue::Date(secs) => Value::Datetime(secs_to_datetime(*secs)),
     }
 }
 
@@ -86,27 +227,7 @@ pub fn normalize(value: &Value) -> String {
 
 /// Turns a toml_edit::Value into its defaults_rs::PrefValue counterpart.
 pub fn toml_edit_to_prefvalue(val: &EditValue) -> anyhow::Result<PrefValue> {
-    Ok(match val {
-        EditValue::String(s) => PrefValue::String(s.value().to_string()),
-        EditValue::Integer(i) => PrefValue::Integer(i.value().to_owned()),
-        EditValue::Float(f) => PrefValue::Float(f.value().to_owned()),
-        EditValue::Boolean(b) => PrefValue::Boolean(b.value().to_owned()),
-        EditValue::Array(arr) => {
-            let mut result = Vec::new();
-            for item in arr.iter() {
-                result.push(toml_edit_to_prefvalue(item)?);
-            }
-            PrefValue::Array(result)
-        }
-        EditValue::InlineTable(tbl) => {
-            let mut dict = HashMap::new();
-            for (k, v) in tbl.iter() {
-                dict.insert(k.to_string(), toml_edit_to_prefvalue(v)?);
-            }
-            PrefValue::Dictionary(dict)
-        }
-        _ => bail!("Unsupported toml_edit value type for PrefValue"),
-    })
+    toml_to_prefvalue(&toml_edit_to_toml(val)?)
 }
 
 /// Converts a toml_edit::Value to a toml::Value for compatibility.
@@ -130,7 +251,7 @@ pub fn toml_edit_to_toml(val: &EditValue) -> anyhow::Result<Value> {
             }
             Value::Table(map)
         }
-        _ => bail!("Unsupported toml_edit value type"),
+        EditValue::Datetime(dt) => Value::Datetime(*dt.value()),
     })
 }
 
@@ -149,7 +270,10 @@ pub fn prefvalue_to_serializable(val: &PrefValue) -> SerializablePrefValue {
                 .map(|(k, v)| (k.clone(), prefvalue_to_serializable(v)))
                 .collect(),
         ),
-        _ => unreachable!(),
+        PrefValue::Data(bytes) => SerializablePrefValue::Data {
+            base64: BASE64.encode(bytes),
+        },
+        PrefValue::Date(secs) => SerializablePrefValue::Date { secs: *secs },
     }
 }
 
@@ -168,5 +292,15 @@ pub fn serializable_to_prefvalue(val: &SerializablePrefValue) -> PrefValue {
                 .map(|(k, v)| (k.clone(), serializable_to_prefvalue(v)))
                 .collect(),
         ),
+        // snapshots are written by us, so a bad blob only means a corrupted file
+        SerializablePrefValue::Data { base64 } => {
+            PrefValue::Data(BASE64.decode(base64).unwrap_or_default())
+        }
+        SerializablePrefValue::Date { secs } => PrefValue::Date(*secs),
     }
 }
+
+/// Renders a PrefValue the way it would be written in the config, for display.
+pub fn display_prefvalue(val: &PrefValue) -> String {
+    normalize(&prefvalue_to_toml(val))
+}
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Plist files addressed by path in `[set]`, e.g.
+//! `[set."/Library/Preferences/SystemConfiguration/com.apple.smb.server.plist"]`.
+//!
+//! Several system settings only live in plists outside the defaults domain registry. Their
+//! keys are read and written in the file directly, through sudo if the current user can't
+//! read or write it.
+
+use anyhow::{Context, Result, anyhow, bail};
+use defaults_rs::{Domain, PrefValue, Preferences};
+use nix::unistd::Uid;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use toml::Table;
+
+use crate::domains::convert::{prefvalue_to_toml, toml_to_prefvalue};
+use crate::domains::import::plist_to_toml;
+use crate::domains::menubar::resolve_host;
+use crate::log_info;
+use crate::plist::core::{expand_path, render};
+use crate::util::exit::{ExitCode, coded};
+use crate::util::sudo::{is_refused, refused_error};
+
+/// Whether a `[set]` domain is a plist file path rather than a defaults domain.
+pub fn is_path_domain(domain: &str) -> bool {
+    domain.starts_with('/') || domain.starts_with("~/")
+}
+
+/// Turns an effective domain into its defaults-rs counterpart.
+/// The symbolic ByHost domain of [menubar] is resolved against this machine here.
+pub fn domain_obj(eff_domain: &str) -> Result<Domain> {
+    Ok(if eff_domain == "NSGlobalDomain" {
+        Domain::Global
+    } else if is_path_domain(eff_domain) {
+        Domain::Path(expand_path(&resolve_host(eff_domain)?))
+    } else {
+        Domain::User(eff_domain.to_string())
+    })
+}
+
+/// Whether a plist file is known not to exist. Files in directories the current user can't
+/// list may exist, like the ones in `/System/Volumes/Data/.Spotlight-V100`.
+pub fn is_missing(path: &Path) -> bool {
+    matches!(path.try_exists(), Ok(false))
+}
+
+/// Helper for: privileged_path()
+/// Whether the current user can write the plist file, or create it if it doesn't exist.
+fn is_writable(path: &Path) -> bool {
+    if !is_missing(path) {
+        return OpenOptions::new().write(true).open(path).is_ok();
+    }
+
+    path.parent()
+        .and_then(|dir| fs::metadata(dir).ok())
+        .is_some_and(|meta| meta.uid() == Uid::current().as_raw())
+}
+
+/// Helper for: write_batch(), delete_batch()
+/// Returns the file of a path domain which can only be written through sudo.
+fn privileged_path(domain: &Domain) -> Option<PathBuf> {
+    match domain {
+        Domain::Path(path) if !Uid::effective().is_root() && !is_writable(path) => {
+            Some(path.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Helper for: read(), sudo_edit()
+/// Reads a whole plist file through sudo. Returns None if it doesn't exist.
+/// Unless `interactive`, sudo never asks for a password, so that checks running in the
+/// background can't hang on a prompt.
+fn sudo_read(path: &Path, interactive: bool) -> Result<Option<PrefValue>> {
+    let mut cmd = Command::new("sudo");
+    if !interactive {
+        cmd.arg("-n");
+    }
+    let out = cmd
+        .arg("cat")
+        .arg(path)
+        .stdin(Stdio::inherit())
+        .output()
+        .context("Could not run sudo")?;
+
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if stderr.contains("No such file") {
+            return Ok(None);
+        }
+        if !interactive && stderr.contains("password is required") {
+            return Err(coded(
+                ExitCode::Sudo,
+                format!(
+                    "Reading {} needs root. Run `sudo -v` first, then try again.",
+                    path.display()
+                ),
+            ));
+        }
+        if is_refused(&stderr) {
+            return Err(refused_error());
+        }
+        bail!("Could not read {} through sudo", path.display())
+    }
+
+    let value = plist::Value::from_reader(Cursor::new(out.stdout))
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+    let value = plist_to_toml(&value).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+
+    Ok(Some(toml_to_prefvalue(&value)?))
+}
+
+/// Reads a key like `Preferences::read()`, but also from plist files only root can read.
+/// Returns None if the key isn't set. Files which can't be read are an error rather than
+/// looking unset, so that their values aren't mistaken for missing ones.
+pub fn read(domain: Domain, key: &str) -> Result<Option<PrefValue>> {
+    match domain {
+        Domain::Path(path)
+            if !Uid::effective().is_root() && !is_missing(&path) && File::open(&path).is_err() =>
+        {
+            match sudo_read(&path, false)? {
+                Some(PrefValue::Dictionary(dict)) => Ok(dict.get(key).cloned()),
+                Some(_) => bail!("{} does not hold a dictionary", path.display()),
+                None => Ok(None),
+            }
+        }
+        domain => Ok(Preferences::read(domain, key).ok()),
+    }
+}
+
+/// Helper for: write_batch(), delete_batch()
+/// Rewrites a plist file through sudo, setting (Some) or deleting (None) the given keys.
+fn sudo_edit(path: &Path, edits: Vec<(String, Option<PrefValue>)>) -> Result<()> {
+    let current = match Preferences::read_domain(Domain::Path(path.to_path_buf())) {
+        Ok(value) => Some(value),
+        Err(_) if is_missing(path) => None,
+        // never replace a file which couldn't be read
+        Err(_) => sudo_read(path, true)?,
+    };
+
+    let mut table: Table = match current {
+        Some(PrefValue::Dictionary(dict)) => dict
+            .iter()
+            .map(|(k, v)| (k.clone(), prefvalue_to_toml(v)))
+            .collect(),
+        Some(_) => bail!("{} does not hold a dictionary", path.display()),
+        None => Table::new(),
+    };
+
+    for (key, value) in edits {
+        match value {
+            Some(value) => table.insert(key, prefvalue_to_toml(&value)),
+            None => table.remove(&key),
+        };
+    }
+
+    log_info!("Writing {} through sudo", path.display());
+    let mut child = Command::new("sudo")
+        .arg("tee")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Could not run sudo")?;
+    child
+        .stdin
+        .take()
+        .context("Could not write to sudo")?
+        .write_all(render(&table)?.as_bytes())?;
+
+    if !child.wait()?.success() {
+        bail!("Could not write {} through sudo", path.display())
+    }
+
+    Ok(())
+}
+
+/// Helper for: write_batch(), delete_batch()
+/// Runs the edits of privileged files through sudo, making the system's preferences daemon
+/// pick up the new contents.
+fn sudo_edit_all(edits: BTreeMap<PathBuf, Vec<(String, Option<PrefValue>)>>) -> Result<()> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+
+    if !Command::new("sudo")
+        .arg("-v")
+        .status()
+        .context("Could not run sudo")?
+        .success()
+    {
+        return Err(refused_error());
+    }
+
+    for (path, edits) in edits {
+        sudo_edit(&path, edits)?;
+    }
+
+    let _ = Command::new("sudo").args(["killall", "cfprefsd"]).output();
+
+    Ok(())
+}
+
+/// Like `Preferences::write_batch()`, but also writes to plist files which need sudo.
+pub fn write_batch(batch: Vec<(Domain, String, PrefValue)>) -> Result<()> {
+    let mut rest = Vec::new();
+    let mut privileged: BTreeMap<PathBuf, Vec<_>> = BTreeMap::new();
+
+    for (domain, key, value) in batch {
+        match privileged_path(&domain) {
+            Some(path) => privileged.entry(path).or_default().push((key, Some(value))),
+            None => rest.push((domain, key, value)),
+        }
+    }
+
+    if !rest.is_empty() {
+        Preferences::write_batch(rest)?;
+    }
+
+    sudo_edit_all(privileged)
+}
+
+/// Like `Preferences::delete_batch()`, but also deletes from plist files which need sudo.
+pub fn delete_batch(batch: Vec<(Domain, String)>) -> Result<()> {
+    let mut rest = Vec::new();
+    let mut privileged: BTreeMap<PathBuf, Vec<_>> = BTreeMap::new();
+
+    for (domain, key) in batch {
+        match privileged_path(&domain) {
+            Some(path) => privileged.entry(path).or_default().push((key, None)),
+            None => rest.push((domain, key)),
+        }
+    }
+
+    if !rest.is_empty() {
+        Preferences::delete_batch(rest)?;
+    }
+
+    sudo_edit_all(privileged)
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use toml::{Table, Value};
+
+use crate::config::core::{Config, HotCorner};
+use crate::domains::shortcuts::modifier;
+
+/// Config domain of the Dock, which owns the hot corner keys.
+const DOCK: &str = "dock";
+
+/// Actions with their accepted names and the code the Dock stores for them.
+const ACTIONS: [(&[&str], i64); 12] = [
+    (&["none", "-"], 1),
+    (&["mission-control"], 2),
+    (&["app-windows", "application-windows"], 3),
+    (&["desktop", "show-desktop"], 4),
+    (&["start-screensaver", "start-screen-saver"], 5),
+    (&["disable-screensaver", "disable-screen-saver"], 6),
+    (&["dashboard"], 7),
+    (&["display-sleep", "put-display-to-sleep"], 10),
+    (&["launchpad"], 11),
+    (&["notification-center"], 12),
+    (&["lock-screen"], 13),
+    (&["quick-note"], 14),
+];
+
+/// Modifier symbols (as in shortcuts) with the flags the Dock stores for them.
+const MODIFIER_FLAGS: [(char, i64); 4] = [
+    ('$', 131072),  // shift
+    ('^', 262144),  // ctrl
+    ('~', 524288),  // opt
+    ('@', 1048576), // cmd
+];
+
+/// Looks up the Dock code of a hot corner action like `start-screensaver`.
+pub fn action_code(action: &str) -> Result<i64> {
+    let name = action.trim().to_lowercase().replace([' ', '_'], "-");
+
+    match ACTIONS
+        .iter()
+        .find(|(names, _)| names.contains(&name.as_str()))
+    {
+        Some((_, code)) => Ok(*code),
+        None => bail!("Unknown hot corner action `{action}`"),
+    }
+}
+
+/// Turns modifiers like `cmd+opt` into the flags the Dock expects. An empty string means none.
+pub fn modifier_flags(modifiers: &str) -> Result<i64> {
+    let mut flags = 0;
+
+    for token in modifiers.split('+').filter(|t| !t.trim().is_empty()) {
+        let Some(sym) = modifier(token) else {
+            bail!("Unknown hot corner modifier `{token}`");
+        };
+        if let Some((_, flag)) = MODIFIER_FLAGS.iter().find(|(s, _)| *s == sym) {
+            flags |= flag;
+        }
+    }
+
+    Ok(flags)
+}
+
+/// Builds the `wvous-*` Dock settings for [hot_corners], keyed by config domain like the
+/// output of collect().
+pub fn hot_corner_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+    let Some(corners) = &config.hot_corners else {
+        return Ok(out);
+    };
+
+    let mut settings = Table::new();
+    for (abbrev, corner) in corners.corners() {
+        let Some(corner) = corner else {
+            continue;
+        };
+        let (action, modifiers) = match corner {
+            HotCorner::Action(action) => (action, ""),
+            HotCorner::Detailed(detailed) => (
+                &detailed.action,
+                detailed.modifier.as_deref().unwrap_or_default(),
+            ),
+        };
+
+        settings.insert(
+            format!("wvous-{abbrev}-corner"),
+            Value::Integer(action_code(action)?),
+        );
+        settings.insert(
+            format!("wvous-{abbrev}-modifier"),
+            Value::Integer(modifier_flags(modifiers)?),
+        );
+    }
+
+    if !settings.is_empty() {
+        out.insert(DOCK.to_string(), settings);
+    }
+
+    Ok(out)
+}
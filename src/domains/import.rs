@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Converts existing preferences into `[set]` tables.
+//!
+//! Two sources are supported: the text printed by `defaults read [domain]`, and the plists
+//! written by `defaults export <domain> <file>`. Text dumps don't carry types (booleans show
+//! up as `1`/`0`), so their values are guessed; exported plists keep them exactly.
+
+use anyhow::{Context, Result, bail};
+use defaults_rs::PrefValue;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::sync::OnceLock;
+use toml::{Table, Value};
+
+use crate::domains::collector;
+use crate::domains::convert::{prefvalue_to_toml, string_to_toml_value};
+
+/// Stands in for data blobs which `defaults read` truncates, e.g. `{length = 256, bytes = 0x62706c69 ... }`.
+const TRUNCATED_DATA: &str = "<cutler: truncated data>";
+
+/// The result of an import: `[set]` tables by config domain, and what couldn't be imported.
+#[derive(Debug, Default, PartialEq)]
+pub struct Import {
+    pub set: BTreeMap<String, Table>,
+    /// Settings which were left out, along with the reason, e.g. `com.apple.dock | foo: ...`.
+    pub skipped: Vec<String>,
+}
+
+impl Import {
+    /// Renders the imported settings as config TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        let set: Table = self
+            .set
+            .iter()
+            .map(|(dom, tbl)| (dom.clone(), Value::Table(tbl.clone())))
+            .collect();
+
+        let mut root = Table::new();
+        root.insert("set".into(), Value::Table(set));
+
+        Ok(toml::to_string(&root)?)
+    }
+
+    /// Helper for: parse_defaults_dump(), parse_plist()
+    fn add_domain(&mut self, domain: &str, dict: &plist::Dictionary, guess_types: bool) {
+        let config_domain = config_domain(domain);
+
+        for (key, value) in dict {
+            match convert(value, guess_types) {
+                Ok(value) => {
+                    self.set
+                        .entry(config_domain.clone())
+                        .or_default()
+                        .insert(key.clone(), value);
+                }
+                Err(reason) => self.skipped.push(format!("{domain} | {key}: {reason}")),
+            }
+        }
+    }
+}
+
+/// Maps a defaults domain to its name in the config, e.g. `com.apple.dock` to `dock`.
+pub fn config_domain(domain: &str) -> String {
+    match domain {
+        "NSGlobalDomain" | "Apple Global Domain" | ".GlobalPreferences" => "NSGlobalDomain".into(),
+        _ => collector::config_domain(domain),
+    }
+}
+
+/// Converts a plist value into TOML exactly, without guessing types.
+pub fn plist_to_toml(value: &plist::Value) -> Result<Value, String> {
+    convert(value, false)
+}
+
+/// Helper for: Import::add_domain(), plist_to_toml()
+/// Converts a plist value into TOML, or explains why it can't be.
+fn convert(value: &plist::Value, guess_types: bool) -> Result<Value, String> {
+    let pref = match value {
+        plist::Value::String(s) if s == TRUNCATED_DATA => {
+            return Err(
+                "data is truncated in `defaults read` output; use `defaults export`".into(),
+            );
+        }
+        plist::Value::String(s) if guess_types => return Ok(string_to_toml_value(s)),
+        plist::Value::String(s) => PrefValue::String(s.clone()),
+        plist::Value::Boolean(b) => PrefValue::Boolean(*b),
+        plist::Value::Real(f) => PrefValue::Float(*f),
+        plist::Value::Integer(i) => match i.as_signed() {
+            Some(i) => PrefValue::Integer(i),
+            None => return Err(format!("integer {i} is out of range")),
+        },
+        plist::Value::Data(bytes) => PrefValue::Data(bytes.clone()),
+        // plist dates are written in UTC (`2024-01-01T10:00:00Z`), which TOML reads as is
+        plist::Value::Date(date) => {
+            return date
+                .to_xml_format()
+                .parse()
+                .map(Value::Datetime)
+                .map_err(|e| format!("invalid date: {e}"));
+        }
+        plist::Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|v| convert(v, guess_types))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(Value::Array(items));
+        }
+        plist::Value::Dictionary(dict) => {
+            let table = dict
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), convert(v, guess_types)?)))
+                .collect::<Result<Table, String>>()?;
+            return Ok(Value::Table(table));
+        }
+        plist::Value::Uid(_) => return Err("keyed-archive UIDs are unsupported".into()),
+        _ => return Err("unsupported value type".into()),
+    };
+
+    Ok(prefvalue_to_toml(&pref))
+}
+
+/// Parses the text printed by `defaults read`. Without a domain, the dump is expected to
+/// hold every domain (plain `defaults read`), keyed by name.
+pub fn parse_defaults_dump(text: &str, domain: Option<&str>) -> Result<Import> {
+    static TRUNCATED: OnceLock<Regex> = OnceLock::new();
+    let truncated =
+        TRUNCATED.get_or_init(|| Regex::new(r"\{\s*length = \d+, bytes = 0x[^}]*\}").unwrap());
+
+    // the truncated data notation isn't valid in old-style plists
+    let text = truncated.replace_all(text, format!("\"{TRUNCATED_DATA}\""));
+    let value = plist::Value::from_reader_ascii(Cursor::new(text.as_bytes()))
+        .context("Failed to parse `defaults read` output")?;
+    let Some(dict) = value.as_dictionary() else {
+        bail!("Expected a dictionary at the top of the dump")
+    };
+
+    let mut import = Import::default();
+    match domain {
+        Some(domain) => import.add_domain(domain, dict, true),
+        None => {
+            for (domain, value) in dict {
+                match value.as_dictionary() {
+                    Some(inner) => import.add_domain(domain, inner, true),
+                    None => import.skipped.push(format!(
+                        "{domain}: not a domain (pass --domain for single-domain dumps)"
+                    )),
+                }
+            }
+        }
+    }
+
+    Ok(import)
+}
+
+/// Parses a plist of any encoding, e.g. one written by `defaults export`.
+pub fn parse_plist(bytes: &[u8], domain: &str) -> Result<Import> {
+    let value = plist::Value::from_reader(Cursor::new(bytes)).context("Failed to parse plist")?;
+    let Some(dict) = value.as_dictionary() else {
+        bail!("Expected a dictionary at the top of the plist")
+    };
+
+    let mut import = Import::default();
+    import.add_domain(domain, dict, false);
+
+    Ok(import)
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Preferences enforced by configuration profiles, e.g. from an MDM.
+//!
+//! macOS keeps forced values in `/Library/Managed Preferences`, per user and for the whole
+//! machine, and they win over anything written to the user's domains. Writing such a key has no
+//! effect, so cutler skips it instead of reporting drift forever.
+
+use nix::unistd::{Uid, User};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use crate::domains::files::is_path_domain;
+
+/// Where macOS keeps the preferences forced by configuration profiles.
+pub const MANAGED_DIR: &str = "/Library/Managed Preferences";
+
+/// Forced keys per effective domain, read once per run.
+static FORCED: LazyLock<Mutex<HashMap<String, HashSet<String>>>> = LazyLock::new(Default::default);
+
+/// Returns the files which may hold forced values of a domain, the user's own ones first.
+/// Path domains can't be managed.
+pub fn managed_paths(dir: &Path, eff_domain: &str, user: Option<&str>) -> Vec<PathBuf> {
+    if is_path_domain(eff_domain) {
+        return Vec::new();
+    }
+
+    let file = if eff_domain == "NSGlobalDomain" {
+        ".GlobalPreferences.plist".to_string()
+    } else {
+        format!("{eff_domain}.plist")
+    };
+
+    let mut paths = Vec::new();
+    if let Some(user) = user {
+        paths.push(dir.join(user).join(&file));
+    }
+    paths.push(dir.join(file));
+    paths
+}
+
+/// Returns the top-level keys forced in the given managed plist files.
+pub fn forced_keys(paths: &[PathBuf]) -> HashSet<String> {
+    paths
+        .iter()
+        .filter_map(|path| plist::Value::from_file(path).ok())
+        .filter_map(|value| value.into_dictionary())
+        .flat_map(|dict| dict.into_iter().map(|(key, _)| key))
+        .collect()
+}
+
+/// Whether the value of a key is forced by a configuration profile.
+pub fn is_managed(eff_domain: &str, eff_key: &str) -> bool {
+    let mut forced = FORCED.lock().unwrap();
+
+    forced
+        .entry(eff_domain.to_string())
+        .or_insert_with(|| {
+            let user = User::from_uid(Uid::current()).ok().flatten();
+            let paths = managed_paths(
+                Path::new(MANAGED_DIR),
+                eff_domain,
+                user.as_ref().map(|u| u.name.as_str()),
+            );
+            forced_keys(&paths)
+        })
+        .contains(eff_key)
+}
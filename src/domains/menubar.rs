@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! [menubar]: which Control Center modules are shown in the menu bar.
+//!
+//! Each module is controlled by two keys: `NSStatusItem Visible <Module>` in
+//! `com.apple.controlcenter`, and `<Module>` in its ByHost variant, which is specific to the
+//! machine and lives in `~/Library/Preferences/ByHost`.
+
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeMap, HashMap};
+use std::process::Command;
+use std::sync::OnceLock;
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+
+/// Config domain of Control Center.
+const CONTROL_CENTER: &str = "controlcenter";
+
+/// ByHost value of a module shown in the menu bar.
+pub const SHOWN: i64 = 18;
+
+/// ByHost value of a module only shown in Control Center.
+pub const HIDDEN: i64 = 24;
+
+/// Menu bar items by their name in [menubar], along with their Control Center module.
+pub const MODULES: [(&str, &str); 14] = [
+    ("wifi", "WiFi"),
+    ("bluetooth", "Bluetooth"),
+    ("airdrop", "AirDrop"),
+    ("focus", "FocusModes"),
+    ("stage_manager", "StageManager"),
+    ("screen_mirroring", "ScreenMirroring"),
+    ("display", "Display"),
+    ("sound", "Sound"),
+    ("now_playing", "NowPlaying"),
+    ("battery", "Battery"),
+    ("keyboard_brightness", "KeyboardBrightness"),
+    ("accessibility_shortcuts", "AccessibilityShortcuts"),
+    ("hearing", "Hearing"),
+    ("user_switcher", "UserSwitcher"),
+];
+
+/// Stands in for the hardware UUID in the ByHost domain until it's read or written, so that
+/// collect() neither depends on `ioreg` nor leaks the UUID into lints and exports.
+pub const CURRENT_HOST: &str = "currentHost";
+
+/// Returns the config domain of Control Center's ByHost preferences for the given hardware UUID.
+pub fn byhost_domain(host_uuid: &str) -> String {
+    format!("~/Library/Preferences/ByHost/com.apple.controlcenter.{host_uuid}.plist")
+}
+
+/// Helper for: resolve_host()
+/// Reads the hardware UUID which ByHost preferences are named after, once per run.
+fn host_uuid() -> Result<&'static str> {
+    static UUID: OnceLock<Option<String>> = OnceLock::new();
+
+    UUID.get_or_init(|| {
+        let out = Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+            .ok()?;
+
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find(|line| line.contains("IOPlatformUUID"))
+            .and_then(|line| line.split('"').nth(3))
+            .map(String::from)
+    })
+    .as_deref()
+    .context("Could not determine the hardware UUID for ByHost preferences")
+}
+
+/// Turns the symbolic ByHost domain into the one of this machine, leaving other effective
+/// domains as they are.
+pub fn resolve_host(eff_domain: &str) -> Result<String> {
+    if eff_domain == byhost_domain(CURRENT_HOST) {
+        Ok(byhost_domain(host_uuid()?))
+    } else {
+        Ok(eff_domain.to_string())
+    }
+}
+
+/// Builds the settings for the given [menubar] items, keyed by config domain like the output
+/// of collect(). The ByHost keys go to the symbolic domain of [CURRENT_HOST].
+pub fn menubar_settings(items: &BTreeMap<String, bool>) -> Result<HashMap<String, Table>> {
+    let mut visible = Table::new();
+    let mut byhost = Table::new();
+
+    for (name, shown) in items {
+        let Some((_, module)) = MODULES.iter().find(|(n, _)| n == name) else {
+            let names: Vec<&str> = MODULES.iter().map(|(n, _)| *n).collect();
+            bail!(
+                "Unknown menu bar item `{name}`, expected one of: {}",
+                names.join(", ")
+            );
+        };
+
+        visible.insert(
+            format!("NSStatusItem Visible {module}"),
+            Value::Boolean(*shown),
+        );
+        byhost.insert(
+            module.to_string(),
+            Value::Integer(if *shown { SHOWN } else { HIDDEN }),
+        );
+    }
+
+    let mut out = HashMap::new();
+    if !items.is_empty() {
+        out.insert(CONTROL_CENTER.to_string(), visible);
+        out.insert(byhost_domain(CURRENT_HOST), byhost);
+    }
+
+    Ok(out)
+}
+
+/// Builds the settings for [menubar], keyed by config domain like the output of collect().
+pub fn menubar_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    match &config.menubar {
+        Some(items) if !items.is_empty() => menubar_settings(items),
+        _ => Ok(HashMap::new()),
+    }
+}
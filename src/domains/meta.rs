@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::fmt;
+use toml::{Table, Value};
+
+use crate::log_warn;
+use crate::util::macos::{MacosVersion, VersionRange};
+
+/// Keys allowed in the extended setting form, e.g. `{ value = 48, desc = "Dock icon size" }`.
+const META_KEYS: [&str; 6] = ["value", "desc", "url", "requires", "min_macos", "max_macos"];
+
+/// What a setting needs before it takes effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requires {
+    Logout,
+    Restart,
+}
+
+impl Requires {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "logout" => Some(Requires::Logout),
+            "restart" => Some(Requires::Restart),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Requires {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requires::Logout => write!(f, "logout"),
+            Requires::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+/// Documentation attached to a single setting.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SettingMeta {
+    pub desc: Option<String>,
+    pub url: Option<String>,
+    /// Set with `requires = "logout"` or `"restart"`.
+    pub requires: Option<Requires>,
+    /// Set with `min_macos` and `max_macos`; the setting is skipped on other versions.
+    pub macos: VersionRange,
+}
+
+/// Settings metadata keyed by the effective domain-key pair.
+pub type SettingsMeta = HashMap<(String, String), SettingMeta>;
+
+impl SettingMeta {
+    /// Reads the metadata of a value if it is written in the extended form. Fails if
+    /// `min_macos` or `max_macos` isn't a valid macOS version.
+    pub fn from_value(value: &Value) -> Result<Option<Self>> {
+        let Some(tbl) = value.as_table().filter(|tbl| is_extended(tbl)) else {
+            return Ok(None);
+        };
+
+        let field = |key: &str| tbl.get(key).and_then(Value::as_str).map(str::to_string);
+        let requires = field("requires").and_then(|name| {
+            let requires = Requires::parse(&name);
+            if requires.is_none() {
+                log_warn!("Unknown requires = \"{name}\", expected \"logout\" or \"restart\"");
+            }
+            requires
+        });
+
+        let version = |key: &str| -> Result<Option<MacosVersion>> {
+            let Some(value) = tbl.get(key) else {
+                return Ok(None);
+            };
+            value
+                .clone()
+                .try_into::<MacosVersion>()
+                .map(Some)
+                .map_err(|e| anyhow!("invalid {key} = {value}: {}", e.message()))
+        };
+
+        Ok(Some(SettingMeta {
+            desc: field("desc"),
+            url: field("url"),
+            requires,
+            macos: VersionRange {
+                min: version("min_macos")?,
+                max: version("max_macos")?,
+            },
+        }))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.desc.is_none()
+            && self.url.is_none()
+            && self.requires.is_none()
+            && self.macos.is_empty()
+    }
+}
+
+impl fmt::Display for SettingMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(desc) = &self.desc {
+            parts.push(desc.clone());
+        }
+        if let Some(url) = &self.url {
+            parts.push(format!("<{url}>"));
+        }
+        if let Some(requires) = self.requires {
+            parts.push(format!("(needs {requires})"));
+        }
+        if !self.macos.is_empty() {
+            parts.push(format!("(for {})", self.macos));
+        }
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Lists the settings meant for other macOS versions than the given one, sorted by domain and
+/// key, along with the versions they're meant for.
+pub fn not_applicable(
+    meta: &SettingsMeta,
+    version: Option<MacosVersion>,
+) -> Vec<(&(String, String), VersionRange)> {
+    let mut out: Vec<_> = meta
+        .iter()
+        .filter(|(_, m)| !m.macos.contains(version))
+        .map(|(id, m)| (id, m.macos))
+        .collect();
+
+    out.sort_by(|a, b| a.0.cmp(b.0));
+    out
+}
+
+/// Helper for: from_value(), unwrap_setting()
+/// A table is in the extended form if it has a `value` and nothing but metadata besides it.
+fn is_extended(tbl: &Table) -> bool {
+    tbl.contains_key("value") && tbl.keys().all(|k| META_KEYS.contains(&k.as_str()))
+}
+
+/// Replaces an extended-form value with its bare `value`, returning the metadata.
+pub fn unwrap_setting(value: &mut Value) -> Result<Option<SettingMeta>> {
+    let Some(meta) = SettingMeta::from_value(value)? else {
+        return Ok(None);
+    };
+
+    if let Value::Table(tbl) = value
+        && let Some(inner) = tbl.remove("value")
+    {
+        *value = inner;
+    }
+
+    Ok(Some(meta))
+}
+
+/// Looks up the metadata of the setting a dotted config path (as in `config::diff`) points
+/// into, e.g. `dock.tilesize.value` inside the [set] table.
+pub fn meta_at(table: &Table, path: &str) -> Option<SettingMeta> {
+    for (key, value) in table {
+        let rest = if path == key {
+            ""
+        } else if let Some(rest) = path
+            .strip_prefix(key.as_str())
+            .and_then(|r| r.strip_prefix('.'))
+        {
+            rest
+        } else {
+            continue;
+        };
+
+        if let Ok(Some(meta)) = SettingMeta::from_value(value) {
+            return Some(meta);
+        }
+        if let Value::Table(tbl) = value
+            && !rest.is_empty()
+            && let Some(meta) = meta_at(tbl, rest)
+        {
+            return Some(meta);
+        }
+    }
+
+    None
+}
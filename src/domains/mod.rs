@@ -1,5 +1,24 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+pub mod catalog;
 pub mod collector;
 pub mod convert;
-pub use collector::{collect, effective, read_current};
+pub mod files;
+pub mod hot_corners;
+pub mod import;
+pub mod managed;
+pub mod menubar;
+pub mod meta;
+pub mod plan;
+pub mod safety;
+pub mod screenshots;
+pub mod shortcuts;
+pub mod software_update;
+pub mod spotlight;
+pub mod tcc;
+pub mod text_replacements;
+pub mod trackpad;
+pub use collector::{
+    collect, collect_with_meta, config_domain, effective, ignored_keys, missing_domains,
+    overlay_settings, read_current, split_setting_path,
+};
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::Result;
+use defaults_rs::{Domain, PrefValue};
+use std::collections::HashMap;
+use std::mem;
+use tokio::task;
+use toml::Table;
+
+use crate::config::core::Config;
+use crate::domains::collector::{collect, effective, read_current};
+use crate::domains::convert::toml_to_prefvalue;
+use crate::domains::files::{self, domain_obj};
+use crate::domains::text_replacements::merge_system_replacements;
+use crate::util::limits;
+
+/// A single setting of the config, compared against the system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanEntry {
+    /// The effective defaults domain, e.g. `com.apple.dock`.
+    pub domain: String,
+    pub key: String,
+    /// The value currently on the system, if set at all.
+    pub current: Option<PrefValue>,
+    pub desired: PrefValue,
+}
+
+impl PlanEntry {
+    /// Whether writing this entry would change the system.
+    pub fn is_changed(&self) -> bool {
+        self.current.as_ref() != Some(&self.desired)
+    }
+
+    /// Whether writing this entry would replace a value of another type, e.g. a string with an integer.
+    pub fn is_type_change(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|current| mem::discriminant(current) != mem::discriminant(&self.desired))
+    }
+
+    /// The defaults-rs domain to write the entry to.
+    pub fn domain_obj(&self) -> Result<Domain> {
+        domain_obj(&self.domain)
+    }
+}
+
+/// The comparison of every setting in `[set]` against the system.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub entries: Vec<PlanEntry>,
+}
+
+impl Plan {
+    /// Compares already collected domains (see collect()) against the system.
+    pub async fn from_domains(domains: HashMap<String, Table>) -> Result<Self> {
+        let mut handles = Vec::new();
+
+        // read the current values concurrently, at most --jobs at once
+        for (dom, table) in domains {
+            for (key, value) in table {
+                let (domain, key) = effective(&dom, &key);
+                let desired = toml_to_prefvalue(&value)?;
+
+                handles.push(task::spawn(async move {
+                    let _slot = limits::acquire().await;
+                    let current = read_current(&domain, &key).await?;
+
+                    Ok::<_, anyhow::Error>(PlanEntry {
+                        domain,
+                        key,
+                        current,
+                        desired,
+                    })
+                }));
+            }
+        }
+
+        let mut entries = Vec::with_capacity(handles.len());
+        for handle in handles {
+            entries.push(handle.await??);
+        }
+
+        Ok(Plan { entries })
+    }
+
+    /// The entries which would change the system.
+    pub fn changes(&self) -> impl Iterator<Item = &PlanEntry> {
+        self.entries.iter().filter(|e| e.is_changed())
+    }
+
+    /// Writes every changed entry in a single batch.
+    ///
+    /// Unlike `cutler apply`, this records nothing in the snapshot and restarts no services,
+    /// except `cfprefsd` after writing root-owned plist files through sudo.
+    pub fn execute(&self) -> Result<()> {
+        let batch: Vec<(Domain, String, PrefValue)> = self
+            .changes()
+            .map(|e| Ok((e.domain_obj()?, e.key.clone(), e.desired.clone())))
+            .collect::<Result<_>>()?;
+
+        if !batch.is_empty() {
+            files::write_batch(batch)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the config's settings and compares them against the system, without writing.
+pub async fn apply_plan(config: &Config) -> Result<Plan> {
+    let mut domains = collect(config).await?;
+    merge_system_replacements(config, &mut domains).await?;
+
+    Plan::from_domains(domains).await
+}
@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::collections::HashSet;
+
+use crate::config::core::Config;
+use crate::domains::collector::{RAW_PREFIX, is_qualified};
+use crate::domains::files::is_path_domain;
+use crate::domains::plan::{Plan, PlanEntry};
+
+/// Domains and settings which can break login, locking or security when changed by accident.
+/// Entries are either whole domains or `domain.key` paths.
+pub const BUILTIN_PROTECTED: &[&str] = &[
+    "com.apple.loginwindow",
+    "com.apple.screensaver.askForPassword",
+    "com.apple.screensaver.askForPasswordDelay",
+    "com.apple.security",
+    "com.apple.alf",
+    "com.apple.SoftwareUpdate.CriticalUpdateInstall",
+    "com.apple.SoftwareUpdate.ConfigDataInstall",
+];
+
+/// Helper for: protected_patterns()
+/// Prefixes an entry with `com.apple.` the same way config domains are, unless it's fully
+/// qualified already, a plist file path, or opted out with `@`.
+fn normalize(entry: &str) -> String {
+    let entry = entry.trim();
+
+    if let Some(raw) = entry.strip_prefix(RAW_PREFIX) {
+        raw.to_string()
+    } else if entry.starts_with("NSGlobalDomain") || is_qualified(entry) || is_path_domain(entry) {
+        entry.to_string()
+    } else {
+        format!("com.apple.{entry}")
+    }
+}
+
+/// Returns the built-in protected entries along with the ones in `[safety] protected`.
+pub fn protected_patterns(config: &Config) -> Vec<String> {
+    let extra = config
+        .safety
+        .iter()
+        .flat_map(|safety| safety.protected.iter().flatten());
+
+    BUILTIN_PROTECTED
+        .iter()
+        .copied()
+        .chain(extra.map(String::as_str))
+        .map(normalize)
+        .collect()
+}
+
+/// Checks if an effective domain-key pair is covered by one of the protected entries.
+pub fn is_protected(patterns: &[String], eff_dom: &str, eff_key: &str) -> bool {
+    let path = format!("{eff_dom}.{eff_key}");
+
+    patterns.iter().any(|p| p == eff_dom || *p == path)
+}
+
+/// Returns the changes of a plan which count toward `[safety] confirm_over`, leaving out the
+/// ignored keys, since macOS keeps changing those by itself.
+pub fn counted_changes<'a>(
+    plan: &'a Plan,
+    ignored: &HashSet<(String, String)>,
+) -> Vec<&'a PlanEntry> {
+    plan.changes()
+        .filter(|e| !ignored.contains(&(e.domain.clone(), e.key.clone())))
+        .collect()
+}
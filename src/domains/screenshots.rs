@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use tokio::fs;
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+use crate::plist::core::expand_path;
+use crate::{log_dry, log_info};
+
+/// Config domain of the screenshot tool.
+const SCREENCAPTURE: &str = "screencapture";
+
+/// Image formats screencapture can write.
+pub const FORMATS: [&str; 7] = ["png", "jpg", "heic", "pdf", "tiff", "gif", "bmp"];
+
+/// Helper for: screenshot_domains()
+/// Validates a format, accepting `jpeg` and any casing.
+fn format(name: &str) -> Result<String> {
+    let name = match name.trim().to_lowercase().as_str() {
+        "jpeg" => "jpg".to_string(),
+        other => other.to_string(),
+    };
+
+    if !FORMATS.contains(&name.as_str()) {
+        bail!(
+            "Unknown screenshot format `{name}`, expected one of: {}",
+            FORMATS.join(", ")
+        );
+    }
+
+    Ok(name)
+}
+
+/// Builds the `com.apple.screencapture` settings for [screenshots], keyed by config domain like
+/// the output of collect().
+pub fn screenshot_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+    let Some(screenshots) = &config.screenshots else {
+        return Ok(out);
+    };
+
+    let mut settings = Table::new();
+    if let Some(location) = &screenshots.location {
+        // screencapture doesn't expand `~` by itself
+        let path = expand_path(location);
+        settings.insert(
+            "location".into(),
+            Value::String(path.to_string_lossy().into_owned()),
+        );
+    }
+    if let Some(name) = &screenshots.format {
+        settings.insert("type".into(), Value::String(format(name)?));
+    }
+    if let Some(shadow) = screenshots.shadow {
+        settings.insert("disable-shadow".into(), Value::Boolean(!shadow));
+    }
+    if let Some(thumbnail) = screenshots.thumbnail {
+        settings.insert("show-thumbnail".into(), Value::Boolean(thumbnail));
+    }
+
+    if !settings.is_empty() {
+        out.insert(SCREENCAPTURE.to_string(), settings);
+    }
+
+    Ok(out)
+}
+
+/// Creates the screenshot location if it doesn't exist yet, since screencapture silently falls
+/// back to the Desktop otherwise.
+pub async fn ensure_location(config: &Config, dry_run: bool) -> Result<()> {
+    let Some(location) = config
+        .screenshots
+        .as_ref()
+        .and_then(|s| s.location.as_ref())
+    else {
+        return Ok(());
+    };
+
+    let path = expand_path(location);
+    if fs::try_exists(&path).await.unwrap_or_default() {
+        return Ok(());
+    }
+
+    if dry_run {
+        log_dry!("Would create screenshot location {}", path.display());
+    } else {
+        fs::create_dir_all(&path)
+            .await
+            .with_context(|| format!("Could not create screenshot location {}", path.display()))?;
+        log_info!("Created screenshot location {}", path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+use crate::domains::collector::config_domain;
+
+/// Preference key holding an app's custom menu shortcuts.
+pub const KEY_EQUIVALENTS: &str = "NSUserKeyEquivalents";
+
+/// Modifiers in the order they are encoded, with their accepted spellings.
+const MODIFIERS: [(char, &[&str]); 4] = [
+    ('@', &["cmd", "command", "⌘"]),
+    ('~', &["opt", "option", "alt", "⌥"]),
+    ('^', &["ctrl", "control", "⌃"]),
+    ('$', &["shift", "⇧"]),
+];
+
+/// Returns the encoded symbol of a modifier spelling like `cmd` or `⌥`, if it is one.
+pub fn modifier(token: &str) -> Option<char> {
+    let token = token.trim().to_lowercase();
+
+    MODIFIERS
+        .iter()
+        .find(|(sym, names)| token == sym.to_string() || names.contains(&token.as_str()))
+        .map(|(sym, _)| *sym)
+}
+
+/// Helper for: encode_shortcut()
+/// Turns a named key into the character macOS expects.
+fn encode_key(key: &str) -> Result<String> {
+    let lower = key.to_lowercase();
+
+    let named = match lower.as_str() {
+        "space" => Some(' '),
+        "tab" => Some('\t'),
+        "return" | "enter" => Some('\r'),
+        "esc" | "escape" => Some('\u{1b}'),
+        "delete" | "backspace" => Some('\u{8}'),
+        "up" | "↑" => Some('\u{F700}'),
+        "down" | "↓" => Some('\u{F701}'),
+        "left" | "←" => Some('\u{F702}'),
+        "right" | "→" => Some('\u{F703}'),
+        _ => None,
+    };
+    if let Some(c) = named {
+        return Ok(c.to_string());
+    }
+
+    // function keys start at U+F704
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok())
+        && (1..=35).contains(&n)
+    {
+        return Ok(char::from_u32(0xF703 + n).unwrap_or_default().to_string());
+    }
+
+    if key.chars().count() != 1 {
+        bail!("Unknown key `{key}` in shortcut");
+    }
+    Ok(lower)
+}
+
+/// Encodes a shortcut like `cmd+shift+k` or `⌘⇧K` into the NSUserKeyEquivalents form (`@$k`).
+/// Already-encoded shortcuts are accepted as well.
+pub fn encode_shortcut(shortcut: &str) -> Result<String> {
+    let mut mods = Vec::new();
+    let mut rest = shortcut.trim();
+
+    loop {
+        // spelled-out modifier followed by `+`
+        if let Some((token, after)) = rest.split_once('+')
+            && !after.is_empty()
+            && let Some(sym) = modifier(token)
+        {
+            mods.push(sym);
+            rest = after.trim_start();
+            continue;
+        }
+
+        // single symbol, as long as something follows it
+        let mut chars = rest.chars();
+        if let Some(first) = chars.next()
+            && !chars.as_str().is_empty()
+            && let Some(sym) = modifier(&first.to_string())
+        {
+            mods.push(sym);
+            rest = chars.as_str();
+            continue;
+        }
+
+        break;
+    }
+
+    if rest.is_empty() {
+        bail!("Shortcut `{shortcut}` has no key");
+    }
+
+    let mut out: String = MODIFIERS
+        .iter()
+        .map(|(sym, _)| *sym)
+        .filter(|sym| mods.contains(sym))
+        .collect();
+    out.push_str(&encode_key(rest)?);
+
+    Ok(out)
+}
+
+/// Builds the NSUserKeyEquivalents dictionaries for every app in [app_shortcuts],
+/// keyed by config domain like the output of collect().
+pub fn shortcut_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+
+    for (app, shortcuts) in config.app_shortcuts.iter().flatten() {
+        let mut equivalents = Table::new();
+        for (title, shortcut) in shortcuts {
+            equivalents.insert(title.clone(), Value::String(encode_shortcut(shortcut)?));
+        }
+
+        let mut settings = Table::new();
+        settings.insert(KEY_EQUIVALENTS.to_string(), Value::Table(equivalents));
+        out.insert(config_domain(app), settings);
+    }
+
+    Ok(out)
+}
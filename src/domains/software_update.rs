@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+
+/// The system-wide update policy, written through sudo.
+pub const SOFTWARE_UPDATE: &str = "/Library/Preferences/com.apple.SoftwareUpdate.plist";
+
+/// Builds the `com.apple.SoftwareUpdate` settings for [softwareupdate], keyed by config domain
+/// like the output of collect().
+pub fn software_update_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+    let Some(update) = &config.softwareupdate else {
+        return Ok(out);
+    };
+
+    let flags = [
+        ("AutomaticCheckEnabled", update.automatic_check),
+        ("AutomaticDownload", update.automatic_download),
+        (
+            "AutomaticallyInstallMacOSUpdates",
+            update.install_macos_updates,
+        ),
+        ("CriticalUpdateInstall", update.critical_updates),
+        ("ConfigDataInstall", update.config_data),
+    ];
+
+    let mut settings = Table::new();
+    for (key, flag) in flags {
+        if let Some(flag) = flag {
+            settings.insert(key.into(), Value::Boolean(flag));
+        }
+    }
+    if let Some(days) = update.schedule_days {
+        if days == 0 {
+            bail!("[softwareupdate] schedule_days must be at least 1");
+        }
+        settings.insert("ScheduleFrequency".into(), Value::Integer(days.into()));
+    }
+
+    if !settings.is_empty() {
+        out.insert(SOFTWARE_UPDATE.to_string(), settings);
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! [spotlight]: folders excluded from indexing and hidden result categories.
+//!
+//! Both compile into regular settings, so they're snapshotted, checked by `cutler status` and
+//! reverted by `cutler unapply` like anything in [set]. Exclusions live in a plist only root
+//! can access and are written through sudo.
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::process::Command;
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+use crate::plist::core::expand_path;
+use crate::{log_dry, log_info};
+
+/// Config domain of Spotlight's own preferences.
+const SPOTLIGHT: &str = "Spotlight";
+
+/// Spotlight's settings for the data volume, holding the privacy exclusions.
+pub const VOLUME_CONFIG: &str = "/System/Volumes/Data/.Spotlight-V100/VolumeConfiguration.plist";
+
+/// Result categories, in the order Spotlight's settings list them.
+pub const CATEGORIES: [&str; 22] = [
+    "APPLICATIONS",
+    "MENU_EXPRESSION",
+    "BOOKMARKS",
+    "CONTACT",
+    "MENU_CONVERSION",
+    "MENU_DEFINITION",
+    "SOURCE",
+    "DOCUMENTS",
+    "EVENT_TODO",
+    "DIRECTORIES",
+    "FONTS",
+    "IMAGES",
+    "MESSAGES",
+    "MOVIES",
+    "MUSIC",
+    "MENU_OTHER",
+    "PDF",
+    "PRESENTATIONS",
+    "MENU_SPOTLIGHT_SUGGESTIONS",
+    "SPREADSHEETS",
+    "SYSTEM_PREFS",
+    "TIPS",
+];
+
+/// Helper for: spotlight_domains()
+/// Validates a category, accepting any casing.
+fn category(name: &str) -> Result<String> {
+    let name = name.trim().to_uppercase();
+
+    if !CATEGORIES.contains(&name.as_str()) {
+        bail!(
+            "Unknown Spotlight category `{name}`, expected one of: {}",
+            CATEGORIES.join(", ")
+        );
+    }
+
+    Ok(name)
+}
+
+/// Builds the settings for [spotlight], keyed by config domain like the output of collect().
+pub fn spotlight_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+    let Some(spotlight) = &config.spotlight else {
+        return Ok(out);
+    };
+
+    if let Some(exclude) = &spotlight.exclude {
+        // mds doesn't expand `~` by itself
+        let paths = exclude
+            .iter()
+            .map(|path| Value::String(expand_path(path).to_string_lossy().into_owned()))
+            .collect();
+
+        let mut settings = Table::new();
+        settings.insert("Exclusions".into(), Value::Array(paths));
+        out.insert(VOLUME_CONFIG.to_string(), settings);
+    }
+
+    if let Some(disabled) = &spotlight.disabled_categories {
+        let disabled = disabled
+            .iter()
+            .map(|name| category(name))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Spotlight expects every category, each with its own flag
+        let items = CATEGORIES
+            .iter()
+            .map(|name| {
+                let mut item = Table::new();
+                item.insert(
+                    "enabled".into(),
+                    Value::Boolean(!disabled.iter().any(|d| d == name)),
+                );
+                item.insert("name".into(), Value::String(name.to_string()));
+                Value::Table(item)
+            })
+            .collect();
+
+        let mut settings = Table::new();
+        settings.insert("orderedItems".into(), Value::Array(items));
+        out.insert(SPOTLIGHT.to_string(), settings);
+    }
+
+    Ok(out)
+}
+
+/// Restarts the Spotlight server if any of the given effective domains belongs to it, so that
+/// changed exclusions and categories take effect.
+pub fn reload<'a>(domains: impl IntoIterator<Item = &'a str>, dry_run: bool) {
+    let spotlight = format!("com.apple.{SPOTLIGHT}");
+    if !domains
+        .into_iter()
+        .any(|dom| dom == spotlight || dom == VOLUME_CONFIG)
+    {
+        return;
+    }
+
+    if dry_run {
+        log_dry!("Would restart the Spotlight server");
+        return;
+    }
+
+    // launchd starts it again right away
+    match Command::new("sudo").args(["killall", "mds"]).output() {
+        Ok(out) if out.status.success() => log_info!("Spotlight server restarted"),
+        _ => log_info!("Could not restart the Spotlight server; changes apply after a restart."),
+    }
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Settings protected by TCC (Transparency, Consent and Control).
+//!
+//! The preferences of sandboxed apps like Safari or Mail live in their containers, which only
+//! apps with Full Disk Access may touch. Without it, writes fail with a generic error, so cutler
+//! leaves such settings out and tells how to grant the permission instead.
+
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::domains::files::is_path_domain;
+
+/// Domains kept in app containers.
+pub const CONTAINER_DOMAINS: [&str; 4] = [
+    "com.apple.Safari",
+    "com.apple.mail",
+    "com.apple.iChat",
+    "com.apple.Notes",
+];
+
+/// Directories, relative to the home folder, which need Full Disk Access.
+pub const PROTECTED_DIRS: [&str; 7] = [
+    "Library/Containers",
+    "Library/Group Containers",
+    "Library/Mail",
+    "Library/Messages",
+    "Library/Safari",
+    "Library/Cookies",
+    "Library/Application Support/com.apple.TCC",
+];
+
+/// Deep link to the Full Disk Access pane of System Settings.
+pub const SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_AllFiles";
+
+/// Whether writing to an effective domain, or a plist file, needs Full Disk Access.
+pub fn needs_full_disk_access(target: &str, home: &Path) -> bool {
+    if !is_path_domain(target) {
+        return CONTAINER_DOMAINS.contains(&target);
+    }
+
+    let path = match target.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(target),
+    };
+    path.starts_with("/Library/Application Support/com.apple.TCC")
+        || PROTECTED_DIRS
+            .iter()
+            .any(|dir| path.starts_with(home.join(dir)))
+}
+
+/// Whether cutler runs with Full Disk Access, probed by opening the user's TCC database. Only
+/// looked up once per run; if it can't be told, access is assumed.
+pub fn has_full_disk_access() -> bool {
+    static ACCESS: OnceLock<bool> = OnceLock::new();
+
+    *ACCESS.get_or_init(|| {
+        let Some(home) = dirs::home_dir() else {
+            return true;
+        };
+        let db = home.join("Library/Application Support/com.apple.TCC/TCC.db");
+
+        !matches!(File::open(db), Err(e) if e.kind() == ErrorKind::PermissionDenied)
+    })
+}
+
+/// Whether a domain or plist file can't be written for lack of Full Disk Access.
+pub fn is_blocked(target: &str) -> bool {
+    let home = dirs::home_dir().unwrap_or_default();
+    needs_full_disk_access(target, &home) && !has_full_disk_access()
+}
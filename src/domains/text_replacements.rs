@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! System-wide text replacements from [text_replacements].
+//!
+//! macOS keeps all replacements in a single array, so the configured ones are merged into
+//! whatever is on the system instead of replacing it. Entries added by hand survive.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+use crate::domains::collector::read_current;
+use crate::domains::convert::prefvalue_to_toml;
+
+/// Keys holding the replacements: the current one, and the one older releases read.
+pub const REPLACEMENT_KEYS: [&str; 2] =
+    ["NSUserDictionaryReplacementItems", "NSUserReplacementItems"];
+
+/// Merges the wanted replacements (shortcut → phrase) into the current array of
+/// `{ on, replace, with }` entries. Existing shortcuts are updated in place, new ones appended.
+pub fn merge_replacements(current: Option<&Value>, wanted: &BTreeMap<String, String>) -> Value {
+    let mut items: Vec<Value> = current
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    for (replace, with) in wanted {
+        let existing = items
+            .iter_mut()
+            .filter_map(Value::as_table_mut)
+            .find(|item| item.get("replace").and_then(Value::as_str) == Some(replace.as_str()));
+
+        match existing {
+            // leave matching entries alone, so that they don't show up as drift
+            Some(item) if item.get("with").and_then(Value::as_str) == Some(with.as_str()) => {}
+            Some(item) => {
+                item.insert("with".into(), Value::String(with.clone()));
+                item.insert("on".into(), Value::Integer(1));
+            }
+            None => {
+                let mut item = Table::new();
+                item.insert("on".into(), Value::Integer(1));
+                item.insert("replace".into(), Value::String(replace.clone()));
+                item.insert("with".into(), Value::String(with.clone()));
+                items.push(Value::Table(item));
+            }
+        }
+    }
+
+    Value::Array(items)
+}
+
+/// Helper for: replacement_domains(), merge_system_replacements()
+fn wanted(config: &Config) -> Option<&BTreeMap<String, String>> {
+    config.text_replacements.as_ref().filter(|w| !w.is_empty())
+}
+
+/// Builds the replacement arrays for [text_replacements] from the config alone, keyed by config
+/// domain like the output of collect(). See merge_system_replacements() for the ones to write.
+pub fn replacement_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+    let Some(wanted) = wanted(config) else {
+        return Ok(out);
+    };
+
+    let mut settings = Table::new();
+    for key in REPLACEMENT_KEYS {
+        settings.insert(key.to_string(), merge_replacements(None, wanted));
+    }
+    out.insert("NSGlobalDomain".to_string(), settings);
+
+    Ok(out)
+}
+
+/// Merges the replacement arrays of collected domains into the ones on the system, so that
+/// applying them keeps entries added by hand. Only meant for applying and checking status;
+/// exports and lints stay independent of the current machine.
+pub async fn merge_system_replacements(
+    config: &Config,
+    domains: &mut HashMap<String, Table>,
+) -> Result<()> {
+    let Some(wanted) = wanted(config) else {
+        return Ok(());
+    };
+    let Some(settings) = domains.get_mut("NSGlobalDomain") else {
+        return Ok(());
+    };
+
+    for key in REPLACEMENT_KEYS {
+        let current = read_current("NSGlobalDomain", key)
+            .await?
+            .map(|v| prefvalue_to_toml(&v));
+        settings.insert(
+            key.to_string(),
+            merge_replacements(current.as_ref(), wanted),
+        );
+    }
+
+    Ok(())
+}
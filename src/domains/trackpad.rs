@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! [trackpad]: high-level trackpad settings.
+//!
+//! macOS keeps trackpad settings for built-in and Bluetooth trackpads in separate domains, and
+//! some also need a global key, so each setting here writes every key it takes to stay consistent.
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+
+/// Config domain of built-in trackpads.
+pub const BUILTIN: &str = "AppleMultitouchTrackpad";
+
+/// Config domain of Bluetooth trackpads, like the Magic Trackpad.
+pub const BLUETOOTH: &str = "driver.AppleBluetoothMultitouch.trackpad";
+
+const GLOBAL: &str = "NSGlobalDomain";
+
+/// Haptic strengths with the click threshold the trackpad stores for them.
+const STRENGTHS: [(&str, i64); 3] = [("light", 0), ("medium", 1), ("firm", 2)];
+
+/// Helper for: trackpad_domains()
+/// Sets a key on both built-in and Bluetooth trackpads.
+fn both(out: &mut HashMap<String, Table>, key: &str, value: Value) {
+    for domain in [BUILTIN, BLUETOOTH] {
+        out.entry(domain.to_string())
+            .or_default()
+            .insert(key.into(), value.clone());
+    }
+}
+
+/// Helper for: trackpad_domains()
+fn global(out: &mut HashMap<String, Table>, key: &str, value: Value) {
+    out.entry(GLOBAL.to_string())
+        .or_default()
+        .insert(key.into(), value);
+}
+
+/// Builds the settings for [trackpad], keyed by config domain like the output of collect().
+pub fn trackpad_domains(config: &Config) -> Result<HashMap<String, Table>> {
+    let mut out = HashMap::new();
+    let Some(trackpad) = &config.trackpad else {
+        return Ok(out);
+    };
+
+    if let Some(tap) = trackpad.tap_to_click {
+        both(&mut out, "Clicking", Value::Boolean(tap));
+        // also enables tapping on the login screen
+        global(
+            &mut out,
+            "com.apple.mouse.tapBehavior",
+            Value::Integer(tap.into()),
+        );
+    }
+    if let Some(drag) = trackpad.three_finger_drag {
+        both(&mut out, "TrackpadThreeFingerDrag", Value::Boolean(drag));
+        // three-finger swipes would otherwise compete with dragging
+        if drag {
+            both(
+                &mut out,
+                "TrackpadThreeFingerHorizSwipeGesture",
+                Value::Integer(0),
+            );
+            both(
+                &mut out,
+                "TrackpadThreeFingerVertSwipeGesture",
+                Value::Integer(0),
+            );
+        }
+    }
+    if let Some(natural) = trackpad.natural_scrolling {
+        global(
+            &mut out,
+            "com.apple.swipescrolldirection",
+            Value::Boolean(natural),
+        );
+    }
+    if let Some(swipe) = trackpad.swipe_between_pages {
+        global(
+            &mut out,
+            "AppleEnableSwipeNavigateWithScrolls",
+            Value::Boolean(swipe),
+        );
+    }
+    if let Some(force) = trackpad.force_click {
+        both(&mut out, "ForceSuppressed", Value::Boolean(!force));
+        global(
+            &mut out,
+            "com.apple.trackpad.forceClick",
+            Value::Boolean(force),
+        );
+    }
+    if let Some(strength) = &trackpad.haptic_strength {
+        let name = strength.trim().to_lowercase();
+        let Some((_, threshold)) = STRENGTHS.iter().find(|(n, _)| *n == name) else {
+            bail!("Unknown haptic strength `{strength}`, expected one of: light, medium, firm");
+        };
+        both(&mut out, "FirstClickThreshold", Value::Integer(*threshold));
+        both(&mut out, "SecondClickThreshold", Value::Integer(*threshold));
+    }
+    if let Some(silent) = trackpad.silent_clicking {
+        both(
+            &mut out,
+            "ActuationStrength",
+            Value::Integer((!silent).into()),
+        );
+    }
+    if let Some(speed) = trackpad.tracking_speed {
+        if !(0.0..=3.0).contains(&speed) {
+            bail!("[trackpad] tracking_speed must be from 0 to 3, got {speed}");
+        }
+        global(&mut out, "com.apple.trackpad.scaling", Value::Float(speed));
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Ephemeral applies, which revert on their own after a while.
+//!
+//! `cutler apply --ephemeral 2h` stores the values it overwrote next to the config and either
+//! waits in the foreground or leaves a LaunchAgent behind which runs
+//! `cutler unapply --ephemeral --wait` to restore them once the time is up.
+
+use anyhow::{Context, Result, bail};
+use nix::unistd::Uid;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::{fs, process::Command, time::sleep};
+use toml::{Table, Value};
+
+use crate::config::core::now;
+use crate::config::path::CONFIG_ENV;
+use crate::domains::convert::serializable_to_prefvalue;
+use crate::domains::files::{self, domain_obj};
+use crate::plist::core::render;
+use crate::snapshot::core::SettingState;
+use crate::snapshot::crypt::{seal, unseal};
+use crate::{log_info, log_warn};
+
+/// Label of the LaunchAgent which reverts a pending ephemeral apply.
+pub const AGENT_LABEL: &str = "io.github.machlit.cutler.ephemeral";
+
+/// How often the wall clock is checked while waiting; timers pause while the Mac sleeps.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EphemeralState {
+    /// Unix timestamp after which the settings are reverted.
+    pub revert_at: u64,
+    /// The written settings, along with their values from right before the apply.
+    pub settings: Vec<SettingState>,
+}
+
+/// Returns the path to the pending ephemeral apply, next to the config.
+pub fn get_ephemeral_path(config_path: &Path) -> Result<PathBuf> {
+    let parent = config_path
+        .parent()
+        .context("Could not determine config parent directory")?;
+
+    Ok(parent.join("ephemeral.json"))
+}
+
+impl EphemeralState {
+    pub fn new(duration: Duration, settings: Vec<SettingState>) -> Self {
+        EphemeralState {
+            revert_at: now() as u64 + duration.as_secs(),
+            settings,
+        }
+    }
+
+    /// Loads the pending ephemeral apply, if any. Encrypted state is decrypted like snapshots.
+    pub async fn load(config_path: &Path) -> Option<Self> {
+        let path = get_ephemeral_path(config_path).ok()?;
+        let content = fs::read_to_string(path).await.ok()?;
+
+        match unseal(content).await {
+            Ok(content) => serde_json::from_str(&content).ok(),
+            Err(e) => {
+                log_warn!("Could not read the pending ephemeral apply: {e}");
+                None
+            }
+        }
+    }
+
+    /// Saves the state, encrypted if `[snapshot] encrypt` is on, since it holds original values.
+    pub async fn save(&self, config_path: &Path) -> Result<()> {
+        let path = get_ephemeral_path(config_path)?;
+        fs::write(path, seal(serde_json::to_string_pretty(self)?).await?).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(config_path: &Path) -> Result<()> {
+        let path = get_ephemeral_path(config_path)?;
+
+        if fs::try_exists(&path).await? {
+            fs::remove_file(path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Time left until the settings are due to be reverted.
+    pub fn remaining(&self) -> Duration {
+        Duration::from_secs(self.revert_at.saturating_sub(now() as u64))
+    }
+
+    /// Waits until the settings are due to be reverted.
+    pub async fn wait(&self) {
+        while !self.remaining().is_zero() {
+            sleep(self.remaining().min(POLL_INTERVAL)).await;
+        }
+    }
+
+    /// Writes back the values from before the apply, deleting keys which didn't exist.
+    pub fn revert(&self) -> Result<()> {
+        let mut restores = Vec::new();
+        let mut deletes = Vec::new();
+
+        for s in &self.settings {
+            let domain = domain_obj(&s.domain)?;
+
+            match &s.original_value {
+                Some(value) => {
+                    log_info!("Restoring: {domain} | {}", s.key);
+                    restores.push((domain, s.key.clone(), serializable_to_prefvalue(value)));
+                }
+                None => {
+                    log_info!("Deleting: {domain} | {}", s.key);
+                    deletes.push((domain, s.key.clone()));
+                }
+            }
+        }
+
+        if !restores.is_empty() {
+            files::write_batch(restores)?;
+        }
+        if !deletes.is_empty() {
+            files::delete_batch(deletes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path of the LaunchAgent plist.
+pub fn get_agent_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+
+    Ok(home
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{AGENT_LABEL}.plist")))
+}
+
+/// Renders the LaunchAgent which waits for and reverts the pending ephemeral apply.
+/// It starts at load (and login), so the revert survives reboots.
+pub fn agent_plist(exe: &Path, config_path: &Path) -> Result<String> {
+    let exe = exe.to_string_lossy();
+    let args = [
+        exe.as_ref(),
+        "--no-sync",
+        "unapply",
+        "--ephemeral",
+        "--wait",
+    ];
+
+    let mut agent = Table::new();
+    agent.insert("Label".into(), AGENT_LABEL.into());
+    agent.insert(
+        "ProgramArguments".into(),
+        Value::Array(args.iter().map(|a| Value::from(*a)).collect()),
+    );
+    agent.insert("RunAtLoad".into(), true.into());
+
+    // the agent must find the same config as this process
+    let mut vars: Table = ["HOME", "XDG_CONFIG_HOME"]
+        .into_iter()
+        .filter_map(|var| Some((var.to_string(), env::var(var).ok()?.into())))
+        .collect();
+    vars.insert(
+        CONFIG_ENV.into(),
+        config_path.to_string_lossy().into_owned().into(),
+    );
+    agent.insert("EnvironmentVariables".into(), Value::Table(vars));
+
+    render(&agent)
+}
+
+/// Helper for: schedule_agent(), remove_agent()
+fn gui_domain() -> String {
+    format!("gui/{}", Uid::current())
+}
+
+/// Installs and loads the LaunchAgent reverting the pending ephemeral apply.
+pub async fn schedule_agent(config_path: &Path) -> Result<()> {
+    let path = get_agent_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(&path, agent_plist(&env::current_exe()?, config_path)?).await?;
+
+    // a leftover agent from an earlier run would make bootstrap fail
+    let _ = Command::new("launchctl")
+        .args(["bootout", &format!("{}/{AGENT_LABEL}", gui_domain())])
+        .output()
+        .await;
+
+    let status = Command::new("launchctl")
+        .args(["bootstrap", &gui_domain()])
+        .arg(&path)
+        .status()
+        .await?;
+    if !status.success() {
+        bail!("Could not load LaunchAgent at {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Removes the LaunchAgent. When run from the agent itself, launchd stops this process,
+/// so call it last.
+pub async fn remove_agent() {
+    if let Ok(path) = get_agent_path()
+        && fs::try_exists(&path).await.unwrap_or_default()
+        && let Err(e) = fs::remove_file(&path).await
+    {
+        log_warn!("Could not remove LaunchAgent at {}: {e}", path.display());
+    }
+
+    let _ = Command::new("launchctl")
+        .args(["bootout", &format!("{}/{AGENT_LABEL}", gui_domain())])
+        .output()
+        .await;
+}
@@ -1,28 +1,93 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::cli::atomic::should_dry_run;
-use crate::config::core::Config;
-use crate::util::logging::{BOLD, RESET};
-use crate::{log_dry, log_exec, log_warn};
+use crate::brew::core::{formula_providing, install_formula};
+use crate::cli::atomic::{should_dry_run, should_fix_missing, should_not_interleave};
+use crate::config::core::{Config, Shell};
+use crate::config::vars::{
+    exported_vars, is_exported, lookup_var, set_exported, substitute_known, substitute_with,
+};
+use crate::exec::logs::create_log;
+use crate::snapshot::core::{BrewChanges, CommandUndo};
+use crate::util::exit::{ExitCode, exit_code_for};
+use crate::util::io::confirm;
+use crate::util::limits;
+use crate::util::logging::{BOLD, RESET, redact};
+use crate::util::macos::{VersionRange, current_version};
+use crate::util::net::ensure_online;
+use crate::util::sudo::{SudoKeepAlive, keep_alive};
+use crate::util::summary;
+use crate::{log_dry, log_exec, log_info, log_warn};
 use anyhow::{Result, anyhow, bail};
-use regex::Regex;
-use std::collections::HashMap;
-use std::env;
+use std::io::{self, Write};
+use std::process::Stdio;
+use std::sync::Mutex;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
 use tokio::process::Command;
 use tokio::task;
 
+/// The tag which the deprecated `flag = true` field maps to.
+pub const FLAGGED_TAG: &str = "flagged";
+
+/// How the output of a command reaches the terminal.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Output {
+    /// Passed through as is, for commands running on their own.
+    Inherit,
+    /// Streamed line by line, each line prefixed with the name of the command.
+    Prefixed,
+    /// Prefixed like `Prefixed`, but held back until the command ends (`--no-interleave`).
+    Buffered,
+}
+
 /// Represents an external command job.
+#[derive(Default)]
 pub struct ExecJob {
     pub name: String,
     pub run: String,
+    pub shell: Shell,
     pub sudo: bool,
     pub ensure_first: bool,
-    pub flag: bool,
+    pub tags: Vec<String>,
     pub required: Vec<String>,
+    pub export_var: Option<String>,
+    pub interactive: bool,
+    pub undo: Option<String>,
+    /// The macOS versions the command runs on.
+    pub macos: VersionRange,
+    /// Only runs the command if this script succeeds.
+    pub only_if: Option<String>,
+    /// Skips the command if this script succeeds.
+    pub skip_if: Option<String>,
+    /// Whether to write the output to a log file (`[exec] logs`).
+    pub log: bool,
+}
+
+impl ExecJob {
+    /// Returns true if the job carries any of the given tags.
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        self.tags.iter().any(|t| tags.contains(t))
+    }
+
+    /// Returns true if the job is flagged (tagged with `flagged`).
+    pub fn is_flagged(&self) -> bool {
+        self.tags.iter().any(|t| t == FLAGGED_TAG)
+    }
 }
 
 /// Extract a single command by name from the user config.
 pub fn extract_cmd(config: &Config, name: &str) -> Result<ExecJob> {
+    let lookup = |name: &str| lookup_var(config, name);
+    extract_cmd_with(config, name, |text| substitute_with(text, lookup))
+}
+
+/// Like `extract_cmd()`, but renders the scripts and conditions through `render`, e.g. to mask
+/// variables instead of resolving them.
+pub fn extract_cmd_with(
+    config: &Config,
+    name: &str,
+    render: impl Fn(&str) -> String,
+) -> Result<ExecJob> {
     let command_map = config
         .command
         .as_ref()
@@ -34,31 +99,72 @@ pub fn extract_cmd(config: &Config, name: &str) -> Result<ExecJob> {
 
     // substitute to get possible variables
     // ultimately turning it into the final command to run
-    let run = substitute(&command.run, config.vars.as_ref().cloned());
+    let shell = command.shell.unwrap_or_default();
+    let run = render(&command.run.render(shell));
+    let undo = command
+        .undo
+        .as_ref()
+        .map(|undo| render(&undo.render(shell)));
+    let only_if = command.only_if.as_deref().map(&render);
+    let skip_if = command.skip_if.as_deref().map(&render);
 
     // extra fields
     let sudo = command.sudo.unwrap_or_default();
-    let flag = command.flag.unwrap_or_default();
     let ensure_first = command.ensure_first.unwrap_or_default();
     let required = command.required.clone().unwrap_or_default();
+    let mut tags = command.tags.clone().unwrap_or_default();
+
+    // `flag = true` is kept as an alias for the `flagged` tag
+    if command.flag.unwrap_or_default() {
+        log_warn!("{name}: `flag` is deprecated, use `tags = [\"{FLAGGED_TAG}\"]` instead.");
+
+        if !tags.iter().any(|t| t == FLAGGED_TAG) {
+            tags.push(FLAGGED_TAG.to_string());
+        }
+    }
 
     Ok(ExecJob {
         name: name.to_string(),
         run,
+        shell,
         sudo,
         ensure_first,
-        flag,
+        tags,
         required,
+        export_var: command.export_var.clone(),
+        interactive: command.interactive.unwrap_or_default(),
+        undo,
+        macos: VersionRange {
+            min: command.min_macos,
+            max: command.max_macos,
+        },
+        only_if,
+        skip_if,
+        log: config
+            .exec
+            .as_ref()
+            .and_then(|exec| exec.logs)
+            .unwrap_or_default(),
     })
 }
 
 // Pull all external commands written in user config into state objects.
+// They're sorted by name, so that sequential commands always run in the same order.
 pub fn extract_all_cmds(config: &Config) -> Vec<ExecJob> {
+    let lookup = |name: &str| lookup_var(config, name);
+    extract_all_cmds_with(config, |text| substitute_with(text, lookup))
+}
+
+/// Like `extract_all_cmds()`, but renders the scripts through `render` (see extract_cmd_with()).
+pub fn extract_all_cmds_with(config: &Config, render: impl Fn(&str) -> String) -> Vec<ExecJob> {
     let mut jobs = Vec::new();
 
     if let Some(command_map) = config.command.as_ref() {
-        for (name, _) in command_map.iter() {
-            if let Ok(job) = extract_cmd(config, name) {
+        let mut names: Vec<&String> = command_map.keys().collect();
+        names.sort();
+
+        for name in names {
+            if let Ok(job) = extract_cmd_with(config, name, &render) {
                 jobs.push(job);
             }
         }
@@ -67,73 +173,329 @@ pub fn extract_all_cmds(config: &Config) -> Vec<ExecJob> {
     jobs
 }
 
-/// Perform variable substitution (env + `[external.variables]`) in a text.
-/// Uses regex to find $var and ${var} patterns.
-fn substitute(text: &str, vars: Option<HashMap<String, String>>) -> String {
-    // regex to match $var or ${var}
-    // $VAR_NAME or ${VAR_NAME}
-    // note: $ followed by [A-Za-z_][A-Za-z0-9_]* or ${...}
-    let re = Regex::new(r"\$([A-Za-z_][A-Za-z0-9_]*)|\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
-
-    // clusure to resolve variable name
-    let resolve_var = |var_name: &str| {
-        vars.as_ref()
-            .and_then(|map| map.get(var_name))
-            .cloned()
-            .or_else(|| env::var(var_name).ok())
-            .unwrap_or_else(|| format!("${{{}}}", var_name))
+/// Undo scripts of the commands which ran successfully in this process, in order.
+static UNDO_LOG: Mutex<Vec<CommandUndo>> = Mutex::new(Vec::new());
+
+/// Takes the undo scripts of the commands which ran successfully so far.
+pub fn take_undo_log() -> Vec<CommandUndo> {
+    UNDO_LOG
+        .lock()
+        .map(|mut log| std::mem::take(&mut *log))
+        .unwrap_or_default()
+}
+
+static INSTALL_LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Takes the formulae installed so far for `required` binaries, to be recorded in the snapshot
+/// like the ones installed by `apply --brew`.
+pub fn take_installed_formulae() -> BrewChanges {
+    BrewChanges {
+        formulae: INSTALL_LOG
+            .lock()
+            .map(|mut log| std::mem::take(&mut *log))
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Helper for: execute_command()
+/// Remembers the undo script of a command which ran successfully.
+fn record_undo(job: &ExecJob) {
+    let Some(undo) = &job.undo else {
+        return;
     };
 
-    // replace all matches
-    let result = re.replace_all(text, |caps: &regex::Captures| {
-        // caps[1] is for $var, caps[2] is for ${var}
-        let var_name = caps
-            .get(1)
-            .or_else(|| caps.get(2))
-            .map(|m| m.as_str())
-            .unwrap_or("");
-        resolve_var(var_name)
-    });
+    if let Ok(mut log) = UNDO_LOG.lock() {
+        log.push(CommandUndo {
+            name: job.name.clone(),
+            run: substitute_known(undo, &exported_vars()),
+            shell: job.shell,
+            sudo: job.sudo,
+        });
+    }
+}
+
+/// Helper for: run_job()
+/// Runs a guard script silently, returning whether it succeeded.
+async fn guard_passes(shell: Shell, script: &str) -> bool {
+    let script = substitute_known(script, &exported_vars());
+
+    Command::new(shell.bin())
+        .args(["-c", &script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+/// Helper for: run_one(), run_all(), run_group(), execute_exports()
+/// Executes a command unless its `only_if` or `skip_if` guard says otherwise. The guards are
+/// checked right before running, so they see what earlier commands did.
+/// Returns whether the command ran.
+async fn run_job(job: ExecJob, dry_run: bool, output: Output) -> Result<bool> {
+    if let Some(cond) = &job.only_if
+        && !guard_passes(job.shell, cond).await
+    {
+        log_info!("Skipping command {}, `only_if` failed.", job.name);
+        return Ok(false);
+    }
+    if let Some(cond) = &job.skip_if
+        && guard_passes(job.shell, cond).await
+    {
+        log_info!("Skipping command {}, `skip_if` succeeded.", job.name);
+        return Ok(false);
+    }
+
+    execute_command(job, dry_run, output).await?;
+    Ok(true)
+}
+
+/// Helper for: execute_command()
+/// Prints the output of a command line by line, prefixed with its name unless the output is
+/// passed through. With `Buffered`, the lines are held back and printed together once the
+/// command closes its output. Every line is also written to the log file, if any.
+/// Secrets are masked in both.
+async fn forward_output(
+    name: &str,
+    stdout: impl AsyncRead + Unpin,
+    stderr: impl AsyncRead + Unpin,
+    output: Output,
+    mut log: Option<&mut File>,
+) {
+    let (mut out, mut err) = (BufReader::new(stdout), BufReader::new(stderr));
+    // raw bytes, since a single line of invalid UTF-8 mustn't stop the pipes from being drained
+    let (mut out_buf, mut err_buf) = (Vec::new(), Vec::new());
+    let (mut out_done, mut err_done) = (false, false);
+    let mut held = Vec::new();
+
+    while !(out_done && err_done) {
+        let (line, is_err) = tokio::select! {
+            read = out.read_until(b'\n', &mut out_buf), if !out_done => match read {
+                Ok(0) if out_buf.is_empty() => {
+                    out_done = true;
+                    continue;
+                }
+                Ok(_) => (take_line(&mut out_buf), false),
+                Err(_) => {
+                    out_done = true;
+                    continue;
+                }
+            },
+            read = err.read_until(b'\n', &mut err_buf), if !err_done => match read {
+                Ok(0) if err_buf.is_empty() => {
+                    err_done = true;
+                    continue;
+                }
+                Ok(_) => (take_line(&mut err_buf), true),
+                Err(_) => {
+                    err_done = true;
+                    continue;
+                }
+            },
+        };
+        let line = redact(&line);
+
+        if let Some(file) = log.as_mut()
+            && file
+                .write_all(format!("{line}\n").as_bytes())
+                .await
+                .is_err()
+        {
+            log_warn!("Could not write the log of command {name}.");
+            log = None;
+        }
+
+        let line = match output {
+            Output::Inherit => line,
+            _ => format!("[{name}] {line}"),
+        };
+        if output == Output::Buffered {
+            held.push((line, is_err));
+        } else if is_err {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+    }
 
-    result.into_owned()
+    // print held lines in one go, so that they don't mix with other commands
+    let (mut stdout, mut stderr) = (io::stdout().lock(), io::stderr().lock());
+    for (line, is_err) in held {
+        let _ = if is_err {
+            writeln!(stderr, "{line}")
+        } else {
+            writeln!(stdout, "{line}")
+        };
+    }
 }
 
-/// Helper for: run_one(), run_all()
-/// Execute a single command with the given template and sudo flag.
-async fn execute_command(job: ExecJob, dry_run: bool) -> Result<()> {
+/// Helper for: forward_output()
+/// Takes a line read from a command out of the buffer, without its line ending.
+pub fn take_line(buf: &mut Vec<u8>) -> String {
+    let line = String::from_utf8_lossy(buf)
+        .trim_end_matches(['\n', '\r'])
+        .to_string();
+    buf.clear();
+    line
+}
+
+/// Helper for: run_job(), run_undo()
+/// Execute a single command with the given template, shell and sudo flag.
+async fn execute_command(job: ExecJob, dry_run: bool, output: Output) -> Result<()> {
+    // resolve variables exported by previously run commands
+    let run = substitute_known(&job.run, &exported_vars());
+
     // build the actual runner
+    let shell = job.shell.bin();
     let (bin, args) = if job.sudo {
-        ("sudo", vec!["sh", "-c", &job.run])
+        ("sudo", vec![shell, "-c", &run])
     } else {
-        ("sh", vec!["-c", &job.run])
+        (shell, vec!["-c", &run])
     };
 
     if dry_run {
-        log_dry!("Would execute: {bin} {}", job.run);
+        log_dry!("Would execute: {bin} {run}");
+        if let Some(var) = &job.export_var {
+            log_dry!("Would export output of {} as ${var}", job.name);
+        }
         return Ok(());
     }
 
     log_exec!("{BOLD}{}{RESET}", job.name);
 
-    let mut child = Command::new(bin).args(&args).spawn()?;
-    let status = child.wait().await?;
+    let mut command = Command::new(bin);
+    command.args(&args);
+
+    // only interactive commands get to read from the terminal
+    if job.interactive {
+        command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+    } else {
+        command.stdin(Stdio::null());
+    }
+
+    if let Some(var) = &job.export_var {
+        let output = command
+            .stdout(Stdio::piped())
+            .spawn()?
+            .wait_with_output()
+            .await?;
+
+        if !output.status.success() {
+            bail!(format!("Command {} failed to execute.", job.name))
+        }
+
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        log_info!("Exported ${var} = {value}");
+        set_exported(var, value);
+        record_undo(&job);
+
+        return Ok(());
+    }
+
+    // interactive commands need the terminal for themselves, so they can't be logged
+    let mut log = None;
+    if job.log && !job.interactive {
+        match create_log(&job.name).await {
+            Ok(created) => log = Some(created),
+            Err(e) => log_warn!("{e:#}; running command {} without a log.", job.name),
+        }
+    }
+
+    let status = if job.interactive || (output == Output::Inherit && log.is_none()) {
+        command.spawn()?.wait().await?
+    } else {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        if let (Some(stdout), Some(stderr)) = (stdout, stderr) {
+            let file = log.as_mut().map(|(_, file)| file);
+            forward_output(&job.name, stdout, stderr, output, file).await;
+        }
+        child.wait().await?
+    };
 
     if !status.success() {
-        bail!(format!("Command {} failed to execute.", job.name))
+        match &log {
+            Some((path, _)) => bail!(
+                "Command {} failed to execute, see {}",
+                job.name,
+                path.display()
+            ),
+            None => bail!(format!("Command {} failed to execute.", job.name)),
+        }
     }
 
+    record_undo(&job);
     Ok(())
 }
 
-/// Helper for: run_all(), run_one()
+/// Helper for: run_all(), run_one(), run_group(), execute_exports()
 /// Checks if the binaries designated in `required` are found in $PATH and whether to skip command execution.
-fn all_bins_present(required: &[String]) -> bool {
+/// Missing binaries which a formula in [brew] provides are installed first, after asking or with
+/// `--fix-missing`.
+async fn all_bins_present(config: &Config, job: &ExecJob) -> bool {
+    let formulae = config
+        .brew
+        .as_ref()
+        .and_then(|brew| brew.formulae.clone())
+        .unwrap_or_default();
     let mut present = true;
 
-    if !required.is_empty() {
-        for bin in required {
-            if which::which(bin).is_err() {
-                log_warn!("{bin} not found in $PATH.");
+    for bin in &job.required {
+        if which::which(bin).is_ok() {
+            continue;
+        }
+
+        let name = &job.name;
+        let Some(formula) = formula_providing(&formulae, bin).await else {
+            log_warn!("{bin} not found in $PATH, skipping command {name}.");
+            present = false;
+            continue;
+        };
+
+        if should_dry_run() {
+            log_dry!("Would install {formula} from [brew] for {bin}, needed by command {name}");
+            continue;
+        }
+
+        let wanted = should_fix_missing()
+            || confirm(&format!(
+                "{bin} is needed by command {name}. Install {formula} from [brew] now?"
+            ));
+        if !wanted {
+            log_warn!("{bin} not found in $PATH, skipping command {name}.");
+            present = false;
+            continue;
+        }
+
+        let installed = match ensure_online(&format!("install {formula}")) {
+            Ok(()) => install_formula(&formula).await,
+            Err(e) => Err(e),
+        };
+        if installed.is_ok()
+            && let Ok(mut log) = INSTALL_LOG.lock()
+        {
+            log.push(formula.clone());
+        }
+
+        match installed {
+            Ok(()) if which::which(bin).is_ok() => {}
+            Ok(()) => {
+                log_warn!(
+                    "{formula} was installed, but {bin} is still not in $PATH, skipping command {name}."
+                );
+                present = false;
+            }
+            Err(e) => {
+                log_warn!("{e}, skipping command {name}.");
+                summary::add_failure(format!("install {formula}"));
                 present = false;
             }
         }
@@ -142,73 +504,221 @@ fn all_bins_present(required: &[String]) -> bool {
     present
 }
 
+/// Helper for: run_all(), run_exports(), run_group()
+/// Checks if the command is meant for the running macOS version, telling why it's skipped if not.
+fn for_this_os(job: &ExecJob) -> bool {
+    let applies = job.macos.contains(current_version());
+    if !applies {
+        log_info!("Skipping command {}, only for {}", job.name, job.macos);
+    }
+
+    applies
+}
+
 /// Execution mode enum.
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum ExecMode {
     Regular,
     All,
     Flagged,
+    /// Only run commands carrying at least one of these tags.
+    Tagged(Vec<String>),
+}
+
+impl ExecMode {
+    /// Decides whether a job should run under this mode.
+    pub fn selects(&self, job: &ExecJob) -> bool {
+        match self {
+            ExecMode::Regular => !job.is_flagged(),
+            ExecMode::All => true,
+            ExecMode::Flagged => job.is_flagged(),
+            ExecMode::Tagged(tags) => job.has_any_tag(tags),
+        }
+    }
+}
+
+/// Helper for: run_all(), run_exports()
+/// Validates sudo once up front and keeps it alive if any selected command needs it.
+async fn sudo_session(
+    config: &Config,
+    mode: &ExecMode,
+    exports_only: bool,
+) -> Result<Option<SudoKeepAlive>> {
+    let jobs = extract_all_cmds(config);
+    let selected = jobs
+        .iter()
+        .filter(|job| mode.selects(job) && (!exports_only || job.export_var.is_some()));
+
+    sudo_session_for(selected).await
+}
+
+/// Helper for: sudo_session(), run_group()
+/// Validates sudo once up front and keeps it alive if any of the given commands needs it.
+/// Fails if the sudo prompt is refused.
+async fn sudo_session_for<'a>(
+    jobs: impl IntoIterator<Item = &'a ExecJob>,
+) -> Result<Option<SudoKeepAlive>> {
+    if should_dry_run() || !jobs.into_iter().any(|job| job.sudo) {
+        return Ok(None);
+    }
+
+    match keep_alive().await {
+        Ok(session) => Ok(Some(session)),
+        Err(e) if exit_code_for(&e) == ExitCode::Sudo => Err(e),
+        Err(e) => {
+            log_warn!("{e} Commands using sudo may prompt again.");
+            Ok(None)
+        }
+    }
+}
+
+/// Helper for: run_all(), run_exports()
+/// Sequentially runs the commands with `export_var` which haven't been exported yet in this run.
+/// Returns the amount of successes and failures.
+async fn execute_exports(config: &Config, mode: &ExecMode) -> (i32, i32) {
+    let dry_run = should_dry_run();
+
+    let mut successes = 0;
+    let mut failures = 0;
+
+    for job in extract_all_cmds(config) {
+        let Some(var) = &job.export_var else {
+            continue;
+        };
+
+        if is_exported(var)
+            || !mode.selects(&job)
+            || !for_this_os(&job)
+            || !all_bins_present(config, &job).await
+        {
+            continue;
+        }
+
+        let name = job.name.clone();
+
+        match run_job(job, dry_run, Output::Inherit).await {
+            Ok(ran) => successes += ran as i32,
+            Err(e) => {
+                log_warn!("{e}");
+                summary::add_failure(format!("command {name}"));
+                failures += 1;
+            }
+        }
+    }
+
+    (successes, failures)
+}
+
+/// Run only the commands which export variables (`export_var`), sequentially.
+/// Used before applying preferences so that `[set]` values can refer to them.
+/// Returns the amount of successfully executed commands.
+pub async fn run_exports(config: &Config, mode: &ExecMode) -> Result<i32> {
+    let _sudo = sudo_session(config, mode, true).await?;
+    let (successes, failures) = execute_exports(config, mode).await;
+
+    if failures > 0 {
+        log_warn!("{failures} variable-exporting commands failed",);
+    }
+
+    summary::add_commands(successes as usize);
+    Ok(successes)
 }
 
-/// Run all extracted external commands via `sh -c` (or `sudo sh -c`) in parallel.
+/// Run all extracted external commands via `<shell> -c` (or `sudo <shell> -c`) in parallel,
+/// at most `--jobs` at once.
 /// Returns the amount of successfully executed commmands.
 pub async fn run_all(config: Config, mode: ExecMode) -> Result<i32> {
+    let _sudo = sudo_session(&config, &mode, false).await?;
+
+    // commands exporting variables always go first so that later ones can use them
+    let (mut successes, mut failures) = execute_exports(&config, &mode).await;
+
     let cmds = extract_all_cmds(&config);
 
-    // separate ensure_first commands from regular commands
+    // separate ensure_first and interactive commands from regular commands
     let mut ensure_first_cmds = Vec::new();
+    let mut interactive_cmds = Vec::new();
     let mut regular_cmds = Vec::new();
 
     for job in cmds {
-        if !all_bins_present(&job.required)
-            || (mode == ExecMode::Regular && job.flag)
-            || (mode == ExecMode::Flagged && !job.flag)
+        if job.export_var.is_some()
+            || !mode.selects(&job)
+            || !for_this_os(&job)
+            || !all_bins_present(&config, &job).await
         {
             continue;
         } else if job.ensure_first {
             ensure_first_cmds.push(job);
+        } else if job.interactive {
+            interactive_cmds.push(job);
         } else {
             regular_cmds.push(job);
         }
     }
 
     let dry_run = should_dry_run();
-
-    let mut failures = 0;
-    let mut successes = 0;
-
-    // run all ensure_first commands sequentially first
-    for job in ensure_first_cmds {
-        if (execute_command(job, dry_run).await).is_err() {
-            failures += 1;
-        } else {
-            successes += 1;
+    let mut skipped = 0;
+
+    // run all ensure_first commands sequentially first,
+    // then interactive ones since they need the terminal for themselves
+    for job in ensure_first_cmds.into_iter().chain(interactive_cmds) {
+        let name = job.name.clone();
+
+        match run_job(job, dry_run, Output::Inherit).await {
+            Ok(true) => successes += 1,
+            Ok(false) => skipped += 1,
+            Err(e) => {
+                log_warn!("{e}");
+                summary::add_failure(format!("command {name}"));
+                failures += 1;
+            }
         }
     }
 
-    // then run all regular commands concurrently
+    // then run all regular commands concurrently, telling their output apart by name
+    let output = if should_not_interleave() {
+        Output::Buffered
+    } else {
+        Output::Prefixed
+    };
     let mut handles = Vec::new();
     for job in regular_cmds {
-        handles.push(task::spawn(
-            async move { execute_command(job, dry_run).await },
-        ));
+        let name = job.name.clone();
+        handles.push(task::spawn(async move {
+            let _slot = limits::acquire().await;
+            (name, run_job(job, dry_run, output).await)
+        }));
     }
 
     for handle in handles {
-        if handle.await?.is_err() {
-            failures += 1;
-        } else {
-            successes += 1;
+        let (name, result) = handle.await?;
+
+        match result {
+            Ok(true) => successes += 1,
+            Ok(false) => skipped += 1,
+            Err(e) => {
+                log_warn!("{e}");
+                summary::add_failure(format!("command {name}"));
+                failures += 1;
+            }
         }
     }
 
     // inspect count
     if failures > 0 {
         log_warn!("{failures} external commands failed",);
-    } else if successes == 0 {
-        log_warn!("No regular external commands found. Maybe you meant flagged or all?",);
+    } else if successes == 0 && skipped == 0 {
+        if let ExecMode::Tagged(tags) = &mode {
+            log_warn!(
+                "No external commands found with tag(s): {}",
+                tags.join(", ")
+            );
+        } else {
+            log_warn!("No regular external commands found. Maybe you meant flagged or all?",);
+        }
     }
 
+    summary::add_commands(successes as usize);
     Ok(successes)
 }
 
@@ -216,10 +726,94 @@ pub async fn run_all(config: Config, mode: ExecMode) -> Result<i32> {
 pub async fn run_one(config: Config, name: &str) -> Result<()> {
     let state = extract_cmd(&config, name)?;
 
-    if !all_bins_present(&state.required) {
+    if !state.macos.contains(current_version()) {
+        bail!("Command {name} is only for {}.", state.macos)
+    }
+    if !all_bins_present(&config, &state).await {
         bail!("Cannot execute command due to missing binaries.")
     }
 
     let dry_run = should_dry_run();
-    execute_command(state, dry_run).await
+    run_job(state, dry_run, Output::Inherit).await?;
+    Ok(())
+}
+
+/// Run the commands of a group strictly in the listed order, stopping at the first failure
+/// since later steps usually build on earlier ones.
+pub async fn run_group(config: Config, name: &str) -> Result<()> {
+    let group = config
+        .group
+        .as_ref()
+        .and_then(|groups| groups.get(name))
+        .ok_or_else(|| anyhow!("no such group {name}"))?;
+
+    // resolve every command first, so that a typo doesn't leave the group half done
+    let jobs = group
+        .commands
+        .iter()
+        .map(|cmd| extract_cmd(&config, cmd).map_err(|e| anyhow!("group {name}: {e}")))
+        .collect::<Result<Vec<_>>>()?;
+
+    let _sudo = sudo_session_for(&jobs).await?;
+    let dry_run = should_dry_run();
+    let mut successes = 0;
+
+    for job in jobs {
+        if !for_this_os(&job) {
+            continue;
+        }
+        if !all_bins_present(&config, &job).await {
+            bail!(
+                "Group {name} stopped at {} due to missing binaries.",
+                job.name
+            )
+        }
+
+        let cmd = job.name.clone();
+        match run_job(job, dry_run, Output::Inherit).await {
+            Ok(ran) => successes += ran as usize,
+            Err(e) => {
+                summary::add_failure(format!("command {cmd}"));
+                bail!("Group {name} stopped at {cmd}: {e}")
+            }
+        }
+    }
+
+    summary::add_commands(successes);
+    Ok(())
+}
+
+/// Run the undo scripts recorded during apply, in reverse order of execution.
+/// Returns the amount of successes and failures.
+pub async fn run_undo(undo: &[CommandUndo]) -> (i32, i32) {
+    let dry_run = should_dry_run();
+
+    let _sudo = if !dry_run && undo.iter().any(|u| u.sudo) {
+        keep_alive().await.ok()
+    } else {
+        None
+    };
+
+    let mut successes = 0;
+    let mut failures = 0;
+
+    for u in undo.iter().rev() {
+        let job = ExecJob {
+            name: format!("{} (undo)", u.name),
+            run: u.run.clone(),
+            shell: u.shell,
+            sudo: u.sudo,
+            ..Default::default()
+        };
+
+        if let Err(e) = execute_command(job, dry_run, Output::Inherit).await {
+            log_warn!("{e}");
+            summary::add_failure(format!("undo {}", u.name));
+            failures += 1;
+        } else {
+            successes += 1;
+        }
+    }
+
+    (successes, failures)
 }
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Log files of external commands, written with `[exec] logs = true`, so that long output
+//! doesn't only exist in the scrollback buffer.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    env,
+    io::ErrorKind,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::fs::{self, File, OpenOptions};
+
+use crate::util::history::utc_datetime;
+
+/// Returns the directory holding the logs, `$XDG_STATE_HOME/cutler/cmd-logs` or
+/// `~/.local/state/cutler/cmd-logs`.
+pub fn get_log_dir() -> Result<PathBuf> {
+    let state = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::home_dir()
+            .context("Could not determine home directory")?
+            .join(".local/state"),
+    };
+
+    Ok(state.join("cutler").join("cmd-logs"))
+}
+
+/// Names the log of a command run at the given unix time, e.g. `dotfiles-20250304-133700.log`.
+pub fn log_file_name(name: &str, secs: u64) -> String {
+    let (year, month, day, hour, minute, second) = utc_datetime(secs);
+    let name = name.replace(['/', ' '], "_");
+
+    format!("{name}-{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}.log")
+}
+
+/// Creates a new log file for a command starting now. Runs within the same second get a
+/// counter, e.g. `dotfiles-20250304-133700-2.log`, instead of overwriting each other's log.
+pub async fn create_log(name: &str) -> Result<(PathBuf, File)> {
+    let dir = get_log_dir()?;
+    fs::create_dir_all(&dir).await?;
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let base = log_file_name(name, secs);
+
+    for n in 1..=100 {
+        let path = match n {
+            1 => dir.join(&base),
+            _ => dir.join(format!("{}-{n}.log", base.trim_end_matches(".log"))),
+        };
+
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Could not create {path:?}")),
+        }
+    }
+
+    bail!("Could not create a log for {name} in {dir:?}: too many runs at once")
+}
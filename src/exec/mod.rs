@@ -1,4 +1,6 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 pub mod core;
+pub mod logs;
+pub mod plan;
 pub use core::{extract_all_cmds, run_all};
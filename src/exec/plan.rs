@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The plan printed by `cutler exec --dry-run --json`, so that reviewing what a shared config
+//! would execute can be automated.
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+use crate::config::core::{Config, Shell};
+use crate::config::vars::{masked_vars, substitute_known};
+use crate::exec::core::{ExecJob, ExecMode, extract_all_cmds_with, extract_cmd_with};
+use crate::util::macos::current_version;
+
+/// How a planned command runs relative to the others.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Commands with `export_var`, run one after another before everything else.
+    Export,
+    /// Commands with `ensure_first`, run one after another.
+    EnsureFirst,
+    /// Interactive commands, run one after another.
+    Interactive,
+    /// Regular commands, run concurrently.
+    Concurrent,
+    /// Commands of a group, run one after another in the listed order.
+    Group,
+    /// A single command, run with `cutler exec NAME`.
+    Single,
+}
+
+/// A command as it would be executed.
+#[derive(Serialize, Debug)]
+pub struct PlannedJob {
+    /// Position in the run. Commands sharing a step run concurrently.
+    pub step: usize,
+    pub stage: Stage,
+    pub name: String,
+    /// The script after substitution. Values of `from_command` variables are masked, and
+    /// environment variables are left as references.
+    pub run: String,
+    pub shell: Shell,
+    pub sudo: bool,
+    pub interactive: bool,
+    pub export_var: Option<String>,
+    pub only_if: Option<String>,
+    pub skip_if: Option<String>,
+    pub tags: Vec<String>,
+    pub required: Vec<String>,
+    /// Binaries from `required` which aren't in $PATH on this machine.
+    pub missing: Vec<String>,
+    /// Why the command wouldn't run here, if it wouldn't.
+    pub skipped: Option<String>,
+}
+
+/// Helper for: plan_all(), plan_one(), plan_group()
+/// Renders scripts with secrets and `from_command` values masked. Nothing is resolved, so
+/// building the plan runs none of the variable commands.
+fn masked(config: &Config) -> impl Fn(&str) -> String {
+    let vars = masked_vars(config);
+    move |text| substitute_known(text, &vars)
+}
+
+/// Helper for: plan_all(), plan_one(), plan_group()
+/// Describes a job extracted with masked variables.
+fn planned(job: ExecJob, step: usize, stage: Stage) -> PlannedJob {
+    let missing: Vec<String> = job
+        .required
+        .iter()
+        .filter(|bin| which::which(bin).is_err())
+        .cloned()
+        .collect();
+    let skipped = if !job.macos.contains(current_version()) {
+        Some(format!("only for {}", job.macos))
+    } else if !missing.is_empty() {
+        Some(format!("missing {}", missing.join(", ")))
+    } else {
+        None
+    };
+
+    PlannedJob {
+        step,
+        stage,
+        run: job.run,
+        only_if: job.only_if,
+        skip_if: job.skip_if,
+        name: job.name,
+        shell: job.shell,
+        sudo: job.sudo,
+        interactive: job.interactive,
+        export_var: job.export_var,
+        tags: job.tags,
+        required: job.required,
+        missing,
+        skipped,
+    }
+}
+
+/// Plans the commands `run_all()` would execute under the given mode, in the same order.
+pub fn plan_all(config: &Config, mode: &ExecMode) -> Vec<PlannedJob> {
+    let mut exports = Vec::new();
+    let mut ensure_first = Vec::new();
+    let mut interactive = Vec::new();
+    let mut regular = Vec::new();
+
+    for job in extract_all_cmds_with(config, masked(config)) {
+        if !mode.selects(&job) {
+            continue;
+        } else if job.export_var.is_some() {
+            exports.push(job);
+        } else if job.ensure_first {
+            ensure_first.push(job);
+        } else if job.interactive {
+            interactive.push(job);
+        } else {
+            regular.push(job);
+        }
+    }
+
+    let sequential = exports
+        .into_iter()
+        .map(|job| (job, Stage::Export))
+        .chain(
+            ensure_first
+                .into_iter()
+                .map(|job| (job, Stage::EnsureFirst)),
+        )
+        .chain(interactive.into_iter().map(|job| (job, Stage::Interactive)));
+
+    let mut plan = Vec::new();
+    for (job, stage) in sequential {
+        plan.push(planned(job, plan.len() + 1, stage));
+    }
+
+    let step = plan.len() + 1;
+    for job in regular {
+        plan.push(planned(job, step, Stage::Concurrent));
+    }
+
+    plan
+}
+
+/// Plans a single command, like `run_one()`.
+pub fn plan_one(config: &Config, name: &str) -> Result<Vec<PlannedJob>> {
+    let job = extract_cmd_with(config, name, masked(config))?;
+    Ok(vec![planned(job, 1, Stage::Single)])
+}
+
+/// Plans the commands of a group, like `run_group()`.
+pub fn plan_group(config: &Config, name: &str) -> Result<Vec<PlannedJob>> {
+    let group = config
+        .group
+        .as_ref()
+        .and_then(|groups| groups.get(name))
+        .ok_or_else(|| anyhow!("no such group {name}"))?;
+
+    group
+        .commands
+        .iter()
+        .enumerate()
+        .map(|(i, cmd)| {
+            let job = extract_cmd_with(config, cmd, masked(config))
+                .map_err(|e| anyhow!("group {name}: {e}"))?;
+            Ok(planned(job, i + 1, Stage::Group))
+        })
+        .collect()
+}
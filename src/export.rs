@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Renders the config in formats which don't need cutler to be applied.
+//!
+//! - A shell script, which only needs `/bin/sh`, `defaults` and (for `[brew]`/`[mas]`) an
+//!   internet connection, to bootstrap machines where cutler can't be installed first.
+//! - A configuration profile (`.mobileconfig`), to hand the same settings to MDM.
+
+use anyhow::Result;
+use defaults_rs::PrefValue;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use toml::{Table, Value};
+
+use crate::config::core::Config;
+use crate::domains::collector::effective;
+use crate::domains::convert::{prefvalue_to_toml, toml_to_prefvalue};
+use crate::domains::files::is_path_domain;
+use crate::domains::menubar::{CURRENT_HOST, byhost_domain};
+use crate::log_warn;
+use crate::plist::core::{render, render_fragment};
+
+/// Helper for: render_script()
+fn quote(text: &str) -> String {
+    shell_words::quote(text).into_owned()
+}
+
+/// Renders the `defaults write` command for a domain. Path domains under `~` expand against
+/// `$HOME` of whoever runs the script, and the ones outside home directories need sudo.
+pub fn defaults_write(domain: &str) -> String {
+    if domain == byhost_domain(CURRENT_HOST) {
+        "defaults -currentHost write com.apple.controlcenter".into()
+    } else if let Some(rest) = domain.strip_prefix("~/") {
+        format!("defaults write \"$HOME\"/{}", quote(rest))
+    } else if is_path_domain(domain) && !domain.starts_with("/Users/") {
+        format!("sudo defaults write {}", quote(domain))
+    } else {
+        format!("defaults write {}", quote(domain))
+    }
+}
+
+/// Renders the `defaults write` arguments for a value, with the matching type flag.
+/// Arrays, dictionaries and dates are passed as XML plist fragments, which `defaults` parses.
+pub fn defaults_args(value: &PrefValue) -> Result<String> {
+    Ok(match value {
+        PrefValue::String(s) => format!("-string {}", quote(s)),
+        PrefValue::Integer(i) => format!("-int {i}"),
+        PrefValue::Float(f) => format!("-float {f}"),
+        PrefValue::Boolean(b) => format!("-bool {b}"),
+        PrefValue::Data(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            format!("-data {hex}")
+        }
+        _ => quote(&render_fragment(&prefvalue_to_toml(value))?),
+    })
+}
+
+/// Renders the `[set]` domains (as returned by collect()) along with `[brew]` and `[mas]`
+/// into a POSIX shell script.
+pub fn render_script(config: &Config, domains: &HashMap<String, Table>) -> Result<String> {
+    let mut out = vec![
+        "#!/bin/sh".to_string(),
+        format!(
+            "# Generated by cutler {} from {}",
+            env!("CARGO_PKG_VERSION"),
+            config.path.display()
+        ),
+        "set -e".to_string(),
+    ];
+
+    if let Some(brew) = &config.brew {
+        out.push(String::new());
+        out.push("# Homebrew".into());
+        out.push("if ! command -v brew >/dev/null 2>&1; then".into());
+        out.push(
+            "    curl -fsSL https://raw.githubusercontent.com/Homebrew/install/HEAD/install.sh | /bin/bash"
+                .into(),
+        );
+        out.push(
+            "    [ -x /opt/homebrew/bin/brew ] && eval \"$(/opt/homebrew/bin/brew shellenv)\""
+                .into(),
+        );
+        out.push("fi".into());
+
+        for tap in brew.taps.iter().flatten() {
+            match tap.url() {
+                Some(url) => out.push(format!("brew tap {} {}", quote(tap.name()), quote(url))),
+                None => out.push(format!("brew tap {}", quote(tap.name()))),
+            }
+            if let Some(revision) = tap.revision() {
+                out.push(format!(
+                    "git -C \"$(brew --repository {})\" checkout --quiet {}",
+                    quote(tap.name()),
+                    quote(revision)
+                ));
+            }
+        }
+        for formula in brew.formulae.iter().flatten() {
+            out.push(format!("brew install {}", quote(formula.name())));
+            if formula.is_pinned() {
+                out.push(format!("brew pin {}", quote(formula.name())));
+            }
+        }
+        for cask in brew.casks.iter().flatten() {
+            out.push(format!("brew install --cask {}", quote(cask)));
+        }
+    }
+
+    if let Some(mas) = &config.mas
+        && !mas.ids.is_empty()
+    {
+        out.push(String::new());
+        out.push("# Mac App Store".into());
+        out.push("command -v mas >/dev/null 2>&1 || brew install mas".into());
+        for app in &mas.ids {
+            match app.name() {
+                Some(name) => out.push(format!("mas install {} # {name}", app.id())),
+                None => out.push(format!("mas install {}", app.id())),
+            }
+        }
+    }
+
+    // sorted, so the script doesn't change between runs
+    let mut settings = BTreeMap::new();
+    for (domain, table) in domains {
+        for (key, value) in table {
+            settings.insert(effective(domain, key), toml_to_prefvalue(value)?);
+        }
+    }
+
+    if !settings.is_empty() {
+        out.push(String::new());
+        out.push("# Preferences".into());
+        for ((dom, key), value) in &settings {
+            out.push(format!(
+                "{} {} {}",
+                defaults_write(dom),
+                quote(key),
+                defaults_args(value)?
+            ));
+        }
+
+        out.push(String::new());
+        out.push(
+            "killall SystemUIServer Dock Finder ControlCenter NotificationCenter 2>/dev/null || true"
+                .into(),
+        );
+    }
+
+    Ok(out.join("\n") + "\n")
+}
+
+/// Default identifier of generated profiles; payloads append their domain to it.
+pub const PROFILE_IDENTIFIER: &str = "io.github.machlit.cutler";
+
+/// Helper for: render_mobileconfig()
+/// Derives a stable UUID from a payload identifier, so re-exports update the same profile.
+fn payload_uuid(identifier: &str) -> String {
+    let mut bytes: [u8; 16] = Sha256::digest(identifier.as_bytes())[..16]
+        .try_into()
+        .unwrap_or_default();
+
+    // RFC 9562 version 8 (custom), variant 10xx
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Helper for: render_mobileconfig()
+fn payload_header(payload: &mut Table, kind: &str, identifier: &str, name: &str) {
+    payload.insert("PayloadType".into(), kind.into());
+    payload.insert("PayloadIdentifier".into(), identifier.into());
+    payload.insert("PayloadUUID".into(), payload_uuid(identifier).into());
+    payload.insert("PayloadDisplayName".into(), name.into());
+    payload.insert("PayloadVersion".into(), 1.into());
+}
+
+/// Renders the `[set]` domains (as returned by collect()) into a configuration profile,
+/// with one custom settings payload per domain.
+/// Path domains are left out, since profiles can only address preference domains.
+pub fn render_mobileconfig(domains: &HashMap<String, Table>, identifier: &str) -> Result<String> {
+    let mut by_domain: BTreeMap<String, Table> = BTreeMap::new();
+    for (domain, table) in domains {
+        if is_path_domain(domain) {
+            log_warn!("Skipping {domain}: profiles can't write plist files by path.");
+            continue;
+        }
+
+        for (key, value) in table {
+            let (dom, key) = effective(domain, key);
+            by_domain.entry(dom).or_default().insert(key, value.clone());
+        }
+    }
+
+    let payloads: Vec<Value> = by_domain
+        .into_iter()
+        .map(|(dom, settings)| {
+            // profiles address the global domain by its file name
+            let kind = if dom == "NSGlobalDomain" {
+                ".GlobalPreferences"
+            } else {
+                dom.as_str()
+            };
+
+            let mut payload = settings;
+            payload_header(&mut payload, kind, &format!("{identifier}.{dom}"), &dom);
+            Value::Table(payload)
+        })
+        .collect();
+
+    let mut profile = Table::new();
+    payload_header(&mut profile, "Configuration", identifier, "cutler");
+    profile.insert("PayloadContent".into(), Value::Array(payloads));
+    profile.insert("PayloadScope".into(), "User".into());
+
+    render(&profile)
+}
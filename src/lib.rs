@@ -1,11 +1,19 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 pub mod autosync;
+pub mod backup;
+pub mod bootstrap;
 pub mod brew;
 pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod domains;
+pub mod ephemeral;
 pub mod exec;
+pub mod export;
+pub mod mas;
+pub mod plist;
+pub mod plugin;
+pub mod prelude;
 pub mod snapshot;
 pub mod util;
@@ -26,12 +26,17 @@ use clap::Parser;
 use cutler::autosync::try_auto_sync;
 
 use cutler::cli::atomic::{
-    set_accept_all, set_dry_run, set_no_restart_services, set_quiet, set_verbose,
+    set_accept_all, set_dry_run, set_json, set_no_prompts, set_no_restart_services, set_offline,
+    set_quiet, set_verbose,
 };
-use cutler::cli::{Args, Command};
-use cutler::commands::Runnable;
+use cutler::cli::{Args, Command, has_caller};
+use cutler::commands::self_update::Installer;
 use cutler::config::core::Config;
-use cutler::config::path::get_config_path;
+use cutler::config::path::{get_config_path, set_config_path};
+use cutler::plugin::run_plugin;
+use cutler::util::exit::{ExitCode, exit_code_for};
+use cutler::util::limits::set_jobs;
+use cutler::util::logging::{set_color_mode, set_log_style, set_porcelain};
 use cutler::util::sudo::{run_with_noroot, run_with_root};
 use cutler::{log_err, log_info};
 
@@ -42,22 +47,35 @@ async fn main() {
     // set some of them atomically
     // (described why in util/globals.rs)
     set_accept_all(args.accept_all);
-    set_quiet(args.quiet);
+    set_quiet(args.quiet || args.command.is_porcelain());
     set_verbose(args.verbose);
     set_dry_run(args.dry_run);
-    set_no_restart_services(args.no_restart_services);
+    set_json(args.json);
+    // tools calling cutler can't answer prompts, nor expect apps to restart under them
+    set_no_prompts(args.no_prompts || has_caller());
+    set_no_restart_services(args.no_restart_services || has_caller());
+    set_offline(args.offline);
+    set_jobs(args.jobs);
+    set_color_mode(args.color);
+    set_log_style(args.log_style);
+    set_porcelain(args.json || args.command.owns_stdout());
 
     // decide configuration path for the entire lifetime of the program
+    if let Some(path) = &args.config {
+        set_config_path(path.clone());
+    }
     let mut config = match get_config_path().await {
         Ok(path) => Config::new(path),
         Err(_) => {
             log_err!("Path could not be decided for the configuration file.");
-            exit(1);
+            exit(ExitCode::Config.code());
         }
     };
 
     // remote config auto-sync logic
-    if !args.no_sync {
+    if args.offline {
+        log_info!("Skipping remote config autosync since offline.");
+    } else if !args.no_sync {
         try_auto_sync(&args.command, &mut config).await;
     } else {
         log_info!("Skipping remote config autosync.");
@@ -65,21 +83,26 @@ async fn main() {
 
     // sudo protection
     let result = match &args.command {
+        // package managers upgrade cutler themselves, and refuse to run as root
+        Command::SelfUpdate(_) if Installer::detect().is_some() => run_with_noroot(),
         Command::SelfUpdate(_) | Command::Lock(_) | Command::Unlock(_) => run_with_root().await,
         _ => run_with_noroot(),
     };
 
     if let Err(err) = result {
         log_err!("{err}");
-        exit(1);
+        exit(exit_code_for(&err).code());
     }
 
     // command invocation (for real this time)
-    let runnable: &dyn Runnable = args.command.as_runnable();
-    let result = runnable.run(&mut config).await;
+    let result = match args.command.as_runnable() {
+        Some(runnable) => runnable.run(&mut config).await,
+        // anything unknown is handed over to a `cutler-<name>` plugin
+        None => run_plugin(&args, &config).await,
+    };
 
     if let Err(err) = result {
         log_err!("{err}");
-        exit(1);
+        exit(exit_code_for(&err).code());
     }
 }
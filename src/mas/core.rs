@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use tokio::process::Command;
+
+use crate::cli::atomic::should_dry_run;
+use crate::config::core::Mas;
+use crate::util::{net::ensure_online, summary};
+use crate::{log_dry, log_err, log_info};
+
+/// Struct representing the diff between config and installed Mac App Store apps.
+/// All entries are human-readable labels, e.g. `Xcode (497799835)`.
+#[derive(Debug, Default)]
+pub struct MasDiff {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    /// Apps whose configured name differs from the installed one.
+    pub mismatched: Vec<String>,
+}
+
+/// Checks if the `mas` CLI is available.
+pub async fn mas_is_installed() -> bool {
+    Command::new("mas")
+        .arg("version")
+        .output()
+        .await
+        .map(|op| op.status.success())
+        .unwrap_or(false)
+}
+
+/// Parses the output of `mas list` into a map of id → name.
+///
+/// Each line looks like: `497799835  Xcode  (16.2)`
+pub fn parse_mas_list(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (id, rest) = line.trim().split_once(char::is_whitespace)?;
+            let name = match rest.rfind('(') {
+                Some(idx) => &rest[..idx],
+                None => rest,
+            };
+            Some((id.to_string(), name.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Lists installed Mac App Store apps as a map of id → name.
+pub async fn mas_list() -> Result<HashMap<String, String>> {
+    let output = Command::new("mas").arg("list").output().await?;
+
+    if !output.status.success() {
+        bail!("`mas list` failed.");
+    }
+
+    Ok(parse_mas_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Compares the [mas] table against the installed apps.
+pub fn diff_mas(mas: &Mas, installed: &HashMap<String, String>) -> MasDiff {
+    let mut diff = MasDiff::default();
+
+    for app in &mas.ids {
+        let id = app.id();
+
+        match installed.get(&id) {
+            None => diff.missing.push(match app.name() {
+                Some(name) => format!("{name} ({id})"),
+                None => id,
+            }),
+            Some(actual) => {
+                if let Some(name) = app.name()
+                    && name != actual
+                {
+                    diff.mismatched
+                        .push(format!("{name} ({id}) is installed as {actual}"));
+                }
+            }
+        }
+    }
+
+    let configured: Vec<String> = mas.ids.iter().map(|a| a.id()).collect();
+    let mut extra: Vec<String> = installed
+        .iter()
+        .filter(|(id, _)| !configured.contains(id))
+        .map(|(id, name)| format!("{name} ({id})"))
+        .collect();
+    extra.sort();
+    diff.extra = extra;
+
+    diff
+}
+
+/// Installs the apps of the [mas] table which aren't installed yet, one after another.
+/// Returns how many were installed.
+pub async fn install_missing(mas: &Mas) -> Result<usize> {
+    let installed = mas_list().await?;
+    let mut count = 0;
+
+    for app in mas.ids.iter().filter(|a| !installed.contains_key(&a.id())) {
+        let id = app.id();
+        let label = match app.name() {
+            Some(name) => format!("{name} ({id})"),
+            None => id.clone(),
+        };
+
+        if should_dry_run() {
+            log_dry!("Would install {label} from the App Store");
+            continue;
+        }
+
+        ensure_online("install App Store apps")?;
+        log_info!("Installing: {label}");
+        let status = Command::new("mas").arg("install").arg(&id).status().await?;
+
+        if status.success() {
+            summary::add_applied(1);
+            count += 1;
+        } else {
+            log_err!("Failed to install: {label}");
+            summary::add_failure(format!("app {label}"));
+        }
+    }
+
+    Ok(count)
+}
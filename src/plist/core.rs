@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::{fs, process::Command};
+use toml::{Table, Value};
+
+use crate::domains::convert::{DATA_KEY, datetime_to_unix, unix_to_datetime};
+use crate::snapshot::{core::PlistState, get_snapshot_path};
+use crate::util::summary;
+use crate::{log_dry, log_err, log_info};
+
+const HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+"#;
+
+/// Expands a leading `~` in a [plist] path to the home directory.
+pub fn expand_path(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Helper for: render_value()
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Helper for: render()
+/// Fails on values XML plists can't hold: NaN or infinite floats, and datetimes without an
+/// offset, which would be read in whatever time zone the Mac is in.
+fn render_value(value: &Value, depth: usize, out: &mut String) -> Result<()> {
+    let indent = "\t".repeat(depth);
+
+    match value {
+        Value::String(s) => out.push_str(&format!("{indent}<string>{}</string>\n", escape(s))),
+        Value::Integer(i) => out.push_str(&format!("{indent}<integer>{i}</integer>\n")),
+        Value::Float(f) if !f.is_finite() => bail!("Plists can't hold the float {f}"),
+        Value::Float(f) => out.push_str(&format!("{indent}<real>{f}</real>\n")),
+        Value::Boolean(b) => out.push_str(&format!("{indent}<{b}/>\n")),
+        Value::Datetime(d) if d.offset.is_none() => {
+            bail!("Datetime `{d}` needs an offset (e.g. `Z`) to be written to a plist")
+        }
+        Value::Datetime(d) => {
+            // plists only take UTC dates
+            let utc = unix_to_datetime(datetime_to_unix(d)?);
+            out.push_str(&format!("{indent}<date>{utc}</date>\n"))
+        }
+        Value::Array(arr) => {
+            out.push_str(&format!("{indent}<array>\n"));
+            for item in arr {
+                render_value(item, depth + 1, out)?;
+            }
+            out.push_str(&format!("{indent}</array>\n"));
+        }
+        // { base64 = "..." } wrappers are Data blobs
+        Value::Table(tbl) if tbl.len() == 1 && tbl.get(DATA_KEY).is_some_and(Value::is_str) => {
+            let data = tbl[DATA_KEY].as_str().unwrap_or_default();
+            out.push_str(&format!("{indent}<data>{}</data>\n", data.trim()));
+        }
+        Value::Table(tbl) => {
+            out.push_str(&format!("{indent}<dict>\n"));
+            for (key, item) in tbl {
+                out.push_str(&format!("{indent}\t<key>{}</key>\n", escape(key)));
+                render_value(item, depth + 1, out)?;
+            }
+            out.push_str(&format!("{indent}</dict>\n"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders a TOML table as an XML property list.
+pub fn render(table: &Table) -> Result<String> {
+    let mut out = HEADER.to_string();
+    render_value(&Value::Table(table.clone()), 0, &mut out)?;
+    out.push_str("</plist>\n");
+    Ok(out)
+}
+
+/// Renders a single value as an XML plist fragment, e.g. `<array>...</array>`.
+pub fn render_fragment(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    render_value(value, 0, &mut out)?;
+    Ok(out.trim_end().to_string())
+}
+
+/// Checks if the plist file at `path` holds exactly the given content.
+pub async fn is_in_sync(path: &Path, table: &Table) -> bool {
+    // fast path: the file is still what cutler wrote
+    if fs::read_to_string(path)
+        .await
+        .is_ok_and(|current| render(table).is_ok_and(|xml| current == xml))
+    {
+        return true;
+    }
+
+    // the app might have rewritten it (e.g. in binary format), so compare the contents
+    let Ok(out) = Command::new("plutil")
+        .args(["-convert", "json", "-o", "-"])
+        .arg(path)
+        .output()
+        .await
+    else {
+        return false;
+    };
+
+    out.status.success()
+        && serde_json::from_slice::<serde_json::Value>(&out.stdout).ok()
+            == serde_json::to_value(table).ok()
+}
+
+/// Returns the path the original of a managed plist is backed up to.
+pub async fn backup_path(path: &Path) -> Result<PathBuf> {
+    let snap_path = get_snapshot_path().await?;
+    let dir = snap_path
+        .parent()
+        .context("Could not determine snapshot directory")?;
+    let hash = Sha256::digest(path.to_string_lossy().as_bytes());
+
+    Ok(dir.join("backups").join(format!("{hash:x}.plist")))
+}
+
+/// Writes all [plist] files which aren't in sync, backing up their originals first.
+/// Backups already recorded in `states` are kept, so the very first original survives.
+pub async fn apply_plists(
+    plists: &[(String, Table)],
+    states: &mut Vec<PlistState>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut written = false;
+
+    for (raw_path, table) in plists {
+        let path = expand_path(raw_path);
+
+        let xml = match render(table) {
+            Ok(xml) => xml,
+            Err(e) => {
+                log_err!("Cannot write plist {raw_path}: {e}");
+                summary::add_failure(format!("plist {raw_path}"));
+                continue;
+            }
+        };
+
+        if is_in_sync(&path, table).await {
+            log_info!("Skipping unchanged plist {raw_path}");
+            summary::add_skipped(1);
+            continue;
+        }
+
+        if dry_run {
+            log_dry!("Would write plist {raw_path}");
+            continue;
+        }
+
+        let path_str = path.to_string_lossy().to_string();
+
+        if !states.iter().any(|s| s.path == path_str) {
+            let backup = if fs::try_exists(&path).await.unwrap_or_default() {
+                let backup = backup_path(&path).await?;
+                fs::create_dir_all(backup.parent().unwrap()).await?;
+                fs::copy(&path, &backup).await?;
+                Some(backup.to_string_lossy().to_string())
+            } else {
+                None
+            };
+
+            states.push(PlistState {
+                path: path_str,
+                backup,
+            });
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+
+        match fs::write(&path, xml).await {
+            Ok(_) => {
+                log_info!("Wrote plist {raw_path}");
+                summary::add_applied(1);
+                written = true;
+            }
+            Err(e) => {
+                log_err!("Failed to write plist {raw_path}: {e}");
+                summary::add_failure(format!("plist {raw_path}"));
+            }
+        }
+    }
+
+    // make the preferences daemon pick up the new files instead of its cache
+    if written {
+        let _ = Command::new("killall").arg("cfprefsd").output().await;
+    }
+
+    Ok(())
+}
+
+/// Restores the originals of all managed plists, deleting files which didn't exist before.
+pub async fn restore_plists(states: &[PlistState], dry_run: bool) -> Result<()> {
+    for state in states {
+        match &state.backup {
+            Some(backup) if dry_run => log_dry!("Would restore plist {} from {backup}", state.path),
+            None if dry_run => log_dry!("Would remove plist {}", state.path),
+            Some(backup) => {
+                log_info!("Restoring plist {}", state.path);
+
+                match fs::copy(backup, &state.path).await {
+                    Ok(_) => {
+                        fs::remove_file(backup).await.ok();
+                        summary::add_applied(1);
+                    }
+                    Err(e) => {
+                        log_err!("Failed to restore plist {}: {e}", state.path);
+                        summary::add_failure(format!("plist {}", state.path));
+                    }
+                }
+            }
+            None => {
+                log_info!("Removing plist {}", state.path);
+                fs::remove_file(&state.path).await.ok();
+                summary::add_applied(1);
+            }
+        }
+    }
+
+    if !dry_run && !states.is_empty() {
+        let _ = Command::new("killall").arg("cfprefsd").output().await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! External `cutler-<name>` plugins.
+//!
+//! Like cargo or git, unknown subcommands are handed over to a `cutler-<name>` binary found in
+//! $PATH. The plugin receives the remaining arguments as-is, and the global flags and config
+//! path through `CUTLER_*` environment variables.
+
+use anyhow::{Context, Result, anyhow, bail};
+use tokio::process::Command as Process;
+
+use crate::cli::atomic::{
+    should_accept_all, should_be_quiet, should_be_verbose, should_dry_run, should_not_prompt,
+    should_not_restart_services, should_output_json, should_stay_offline,
+};
+use crate::cli::{Args, Command};
+use crate::config::core::Config;
+use crate::log_info;
+
+/// Returns the binary name of a plugin, e.g. `cutler-asdf`.
+pub fn plugin_binary(name: &str) -> String {
+    format!("cutler-{name}")
+}
+
+/// Builds the environment variables passed to a plugin.
+/// Flags are passed as "1" or "0", the plugin's own `[plugin.<name>]` table as JSON.
+pub fn plugin_env(name: &str, config: &Config) -> Vec<(String, String)> {
+    let flag = |set: bool| if set { "1" } else { "0" }.to_string();
+
+    let mut env = vec![
+        (
+            "CUTLER_CONFIG".to_string(),
+            config.path.to_string_lossy().to_string(),
+        ),
+        (
+            "CUTLER_VERSION".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ),
+        ("CUTLER_VERBOSE".to_string(), flag(should_be_verbose())),
+        ("CUTLER_QUIET".to_string(), flag(should_be_quiet())),
+        ("CUTLER_DRY_RUN".to_string(), flag(should_dry_run())),
+        ("CUTLER_ACCEPT_ALL".to_string(), flag(should_accept_all())),
+        ("CUTLER_JSON".to_string(), flag(should_output_json())),
+        ("CUTLER_NO_PROMPTS".to_string(), flag(should_not_prompt())),
+        ("CUTLER_OFFLINE".to_string(), flag(should_stay_offline())),
+        (
+            "CUTLER_NO_RESTART_SERVICES".to_string(),
+            flag(should_not_restart_services()),
+        ),
+    ];
+
+    if let Some(table) = config.plugin.as_ref().and_then(|p| p.get(name))
+        && let Ok(json) = serde_json::to_string(table)
+    {
+        env.push(("CUTLER_PLUGIN_CONFIG".to_string(), json));
+    }
+
+    env
+}
+
+/// Runs the plugin behind an external subcommand, e.g. `cutler asdf install`.
+pub async fn run_plugin(args: &Args, config: &Config) -> Result<()> {
+    let Command::External(argv) = &args.command else {
+        bail!("Not an external subcommand.")
+    };
+    let (name, rest) = argv.split_first().context("Missing subcommand name.")?;
+
+    let bin = which::which(plugin_binary(name)).map_err(|_| {
+        anyhow!(
+            "Unknown command `{name}`: no built-in command or `{}` in $PATH.",
+            plugin_binary(name)
+        )
+    })?;
+
+    // plugin tables are optional, so a missing or broken config is the plugin's business
+    let mut loaded = config.clone();
+    if loaded.load(false).await.is_err() {
+        loaded = config.clone();
+    }
+
+    log_info!("Running plugin: {}", bin.display());
+
+    let status = Process::new(&bin)
+        .args(rest)
+        .envs(plugin_env(name, &loaded))
+        .status()
+        .await
+        .with_context(|| format!("Could not run {}", bin.display()))?;
+
+    if !status.success() {
+        bail!("{} exited with {status}", plugin_binary(name));
+    }
+
+    Ok(())
+}
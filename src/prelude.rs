@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! API for driving cutler from other Rust tools (e.g. a GUI frontend).
+//!
+//! The items re-exported here are the intended entry points, and renaming or removing them only
+//! lands in major releases. Their structs still gain public fields as cutler grows, so build
+//! them through constructors like `Config::new()` rather than struct literals. The rest of the
+//! crate is shaped around the CLI and may change at any time.
+//!
+//! These items ignore `--dry-run`, but they have side effects worth knowing about:
+//!
+//! - `Config::load()` sets process-wide state from the config (`jobs`, `[theme]`, `[network]`
+//!   and snapshot encryption), which stays in effect for the rest of the process.
+//! - Settings in plist files only root can read are read through `sudo -n`, which never
+//!   prompts but fails if no sudo timestamp is cached.
+//! - `Plan::execute()` writes such root-owned plists through `sudo`, which may ask for a
+//!   password on the terminal, and then restarts `cfprefsd` so that the new contents are picked
+//!   up. Other services are never restarted.
+//!
+//! Logging goes to stderr (e.g. a failing `from_command` variable, or writing through sudo);
+//! call `cutler::cli::atomic::set_quiet(true)` beforehand if that is unwanted.
+//!
+//! ```no_run
+//! use cutler::prelude::*;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let mut config = Config::new("/path/to/config.toml".into());
+//! config.load(false).await?;
+//!
+//! let plan = apply_plan(&config).await?;
+//! for entry in plan.changes() {
+//!     println!("{} {}: {:?} -> {}", entry.domain, entry.key, entry.current, entry.desired);
+//! }
+//! plan.execute()?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::brew::core::diff_brew;
+pub use crate::brew::types::BrewDiff;
+pub use crate::config::core::Config;
+pub use crate::domains::collect;
+pub use crate::domains::plan::{Plan, PlanEntry, apply_plan};
+pub use crate::snapshot::core::{PlistState, SettingState, Snapshot};
+pub use defaults_rs::PrefValue;
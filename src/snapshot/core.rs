@@ -2,11 +2,16 @@
 
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 use tokio::fs;
 
+use crate::config::core::Shell;
 use crate::domains::convert::SerializablePrefValue;
-use crate::snapshot::get_snapshot_path;
+use crate::log_info;
+use crate::snapshot::{crypt, get_snapshot_path};
 
 /// A single defaults‑setting change.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -17,6 +22,63 @@ pub struct SettingState {
     pub original_value: Option<SerializablePrefValue>,
 }
 
+/// A plist file fully managed through [plist], along with a backup of its original.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PlistState {
+    pub path: String,
+    /// Path to the backed up original, or None if the file didn't exist before.
+    pub backup: Option<String>,
+}
+
+/// The `undo` script of an external command which ran during apply.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandUndo {
+    pub name: String,
+    /// The undo script, with variables already resolved.
+    pub run: String,
+    #[serde(default)]
+    pub shell: Shell,
+    #[serde(default)]
+    pub sudo: bool,
+}
+
+/// Homebrew software newly installed by `apply --brew`, removable with `unapply --brew`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BrewChanges {
+    #[serde(default)]
+    pub formulae: Vec<String>,
+    #[serde(default)]
+    pub casks: Vec<String>,
+    #[serde(default)]
+    pub taps: Vec<String>,
+}
+
+impl BrewChanges {
+    pub fn is_empty(&self) -> bool {
+        self.formulae.is_empty() && self.casks.is_empty() && self.taps.is_empty()
+    }
+
+    /// Adds newly installed software, skipping what's already recorded.
+    pub fn merge(&mut self, other: BrewChanges) {
+        let pairs = [
+            (&mut self.formulae, other.formulae),
+            (&mut self.casks, other.casks),
+            (&mut self.taps, other.taps),
+        ];
+
+        for (ours, theirs) in pairs {
+            for name in theirs {
+                if !ours.contains(&name) {
+                    ours.push(name);
+                }
+            }
+        }
+    }
+}
+
 /// Represents a snapshot.
 ///
 /// This struct has also implemented I/O operations and functions for using across cutler's codebase,
@@ -25,11 +87,21 @@ pub struct SettingState {
 #[serde(deny_unknown_fields)]
 pub struct Snapshot {
     pub settings: Vec<SettingState>,
+    #[serde(default)]
+    pub plists: Vec<PlistState>,
     pub exec_run_count: i32,
+    /// Undo scripts of the commands which ran, in order of execution.
+    #[serde(default)]
+    pub undo: Vec<CommandUndo>,
+    #[serde(default)]
+    pub brew: BrewChanges,
     pub version: String,
     pub digest: String,
     #[serde(skip)]
     pub path: PathBuf,
+    /// Whether the snapshot is saved encrypted (see crypt).
+    #[serde(skip)]
+    pub encrypted: bool,
 }
 
 impl Snapshot {
@@ -49,12 +121,16 @@ impl Snapshot {
     pub async fn new() -> Self {
         Snapshot {
             settings: Vec::new(),
+            plists: Vec::new(),
             version: env!("CARGO_PKG_VERSION").into(),
             path: get_snapshot_path()
                 .await
                 .expect("Failed to get snapshot path."),
             exec_run_count: 0,
+            undo: Vec::new(),
+            brew: BrewChanges::default(),
             digest: String::new(),
+            encrypted: crypt::wants_encryption().unwrap_or_default(),
         }
     }
 
@@ -65,44 +141,84 @@ impl Snapshot {
         }
 
         let json = serde_json::to_string_pretty(self)?;
-        fs::write(&self.path, json).await?;
+        let data = if self.encrypted {
+            crypt::encrypt(&json, &crypt::get_key(true).await?)?
+        } else {
+            json
+        };
+
+        fs::write(&self.path, data).await?;
         Ok(())
     }
 
-    /// Loads the snapshot from the given path.
+    /// Loads the snapshot from the given path, decrypting it if needed.
     /// If deserialization of the full Snapshot fails, try to deserialize only the `settings` field.
+    /// A snapshot which is (not) encrypted against the loaded config is converted right away.
     pub async fn load(path: &PathBuf) -> Result<Self> {
+        let mut snap = Self::read(path).await?;
+        let was_encrypted = snap.encrypted;
+
+        // without a loaded config, the snapshot stays as it is
+        snap.encrypted = crypt::wants_encryption().unwrap_or(was_encrypted);
+        if snap.encrypted != was_encrypted {
+            snap.save().await?;
+            log_info!(
+                "{} snapshot at {path:?}.",
+                if snap.encrypted {
+                    "Encrypted"
+                } else {
+                    "Decrypted"
+                }
+            );
+        }
+
+        Ok(snap)
+    }
+
+    /// Helper for: load()
+    async fn read(path: &PathBuf) -> Result<Self> {
         if fs::try_exists(path).await.unwrap_or_default() {
-            let txt = fs::read_to_string(path).await?;
-            let snap_result: Result<Snapshot, _> = serde_json::from_str(&txt);
+            Self::parse(fs::read_to_string(path).await?, path).await
+        } else {
+            bail!("Invalid path, cannot load.")
+        }
+    }
+
+    /// Parses a snapshot as written by save(), decrypting it if needed, as if it was at `path`.
+    pub async fn parse(mut txt: String, path: &Path) -> Result<Self> {
+        let encrypted = crypt::is_encrypted(&txt);
+        if encrypted {
+            txt = crypt::decrypt(&txt, &crypt::get_key(false).await?)?;
+        }
 
-            match snap_result {
-                Ok(mut snap) => {
-                    snap.path = path.clone();
-                    Ok(snap)
+        let snap_result: Result<Snapshot, _> = serde_json::from_str(&txt);
+
+        match snap_result {
+            Ok(mut snap) => {
+                snap.path = path.to_path_buf();
+                snap.encrypted = encrypted;
+                Ok(snap)
+            }
+            Err(e) => {
+                // Try to deserialize only the settings field if everything else fails.
+                #[derive(Deserialize)]
+                struct SettingsOnly {
+                    settings: Vec<SettingState>,
                 }
-                Err(e) => {
-                    // Try to deserialize only the settings field if everything else fails.
-                    #[derive(Deserialize)]
-                    struct SettingsOnly {
-                        settings: Vec<SettingState>,
+                let settings_only_result: Result<SettingsOnly, _> = serde_json::from_str(&txt);
+                match settings_only_result {
+                    Ok(settings_only) => {
+                        let mut snap = Snapshot::new().await;
+                        snap.settings = settings_only.settings;
+                        snap.path = path.to_path_buf();
+                        snap.encrypted = encrypted;
+                        Ok(snap)
                     }
-                    let settings_only_result: Result<SettingsOnly, _> = serde_json::from_str(&txt);
-                    match settings_only_result {
-                        Ok(settings_only) => {
-                            let mut snap = Snapshot::new().await;
-                            snap.settings = settings_only.settings;
-                            snap.path = path.clone();
-                            Ok(snap)
-                        }
-                        Err(_) => {
-                            bail!("Failed to deserialize snapshot: {e}")
-                        }
+                    Err(_) => {
+                        bail!("Failed to deserialize snapshot: {e}")
                     }
                 }
             }
-        } else {
-            bail!("Invalid path, cannot load.")
         }
     }
 
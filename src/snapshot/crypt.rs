@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Optional encryption of the snapshot at rest.
+//!
+//! Original values can hold personal data (device names, paths, account-linked settings), and
+//! snapshots often end up in synced folders and backups. With `[snapshot] encrypt = true`, the
+//! snapshot is sealed with AES-256-GCM under a random key kept in the login Keychain.
+//! Encrypted snapshots are recognized by their header, so loading them is transparent.
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use nix::unistd::{Uid, User};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Starts every encrypted snapshot, followed by base64 of the nonce and the sealed JSON.
+pub const HEADER: &str = "cutler-encrypted-snapshot:v1:";
+
+/// Keychain service under which the snapshot key is stored.
+pub const KEYCHAIN_SERVICE: &str = "io.github.machlit.cutler.snapshot";
+
+pub const KEY_LEN: usize = 32;
+
+// [snapshot] encrypt of the loaded config; unknown until a config is loaded
+const UNKNOWN: u8 = 0;
+const OFF: u8 = 1;
+const ON: u8 = 2;
+static ENCRYPT: AtomicU8 = AtomicU8::new(UNKNOWN);
+pub fn set_encryption(encrypt: bool) {
+    ENCRYPT.store(if encrypt { ON } else { OFF }, Ordering::SeqCst);
+}
+
+/// Whether snapshots should be encrypted, or None if no config was loaded in this run.
+pub fn wants_encryption() -> Option<bool> {
+    match ENCRYPT.load(Ordering::SeqCst) {
+        ON => Some(true),
+        OFF => Some(false),
+        _ => None,
+    }
+}
+
+pub fn is_encrypted(data: &str) -> bool {
+    data.starts_with(HEADER)
+}
+
+/// Helper for: encrypt(), decrypt()
+fn sealing_key(key: &[u8; KEY_LEN]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, key)
+        .ok()
+        .context("Invalid key")?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Encrypts the snapshot JSON under the given key, with a fresh random nonce.
+pub fn encrypt(plain: &str, key: &[u8; KEY_LEN]) -> Result<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .ok()
+        .context("Could not generate a nonce")?;
+
+    let mut sealed = plain.as_bytes().to_vec();
+    sealing_key(key)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut sealed,
+        )
+        .ok()
+        .context("Could not encrypt snapshot")?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend(sealed);
+
+    Ok(format!("{HEADER}{}\n", BASE64_STANDARD.encode(payload)))
+}
+
+/// Decrypts an encrypted snapshot back into its JSON.
+pub fn decrypt(data: &str, key: &[u8; KEY_LEN]) -> Result<String> {
+    let Some(encoded) = data.trim().strip_prefix(HEADER) else {
+        bail!("Snapshot is not encrypted")
+    };
+    let mut payload = BASE64_STANDARD
+        .decode(encoded)
+        .context("Encrypted snapshot is corrupt")?;
+    if payload.len() < NONCE_LEN {
+        bail!("Encrypted snapshot is corrupt")
+    }
+
+    let mut sealed = payload.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&payload)
+        .ok()
+        .context("Encrypted snapshot is corrupt")?;
+    let plain = sealing_key(key)?
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .ok()
+        .context(
+            "Could not decrypt snapshot; it was encrypted with another key or tampered with",
+        )?;
+
+    Ok(String::from_utf8(plain.to_vec())?)
+}
+
+/// Helper for: get_key(), delete_key()
+fn account() -> String {
+    User::from_uid(Uid::current())
+        .ok()
+        .flatten()
+        .map(|u| u.name)
+        .unwrap_or_else(|| "cutler".into())
+}
+
+/// Encrypts other state holding original values (e.g. of ephemeral applies) like the snapshot,
+/// if encryption is on.
+pub async fn seal(plain: String) -> Result<String> {
+    if wants_encryption() == Some(true) {
+        encrypt(&plain, &get_key(true).await?)
+    } else {
+        Ok(plain)
+    }
+}
+
+/// Decrypts state sealed by seal(), leaving plain state as it is.
+pub async fn unseal(data: String) -> Result<String> {
+    if is_encrypted(&data) {
+        decrypt(&data, &get_key(false).await?)
+    } else {
+        Ok(data)
+    }
+}
+
+/// Removes the snapshot key from the Keychain, returning whether there was one.
+pub async fn delete_key() -> Result<bool> {
+    let status = Command::new("security")
+        .args([
+            "delete-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            &account(),
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("Could not run `security`")?;
+
+    Ok(status.success())
+}
+
+/// Helper for: get_key()
+/// Reads the snapshot key of the account from the Keychain, if there is one.
+async fn find_key(account: &str) -> Result<Option<[u8; KEY_LEN]>> {
+    let out = Command::new("security")
+        .args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account,
+            "-w",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .context("Could not run `security`")?;
+
+    if !out.status.success() {
+        return Ok(None);
+    }
+
+    let key = BASE64_STANDARD
+        .decode(String::from_utf8_lossy(&out.stdout).trim())
+        .context("Snapshot key in the Keychain is corrupt")?;
+    key.try_into()
+        .map(Some)
+        .ok()
+        .context("Snapshot key in the Keychain is corrupt")
+}
+
+/// Builds the `security` command storing the snapshot key. It's fed through stdin of
+/// `security -i` (interactive mode, see `man security`), which reads one command per line, so
+/// the secret never shows up in argv where other users could read it.
+pub fn add_key_command(account: &str, secret: &str) -> String {
+    let command = shell_words::join([
+        "add-generic-password",
+        "-U",
+        "-s",
+        KEYCHAIN_SERVICE,
+        "-a",
+        account,
+        "-l",
+        "cutler snapshot key",
+        "-w",
+        secret,
+    ]);
+    format!("{command}\n")
+}
+
+/// Reads the snapshot key from the Keychain. With `create`, a missing key is generated and
+/// stored first.
+pub async fn get_key(create: bool) -> Result<[u8; KEY_LEN]> {
+    let account = account();
+    if let Some(key) = find_key(&account).await? {
+        return Ok(key);
+    }
+
+    if !create {
+        bail!(
+            "Snapshot is encrypted, but its key ({KEYCHAIN_SERVICE}) is missing from the Keychain."
+        )
+    }
+
+    let mut key = [0u8; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .ok()
+        .context("Could not generate a key")?;
+
+    let mut child = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Could not run `security`")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(add_key_command(&account, &BASE64_STANDARD.encode(key)).as_bytes())
+            .await?;
+    }
+    child.wait().await.context("Could not run `security`")?;
+
+    // interactive mode exits cleanly even if a command failed, so check what actually got stored
+    if find_key(&account).await? != Some(key) {
+        bail!("Could not store the snapshot key in the Keychain.")
+    }
+
+    Ok(key)
+}
@@ -2,5 +2,6 @@
 
 pub mod core;
 pub use core::Snapshot;
+pub mod crypt;
 pub mod path;
 pub use path::get_snapshot_path;
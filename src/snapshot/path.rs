@@ -1,17 +1,67 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use anyhow::{Context, Result};
-use std::{path::PathBuf, sync::OnceLock};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 use tokio::fs;
 
-use crate::config::path::get_config_path;
+use crate::config::core::{Config, SnapshotConfig};
+use crate::config::path::{get_config_path, get_default_config_path};
+use crate::plist::core::expand_path;
 
 /// The static snapshot path to use throughout each command run.
 /// This is to make sure that accidental variable changes don't alter the snapshot being written.
 static SNAP_PATH: OnceLock<PathBuf> = OnceLock::new();
 
-/// Returns the path to the snapshot file ($HOME/.cutler_snapshot).
-/// If for some reason the home directory cannot be detected, this function will return None.
+/// Resolves `[snapshot] path` of a config: `~/` is expanded, and relative paths are taken
+/// from the directory of the config file (or the config directory itself).
+pub fn resolve_snapshot_path(config_path: &Path, path: &str) -> PathBuf {
+    let path = expand_path(path);
+    if path.is_absolute() {
+        return path;
+    }
+
+    let base = if config_path.is_dir() {
+        Some(config_path)
+    } else {
+        config_path.parent()
+    };
+
+    base.map(|b| b.join(&path)).unwrap_or(path)
+}
+
+/// Helper for: configured_path()
+/// Only the `[snapshot]` table of a config layer; everything else is ignored.
+#[derive(Deserialize)]
+struct SnapshotLayer {
+    snapshot: Option<SnapshotConfig>,
+}
+
+/// Helper for: get_snapshot_path()
+/// Reads `[snapshot] path` from the config files, since the snapshot may be needed without the
+/// config being loaded (e.g. `unapply --key`). Later layers win, and unreadable layers are skipped.
+async fn configured_path(config_path: &Path) -> Option<PathBuf> {
+    let config = Config::new(config_path.to_path_buf());
+    if !config.is_loadable() {
+        return None;
+    }
+
+    let path = config
+        .read_layers()
+        .await
+        .ok()?
+        .into_iter()
+        .filter_map(|(_, data)| toml::from_str::<SnapshotLayer>(&data).ok()?.snapshot?.path)
+        .next_back()?;
+    Some(resolve_snapshot_path(config_path, &path))
+}
+
+/// Returns the path to the snapshot file, `[snapshot] path` if set or else next to the config.
+/// The default config uses `snapshot.json`; any other config (see `--config`) gets its own,
+/// e.g. `work.snapshot.json` for `work.toml`, so they never share state.
 /// It also initializes the path once, meaning that all future calls from the first one will
 /// return the same path despite of snapshot changes.
 pub async fn get_snapshot_path() -> Result<PathBuf> {
@@ -19,12 +69,28 @@ pub async fn get_snapshot_path() -> Result<PathBuf> {
         return Ok(cached);
     }
 
-    let config_parent = get_config_path()
-        .await?
+    let config_path = get_config_path().await?;
+
+    if let Some(path) = configured_path(&config_path).await {
+        SNAP_PATH.set(path.clone()).ok();
+        return Ok(path);
+    }
+    let config_parent = config_path
         .parent()
         .context("Could not determine config parent directory")?
         .to_path_buf();
 
+    if config_path != get_default_config_path().await? {
+        let stem = config_path
+            .file_stem()
+            .context("Could not determine config file name")?
+            .to_string_lossy();
+        let path = config_parent.join(format!("{stem}.snapshot.json"));
+
+        SNAP_PATH.set(path.clone()).ok();
+        return Ok(path);
+    }
+
     let old_home = dirs::home_dir().context("Could not determine home directory")?;
     let old_path = old_home.join(".cutler_snapshot");
     let new_path = config_parent.join("snapshot.json");
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Process exit codes, so that scripts around cutler can tell failures apart.
+//!
+//! Like the porcelain format, the numbers are part of cutler's public interface and must not
+//! change between releases. Run `cutler exit-codes` for the full list.
+
+use std::fmt;
+
+/// Why cutler exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    /// Any failure without a more specific code.
+    Failure = 1,
+    /// Invalid arguments; reported by clap itself.
+    Usage = 2,
+    /// The config is missing or could not be parsed.
+    Config = 3,
+    /// The config is locked.
+    Locked = 4,
+    /// `cutler status --exit-code` found the system drifted from the config.
+    Drift = 5,
+    /// Some settings, packages or commands failed while the rest were applied.
+    PartialApply = 6,
+    /// A download or request failed.
+    Network = 7,
+    /// The command was run with the wrong privileges, or sudo was refused.
+    Sudo = 8,
+}
+
+impl ExitCode {
+    /// Every exit code, in ascending order.
+    pub const ALL: [ExitCode; 9] = [
+        ExitCode::Success,
+        ExitCode::Failure,
+        ExitCode::Usage,
+        ExitCode::Config,
+        ExitCode::Locked,
+        ExitCode::Drift,
+        ExitCode::PartialApply,
+        ExitCode::Network,
+        ExitCode::Sudo,
+    ];
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Short, stable name of the exit code, e.g. `partial-apply`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExitCode::Success => "success",
+            ExitCode::Failure => "failure",
+            ExitCode::Usage => "usage",
+            ExitCode::Config => "config",
+            ExitCode::Locked => "locked",
+            ExitCode::Drift => "drift",
+            ExitCode::PartialApply => "partial-apply",
+            ExitCode::Network => "network",
+            ExitCode::Sudo => "sudo",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ExitCode::Success => "Everything went fine.",
+            ExitCode::Failure => "Any other failure.",
+            ExitCode::Usage => "Invalid command-line arguments.",
+            ExitCode::Config => "The config is missing or could not be parsed.",
+            ExitCode::Locked => "The config is locked.",
+            ExitCode::Drift => "The system drifted from the config (status --exit-code).",
+            ExitCode::PartialApply => "Some items failed while the rest were applied.",
+            ExitCode::Network => "A download or request failed.",
+            ExitCode::Sudo => "Wrong privileges for the command, or sudo was refused.",
+        }
+    }
+}
+
+/// An error which ends cutler with a specific exit code.
+#[derive(Debug)]
+pub struct CodedError {
+    pub code: ExitCode,
+    pub message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+/// Creates an error ending cutler with `code`, e.g. `return Err(coded(ExitCode::Locked, "..."))`.
+pub fn coded(code: ExitCode, message: impl Into<String>) -> anyhow::Error {
+    anyhow::Error::new(CodedError {
+        code,
+        message: message.into(),
+    })
+}
+
+/// Decides the exit code for an error: an explicit code anywhere in its chain wins, otherwise
+/// the code is derived from the type of its causes.
+pub fn exit_code_for(err: &anyhow::Error) -> ExitCode {
+    if let Some(coded) = err.chain().find_map(|e| e.downcast_ref::<CodedError>()) {
+        return coded.code;
+    }
+
+    for cause in err.chain() {
+        if cause.is::<toml::de::Error>() || cause.is::<toml_edit::TomlError>() {
+            return ExitCode::Config;
+        }
+        if cause.is::<reqwest::Error>() {
+            return ExitCode::Network;
+        }
+    }
+
+    ExitCode::Failure
+}
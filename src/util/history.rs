@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Local history of apply, unapply and reset runs, for `cutler history`.
+//!
+//! Every run appends one JSON line to `history.jsonl` next to the config, listing the settings
+//! it changed. Changes are collected into a process-wide list while a command runs, like the
+//! summary tallies. Only the latest MAX_RUNS runs are kept.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+use crate::cli::CALLER_ENV;
+use crate::domains::convert::civil_from_days;
+use crate::log_warn;
+use crate::snapshot::crypt::wants_encryption;
+use crate::util::{sha::get_digest, summary::Summary};
+
+/// How many runs are kept.
+const MAX_RUNS: usize = 500;
+
+/// Stands in for values while snapshots are encrypted, which the history would leak otherwise.
+pub const HIDDEN_VALUE: &str = "(hidden)";
+
+/// A setting changed by a run. Values are displayed as in logs; None means unset.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Change {
+    pub domain: String,
+    pub key: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// A recorded run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Run {
+    pub id: u64,
+    /// Unix timestamp of the end of the run.
+    pub timestamp: u64,
+    /// The subcommand, e.g. `apply`.
+    pub command: String,
+    /// The full command line, e.g. `cutler apply --no-cmd`.
+    pub args: String,
+    /// The tool which ran cutler, from $CUTLER_CALLER.
+    pub caller: Option<String>,
+    pub changed: usize,
+    pub failed: usize,
+    pub elapsed_secs: f64,
+    /// Digest of the config at the time of the run.
+    pub digest: String,
+    pub changes: Vec<Change>,
+}
+
+static CHANGES: LazyLock<Mutex<Vec<Change>>> = LazyLock::new(Default::default);
+
+/// Records a changed setting for the history of the current run.
+/// With `[snapshot] encrypt = true`, its values are left out.
+pub fn add_change(mut change: Change) {
+    if wants_encryption() == Some(true) {
+        let hide = |value: Option<String>| value.map(|_| HIDDEN_VALUE.to_string());
+        change.from = hide(change.from);
+        change.to = hide(change.to);
+    }
+
+    CHANGES.lock().unwrap().push(change);
+}
+
+/// Returns the path to the history, next to the config.
+pub fn get_history_path(config_path: &Path) -> Result<PathBuf> {
+    let parent = config_path
+        .parent()
+        .context("Could not determine config parent directory")?;
+
+    Ok(parent.join("history.jsonl"))
+}
+
+/// Splits a unix timestamp into its UTC date and time: year, month, day, hour, minute, second.
+pub fn utc_datetime(secs: u64) -> (i64, i64, i64, i64, i64, i64) {
+    let secs = secs as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+
+    (year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// Formats a unix timestamp as a UTC date and time, e.g. `2025-03-04 13:37 UTC`.
+pub fn format_timestamp(secs: u64) -> String {
+    let (year, month, day, hour, minute, _) = utc_datetime(secs);
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+}
+
+/// Loads every recorded run, oldest first. Broken lines are skipped.
+pub async fn load(config_path: &Path) -> Vec<Run> {
+    let Ok(path) = get_history_path(config_path) else {
+        return Vec::new();
+    };
+    let Ok(data) = fs::read_to_string(path).await else {
+        return Vec::new();
+    };
+
+    data.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Helper for: record()
+async fn append(config_path: &Path, command: &str, summary: &Summary) -> Result<()> {
+    let mut runs = load(config_path).await;
+    let changes = std::mem::take(&mut *CHANGES.lock().unwrap());
+
+    runs.push(Run {
+        id: runs.last().map_or(1, |r| r.id + 1),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        command: command.to_string(),
+        args: std::iter::once("cutler".to_string())
+            .chain(env::args().skip(1))
+            .map(|a| shell_words::quote(&a).into_owned())
+            .collect::<Vec<_>>()
+            .join(" "),
+        caller: env::var(CALLER_ENV).ok().filter(|c| !c.is_empty()),
+        changed: changes.len(),
+        failed: summary.failed.len(),
+        elapsed_secs: summary.elapsed_secs,
+        digest: get_digest(config_path.to_path_buf()).unwrap_or_default(),
+        changes,
+    });
+
+    let skip = runs.len().saturating_sub(MAX_RUNS);
+    let mut data = String::new();
+    for run in &runs[skip..] {
+        data.push_str(&serde_json::to_string(run)?);
+        data.push('\n');
+    }
+    fs::write(get_history_path(config_path)?, data).await?;
+
+    Ok(())
+}
+
+/// Records the run which just finished, along with the changes collected during it.
+/// Failures are only warned about, since the run itself is done.
+pub async fn record(config_path: &Path, command: &str, summary: &Summary) {
+    if let Err(e) = append(config_path, command, summary).await {
+        log_warn!("Could not record run in history: {e}");
+    }
+}
@@ -1,20 +1,43 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use dialoguer::Confirm;
-use tokio::process::Command;
+use dialoguer::{Confirm, Input, Password, console::Term};
+use nix::unistd::{Uid, User};
+use std::path::Path;
+use std::time::Duration;
+use tokio::{process::Command, time::sleep};
 
 use crate::{
-    cli::atomic::{should_accept_all, should_dry_run, should_not_restart_services},
+    cli::atomic::{
+        should_accept_all, should_dry_run, should_not_prompt, should_not_restart_services,
+    },
     log_dry, log_err, log_info, log_prompt, log_warn,
 };
-use anyhow::Result;
+use anyhow::{Result, bail};
 
 /// Ask "Y/N?"; returns true if accept_all is set or the user types "y" or "Y"
+/// Returns false without asking if prompts are disabled.
 pub fn confirm(prompt: &str) -> bool {
     if should_accept_all() {
         log_prompt!("{prompt} (auto-accepted)");
         return true;
     }
+    if should_not_prompt() {
+        log_prompt!("{prompt} (declined, prompts are disabled)");
+        return false;
+    }
+
+    Confirm::new()
+        .with_prompt(prompt)
+        .interact()
+        .unwrap_or_default()
+}
+
+/// Ask "Y/N?" even if accept_all is set; returns false when there's nobody to answer.
+pub fn confirm_always(prompt: &str) -> bool {
+    if should_not_prompt() {
+        log_prompt!("{prompt} (declined, prompts are disabled)");
+        return false;
+    }
 
     Confirm::new()
         .with_prompt(prompt)
@@ -22,6 +45,21 @@ pub fn confirm(prompt: &str) -> bool {
         .unwrap_or_default()
 }
 
+/// Ask for a line of text, hiding what's typed with `secret`. Like `confirm_always()`, this
+/// isn't answered by accept_all; returns None when there's nobody to answer.
+pub fn ask(prompt: &str, secret: bool) -> Option<String> {
+    if should_not_prompt() {
+        log_prompt!("{prompt} (declined, prompts are disabled)");
+        return None;
+    }
+
+    if secret {
+        Password::new().with_prompt(prompt).interact().ok()
+    } else {
+        Input::new().with_prompt(prompt).interact_text().ok()
+    }
+}
+
 /// Run the `open` shell command on a given argument.
 pub async fn open(arg: &str) -> Result<()> {
     let _ = Command::new("open")
@@ -33,6 +71,54 @@ pub async fn open(arg: &str) -> Result<()> {
     Ok(())
 }
 
+/// Open a file in the given editor command (e.g. the value of $EDITOR) and wait for it to exit.
+pub async fn open_in_editor(editor: &str, path: &Path) -> Result<()> {
+    // split the editor command into program and args, respecting quoted arguments
+    let (program, args) = match shell_words::split(editor) {
+        Ok(mut parts) if !parts.is_empty() => {
+            let prog = parts.remove(0);
+            (prog, parts)
+        }
+        Ok(_) => {
+            bail!("EDITOR environment variable is empty.");
+        }
+        Err(e) => {
+            bail!("Failed to parse EDITOR: {}", e);
+        }
+    };
+
+    log_info!("Executing: {} {:?}", editor, path);
+
+    match Command::new(program).args(&args).arg(path).status().await {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => {
+            bail!("Editor exited with status: {}", s);
+        }
+        Err(e) => {
+            bail!("Failed to launch editor: {}", e);
+        }
+    }
+}
+
+/// How long to give launchd to bring cfprefsd back after flushing it.
+const FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+/// Restarts the user's cfprefsd, so that cached preferences are dropped and read from disk again.
+pub async fn flush_prefs() {
+    let mut cmd = Command::new("killall");
+    // only the user's own instance, the system one belongs to root
+    if let Ok(Some(user)) = User::from_uid(Uid::current()) {
+        cmd.args(["-u", &user.name]);
+    }
+
+    match cmd.arg("cfprefsd").output().await {
+        Ok(out) if out.status.success() => log_info!("cfprefsd flushed"),
+        _ => log_warn!("Could not flush cfprefsd; values may still be served from its cache."),
+    }
+
+    sleep(FLUSH_DELAY).await;
+}
+
 /// Restart Finder, Dock, SystemUIServer so defaults take effect.
 pub async fn restart_services() {
     if should_not_restart_services() {
@@ -77,3 +163,24 @@ pub async fn restart_services() {
         log_warn!("Being quick with commands can cause your computer to run out of breath.");
     }
 }
+
+/// Returns the width of the terminal in columns, or None if neither stdout nor stderr is one.
+pub fn terminal_width() -> Option<usize> {
+    Term::stdout()
+        .size_checked()
+        .or_else(|| Term::stderr().size_checked())
+        .map(|(_, cols)| cols as usize)
+}
+
+/// Shortens text to at most `max` characters, ending it with `…` if anything was cut off.
+/// Line breaks are shown as `\n`, so that the text stays on one line.
+pub fn truncate(text: &str, max: usize) -> String {
+    let text = text.replace('\n', "\\n");
+    if text.chars().count() <= max {
+        return text;
+    }
+
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
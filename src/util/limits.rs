@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Global concurrency limit (`--jobs`).
+//!
+//! Concurrent work (external commands, brew fetches, preference reads) takes a permit from a
+//! single shared semaphore, so cutler never runs more than `jobs()` things at once.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::available_parallelism;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// 0 means unset
+static CLI_JOBS: AtomicUsize = AtomicUsize::new(0);
+static CONFIG_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+static SEMAPHORE: OnceLock<Arc<Semaphore>> = OnceLock::new();
+
+/// Sets the limit passed through `--jobs`.
+pub fn set_jobs(jobs: Option<usize>) {
+    CLI_JOBS.store(jobs.unwrap_or_default(), Ordering::SeqCst);
+}
+
+/// Sets the limit from the top-level `jobs` of the config, used unless `--jobs` is passed.
+pub fn set_config_jobs(jobs: Option<usize>) {
+    CONFIG_JOBS.store(jobs.unwrap_or_default(), Ordering::SeqCst);
+}
+
+/// The limit passed through `--jobs`, if any.
+pub fn cli_jobs() -> Option<usize> {
+    Some(CLI_JOBS.load(Ordering::SeqCst)).filter(|&n| n > 0)
+}
+
+/// The effective limit: `--jobs`, then the config, then the number of CPUs.
+pub fn jobs() -> usize {
+    cli_jobs()
+        .or_else(|| Some(CONFIG_JOBS.load(Ordering::SeqCst)).filter(|&n| n > 0))
+        .unwrap_or_else(|| available_parallelism().map_or(4, |n| n.get()))
+}
+
+/// Waits for a slot in the shared limit. The slot is freed when the permit is dropped.
+///
+/// The semaphore is sized on first use, so set the limits before spawning anything.
+pub async fn acquire() -> OwnedSemaphorePermit {
+    SEMAPHORE
+        .get_or_init(|| Arc::new(Semaphore::new(jobs())))
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("the jobs semaphore is never closed")
+}
@@ -4,7 +4,21 @@
 //!
 //! Use the log_*! macros for pretty-printing text inside cutler.
 
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::{
+        OnceLock, RwLock,
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+    },
+};
+
+use clap::ValueEnum;
+use regex::Regex;
+
 use crate::cli::atomic::{should_be_quiet, should_be_verbose};
+use crate::config::core::Theme;
+use crate::log_warn;
 
 // ANSI color codes.
 pub const RED: &str = "\x1b[31m";
@@ -16,6 +30,222 @@ pub const CYAN: &str = "\x1b[36m";
 pub const RESET: &str = "\x1b[0m";
 pub const BOLD: &str = "\x1b[1m";
 
+/// Choices for `--color`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, ValueEnum)]
+pub enum ColorMode {
+    /// Color only when writing to a terminal and NO_COLOR is unset.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// --color
+static COLOR_MODE: AtomicU8 = AtomicU8::new(ColorMode::Auto as u8);
+pub fn set_color_mode(mode: ColorMode) {
+    COLOR_MODE.store(mode as u8, Ordering::SeqCst);
+}
+
+/// Decides whether output written to stdout (or stderr) should be colored.
+pub fn should_color(stderr: bool) -> bool {
+    match COLOR_MODE.load(Ordering::SeqCst) {
+        m if m == ColorMode::Always as u8 => true,
+        m if m == ColorMode::Never as u8 => false,
+        _ => {
+            // https://no-color.org: any non-empty value disables color
+            if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                return false;
+            }
+
+            if stderr {
+                io::stderr().is_terminal()
+            } else {
+                io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Choices for `--log-style`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, ValueEnum)]
+pub enum LogStyle {
+    /// GitHub Actions annotations when GITHUB_ACTIONS=true, plain logs otherwise.
+    #[default]
+    Auto,
+    Plain,
+    /// Errors and warnings as `::error::`/`::warning::` annotations, sections as groups.
+    Github,
+}
+
+// --log-style
+static LOG_STYLE: AtomicU8 = AtomicU8::new(LogStyle::Auto as u8);
+pub fn set_log_style(style: LogStyle) {
+    LOG_STYLE.store(style as u8, Ordering::SeqCst);
+}
+
+/// Decides whether logs should be written as GitHub Actions workflow commands.
+pub fn should_annotate() -> bool {
+    match LOG_STYLE.load(Ordering::SeqCst) {
+        s if s == LogStyle::Github as u8 => true,
+        s if s == LogStyle::Plain as u8 => false,
+        _ => env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true"),
+    }
+}
+
+/// Escapes a message for use in a workflow command, which must stay on one line.
+pub fn escape_annotation(msg: &str) -> String {
+    strip_ansi(msg)
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Stands in for secrets in logs and plans.
+pub const MASK: &str = "********";
+
+/// Secrets shorter than this are too likely to show up by chance, so they're not masked.
+const MIN_SECRET_LEN: usize = 4;
+
+// values of secret variables, masked in everything logged from now on
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Masks a value wherever it appears in logs, e.g. the value of a `secret = true` variable.
+pub fn add_secret(value: &str) {
+    let value = value.trim();
+    if value.len() < MIN_SECRET_LEN {
+        return;
+    }
+
+    if let Ok(mut secrets) = SECRETS.write()
+        && !secrets.iter().any(|s| s == value)
+    {
+        secrets.push(value.to_string());
+        // longest first, so that a secret containing another one is masked as a whole
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+}
+
+/// Replaces every known secret in the text with `MASK`.
+pub fn redact(text: &str) -> String {
+    let Ok(secrets) = SECRETS.read() else {
+        return text.to_string();
+    };
+
+    secrets
+        .iter()
+        .fold(text.to_string(), |text, secret| text.replace(secret, MASK))
+}
+
+// only errors are printed, for commands whose whole output is meant for scripts
+static SILENT: AtomicBool = AtomicBool::new(false);
+pub fn set_silent(value: bool) {
+    SILENT.store(value, Ordering::SeqCst);
+}
+
+// stdout carries output for other programs, e.g. `status --porcelain` or `--json`
+static PORCELAIN: AtomicBool = AtomicBool::new(false);
+pub fn set_porcelain(value: bool) {
+    PORCELAIN.store(value, Ordering::SeqCst);
+}
+pub fn is_porcelain() -> bool {
+    PORCELAIN.load(Ordering::SeqCst)
+}
+
+// GitHub doesn't nest groups, so only the outermost one is printed
+static GROUP_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// A collapsible section of log output, closed when dropped.
+pub struct Group {
+    printed: bool,
+}
+
+/// Opens a collapsible section titled `title`, e.g. `let _group = group("Homebrew");`.
+/// Only printed with `--log-style github`, and never in quiet mode.
+pub fn group(title: &str) -> Group {
+    let outermost = GROUP_DEPTH.fetch_add(1, Ordering::SeqCst) == 0;
+    let printed = outermost && should_annotate() && !should_be_quiet();
+
+    if printed && is_porcelain() {
+        eprintln!("::group::{}", escape_annotation(title));
+    } else if printed {
+        println!("::group::{}", escape_annotation(title));
+    }
+    Group { printed }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        GROUP_DEPTH.fetch_sub(1, Ordering::SeqCst);
+
+        if self.printed && is_porcelain() {
+            eprintln!("::endgroup::");
+        } else if self.printed {
+            println!("::endgroup::");
+        }
+    }
+}
+
+/// Removes ANSI escape codes from text.
+pub fn strip_ansi(text: &str) -> String {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let ansi = ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
+    ansi.replace_all(text, "").into_owned()
+}
+
+/// Strips colors from text meant for stdout, unless coloring is enabled.
+pub fn paint(text: &str) -> String {
+    if should_color(false) {
+        text.to_string()
+    } else {
+        strip_ansi(text)
+    }
+}
+
+/// Resolves a color name (or 256-color number) from [theme] into its ANSI code.
+pub fn color_code(name: &str) -> Option<String> {
+    let code = match name.trim().to_lowercase().as_str() {
+        "black" => "30",
+        "red" => "31",
+        "green" => "32",
+        "yellow" => "33",
+        "blue" => "34",
+        "magenta" | "pink" => "35",
+        "cyan" => "36",
+        "white" => "37",
+        "orange" => "38;5;208",
+        "none" => return Some(String::new()),
+        other => return other.parse::<u8>().ok().map(|n| format!("\x1b[38;5;{n}m")),
+    };
+
+    Some(format!("\x1b[{code}m"))
+}
+
+static THEME: RwLock<Option<Theme>> = RwLock::new(None);
+
+/// Overrides the colors of log tags. Unknown colors are warned about and ignored.
+pub fn set_theme(theme: Option<Theme>) {
+    if let Some(theme) = &theme {
+        let colors = [
+            &theme.error,
+            &theme.warning,
+            &theme.info,
+            &theme.exec,
+            &theme.prompt,
+            &theme.dry,
+        ];
+        for name in colors.into_iter().flatten() {
+            if color_code(name).is_none() {
+                log_warn!("Unknown color in [theme]: {name}");
+            }
+        }
+    }
+
+    if let Ok(mut current) = THEME.write() {
+        *current = theme;
+    }
+}
+
 #[doc(hidden)]
 #[derive(PartialEq)]
 pub enum LogLevel {
@@ -28,14 +258,47 @@ pub enum LogLevel {
     Fruitful, // 🍎
 }
 
+/// Helper for: _print_log()
+/// Returns the [theme] color of a log level, if overridden.
+fn themed_color(level: &LogLevel) -> Option<String> {
+    let theme = THEME.read().ok()?;
+    let theme = theme.as_ref()?;
+
+    let name = match level {
+        LogLevel::Error => &theme.error,
+        LogLevel::Warning => &theme.warning,
+        LogLevel::Info => &theme.info,
+        LogLevel::Exec => &theme.exec,
+        LogLevel::Prompt => &theme.prompt,
+        LogLevel::Dry => &theme.dry,
+        LogLevel::Fruitful => &None,
+    };
+
+    color_code(name.as_deref()?)
+}
+
 #[doc(hidden)]
 pub fn _print_log(level: LogLevel, msg: &str) {
+    let msg = &redact(msg);
+
     if (should_be_quiet() && level != LogLevel::Error && level != LogLevel::Warning)
+        || (SILENT.load(Ordering::SeqCst) && level != LogLevel::Error)
         || (level == LogLevel::Info && !should_be_verbose())
     {
         return;
     }
 
+    // GitHub reads workflow commands from stderr too, which keeps them out of piped output
+    if should_annotate() && (level == LogLevel::Error || level == LogLevel::Warning) {
+        let command = if level == LogLevel::Error {
+            "error"
+        } else {
+            "warning"
+        };
+        eprintln!("::{command}::{}", escape_annotation(msg));
+        return;
+    }
+
     let (tag, color) = match level {
         LogLevel::Error => ("ERR  ", RED),
         LogLevel::Warning => ("WARN ", ORANGE),
@@ -45,6 +308,7 @@ pub fn _print_log(level: LogLevel, msg: &str) {
         LogLevel::Dry => ("DRY  ", YELLOW),
         LogLevel::Fruitful => ("🍎", ""),
     };
+    let color = themed_color(&level).unwrap_or_else(|| color.to_string());
 
     let line = if level == LogLevel::Fruitful {
         format!("{tag} {msg}")
@@ -52,7 +316,14 @@ pub fn _print_log(level: LogLevel, msg: &str) {
         format!("{color}{tag}{RESET} {msg}")
     };
 
-    if level == LogLevel::Error || level == LogLevel::Warning {
+    let stderr = level == LogLevel::Error || level == LogLevel::Warning;
+    let line = if should_color(stderr) {
+        line
+    } else {
+        strip_ansi(&line)
+    };
+
+    if stderr || is_porcelain() {
         eprintln!("{line}");
     } else {
         println!("{line}");
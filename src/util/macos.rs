@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! macOS versions, for settings which only exist on some releases.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{fmt, process::Command, str::FromStr, sync::OnceLock};
+
+/// Release names of macOS versions, so that configs can say `sonoma` instead of `14`.
+const NAMES: [(&str, u32, u32); 11] = [
+    ("el_capitan", 10, 11),
+    ("sierra", 10, 12),
+    ("high_sierra", 10, 13),
+    ("mojave", 10, 14),
+    ("catalina", 10, 15),
+    ("big_sur", 11, 0),
+    ("monterey", 12, 0),
+    ("ventura", 13, 0),
+    ("sonoma", 14, 0),
+    ("sequoia", 15, 0),
+    ("tahoe", 26, 0),
+];
+
+/// A macOS version, e.g. `14.2`. Patch versions are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacosVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl MacosVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Release name of the version, e.g. `Big Sur`.
+    pub fn name(&self) -> Option<String> {
+        NAMES
+            .iter()
+            .find(|(_, major, minor)| {
+                *major == self.major && (*major >= 11 || *minor == self.minor)
+            })
+            .map(|(name, ..)| {
+                name.split('_')
+                    .map(|word| word[..1].to_uppercase() + &word[1..])
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+    }
+}
+
+impl FromStr for MacosVersion {
+    type Err = String;
+
+    /// Parses `14`, `14.2`, `10.15.7` or a release name like `sonoma` or `big-sur`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let name = s.replace(['-', ' '], "_");
+        if let Some((_, major, minor)) = NAMES.iter().find(|(n, ..)| *n == name) {
+            return Ok(Self::new(*major, *minor));
+        }
+
+        let mut parts = s.split('.').map(str::parse::<u32>);
+        match (parts.next(), parts.next()) {
+            (Some(Ok(major)), None) => Ok(Self::new(major, 0)),
+            (Some(Ok(major)), Some(Ok(minor))) => Ok(Self::new(major, minor)),
+            _ => Err(format!(
+                "invalid macOS version `{s}`, expected e.g. `14`, `10.15` or `sonoma`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MacosVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.major >= 11 && self.minor == 0 {
+            write!(f, "macOS {}", self.major)?;
+        } else {
+            write!(f, "macOS {}.{}", self.major, self.minor)?;
+        }
+        match self.name() {
+            Some(name) => write!(f, " {name}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Serialize for MacosVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}.{}", self.major, self.minor))
+    }
+}
+
+impl<'de> Deserialize<'de> for MacosVersion {
+    /// Accepts strings like `"14"`, `"10.15"` or `"sonoma"` as well as whole numbers like `14`.
+    /// Floats are rejected since TOML reads `10.10` as `10.1`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = MacosVersion;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a macOS version like 14, \"10.15\" or \"sonoma\"")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Err(E::custom(format!(
+                    "{v} is a number; quote the version (e.g. \"10.10\"), as TOML reads 10.10 as 10.1"
+                )))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// The macOS versions a setting or command is meant for, from `min_macos` and `max_macos`.
+/// Both ends are inclusive, and a bare major version like `14` includes all of its updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VersionRange {
+    pub min: Option<MacosVersion>,
+    pub max: Option<MacosVersion>,
+}
+
+impl VersionRange {
+    pub fn is_empty(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+
+    /// Whether the range includes the version. An unknown version is always included, so that
+    /// nothing is skipped when the running macOS can't be determined.
+    pub fn contains(&self, version: Option<MacosVersion>) -> bool {
+        let Some(version) = version else {
+            return true;
+        };
+
+        let above_max = self.max.is_some_and(|max| {
+            if max.major >= 11 && max.minor == 0 {
+                version.major > max.major
+            } else {
+                version > max
+            }
+        });
+
+        !above_max && self.min.is_none_or(|min| version >= min)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => write!(f, "{min} to {max}"),
+            (Some(min), None) => write!(f, "{min} and later"),
+            (None, Some(max)) => write!(f, "up to {max}"),
+            (None, None) => write!(f, "any macOS"),
+        }
+    }
+}
+
+/// Returns the version of the running macOS, or None when it can't be determined.
+/// It's only looked up once per run.
+pub fn current_version() -> Option<MacosVersion> {
+    static CURRENT: OnceLock<Option<MacosVersion>> = OnceLock::new();
+
+    *CURRENT.get_or_init(|| {
+        let out = Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()?;
+
+        if !out.status.success() {
+            return None;
+        }
+
+        String::from_utf8_lossy(&out.stdout).parse().ok()
+    })
+}
@@ -1,6 +1,16 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+pub mod exit;
+pub mod history;
 pub mod io;
+pub mod limits;
 pub mod logging;
+pub mod macos;
+pub mod net;
+pub mod notify;
+pub mod porcelain;
+pub mod release;
 pub mod sha;
+pub mod status_cache;
 pub mod sudo;
+pub mod summary;
@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Shared helpers for everything which goes over the network.
+//!
+//! All requests go through [`client`] and [`send`], so that `--offline`, the proxy, the
+//! timeout and retries from `[network]` apply to every one of them alike.
+
+use anyhow::{Context, Result};
+use reqwest::{Client, Proxy, RequestBuilder, Response, StatusCode};
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::autosync::parse_interval;
+use crate::cli::atomic::should_stay_offline;
+use crate::config::core::Network;
+use crate::util::exit::{ExitCode, coded};
+use crate::{log_info, log_warn};
+
+/// Retries of a failed request, unless set in `[network]`.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Time limit of a single request, unless set in `[network]`.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wait before the first retry, doubled after every further attempt.
+const BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest wait between two attempts, however many retries are configured.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+static NETWORK: RwLock<Option<Network>> = RwLock::new(None);
+
+/// Sets the `[network]` table of the config.
+pub fn set_network(network: Option<Network>) {
+    if let Ok(mut lock) = NETWORK.write() {
+        *lock = network;
+    }
+}
+
+/// Helper for: proxy(), retries(), timeout()
+fn network() -> Network {
+    NETWORK
+        .read()
+        .ok()
+        .and_then(|n| n.clone())
+        .unwrap_or_default()
+}
+
+/// The proxy from the config, if any.
+pub fn proxy() -> Option<String> {
+    network().proxy.filter(|p| !p.trim().is_empty())
+}
+
+/// How often failed requests are retried.
+pub fn retries() -> u32 {
+    network().retries.unwrap_or(DEFAULT_RETRIES)
+}
+
+/// Time limit of a single request.
+pub fn timeout() -> Duration {
+    match network().timeout.as_deref().map(parse_interval) {
+        Some(Ok(timeout)) if !timeout.is_zero() => timeout,
+        Some(Ok(_)) => DEFAULT_TIMEOUT,
+        Some(Err(e)) => {
+            log_warn!("Ignoring `timeout` in [network]: {e}");
+            DEFAULT_TIMEOUT
+        }
+        None => DEFAULT_TIMEOUT,
+    }
+}
+
+/// Fails right away if cutler runs with `--offline`, naming what needed the network,
+/// e.g. `ensure_online("fetch the remote config")?`.
+pub fn ensure_online(what: &str) -> Result<()> {
+    if should_stay_offline() {
+        return Err(coded(
+            ExitCode::Network,
+            format!("Cannot {what} while offline (--offline)."),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Builds an HTTP client for all of cutler's requests.
+/// The proxy from the config wins; otherwise reqwest picks up the usual proxy variables.
+pub fn client(user_agent: &str) -> Result<Client> {
+    let mut builder = Client::builder().user_agent(user_agent).timeout(timeout());
+
+    if let Some(proxy) = proxy() {
+        builder = builder.proxy(
+            Proxy::all(&proxy).with_context(|| format!("Invalid proxy in [network]: {proxy}"))?,
+        );
+    }
+
+    builder.build().context("Failed to build HTTP client")
+}
+
+/// Helper for: is_transient(), is_transient_message()
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Helper for: is_transient(), is_transient_message()
+/// Whether an error message is about TLS, like an untrusted certificate, which fails the same
+/// way on every attempt.
+fn is_tls_failure(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["certificate", "tls", "handshake"]
+        .iter()
+        .any(|word| message.contains(word))
+}
+
+/// Whether a failed request is worth another try: connection problems (including DNS),
+/// timeouts, rate limits and server errors. Other responses and TLS failures aren't.
+pub fn is_transient(err: &reqwest::Error) -> bool {
+    if let Some(status) = err.status() {
+        return is_transient_status(status);
+    }
+
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        if is_tls_failure(&cause.to_string()) {
+            return false;
+        }
+        source = cause.source();
+    }
+
+    err.is_connect() || err.is_timeout()
+}
+
+/// Like is_transient(), for failures only known by their message, e.g.
+/// "Download request failed with status: 404".
+pub fn is_transient_message(message: &str) -> bool {
+    let status = message
+        .split_once("status: ")
+        .and_then(|(_, rest)| rest.get(..3))
+        .and_then(|code| code.parse().ok())
+        .and_then(|code| StatusCode::from_u16(code).ok());
+
+    match status {
+        Some(status) => is_transient_status(status),
+        None => !is_tls_failure(message),
+    }
+}
+
+/// Runs `op` again while it fails with a transient error, waiting twice as long after every
+/// attempt, for at most [`retries()`] retries.
+pub async fn retry<T, E, F, Fut>(
+    what: &str,
+    transient: impl Fn(&E) -> bool,
+    mut op: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let retries = retries();
+    let mut delay = BACKOFF;
+    let mut attempt = 0;
+
+    loop {
+        match op().await {
+            Err(e) if attempt < retries && transient(&e) => {
+                attempt += 1;
+                log_info!(
+                    "Could not {what} ({e}), retrying in {:.1}s ({attempt}/{retries})...",
+                    delay.as_secs_f32()
+                );
+                sleep(delay).await;
+                delay = delay.saturating_mul(2).min(MAX_BACKOFF);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Sends a request, retrying it on transient failures. Once retries run out, server errors
+/// and rate limits are returned as errors; any other status is left to the caller.
+pub async fn send(what: &str, req: RequestBuilder) -> Result<Response> {
+    retry(what, is_transient, || async {
+        let resp = req
+            .try_clone()
+            .expect("requests without streaming bodies can be cloned")
+            .send()
+            .await?;
+
+        match resp.error_for_status_ref() {
+            Err(e) if is_transient(&e) => Err(e),
+            _ => Ok(resp),
+        }
+    })
+    .await
+    .with_context(|| format!("Could not {what}"))
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use tokio::process::Command;
+
+use crate::{cli::atomic::should_dry_run, config::core::Config, log_dry, log_warn};
+
+/// The kinds of events which can trigger a notification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotifyEvent {
+    /// The system or the remote config drifted from the local config.
+    Drift,
+    /// A remote config was fetched and applied by autosync.
+    Sync,
+    /// `cutler watch` re-applied the config after drift.
+    Heal,
+}
+
+/// Checks if the config opted into notifications for the given event.
+pub fn is_enabled(config: &Config, event: NotifyEvent) -> bool {
+    let Some(notify) = &config.notify else {
+        return false;
+    };
+
+    match event {
+        NotifyEvent::Drift => notify.drift,
+        NotifyEvent::Sync => notify.sync,
+        NotifyEvent::Heal => notify.heal,
+    }
+    .unwrap_or_default()
+}
+
+/// Helper for: notify()
+/// Escapes text for use inside an AppleScript string literal.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Posts a macOS notification through `osascript`.
+/// Failures are only logged since notifications are never critical.
+pub async fn notify(title: &str, message: &str) {
+    if should_dry_run() {
+        log_dry!("Would notify: {title}: {message}");
+        return;
+    }
+
+    let script = format!(
+        "display notification \"{}\" with title \"{}\"",
+        escape(message),
+        escape(title)
+    );
+
+    match Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .await
+    {
+        Ok(out) if out.status.success() => {}
+        Ok(out) => log_warn!(
+            "Could not post notification: osascript exited with {}",
+            out.status
+        ),
+        Err(e) => log_warn!("Could not post notification: {e}"),
+    }
+}
+
+/// Posts a notification for the given event, only if enabled in the config.
+pub async fn notify_event(config: &Config, event: NotifyEvent, message: &str) {
+    if is_enabled(config, event) {
+        notify("cutler", message).await;
+    }
+}
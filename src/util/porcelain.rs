@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Stable, line-oriented output of `cutler status --porcelain`.
+//!
+//! Unlike the log_*! output, this format is part of cutler's public interface and must not
+//! change between releases. Every record has exactly five fields:
+//! `domain`, `key`, `state`, `current` and `desired`.
+
+/// The state of a single setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    /// The system value matches the config.
+    Match,
+    /// The system value differs from the config.
+    Drift,
+    /// The key isn't set on the system at all.
+    Missing,
+    /// The value is forced by a configuration profile and differs from the config.
+    Managed,
+}
+
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Match => "match",
+            State::Drift => "drift",
+            State::Missing => "missing",
+            State::Managed => "managed",
+        }
+    }
+}
+
+/// Helper for: record()
+/// Escapes the characters which would break the tab-separated format.
+fn escape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+
+    for c in field.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Formats a single record.
+///
+/// By default, fields are escaped and separated by tabs, and the record ends with a newline.
+/// With `nul`, fields are written verbatim and each of them is terminated by a NUL byte.
+pub fn record(
+    domain: &str,
+    key: &str,
+    state: State,
+    current: &str,
+    desired: &str,
+    nul: bool,
+) -> String {
+    let fields = [domain, key, state.as_str(), current, desired];
+
+    if nul {
+        fields.iter().map(|f| format!("{f}\0")).collect()
+    } else {
+        let escaped: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+        format!("{}\n", escaped.join("\t"))
+    }
+}
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Release lookup for `cutler check-update` and `cutler self-update`, and verification of release
+//! artifacts before the binary is replaced with them.
+//!
+//! Every release must publish a SHA256 checksum of its archive, either as `<asset>.sha256` or in
+//! a shared checksum file. With `[update] public_key`, a minisign signature (`<asset>.minisig`)
+//! is required as well. Anything missing or mismatching fails the update.
+
+use anyhow::{Context, Result, bail};
+use self_update::update::{Release, ReleaseAsset};
+use semver::Version;
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::core::Channel;
+use crate::util::net::{client, send};
+use crate::util::sha::get_file_digest;
+
+/// The most recent releases of cutler, newest first.
+const RELEASES_URL: &str = "https://api.github.com/repos/machlit/cutler/releases?per_page=100";
+
+/// A release as listed by the GitHub API.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct GithubRelease {
+    pub tag_name: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+impl GithubRelease {
+    /// The version of the release, from its tag (e.g. `v1.2.0-rc.1`).
+    pub fn version(&self) -> Option<Version> {
+        Version::parse(self.tag_name.trim_start_matches('v')).ok()
+    }
+}
+
+/// Picks the newest release on the given channel. Drafts are never picked, and neither are
+/// pre-releases on the stable channel, whether marked on GitHub or by their version.
+pub fn latest(releases: &[GithubRelease], channel: Channel) -> Option<&GithubRelease> {
+    releases
+        .iter()
+        .filter(|r| !r.draft)
+        .filter_map(|r| Some((r.version()?, r)))
+        .filter(|(v, r)| channel == Channel::Prerelease || (!r.prerelease && v.pre.is_empty()))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, r)| r)
+}
+
+/// Finds the release of a specific version, e.g. to roll back to it.
+pub fn find<'a>(releases: &'a [GithubRelease], version: &Version) -> Option<&'a GithubRelease> {
+    releases
+        .iter()
+        .find(|r| !r.draft && r.version().as_ref() == Some(version))
+}
+
+/// Fetches the most recent releases from GitHub.
+pub async fn fetch_releases(user_agent: &str) -> Result<Vec<GithubRelease>> {
+    let req = client(user_agent)?
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github.v3+json");
+    let resp = send("fetch releases from GitHub", req)
+        .await?
+        .error_for_status()?;
+
+    resp.json()
+        .await
+        .context("Failed to parse GitHub API response")
+}
+
+/// Checksum files covering all assets of a release, looked up after `<asset>.sha256`.
+const CHECKSUM_FILES: &[&str] = &["SHA256SUMS", "sha256sums.txt", "checksums.txt"];
+
+/// Finds the asset holding the checksum of `asset`.
+pub fn checksum_asset(release: &Release, asset: &ReleaseAsset) -> Option<ReleaseAsset> {
+    let own = format!("{}.sha256", asset.name);
+
+    std::iter::once(own.as_str())
+        .chain(CHECKSUM_FILES.iter().copied())
+        .find_map(|name| release.assets.iter().find(|a| a.name == name))
+        .cloned()
+}
+
+/// Finds the signature of `asset`.
+pub fn signature_asset(release: &Release, asset: &ReleaseAsset) -> Option<ReleaseAsset> {
+    let name = format!("{}.minisig", asset.name);
+
+    release.assets.iter().find(|a| a.name == name).cloned()
+}
+
+/// Reads the checksum of `asset_name` from the output of `shasum -a 256` or `sha256sum`.
+/// A file holding nothing but a hash counts as the checksum of the asset.
+pub fn parse_checksum(text: &str, asset_name: &str) -> Option<String> {
+    let is_hash = |s: &str| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit());
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+
+    for line in &lines {
+        let mut parts = line.split_whitespace();
+        let (Some(hash), name) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        if !is_hash(hash) {
+            continue;
+        }
+
+        // binary mode marks file names with a leading `*`
+        let matches = match name.map(|n| n.trim_start_matches('*')) {
+            Some(name) => Path::new(name).file_name() == Some(asset_name.as_ref()),
+            None => lines.len() == 1,
+        };
+        if matches {
+            return Some(hash.to_lowercase());
+        }
+    }
+
+    None
+}
+
+/// Checks a downloaded file against its published checksum.
+pub fn verify_checksum(path: &Path, expected: &str) -> Result<()> {
+    let actual = get_file_digest(path)?;
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!(
+            "Checksum mismatch for {}: expected {expected}, got {actual}. Refusing to install it.",
+            path.display()
+        )
+    }
+
+    Ok(())
+}
+
+/// Checks the minisign signature of a downloaded file, using the `minisign` CLI.
+pub fn verify_signature(path: &Path, signature: &Path, public_key: &str) -> Result<()> {
+    let out = Command::new("minisign")
+        .arg("-V")
+        .arg("-q")
+        .args(["-P", public_key])
+        .arg("-m")
+        .arg(path)
+        .arg("-x")
+        .arg(signature)
+        .output()
+        .context("Could not run minisign, which is needed for `[update] public_key`")?;
+
+    if !out.status.success() {
+        bail!(
+            "Signature check failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        )
+    }
+
+    Ok(())
+}
@@ -2,17 +2,18 @@
 
 use anyhow::Result;
 use sha2::{Digest, Sha256};
+
+use crate::config::path::get_overlay_path;
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{BufReader, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-/// Gets the SHA256 digest of a file, given its path.
-pub fn get_digest(path: PathBuf) -> Result<String> {
+/// Helper for: get_digest()
+fn hash_file(hasher: &mut Sha256, path: &Path) -> Result<()> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
-    let mut hasher = Sha256::new();
     let mut buffer = [0u8; 8192];
 
     loop {
@@ -23,6 +24,45 @@ pub fn get_digest(path: PathBuf) -> Result<String> {
         hasher.update(&buffer[..n]);
     }
 
+    Ok(())
+}
+
+/// Gets the plain SHA256 digest of a single file, e.g. a downloaded archive.
+pub fn get_file_digest(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hash_file(&mut hasher, path)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Gets the SHA256 digest of a config, given its path.
+/// Every layer merged by `Config::load` is hashed: the names and contents of all `*.toml` files
+/// of a config directory, or the config file along with its local overlay.
+pub fn get_digest(path: PathBuf) -> Result<String> {
+    let mut hasher = Sha256::new();
+
+    if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(&path)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        files.sort();
+
+        for file in files {
+            hasher.update(file.file_name().unwrap_or_default().as_encoded_bytes());
+            hash_file(&mut hasher, &file)?;
+        }
+    } else {
+        hash_file(&mut hasher, &path)?;
+
+        // configs without an overlay keep the digest of the file alone
+        let overlay = get_overlay_path(&path);
+        if overlay.is_file() {
+            hasher.update(overlay.file_name().unwrap_or_default().as_encoded_bytes());
+            hash_file(&mut hasher, &overlay)?;
+        }
+    }
+
     let result = hasher.finalize();
     Ok(format!("{:x}", result))
 }
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Result of the last full check, for `cutler status --cached`.
+//!
+//! Full `status` runs store what drifted along with a timestamp, so shell prompts can show drift
+//! without reading every preference again. `apply` only refreshes the preferences part of it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::config::core::{Prompt, now};
+use crate::log_warn;
+use crate::util::sha::get_digest;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusCache {
+    /// Unix timestamp of the check.
+    pub checked_at: u64,
+    /// Everything which diverged, e.g. `com.apple.dock | tilesize` or `brew`.
+    pub drifted: Vec<String>,
+    /// Digest of the config the check ran against.
+    pub digest: String,
+}
+
+/// Returns the path to the cache file, next to the config.
+pub fn get_status_cache_path(config_path: &Path) -> Result<PathBuf> {
+    let parent = config_path
+        .parent()
+        .context("Could not determine config parent directory")?;
+
+    Ok(parent.join("last_status.json"))
+}
+
+/// Formats a duration in seconds as a short age, e.g. `42s`, `5m`, `3h` or `2d`.
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+impl StatusCache {
+    /// Records a check of the given config which just happened.
+    pub fn new(config_path: &Path, drifted: Vec<String>) -> Result<Self> {
+        Ok(StatusCache {
+            checked_at: now() as u64,
+            drifted,
+            digest: get_digest(config_path.to_path_buf())?,
+        })
+    }
+
+    pub async fn load(config_path: &Path) -> Option<Self> {
+        let data = fs::read_to_string(get_status_cache_path(config_path).ok()?)
+            .await
+            .ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub async fn save(&self, config_path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(get_status_cache_path(config_path)?, json).await?;
+
+        Ok(())
+    }
+
+    /// Stores the result of a check which just happened, warning if that fails.
+    pub async fn record(config_path: &Path, drifted: Vec<String>) {
+        let result = match StatusCache::new(config_path, drifted) {
+            Ok(cache) => cache.save(config_path).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            log_warn!("Could not save status cache: {e}");
+        }
+    }
+
+    /// Stores the preferences written by `apply` which still drift, keeping what the last full
+    /// check found about Homebrew and the App Store. If the config declares either of them and
+    /// there's no such check for it, nothing is stored, since the result would look complete.
+    pub async fn record_prefs(config_path: &Path, drifted: Vec<String>, has_software: bool) {
+        let mut drifted = drifted;
+
+        if has_software {
+            let Some(last) = StatusCache::load(config_path).await else {
+                return;
+            };
+            if last.is_stale(config_path) {
+                return;
+            }
+            drifted.extend(
+                last.drifted
+                    .into_iter()
+                    .filter(|item| item == "brew" || item == "mas"),
+            );
+        }
+
+        StatusCache::record(config_path, drifted).await;
+    }
+
+    /// Whether the config changed since the check, making its result meaningless.
+    pub fn is_stale(&self, config_path: &Path) -> bool {
+        get_digest(config_path.to_path_buf()).map_or(true, |digest| digest != self.digest)
+    }
+
+    /// A one-line description for prompts, e.g. `drifted: 2 (checked 5m ago)`.
+    pub fn describe(&self, stale: bool) -> String {
+        let age = format_age((now() as u64).saturating_sub(self.checked_at));
+
+        if stale {
+            format!("unknown (config changed since last check {age} ago)")
+        } else if self.drifted.is_empty() {
+            format!("in sync (checked {age} ago)")
+        } else {
+            format!("drifted: {} (checked {age} ago)", self.drifted.len())
+        }
+    }
+}
+
+/// Renders the token printed by `cutler prompt`, e.g. `✓` or `±3`, for the last check (if any).
+pub fn render_prompt(cache: Option<&StatusCache>, stale: bool, prompt: &Prompt) -> String {
+    let symbol = match cache {
+        Some(cache) if !stale && cache.drifted.is_empty() => prompt.clean.as_deref().unwrap_or("✓"),
+        Some(_) if !stale => prompt.drifted.as_deref().unwrap_or("±{count}"),
+        _ => prompt.unknown.as_deref().unwrap_or("✗"),
+    };
+    let count = cache.map_or(0, |c| c.drifted.len());
+    let age = cache
+        .map(|c| format_age((now() as u64).saturating_sub(c.checked_at)))
+        .unwrap_or_default();
+
+    prompt
+        .format
+        .as_deref()
+        .unwrap_or("{symbol}")
+        .replace("{symbol}", symbol)
+        .replace("{count}", &count.to_string())
+        .replace("{age}", &age)
+}
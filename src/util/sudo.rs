@@ -1,19 +1,51 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use std::{env, process::exit};
+use std::{env, process::exit, time::Duration};
 
-use anyhow::{Result, bail};
+use anyhow::Result;
 use nix::unistd::Uid;
-use tokio::process::Command;
+use tokio::{fs, process::Command, task::JoinHandle, time::sleep};
+
+use crate::log_info;
+use crate::util::exit::{ExitCode, coded};
+
+/// How often the sudo timestamp is refreshed; sudo forgets it after 5 minutes by default.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// PAM configs which may enable Touch ID (`pam_tid.so`) for sudo.
+const SUDO_PAM_FILES: [&str; 2] = ["/etc/pam.d/sudo_local", "/etc/pam.d/sudo"];
+
+/// What sudo prints when it refuses to run something, by itself rather than the command it runs.
+const REFUSALS: [&str; 4] = [
+    "password is required",
+    "incorrect password attempt",
+    "is not in the sudoers file",
+    "is not allowed to run sudo",
+];
+
+/// Whether sudo's error output says it was refused, e.g. a wrong password or no sudo rights.
+pub fn is_refused(stderr: &str) -> bool {
+    REFUSALS.iter().any(|r| stderr.contains(r))
+}
+
+/// The error for refused sudo credentials, ending cutler with the sudo exit code.
+pub fn refused_error() -> anyhow::Error {
+    coded(ExitCode::Sudo, "Could not validate sudo credentials.")
+}
 
 /// Only run the command if cutler is running as root.
 /// If not running as root, rerun the command with sudo.
 pub async fn run_with_root() -> Result<()> {
     if !Uid::effective().is_root() {
+        // validate first, so that a refused prompt isn't mistaken for a failure of the command
+        if !Command::new("sudo").arg("-v").status().await?.success() {
+            return Err(refused_error());
+        }
+
         let args: Vec<String> = env::args().collect();
         let status = Command::new("sudo").args(&args).status().await?;
 
-        exit(status.code().unwrap_or(1));
+        exit(status.code().unwrap_or(ExitCode::Sudo.code()));
     }
 
     Ok(())
@@ -22,8 +54,66 @@ pub async fn run_with_root() -> Result<()> {
 /// Only run the command if cutler is running as non-root.
 pub fn run_with_noroot() -> Result<()> {
     if Uid::effective().is_root() {
-        bail!("Do not use sudo on this command!");
+        return Err(coded(ExitCode::Sudo, "Do not use sudo on this command!"));
     }
 
     Ok(())
 }
+
+/// Checks if a PAM config enables Touch ID through an uncommented `pam_tid.so` line.
+pub fn pam_has_tid(content: &str) -> bool {
+    content
+        .lines()
+        .map(str::trim)
+        .any(|line| !line.starts_with('#') && line.contains("pam_tid.so"))
+}
+
+/// Whether sudo can be authenticated with Touch ID on this machine.
+pub async fn touch_id_enabled() -> bool {
+    for path in SUDO_PAM_FILES {
+        if let Ok(content) = fs::read_to_string(path).await
+            && pam_has_tid(&content)
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Keeps the sudo timestamp fresh in the background until dropped.
+pub struct SudoKeepAlive {
+    handle: JoinHandle<()>,
+}
+
+impl Drop for SudoKeepAlive {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Asks for sudo credentials once (through Touch ID if enabled), then refreshes them
+/// periodically so that long runs with several `sudo = true` commands don't prompt again.
+pub async fn keep_alive() -> Result<SudoKeepAlive> {
+    if !touch_id_enabled().await {
+        log_info!(
+            "Tip: enable Touch ID for sudo in /etc/pam.d/sudo_local to skip typing your password."
+        );
+    }
+
+    let status = Command::new("sudo").arg("-v").status().await?;
+    if !status.success() {
+        return Err(refused_error());
+    }
+
+    let handle = tokio::spawn(async {
+        loop {
+            sleep(KEEP_ALIVE_INTERVAL).await;
+
+            // -n: never prompt from the background
+            let _ = Command::new("sudo").args(["-n", "-v"]).status().await;
+        }
+    });
+
+    Ok(SudoKeepAlive { handle })
+}
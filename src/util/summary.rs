@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! End-of-run summary for apply, unapply and brew install.
+//!
+//! Counts are recorded into a process-wide tally while a command runs, and printed once the
+//! outermost command finishes (e.g. `apply --brew` prints a single summary).
+
+use serde::Serialize;
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+
+use crate::cli::atomic::should_output_json;
+use crate::{log_cute, log_err, log_warn};
+
+/// Tallies of what happened during a run.
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct Summary {
+    pub applied: usize,
+    /// Written preferences which were read back with the desired value.
+    pub verified: usize,
+    /// Written preferences which were read back with a different value.
+    pub unverified: Vec<String>,
+    pub skipped: usize,
+    pub failed: Vec<String>,
+    pub commands_run: usize,
+    pub elapsed_secs: f64,
+}
+
+impl Summary {
+    /// Whether anything failed or didn't verify, i.e. the run only partially succeeded.
+    pub fn is_partial(&self) -> bool {
+        !self.failed.is_empty() || !self.unverified.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    depth: usize,
+    start: Option<Instant>,
+    summary: Summary,
+}
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(Default::default);
+
+/// Starts tracking a run. Nested calls keep the outer run's tally.
+pub fn begin() {
+    let mut state = STATE.lock().unwrap();
+
+    if state.depth == 0 {
+        state.start = Some(Instant::now());
+        state.summary = Summary::default();
+    }
+    state.depth += 1;
+}
+
+pub fn add_applied(count: usize) {
+    STATE.lock().unwrap().summary.applied += count;
+}
+
+pub fn add_verified(count: usize) {
+    STATE.lock().unwrap().summary.verified += count;
+}
+
+/// Records a written preference which didn't take effect, e.g. `com.apple.dock | tilesize`.
+pub fn add_unverified(item: impl Into<String>) {
+    STATE.lock().unwrap().summary.unverified.push(item.into());
+}
+
+pub fn add_skipped(count: usize) {
+    STATE.lock().unwrap().summary.skipped += count;
+}
+
+pub fn add_commands(count: usize) {
+    STATE.lock().unwrap().summary.commands_run += count;
+}
+
+/// Records a failed item, e.g. `com.apple.dock | tilesize` or `command hello`.
+pub fn add_failure(item: impl Into<String>) {
+    STATE.lock().unwrap().summary.failed.push(item.into());
+}
+
+/// Whether anything failed or didn't verify so far in the current run.
+pub fn has_failures() -> bool {
+    let state = STATE.lock().unwrap();
+    !state.summary.failed.is_empty() || !state.summary.unverified.is_empty()
+}
+
+/// Ends a run, returning the summary if this was the outermost one.
+pub fn end() -> Option<Summary> {
+    let mut state = STATE.lock().unwrap();
+    state.depth = state.depth.saturating_sub(1);
+
+    if state.depth > 0 {
+        return None;
+    }
+
+    let mut summary = std::mem::take(&mut state.summary);
+    summary.elapsed_secs = state
+        .start
+        .take()
+        .map(|s| s.elapsed().as_secs_f64())
+        .unwrap_or_default();
+
+    Some(summary)
+}
+
+/// Ends a run and prints the summary (as JSON with `--json`).
+/// Returns the summary if this was the outermost run.
+pub fn finish(title: &str) -> Option<Summary> {
+    let summary = end()?;
+
+    if should_output_json() {
+        match serde_json::to_string_pretty(&summary) {
+            Ok(json) => println!("{json}"),
+            Err(e) => log_err!("Could not serialize summary: {e}"),
+        }
+        return Some(summary);
+    }
+
+    // only runs which verified anything mention it
+    let verified = if summary.verified > 0 || !summary.unverified.is_empty() {
+        format!(" ({} verified)", summary.verified)
+    } else {
+        String::new()
+    };
+
+    let line = format!(
+        "{title} complete: {} applied{verified}, {} skipped, {} failed, {} commands run ({:.1}s)",
+        summary.applied,
+        summary.skipped,
+        summary.failed.len(),
+        summary.commands_run,
+        summary.elapsed_secs
+    );
+
+    if summary.failed.is_empty() && summary.unverified.is_empty() {
+        log_cute!("{line}");
+    } else {
+        log_warn!("{line}");
+        for item in &summary.unverified {
+            log_warn!("  Not verified: {item}");
+        }
+        for item in &summary.failed {
+            log_err!("  Failed: {item}");
+        }
+    }
+
+    Some(summary)
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::autosync::parse_interval;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_interval("6h").unwrap(), Duration::from_secs(6 * 3600));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86400));
+
+        assert!(parse_interval("6").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("2w").is_err());
+        assert!(parse_interval("999999999999999999d").is_err());
+    }
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::backup::{
+        Bundle,
+        core::{CONFIG_FILE, SNAPSHOT_FILE, backup_entry, backup_file_name},
+    };
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("backup.tar.zst");
+
+        let mut bundle = Bundle::new();
+        bundle.add(CONFIG_FILE, b"[set.dock]\ntilesize = 46\n".to_vec());
+        bundle.add(SNAPSHOT_FILE, b"{}".to_vec());
+        bundle.add(CONFIG_FILE, b"[set.dock]\ntilesize = 50\n".to_vec());
+        assert_eq!(bundle.manifest.files, vec![CONFIG_FILE, SNAPSHOT_FILE]);
+
+        bundle.write(&path).unwrap();
+        let read = Bundle::read(&path).unwrap();
+
+        assert_eq!(read, bundle);
+        assert_eq!(
+            read.get(CONFIG_FILE).unwrap(),
+            b"[set.dock]\ntilesize = 50\n"
+        );
+    }
+
+    #[test]
+    fn test_backup_entries() {
+        let entry = backup_entry("abc.plist");
+        assert_eq!(entry, "backups/abc.plist");
+        assert_eq!(backup_file_name(&entry), Some("abc.plist"));
+
+        // nothing may escape the backups directory on restore
+        assert_eq!(backup_file_name("backups/../config.toml"), None);
+        assert_eq!(backup_file_name("backups/.."), None);
+        assert_eq!(backup_file_name("backups/"), None);
+        assert_eq!(backup_file_name(SNAPSHOT_FILE), None);
+    }
+
+    #[test]
+    fn test_bundle_rejects_other_archives() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("junk.tar.zst");
+
+        fs::write(&path, b"definitely not zstd").unwrap();
+        assert!(Bundle::read(&path).is_err());
+    }
+}
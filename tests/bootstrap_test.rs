@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::bootstrap::{Checkpoint, Step, get_checkpoint_path};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_bootstrap_checkpoint() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("cutler").join("config.toml");
+
+        assert_eq!(
+            get_checkpoint_path(&config_path).unwrap(),
+            dir.path().join("cutler").join("bootstrap.json")
+        );
+        assert!(Checkpoint::load(&config_path).await.is_none());
+
+        let mut checkpoint = Checkpoint::default();
+        assert_eq!(checkpoint.next(), Some(Step::Fetch));
+
+        checkpoint.mark_done(Step::Fetch);
+        checkpoint.mark_done(Step::Homebrew);
+        checkpoint.mark_done(Step::Fetch);
+        assert_eq!(checkpoint.done, vec![Step::Fetch, Step::Homebrew]);
+        assert_eq!(checkpoint.next(), Some(Step::Brew));
+
+        // resuming picks up the saved steps, even before the config exists
+        checkpoint.save(&config_path).await.unwrap();
+        let loaded = Checkpoint::load(&config_path).await.unwrap();
+        assert_eq!(loaded, checkpoint);
+
+        for step in Step::ALL {
+            checkpoint.mark_done(step);
+        }
+        assert_eq!(checkpoint.next(), None);
+
+        Checkpoint::delete(&config_path).await.unwrap();
+        assert!(Checkpoint::load(&config_path).await.is_none());
+    }
+}
@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::brew::{
+        core::{autoremovable, formula_named, resolve_revision, rev_parse},
+        types::{BrewInfo, BrewListType},
+    };
+    use cutler::config::core::Formula;
+
+    const INFO: &str = r#"{
+        "formulae": [
+            {
+                "name": "wget",
+                "full_name": "wget",
+                "dependencies": ["openssl@3"],
+                "installed": [{ "version": "1.24", "installed_as_dependency": false, "installed_on_request": true }]
+            },
+            {
+                "name": "openssl@3",
+                "full_name": "openssl@3",
+                "dependencies": ["ca-certificates"],
+                "installed": [{ "version": "3.3", "installed_as_dependency": true, "installed_on_request": false }]
+            },
+            {
+                "name": "ca-certificates",
+                "full_name": "ca-certificates",
+                "dependencies": [],
+                "installed": [{ "version": "2024", "installed_as_dependency": true, "installed_on_request": false }]
+            },
+            {
+                "name": "libyaml",
+                "full_name": "libyaml",
+                "dependencies": [],
+                "installed": [{ "version": "0.2", "installed_as_dependency": true, "installed_on_request": false }]
+            },
+            {
+                "name": "pcre2",
+                "full_name": "pcre2",
+                "dependencies": [],
+                "installed": [{ "version": "10", "installed_as_dependency": true, "installed_on_request": false }],
+                "pinned": true
+            }
+        ],
+        "casks": [
+            { "token": "aerospace", "full_token": "nikitabobko/tap/aerospace", "installed": "0.19" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_autoremovable_respects_config() {
+        let info: BrewInfo = serde_json::from_str(INFO).unwrap();
+
+        // nothing in the closure of wget is removable
+        let removable = autoremovable(&info, &[]);
+        assert_eq!(removable, vec!["libyaml", "pcre2"]);
+
+        // formulae declared in config are kept even if installed as dependencies
+        let removable = autoremovable(&info, &["pcre2".to_string()]);
+        assert_eq!(removable, vec!["libyaml"]);
+    }
+
+    #[test]
+    fn test_formula_for_binary() {
+        let formulae = vec![
+            Formula::from("machlit/tap/cutler"),
+            Formula::from("python@3.12"),
+            Formula::Detailed {
+                name: "jq".into(),
+                pin: Some(true),
+            },
+        ];
+
+        assert_eq!(formula_named(&formulae, "jq").map(|f| f.name()), Some("jq"));
+        assert_eq!(
+            formula_named(&formulae, "cutler").map(|f| f.name()),
+            Some("machlit/tap/cutler")
+        );
+        assert_eq!(
+            formula_named(&formulae, "python").map(|f| f.name()),
+            Some("python@3.12")
+        );
+        assert!(formula_named(&formulae, "rg").is_none());
+    }
+
+    #[test]
+    fn test_brew_info_listing() {
+        let info: BrewInfo = serde_json::from_str(INFO).unwrap();
+
+        assert_eq!(info.list(BrewListType::Formula).len(), 5);
+        assert_eq!(
+            info.list(BrewListType::Cask),
+            vec!["nikitabobko/tap/aerospace"]
+        );
+        assert_eq!(info.list(BrewListType::Pinned), vec!["pcre2"]);
+        assert!(
+            !info
+                .list(BrewListType::Dependency)
+                .contains(&"wget".to_string())
+        );
+
+        let versions = info.versions();
+        assert_eq!(versions.get("wget").unwrap(), "1.24");
+        assert_eq!(versions.get("aerospace").unwrap(), "0.19");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_branch_revision() {
+        let git = |dir: &std::path::Path, args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        let (remote, tap) = (tmp.path().join("remote"), tmp.path().join("tap"));
+        std::fs::create_dir(&remote).unwrap();
+        git(&remote, &["init", "--quiet", "--initial-branch=main"]);
+        git(
+            &remote,
+            &["commit", "--quiet", "--allow-empty", "-m", "one"],
+        );
+        git(
+            tmp.path(),
+            &["clone", "--quiet", remote.to_str().unwrap(), "tap"],
+        );
+        git(
+            &remote,
+            &["commit", "--quiet", "--allow-empty", "-m", "two"],
+        );
+        git(&tap, &["fetch", "--quiet", "origin"]);
+
+        // the local branch stays behind, the pin follows the remote
+        let head = rev_parse(&tap, "HEAD").await;
+        let upstream = rev_parse(&remote, "HEAD").await;
+        assert_ne!(head, upstream);
+        assert_eq!(resolve_revision(&tap, "main").await, upstream);
+        let commit = head.clone().unwrap();
+        assert_eq!(resolve_revision(&tap, &commit).await, head);
+    }
+}
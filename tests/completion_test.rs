@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::commands::completion::{InstallDirs, Shell, is_brew_managed};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_detect_shell() {
+        assert_eq!(Shell::from_path("/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(
+            Shell::from_path("/opt/homebrew/bin/fish"),
+            Some(Shell::Fish)
+        );
+        assert_eq!(Shell::from_path("pwsh"), Some(Shell::PowerShell));
+        assert_eq!(
+            Shell::from_path("/opt/homebrew/bin/nu"),
+            Some(Shell::Nushell)
+        );
+        assert_eq!(Shell::from_path("/bin/tcsh"), None);
+        assert_eq!(Shell::from_path(""), None);
+    }
+
+    #[test]
+    fn test_install_paths() {
+        let mut dirs = InstallDirs {
+            home: PathBuf::from("/Users/u"),
+            config: PathBuf::from("/Users/u/.config"),
+            data: PathBuf::from("/Users/u/.local/share"),
+            brew_prefix: Some(PathBuf::from("/opt/homebrew")),
+        };
+
+        assert_eq!(
+            dirs.path(Shell::Zsh).unwrap(),
+            PathBuf::from("/opt/homebrew/share/zsh/site-functions/_cutler")
+        );
+        assert_eq!(
+            dirs.path(Shell::Fish).unwrap(),
+            PathBuf::from("/Users/u/.config/fish/completions/cutler.fish")
+        );
+        assert_eq!(
+            dirs.path(Shell::Bash).unwrap(),
+            PathBuf::from("/Users/u/.local/share/bash-completion/completions/cutler")
+        );
+        assert_eq!(dirs.path(Shell::PowerShell), None);
+        assert_eq!(dirs.path(Shell::Nushell), None);
+
+        // without Homebrew, zsh needs ~/.zfunc in its fpath
+        dirs.brew_prefix = None;
+        assert_eq!(
+            dirs.path(Shell::Zsh).unwrap(),
+            PathBuf::from("/Users/u/.zfunc/_cutler")
+        );
+    }
+
+    #[test]
+    fn test_zsh_fpath_hint() {
+        let mut dirs = InstallDirs {
+            home: PathBuf::from("/Users/u"),
+            config: PathBuf::from("/Users/u/.config"),
+            data: PathBuf::from("/Users/u/.local/share"),
+            brew_prefix: Some(PathBuf::from("/opt/homebrew")),
+        };
+
+        // only Homebrew's zsh looks in its site-functions
+        assert_eq!(dirs.zsh_fpath_hint(Some("/opt/homebrew/bin/zsh")), None);
+        assert_eq!(
+            dirs.zsh_fpath_hint(Some("/bin/zsh")).unwrap(),
+            "/opt/homebrew/share/zsh/site-functions"
+        );
+        assert!(dirs.zsh_fpath_hint(None).is_some());
+
+        dirs.brew_prefix = None;
+        assert_eq!(dirs.zsh_fpath_hint(Some("/bin/zsh")).unwrap(), "~/.zfunc");
+    }
+
+    #[test]
+    fn test_brew_managed_completions() {
+        let dir = tempfile::tempdir().unwrap();
+        let cellar = dir
+            .path()
+            .join("Cellar/cutler/1.0/share/zsh/site-functions");
+        std::fs::create_dir_all(&cellar).unwrap();
+        std::fs::write(cellar.join("_cutler"), "").unwrap();
+
+        let linked = dir.path().join("_cutler");
+        std::os::unix::fs::symlink(cellar.join("_cutler"), &linked).unwrap();
+        assert!(is_brew_managed(&linked));
+        assert!(!is_brew_managed(&cellar.join("_cutler")));
+        assert!(!is_brew_managed(&dir.path().join("missing")));
+    }
+}
@@ -0,0 +1,535 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use cutler::brew::types::TapDrift;
+    use cutler::cli::Args;
+    use cutler::commands::watch::ConfigFiles;
+    use cutler::config::core::{Config, Formula, Lock, Tap};
+    use cutler::config::diff::{Change, diff_tables};
+    use cutler::config::fmt::format_str;
+    use cutler::config::git::{auto_commit, hook_script, install_hook};
+    use cutler::config::keypath::{get_item, parse_key_path, parse_value, set_item, unset_item};
+    use cutler::domains::{collect, overlay_settings};
+    use cutler::mas::core::{diff_mas, parse_mas_list};
+    use cutler::util::exit::{ExitCode, exit_code_for};
+    use cutler::util::notify::{NotifyEvent, is_enabled};
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_detailed_formulae() {
+        let config: Config = toml::from_str(
+            r#"
+[brew]
+formulae = ["node@20", { name = "terraform", pin = true }]
+"#,
+        )
+        .unwrap();
+
+        let formulae = config.brew.unwrap().formulae.unwrap();
+        assert_eq!(formulae.len(), 2);
+
+        assert_eq!(formulae[0], Formula::from("node@20"));
+        assert!(!formulae[0].is_pinned());
+
+        assert_eq!(formulae[1].name(), "terraform");
+        assert!(formulae[1].is_pinned());
+    }
+
+    #[test]
+    fn test_parse_detailed_taps() {
+        let config: Config = toml::from_str(
+            r#"
+[brew]
+taps = [
+    "machlit/tap",
+    { name = "org/tap", url = "git@github.com:org/homebrew-tap.git", revision = "abc123" },
+]
+"#,
+        )
+        .unwrap();
+
+        let taps = config.brew.unwrap().taps.unwrap();
+        assert_eq!(taps[0], Tap::from("machlit/tap"));
+        assert_eq!(taps[0].url(), None);
+        assert_eq!(taps[0].revision(), None);
+
+        assert_eq!(taps[1].name(), "org/tap");
+        assert_eq!(taps[1].url(), Some("git@github.com:org/homebrew-tap.git"));
+        assert_eq!(taps[1].revision(), Some("abc123"));
+
+        let drift = TapDrift {
+            name: "org/tap".into(),
+            revision: "abc123".into(),
+            current: Some("0123456789abcdef".into()),
+        };
+        assert_eq!(drift.to_string(), "org/tap (at 0123456, pinned to abc123)");
+    }
+
+    #[test]
+    fn test_brew_arch_lists() {
+        let config: Config = toml::from_str(
+            r#"
+[brew]
+formulae = ["wget"]
+casks = ["firefox"]
+
+[brew.arm64]
+casks = ["firefox", "whisky"]
+
+[brew.x86_64]
+formulae = ["wget", { name = "x86-tool", pin = true }]
+"#,
+        )
+        .unwrap();
+        let brew = config.brew.unwrap();
+
+        let arm = brew.clone().for_arch("aarch64");
+        assert_eq!(arm.casks, Some(vec!["firefox".into(), "whisky".into()]));
+        assert_eq!(arm.formulae, Some(vec![Formula::from("wget")]));
+        assert!(arm.arm64.is_none() && arm.x86_64.is_none());
+
+        let intel = brew.clone().for_arch("x86_64");
+        assert_eq!(intel.casks, Some(vec!["firefox".into()]));
+        let formulae = intel.formulae.unwrap();
+        assert_eq!(formulae.len(), 2);
+        assert!(formulae[1].is_pinned());
+
+        // unknown architectures only get the main lists
+        assert_eq!(brew.for_arch("riscv64").casks, Some(vec!["firefox".into()]));
+    }
+
+    #[test]
+    fn test_mas_names() {
+        let config: Config = toml::from_str(
+            r#"
+[mas]
+ids = ["1333542190", { id = 497799835, name = "Xcode" }, { id = 409183694, name = "Keynote" }]
+"#,
+        )
+        .unwrap();
+        let mas = config.mas.unwrap();
+        assert_eq!(mas.ids[1].id(), "497799835");
+        assert_eq!(mas.ids[1].name(), Some("Xcode"));
+
+        let installed = parse_mas_list(
+            "497799835  Xcode            (16.2)\n409183694  Keynote Pro      (14.3)\n",
+        );
+        assert_eq!(installed["409183694"], "Keynote Pro");
+
+        let diff = diff_mas(&mas, &installed);
+        assert_eq!(diff.missing, vec!["1333542190"]);
+        assert!(diff.extra.is_empty());
+        assert_eq!(diff.mismatched.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_tables() {
+        let old: toml::Table = toml::from_str(
+            r#"
+[set.dock]
+tilesize = 46
+autohide = true
+
+[brew]
+formulae = ["git", "wget"]
+
+[command.hello]
+run = "echo hello"
+"#,
+        )
+        .unwrap();
+        let new: toml::Table = toml::from_str(
+            r#"
+[set.dock]
+tilesize = 64
+
+[set.finder]
+AppleShowAllFiles = true
+
+[brew]
+formulae = ["git", "node"]
+
+[command.hello]
+run = "echo hello"
+"#,
+        )
+        .unwrap();
+
+        let changes = diff_tables(&old, &new);
+        let lines: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "~ brew.formulae: +\"node\", -\"wget\"",
+                "- set.dock.autohide = true",
+                "~ set.dock.tilesize: 46 -> 64",
+                "+ set.finder = { AppleShowAllFiles = true }",
+            ]
+        );
+        assert!(matches!(changes[3], Change::Added { .. }));
+        assert!(diff_tables(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_keypath_edits() {
+        assert_eq!(
+            parse_key_path(r#"set."com.apple.dock".tilesize"#).unwrap(),
+            vec!["set", "com.apple.dock", "tilesize"]
+        );
+        assert!(parse_key_path("set..dock").is_err());
+
+        assert!(parse_value("48").is_integer());
+        assert!(parse_value("true").is_bool());
+        assert!(parse_value("[1, 2]").is_array());
+        assert_eq!(parse_value("hello").as_str(), Some("hello"));
+
+        let mut doc: toml_edit::DocumentMut = r#"# my config
+[set.dock]
+tilesize = 46 # small
+"#
+        .parse()
+        .unwrap();
+
+        let tilesize = parse_key_path("set.dock.tilesize").unwrap();
+        set_item(&mut doc, &tilesize, parse_value("48")).unwrap();
+        set_item(
+            &mut doc,
+            &parse_key_path("set.finder.AppleShowAllFiles").unwrap(),
+            parse_value("true"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.to_string(),
+            r#"# my config
+[set.dock]
+tilesize = 48 # small
+
+[set.finder]
+AppleShowAllFiles = true
+"#
+        );
+        assert_eq!(get_item(&doc, &tilesize).unwrap().as_integer(), Some(48));
+
+        assert!(unset_item(&mut doc, &tilesize).is_some());
+        assert!(get_item(&doc, &tilesize).is_none());
+        assert!(unset_item(&mut doc, &tilesize).is_none());
+    }
+
+    #[test]
+    fn test_format_config() {
+        let input = r#"lock   =  true
+
+# finder tweaks
+[set.finder]
+  AppleShowAllFiles=true # show hidden files
+
+[set.dock]
+tilesize=46
+persistent-apps = [ "/Applications/Safari.app","/Applications/Mail.app" ,"/System/Applications/Music.app" ]
+
+[brew]
+formulae = ["git",   { name = "terraform",pin = true }]
+"#;
+
+        let expected = r#"lock = true
+
+[set.dock]
+tilesize = 46
+persistent-apps = [
+    "/Applications/Safari.app",
+    "/Applications/Mail.app",
+    "/System/Applications/Music.app",
+]
+
+# finder tweaks
+[set.finder]
+AppleShowAllFiles = true # show hidden files
+
+[brew]
+formulae = ["git", { name = "terraform", pin = true }]
+"#;
+
+        let formatted = format_str(input).unwrap();
+        assert_eq!(formatted, expected);
+        // formatting is idempotent
+        assert_eq!(format_str(&formatted).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_notify_options() {
+        let config: Config = toml::from_str(
+            r#"
+[notify]
+drift = true
+"#,
+        )
+        .unwrap();
+
+        assert!(is_enabled(&config, NotifyEvent::Drift));
+        assert!(!is_enabled(&config, NotifyEvent::Sync));
+
+        let config: Config = toml::from_str("").unwrap();
+        assert!(!is_enabled(&config, NotifyEvent::Drift));
+    }
+
+    fn git(dir: &std::path::Path, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_git_auto_commit() {
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.name", "test"]);
+        git(dir.path(), &["config", "user.email", "test@example.com"]);
+
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[git]\nauto_commit = true\n").unwrap();
+        std::fs::write(dir.path().join("other.txt"), "untouched").unwrap();
+        git(dir.path(), &["add", "other.txt"]);
+
+        let mut config = Config::new(path.clone());
+        config.load(false).await.unwrap();
+        auto_commit(&config, "cutler: test").await;
+
+        // only the config is committed
+        assert_eq!(
+            git(dir.path(), &["log", "--format=%s"]).trim(),
+            "cutler: test"
+        );
+        assert_eq!(
+            git(dir.path(), &["show", "--name-only", "--format="]).trim(),
+            "config.toml"
+        );
+
+        // unchanged configs make no empty commits
+        auto_commit(&config, "cutler: again").await;
+        assert_eq!(
+            git(dir.path(), &["rev-list", "--count", "HEAD"]).trim(),
+            "1"
+        );
+
+        // disabled by default
+        config.git = None;
+        std::fs::write(&path, "").unwrap();
+        auto_commit(&config, "cutler: disabled").await;
+        assert_eq!(
+            git(dir.path(), &["rev-list", "--count", "HEAD"]).trim(),
+            "1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_overlay() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+[set.dock]
+tilesize = 48
+autohide = true
+persistent-apps = { a = 1, b = 2 }
+
+[brew]
+formulae = ["git"]
+casks = ["firefox"]
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("config.local.toml"),
+            r#"
+[set.dock]
+tilesize = 36
+persistent-apps = { c = 3 }
+
+[brew]
+casks = ["slack"]
+"#,
+        )
+        .unwrap();
+
+        let mut config = Config::new(path);
+        config.load(false).await.unwrap();
+
+        // tables merge key by key, anything else is replaced
+        let brew = config.brew.clone().unwrap();
+        assert_eq!(brew.formulae.unwrap(), vec![Formula::from("git")]);
+        assert_eq!(brew.casks.unwrap(), vec!["slack".to_string()]);
+
+        // settings are replaced as a whole, even dictionaries
+        let domains = collect(&config).await.unwrap();
+        let dock = &domains["dock"];
+        assert_eq!(dock["tilesize"], toml::Value::Integer(36));
+        assert_eq!(dock["autohide"], toml::Value::Boolean(true));
+        assert_eq!(dock["persistent-apps"].as_table().unwrap().len(), 1);
+
+        let local = overlay_settings(&config).await.unwrap();
+        assert!(local.contains(&("com.apple.dock".into(), "tilesize".into())));
+        assert!(!local.contains(&("com.apple.dock".into(), "autohide".into())));
+    }
+
+    #[tokio::test]
+    async fn test_install_hook() {
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), &["init", "-q"]);
+
+        let conf_dir = dir.path().join("cutler");
+        std::fs::create_dir(&conf_dir).unwrap();
+        let path = conf_dir.join("my config.toml");
+        std::fs::write(&path, "").unwrap();
+
+        // the path is relative to the repository root, where hooks run
+        let hook = install_hook(&path, false, false).await.unwrap();
+        assert!(hook.ends_with(".git/hooks/pre-commit"));
+        let script = std::fs::read_to_string(&hook).unwrap();
+        assert_eq!(script, hook_script("cutler/my config.toml", false));
+        assert!(script.contains("--config 'cutler/my config.toml' config lint"));
+        assert!(!script.contains("--dry-run"));
+        assert!(hook_script("config.toml", true).contains("--dry-run --config config.toml apply"));
+
+        // hooks written by cutler are replaced, others only with --force
+        install_hook(&path, true, false).await.unwrap();
+        std::fs::write(&hook, "#!/bin/sh\nexit 0\n").unwrap();
+        assert!(install_hook(&path, false, false).await.is_err());
+        install_hook(&path, false, true).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_config_directory() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("10-dock.toml"),
+            "[set.dock]\ntilesize = 48\nautohide = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("20-brew.toml"),
+            "[brew]\nformulae = [\"git\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("30-work.toml"),
+            "[set.dock]\ntilesize = 36\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a config").unwrap();
+
+        let mut config = Config::new(dir.path().to_path_buf());
+        assert!(config.is_dir());
+        config.load(false).await.unwrap();
+
+        // files are merged in lexical order
+        assert_eq!(
+            config.brew.clone().unwrap().formulae.unwrap(),
+            vec![Formula::from("git")]
+        );
+        let domains = collect(&config).await.unwrap();
+        assert_eq!(domains["dock"]["tilesize"], toml::Value::Integer(36));
+        assert_eq!(domains["dock"]["autohide"], toml::Value::Boolean(true));
+
+        // writing commands refuse directories
+        assert!(config.load_as_mut(false).await.is_err());
+    }
+
+    #[test]
+    fn test_watched_config_files() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("config.toml"), "").unwrap();
+
+        // the overlay counts even before it exists, other files next to the config don't
+        let files = ConfigFiles::new(&root.join("config.toml"));
+        assert_eq!(files.dirs(), vec![root.clone()]);
+        assert!(files.contains(&root.join("config.toml")));
+        assert!(files.contains(&root.join("config.local.toml")));
+        assert!(!files.contains(&root.join("snapshot.json")));
+        assert!(!files.contains(&root.join("other.toml")));
+
+        let conf_d = root.join("conf.d");
+        std::fs::create_dir(&conf_d).unwrap();
+        let files = ConfigFiles::new(&conf_d);
+        assert_eq!(files.dirs(), vec![conf_d.clone()]);
+        assert!(files.contains(&conf_d.join("10-dock.toml")));
+        assert!(!files.contains(&conf_d.join("notes.txt")));
+        assert!(!files.contains(&root.join("config.toml")));
+    }
+
+    #[test]
+    fn test_lock_details() {
+        // 2025-01-01T00:00:00Z
+        let until = 1_735_689_600;
+
+        let lock = Lock::read(
+            r#"lock = { by = "alice", reason = "mid-migration", until = 2025-01-01T00:00:00Z }"#,
+        )
+        .unwrap();
+        assert!(lock.is_active(until - 1));
+        assert!(!lock.is_active(until));
+        assert!(lock.is_expired(until));
+        assert_eq!(
+            lock.describe().unwrap(),
+            "mid-migration, by alice, until 2025-01-01 00:00 UTC"
+        );
+
+        // plain locks never expire and have nothing to tell
+        let lock = Lock::read("lock = true").unwrap();
+        assert!(lock.is_active(until) && !lock.is_expired(until));
+        assert_eq!(lock.describe(), None);
+        assert!(!Lock::read("lock = false").unwrap().is_active(0));
+
+        // merging the layers of a config turns datetimes into strings
+        let table: toml::Table = toml::from_str("lock = { until = 2025-01-01 }").unwrap();
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        assert!(config.lock.unwrap().is_expired(until));
+    }
+
+    #[tokio::test]
+    async fn test_one_off_respects_lock() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "lock = true\n\n[set.dock]\ntilesize = 48\n").unwrap();
+
+        // one-off writes leave [set] alone, but not the lock
+        for argv in [
+            &["cutler", "apply", "--set", "dock.tilesize=36"][..],
+            &["cutler", "unapply", "--key", "dock.tilesize"][..],
+        ] {
+            let args = Args::try_parse_from(argv).unwrap();
+            let mut config = Config::new(path.clone());
+            let err = args
+                .command
+                .as_runnable()
+                .unwrap()
+                .run(&mut config)
+                .await
+                .unwrap_err();
+            assert_eq!(exit_code_for(&err), ExitCode::Locked, "{argv:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlay_lock_blocks_edits() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[set.dock]\ntilesize = 48\n").unwrap();
+        std::fs::write(dir.path().join("config.local.toml"), "lock = true\n").unwrap();
+
+        let config = Config::new(path);
+        let err = config.load_as_mut(true).await.unwrap_err();
+        assert_eq!(exit_code_for(&err), ExitCode::Locked);
+        assert!(config.load_as_mut(false).await.is_ok());
+    }
+}
@@ -2,10 +2,38 @@
 
 #[cfg(test)]
 mod tests {
-    use cutler::config::core::Config;
-    use cutler::domains::{collect, effective};
-    use std::collections::HashMap;
+    use cutler::config::core::Status;
+    use cutler::config::core::{
+        Config, HotCorners, Safety, Screenshots, SoftwareUpdate, Spotlight, Trackpad,
+    };
+    use cutler::domains::catalog::{by_alias, by_effective, by_key, retirements};
+    use cutler::domains::collector::is_qualified;
+    use cutler::domains::files::{domain_obj, is_path_domain};
+    use cutler::domains::hot_corners::{action_code, modifier_flags};
+    use cutler::domains::import::{parse_defaults_dump, parse_plist};
+    use cutler::domains::managed::{forced_keys, managed_paths};
+    use cutler::domains::menubar::{
+        CURRENT_HOST, HIDDEN, SHOWN, byhost_domain, menubar_settings, resolve_host,
+    };
+    use cutler::domains::meta::{Requires, SettingMeta, SettingsMeta, meta_at, not_applicable};
+    use cutler::domains::safety::{counted_changes, is_protected, protected_patterns};
+    use cutler::domains::shortcuts::{KEY_EQUIVALENTS, encode_shortcut};
+    use cutler::domains::software_update::SOFTWARE_UPDATE;
+    use cutler::domains::spotlight::{CATEGORIES, VOLUME_CONFIG};
+    use cutler::domains::tcc::needs_full_disk_access;
+    use cutler::domains::text_replacements::{
+        REPLACEMENT_KEYS, merge_replacements, replacement_domains,
+    };
+    use cutler::domains::trackpad::{BLUETOOTH, BUILTIN};
+    use cutler::domains::{
+        collect, collect_with_meta, config_domain, effective, ignored_keys, missing_domains,
+        split_setting_path,
+    };
+    use cutler::util::macos::MacosVersion;
+    use defaults_rs::Domain;
+    use std::collections::{BTreeMap, HashMap, HashSet};
     use std::io::Write;
+    use std::path::PathBuf;
     use tempfile::NamedTempFile;
     use toml::{Value, value::Table};
 
@@ -86,6 +114,96 @@ mod tests {
         assert_eq!((d, k), ("NSGlobalDomain".into(), "bar.Baz".into()));
     }
 
+    #[test]
+    fn test_third_party_domains() {
+        // fully qualified reverse-DNS domains are used as is
+        assert!(is_qualified("org.hammerspoon.Hammerspoon"));
+        assert!(!is_qualified("menuextra.clock"));
+        assert!(!is_qualified("driver.AppleBluetoothMultitouch.trackpad"));
+        assert_eq!(
+            effective("org.hammerspoon.Hammerspoon", "MenuIcon").0,
+            "org.hammerspoon.Hammerspoon"
+        );
+        assert_eq!(effective("com.apple.dock", "autohide").0, "com.apple.dock");
+        assert_eq!(
+            effective("menuextra.clock", "x").0,
+            "com.apple.menuextra.clock"
+        );
+
+        // `@` opts out of the prefix
+        assert_eq!(effective("@MyDomain", "Foo").0, "MyDomain");
+
+        let (dom, key) = split_setting_path("org.hammerspoon.Hammerspoon.MenuIcon").unwrap();
+        assert_eq!(
+            effective(&dom, &key),
+            ("org.hammerspoon.Hammerspoon".into(), "MenuIcon".into())
+        );
+
+        // and back again
+        assert_eq!(config_domain("com.apple.dock"), "dock");
+        assert_eq!(config_domain("NSGlobalDomain"), "NSGlobalDomain");
+        assert_eq!(
+            config_domain("org.hammerspoon.Hammerspoon"),
+            "org.hammerspoon.Hammerspoon"
+        );
+        assert_eq!(config_domain("MyDomain"), "@MyDomain");
+
+        let mut config = Config::new(Default::default());
+        config.safety = Some(Safety {
+            protected: Some(vec![
+                "org.hammerspoon.Hammerspoon.MenuIcon".into(),
+                "@MyDomain".into(),
+            ]),
+            ..Default::default()
+        });
+        let patterns = protected_patterns(&config);
+        assert!(is_protected(
+            &patterns,
+            "org.hammerspoon.Hammerspoon",
+            "MenuIcon"
+        ));
+        assert!(is_protected(&patterns, "MyDomain", "Foo"));
+    }
+
+    #[tokio::test]
+    async fn test_path_domains() {
+        const SMB: &str = "/Library/Preferences/SystemConfiguration/com.apple.smb.server.plist";
+
+        assert!(is_path_domain(SMB));
+        assert!(is_path_domain("~/Library/Preferences/foo.plist"));
+        assert!(!is_path_domain("dock"));
+
+        // path domains are used as is
+        let (d, k) = effective(SMB, "NetBIOSName");
+        assert_eq!((d.as_str(), k.as_str()), (SMB, "NetBIOSName"));
+        assert_eq!(domain_obj(&d).unwrap(), Domain::Path(PathBuf::from(SMB)));
+        assert_eq!(domain_obj("NSGlobalDomain").unwrap(), Domain::Global);
+        assert_eq!(
+            domain_obj("com.apple.dock").unwrap(),
+            Domain::User("com.apple.dock".into())
+        );
+
+        let (dom, key) = split_setting_path(&format!("{SMB}.NetBIOSName")).unwrap();
+        assert_eq!(
+            effective(&dom, &key),
+            (SMB.to_string(), "NetBIOSName".into())
+        );
+
+        let config_content = format!("[set.\"{SMB}\"]\nNetBIOSName = \"mac\"\n");
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let mut config: Config = toml::from_str(&config_content).unwrap();
+        config.path = temp_file.path().to_path_buf();
+        let domains = collect(&config).await.unwrap();
+        assert_eq!(domains[SMB]["NetBIOSName"].as_str(), Some("mac"));
+
+        // missing unless the file exists, regardless of the registry
+        let existing = temp_file.path().to_string_lossy().to_string();
+        let config_domains = [SMB.to_string(), existing];
+        assert_eq!(missing_domains(&config_domains, &[]), [SMB]);
+    }
+
     #[tokio::test]
     async fn test_collect_domains_set() {
         let config_content = r#"
@@ -113,4 +231,843 @@ fnState = false
         let kb = domains.get("NSGlobalDomain.com.apple.keyboard").unwrap();
         assert!(!kb.get("fnState").unwrap().as_bool().unwrap());
     }
+
+    #[tokio::test]
+    async fn test_collect_substitutes_vars() {
+        let config_content = r#"
+[vars]
+iface = "en0"
+
+[set.dock]
+label = "Interface: $iface"
+literal = "costs $5 and $unknown"
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let mut config: Config = toml::from_str(config_content).unwrap();
+        config.path = temp_file.path().to_path_buf();
+
+        let domains = collect(&config).await.unwrap();
+        let dock = domains.get("dock").unwrap();
+        assert_eq!(
+            dock.get("label").unwrap().as_str().unwrap(),
+            "Interface: en0"
+        );
+        // unknown variables are left untouched
+        assert_eq!(
+            dock.get("literal").unwrap().as_str().unwrap(),
+            "costs $5 and $unknown"
+        );
+    }
+
+    #[test]
+    fn test_split_setting_path() {
+        let (dom, key) = split_setting_path("com.apple.dock.tilesize").unwrap();
+        assert_eq!((dom.as_str(), key.as_str()), ("dock", "tilesize"));
+        assert_eq!(
+            effective(&dom, &key),
+            ("com.apple.dock".into(), "tilesize".into())
+        );
+
+        let (dom, key) = split_setting_path("menuextra.clock.FlashDateSeparators").unwrap();
+        assert_eq!(
+            effective(&dom, &key),
+            (
+                "com.apple.menuextra.clock".into(),
+                "FlashDateSeparators".into()
+            )
+        );
+
+        let (dom, key) = split_setting_path("NSGlobalDomain.AppleShowAllExtensions").unwrap();
+        assert_eq!(
+            effective(&dom, &key),
+            ("NSGlobalDomain".into(), "AppleShowAllExtensions".into())
+        );
+
+        assert!(split_setting_path("tilesize").is_err());
+        assert!(split_setting_path("dock.").is_err());
+    }
+
+    #[test]
+    fn test_encode_shortcut() {
+        assert_eq!(encode_shortcut("cmd+shift+k").unwrap(), "@$k");
+        assert_eq!(encode_shortcut("Shift + Command + K").unwrap(), "@$k");
+        assert_eq!(encode_shortcut("⌘⇧K").unwrap(), "@$k");
+        assert_eq!(encode_shortcut("ctrl+opt+cmd+t").unwrap(), "@~^t");
+        // already encoded
+        assert_eq!(encode_shortcut("@~t").unwrap(), "@~t");
+        // the modifier symbols themselves can be keys
+        assert_eq!(encode_shortcut("cmd++").unwrap(), "@+");
+        assert_eq!(encode_shortcut("cmd+$").unwrap(), "@$");
+        // named keys
+        assert_eq!(encode_shortcut("cmd+left").unwrap(), "@\u{F702}");
+        assert_eq!(encode_shortcut("opt+f1").unwrap(), "~\u{F704}");
+
+        assert!(encode_shortcut("cmd+").is_err());
+        assert!(encode_shortcut("cmd+nope").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_app_shortcuts() {
+        let mut config = Config::new(Default::default());
+        config.app_shortcuts = Some(HashMap::from([(
+            "com.apple.mail".to_string(),
+            HashMap::from([("Send".to_string(), "cmd+shift+d".to_string())]),
+        )]));
+
+        let domains = collect(&config).await.unwrap();
+        let equivalents = domains["mail"][KEY_EQUIVALENTS].as_table().unwrap();
+        assert_eq!(equivalents["Send"].as_str().unwrap(), "@$d");
+        assert_eq!(
+            effective("mail", KEY_EQUIVALENTS).0,
+            "com.apple.mail".to_string()
+        );
+
+        // third-party apps are addressed by their full domain
+        config.app_shortcuts = Some(HashMap::from([(
+            "com.example.app".to_string(),
+            HashMap::new(),
+        )]));
+        let domains = collect(&config).await.unwrap();
+        assert_eq!(
+            effective("com.example.app", KEY_EQUIVALENTS).0,
+            "com.example.app"
+        );
+        assert!(domains.contains_key("com.example.app"));
+    }
+
+    #[tokio::test]
+    async fn test_retired_keys() {
+        let config = config_with_set(HashMap::from([
+            (
+                "menuextra.clock".to_string(),
+                HashMap::from([
+                    ("DateFormat".to_string(), Value::String("HH:mm".into())),
+                    ("ShowSeconds".to_string(), Value::Boolean(true)),
+                ]),
+            ),
+            (
+                "dashboard".to_string(),
+                HashMap::from([("mcx-disabled".to_string(), Value::Boolean(true))]),
+            ),
+        ]));
+        let domains = collect(&config).await.unwrap();
+
+        // every retired key without a target version
+        let lines = retirements(&domains, None);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("dashboard.mcx-disabled is ignored since macOS 10.15"));
+        assert!(lines[1].contains("menuextra.clock.DateFormat"));
+        assert!(lines[1].contains("use ShowSeconds"), "{}", lines[1]);
+
+        // only keys retired in or before the target version
+        let lines = retirements(&domains, Some(MacosVersion::new(10, 15)));
+        assert_eq!(lines.len(), 1);
+        assert!(retirements(&domains, Some(MacosVersion::new(10, 14))).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_catalog_aliases() {
+        let entry = by_alias("dock", "icon_size").unwrap();
+        assert_eq!(entry.key, "tilesize");
+        assert!(entry.check(&Value::Integer(48)).is_ok());
+        assert!(entry.check(&Value::String("48".into())).is_ok());
+        assert!(entry.check(&Value::Integer(500)).is_err());
+        assert!(entry.check(&Value::Boolean(true)).is_err());
+        assert_eq!(by_effective("com.apple.dock", "tilesize"), Some(entry));
+        assert_eq!(entry.requires, None);
+        assert_eq!(
+            by_alias("NSGlobalDomain", "key_repeat").unwrap().requires,
+            Some(Requires::Logout)
+        );
+
+        // aliases are resolved to their raw keys
+        let config = config_with_set(HashMap::from([(
+            "dock".to_string(),
+            HashMap::from([
+                ("icon_size".to_string(), Value::Integer(48)),
+                ("position".to_string(), Value::String("left".into())),
+            ]),
+        )]));
+        let domains = collect(&config).await.unwrap();
+        assert_eq!(domains["dock"]["tilesize"], Value::Integer(48));
+        assert_eq!(domains["dock"]["orientation"].as_str(), Some("left"));
+        assert!(!domains["dock"].contains_key("icon_size"));
+
+        // invalid values are caught, whether set by alias or raw key
+        let config = config_with_set(HashMap::from([(
+            "dock".to_string(),
+            HashMap::from([("orientation".to_string(), Value::String("top".into()))]),
+        )]));
+        let err = collect(&config).await.unwrap_err().to_string();
+        assert!(err.contains("dock.position (orientation)"), "{err}");
+
+        // fully qualified domains are checked just the same
+        assert_eq!(by_key("com.apple.dock", "tilesize"), Some(entry));
+        assert_eq!(by_alias("com.apple.dock", "icon_size"), Some(entry));
+        let config = config_with_set(HashMap::from([(
+            "com.apple.dock".to_string(),
+            HashMap::from([("orientation".to_string(), Value::String("top".into()))]),
+        )]));
+        let err = collect(&config).await.unwrap_err().to_string();
+        assert!(
+            err.contains("com.apple.dock.position (orientation)"),
+            "{err}"
+        );
+
+        // both spellings at once are ambiguous
+        let config = config_with_set(HashMap::from([(
+            "dock".to_string(),
+            HashMap::from([
+                ("icon_size".to_string(), Value::Integer(48)),
+                ("tilesize".to_string(), Value::Integer(36)),
+            ]),
+        )]));
+        assert!(collect(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_hot_corners() {
+        assert_eq!(action_code("Start Screensaver").unwrap(), 5);
+        assert_eq!(action_code("lock_screen").unwrap(), 13);
+        assert!(action_code("make-coffee").is_err());
+        assert_eq!(modifier_flags("").unwrap(), 0);
+        assert_eq!(modifier_flags("cmd+opt").unwrap(), 1048576 | 524288);
+        assert!(modifier_flags("hyper").is_err());
+
+        let mut config = Config::new(Default::default());
+        config.hot_corners = Some(
+            toml::from_str::<HotCorners>(
+                r#"
+                bottom_right = "start-screensaver"
+                top_left = { action = "mission-control", modifier = "cmd" }
+                "#,
+            )
+            .unwrap(),
+        );
+
+        let domains = collect(&config).await.unwrap();
+        let dock = &domains["dock"];
+        assert_eq!(dock["wvous-br-corner"], Value::Integer(5));
+        assert_eq!(dock["wvous-br-modifier"], Value::Integer(0));
+        assert_eq!(dock["wvous-tl-corner"], Value::Integer(2));
+        assert_eq!(dock["wvous-tl-modifier"], Value::Integer(1048576));
+        assert!(!dock.contains_key("wvous-tr-corner"));
+
+        // the raw keys can't be set twice
+        config.set = Some(HashMap::from([(
+            "dock".to_string(),
+            HashMap::from([("wvous-br-corner".to_string(), Value::Integer(1))]),
+        )]));
+        assert!(collect(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_screenshots() {
+        let mut config = Config::new(Default::default());
+        config.screenshots = Some(Screenshots {
+            location: Some("/tmp/Screenshots".into()),
+            format: Some("JPEG".into()),
+            shadow: Some(false),
+            thumbnail: Some(true),
+        });
+
+        let domains = collect(&config).await.unwrap();
+        let capture = &domains["screencapture"];
+        assert_eq!(capture["location"].as_str(), Some("/tmp/Screenshots"));
+        assert_eq!(capture["type"].as_str(), Some("jpg"));
+        assert_eq!(capture["disable-shadow"], Value::Boolean(true));
+        assert_eq!(capture["show-thumbnail"], Value::Boolean(true));
+
+        config.screenshots = Some(Screenshots {
+            format: Some("webp".into()),
+            ..Default::default()
+        });
+        assert!(collect(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_trackpad() {
+        let mut config = Config::new(Default::default());
+        config.trackpad = Some(Trackpad {
+            tap_to_click: Some(true),
+            three_finger_drag: Some(true),
+            haptic_strength: Some("Firm".into()),
+            ..Default::default()
+        });
+
+        let domains = collect(&config).await.unwrap();
+        for domain in [BUILTIN, BLUETOOTH] {
+            assert_eq!(domains[domain]["Clicking"], Value::Boolean(true));
+            assert_eq!(
+                domains[domain]["TrackpadThreeFingerDrag"],
+                Value::Boolean(true)
+            );
+            assert_eq!(
+                domains[domain]["TrackpadThreeFingerHorizSwipeGesture"],
+                Value::Integer(0)
+            );
+            assert_eq!(domains[domain]["FirstClickThreshold"], Value::Integer(2));
+            assert_eq!(domains[domain]["SecondClickThreshold"], Value::Integer(2));
+        }
+        assert_eq!(
+            domains["NSGlobalDomain"]["com.apple.mouse.tapBehavior"],
+            Value::Integer(1)
+        );
+
+        config.trackpad = Some(Trackpad {
+            haptic_strength: Some("strong".into()),
+            ..Default::default()
+        });
+        assert!(collect(&config).await.is_err());
+
+        config.trackpad = Some(Trackpad {
+            tracking_speed: Some(4.0),
+            ..Default::default()
+        });
+        assert!(collect(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_spotlight() {
+        let mut config = Config::new(Default::default());
+        config.spotlight = Some(Spotlight {
+            exclude: Some(vec!["/tmp/node_modules".into()]),
+            disabled_categories: Some(vec!["fonts".into(), "MESSAGES".into()]),
+        });
+
+        let domains = collect(&config).await.unwrap();
+        assert_eq!(
+            domains[VOLUME_CONFIG]["Exclusions"],
+            Value::Array(vec![Value::String("/tmp/node_modules".into())])
+        );
+        assert_eq!(effective(VOLUME_CONFIG, "Exclusions").0, VOLUME_CONFIG);
+
+        // every category is listed, with only the disabled ones turned off
+        let items = domains["Spotlight"]["orderedItems"].as_array().unwrap();
+        assert_eq!(items.len(), CATEGORIES.len());
+        let disabled: Vec<&str> = items
+            .iter()
+            .filter(|item| item["enabled"] == Value::Boolean(false))
+            .map(|item| item["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(disabled, ["FONTS", "MESSAGES"]);
+        assert_eq!(
+            effective("Spotlight", "orderedItems").0,
+            "com.apple.Spotlight"
+        );
+
+        config.spotlight = Some(Spotlight {
+            disabled_categories: Some(vec!["EMAILS".into()]),
+            ..Default::default()
+        });
+        assert!(collect(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_software_update() {
+        let mut config = Config::new(Default::default());
+        config.softwareupdate = Some(SoftwareUpdate {
+            automatic_check: Some(true),
+            critical_updates: Some(true),
+            install_macos_updates: Some(false),
+            schedule_days: Some(7),
+            ..Default::default()
+        });
+
+        let domains = collect(&config).await.unwrap();
+        let update = &domains[SOFTWARE_UPDATE];
+        assert_eq!(update["AutomaticCheckEnabled"], Value::Boolean(true));
+        assert_eq!(update["CriticalUpdateInstall"], Value::Boolean(true));
+        assert_eq!(
+            update["AutomaticallyInstallMacOSUpdates"],
+            Value::Boolean(false)
+        );
+        assert_eq!(update["ScheduleFrequency"], Value::Integer(7));
+        assert!(!update.contains_key("AutomaticDownload"));
+        assert_eq!(
+            effective(SOFTWARE_UPDATE, "AutomaticDownload").0,
+            SOFTWARE_UPDATE
+        );
+
+        config.softwareupdate = Some(SoftwareUpdate {
+            schedule_days: Some(0),
+            ..Default::default()
+        });
+        assert!(collect(&config).await.is_err());
+    }
+
+    #[test]
+    fn test_menubar_settings() {
+        let items = BTreeMap::from([("bluetooth".to_string(), true), ("wifi".to_string(), false)]);
+        let domains = menubar_settings(&items).unwrap();
+
+        let visible = &domains["controlcenter"];
+        assert_eq!(
+            visible["NSStatusItem Visible Bluetooth"],
+            Value::Boolean(true)
+        );
+        assert_eq!(visible["NSStatusItem Visible WiFi"], Value::Boolean(false));
+
+        // the ByHost variant is a path domain, symbolic until it's read or written
+        let byhost_dom = byhost_domain(CURRENT_HOST);
+        assert_eq!(
+            byhost_dom,
+            "~/Library/Preferences/ByHost/com.apple.controlcenter.currentHost.plist"
+        );
+        assert_eq!(domains[&byhost_dom]["Bluetooth"], Value::Integer(SHOWN));
+        assert_eq!(domains[&byhost_dom]["WiFi"], Value::Integer(HIDDEN));
+        assert_eq!(effective(&byhost_dom, "WiFi").0, byhost_dom);
+
+        let unknown = BTreeMap::from([("clock".to_string(), false)]);
+        assert!(menubar_settings(&unknown).is_err());
+        assert!(menubar_settings(&BTreeMap::new()).unwrap().is_empty());
+
+        // other domains pass through untouched
+        assert_eq!(resolve_host("com.apple.dock").unwrap(), "com.apple.dock");
+    }
+
+    #[test]
+    fn test_merge_text_replacements() {
+        let current: Value = toml::from_str::<Table>(
+            r#"
+            items = [
+                { on = 1, replace = "omw", with = "On my way!" },
+                { on = 1, replace = "ty", with = "Thanks" },
+                { on = 0, replace = "brb", with = "Be right back" },
+            ]
+            "#,
+        )
+        .unwrap()["items"]
+            .clone();
+
+        let wanted = [
+            ("omw", "On my way!"),
+            ("ty", "Thank you!"),
+            ("afaik", "As far as I know"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let merged = merge_replacements(Some(&current), &wanted);
+        let items = merged.as_array().unwrap();
+
+        // unmanaged entries survive, matching ones stay as they are
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0], current.as_array().unwrap()[0]);
+        assert_eq!(items[2], current.as_array().unwrap()[2]);
+        assert_eq!(items[1]["with"].as_str(), Some("Thank you!"));
+        assert_eq!(items[3]["replace"].as_str(), Some("afaik"));
+        assert_eq!(items[3]["on"], Value::Integer(1));
+
+        // merging again changes nothing
+        assert_eq!(merge_replacements(Some(&merged), &wanted), merged);
+        assert_eq!(
+            merge_replacements(None, &wanted).as_array().unwrap().len(),
+            3
+        );
+
+        // collecting doesn't look at the system
+        let mut config = Config::new("config.toml".into());
+        config.text_replacements = Some(wanted);
+        let domains = replacement_domains(&config).unwrap();
+        for key in REPLACEMENT_KEYS {
+            assert_eq!(
+                domains["NSGlobalDomain"][key],
+                merge_replacements(None, config.text_replacements.as_ref().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_ignored_keys() {
+        let mut config = Config::new(Default::default());
+        assert!(ignored_keys(&config).is_empty());
+
+        config.status = Some(Status {
+            ignore: Some(vec![
+                "com.apple.dock.mod-count".into(),
+                "NSGlobalDomain.AppleLanguages".into(),
+                "NSGlobalDomain.com.apple.mouse.linear".into(),
+                "bogus".into(),
+            ]),
+        });
+
+        let ignored = ignored_keys(&config);
+        assert_eq!(ignored.len(), 3);
+        assert!(ignored.contains(&effective("dock", "mod-count")));
+        assert!(ignored.contains(&("NSGlobalDomain".into(), "AppleLanguages".into())));
+        assert!(ignored.contains(&effective("NSGlobalDomain.com.apple.mouse", "linear")));
+    }
+
+    #[tokio::test]
+    async fn test_collect_setting_meta() {
+        let set: toml::Table = toml::from_str(
+            r#"
+            [dock]
+            tilesize = { value = 48, desc = "Dock icon size" }
+            autohide = { value = true, url = "https://example.com" }
+            orientation = { value = "left" }
+            magnification = { value = true, requires = "logout" }
+            # not the extended form, stays a dictionary
+            persistent = { value = 1, other = 2 }
+            "#,
+        )
+        .unwrap();
+        let mut domain_map: HashMap<String, Value> = HashMap::new();
+        for (k, v) in set["dock"].as_table().unwrap() {
+            domain_map.insert(k.clone(), v.clone());
+        }
+        let config = config_with_set(HashMap::from([("dock".to_string(), domain_map)]));
+
+        let (domains, meta) = collect_with_meta(&config).await.unwrap();
+        let dock = &domains["dock"];
+        assert_eq!(dock["tilesize"], Value::Integer(48));
+        assert_eq!(dock["autohide"], Value::Boolean(true));
+        assert_eq!(dock["orientation"], Value::String("left".into()));
+        assert!(dock["persistent"].is_table());
+
+        assert_eq!(meta.len(), 3);
+        let magnification = &meta[&effective("dock", "magnification")];
+        assert_eq!(magnification.requires, Some(Requires::Logout));
+        assert_eq!(magnification.to_string(), "(needs logout)");
+        let tilesize = &meta[&effective("dock", "tilesize")];
+        assert_eq!(tilesize.to_string(), "Dock icon size");
+        assert_eq!(
+            meta[&effective("dock", "autohide")].to_string(),
+            "<https://example.com>"
+        );
+
+        // lookups by diff path
+        assert_eq!(meta_at(&set, "dock.tilesize.value"), Some(tilesize.clone()));
+        assert_eq!(meta_at(&set, "dock.tilesize"), Some(tilesize.clone()));
+        assert_eq!(meta_at(&set, "dock.persistent.other"), None);
+        assert_eq!(
+            meta_at(&set, "dock.orientation"),
+            Some(SettingMeta::default())
+        );
+    }
+
+    #[test]
+    fn test_setting_macos_range() {
+        let value: Value = toml::from_str::<Table>(
+            r#"v = { value = false, min_macos = "sonoma", max_macos = 15 }"#,
+        )
+        .unwrap()["v"]
+            .clone();
+        let meta = SettingMeta::from_value(&value).unwrap().unwrap();
+        assert_eq!(meta.macos.min, Some(MacosVersion::new(14, 0)));
+        assert_eq!(meta.macos.max, Some(MacosVersion::new(15, 0)));
+        assert_eq!(
+            meta.to_string(),
+            "(for macOS 14 Sonoma to macOS 15 Sequoia)"
+        );
+
+        let all = SettingsMeta::from([(effective("dock", "show-recents"), meta)]);
+        assert!(not_applicable(&all, Some(MacosVersion::new(15, 3))).is_empty());
+        assert!(not_applicable(&all, None).is_empty());
+        let skipped = not_applicable(&all, Some(MacosVersion::new(13, 0)));
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0.1, "show-recents");
+    }
+
+    #[test]
+    fn test_setting_invalid_macos() {
+        let table = toml::from_str::<Table>(
+            r#"
+            float = { value = false, max_macos = 10.10 }
+            name = { value = false, min_macos = "fourteen" }
+            quoted = { value = false, max_macos = "10.10" }
+            "#,
+        )
+        .unwrap();
+        assert!(SettingMeta::from_value(&table["float"]).is_err());
+        assert!(SettingMeta::from_value(&table["name"]).is_err());
+        let meta = SettingMeta::from_value(&table["quoted"]).unwrap().unwrap();
+        assert_eq!(meta.macos.max, Some(MacosVersion::new(10, 10)));
+    }
+
+    #[tokio::test]
+    async fn test_apply_plan() {
+        use cutler::prelude::{PrefValue, apply_plan};
+
+        let mut domain_map = HashMap::new();
+        domain_map.insert("some-key".into(), Value::Integer(48));
+        let config = config_with_set(HashMap::from([(
+            "cutler-test-nonexistent".to_string(),
+            domain_map,
+        )]));
+
+        let plan = apply_plan(&config).await.unwrap();
+        assert_eq!(plan.entries.len(), 1);
+
+        let entry = &plan.entries[0];
+        assert_eq!(entry.domain, "com.apple.cutler-test-nonexistent");
+        assert_eq!(entry.key, "some-key");
+        assert_eq!(entry.current, None);
+        assert_eq!(entry.desired, PrefValue::Integer(48));
+        assert!(entry.is_changed());
+        assert_eq!(plan.changes().count(), 1);
+
+        // already matching entries aren't changes
+        let mut matching = entry.clone();
+        matching.current = Some(PrefValue::Integer(48));
+        assert!(!matching.is_changed());
+        assert!(!matching.is_type_change());
+
+        // new keys and same-typed values aren't type changes, others are
+        assert!(!entry.is_type_change());
+        let mut retyped = entry.clone();
+        retyped.current = Some(PrefValue::Integer(36));
+        assert!(!retyped.is_type_change());
+        retyped.current = Some(PrefValue::String("48".into()));
+        assert!(retyped.is_type_change());
+    }
+
+    #[test]
+    fn test_missing_domains() {
+        let known = vec!["com.apple.dock".to_string(), "com.apple.finder".to_string()];
+        let config_domains: Vec<String> = vec![
+            "dock".into(),
+            "NSGlobalDomain".into(),
+            "NSGlobalDomain.com.apple.mouse".into(),
+            "zeta".into(),
+            "alpha".into(),
+            "alpha".into(),
+        ];
+
+        assert_eq!(
+            missing_domains(&config_domains, &known),
+            vec!["com.apple.alpha", "com.apple.zeta"]
+        );
+        assert!(missing_domains(&config_domains[..3], &known).is_empty());
+    }
+
+    #[test]
+    fn test_protected_settings() {
+        let mut config = Config::new(Default::default());
+        config.safety = Some(Safety {
+            protected: Some(vec!["dock.autohide".into()]),
+            ..Default::default()
+        });
+        let patterns = protected_patterns(&config);
+
+        // whole domains cover every key
+        assert!(is_protected(
+            &patterns,
+            "com.apple.loginwindow",
+            "GuestEnabled"
+        ));
+
+        // single keys only cover themselves
+        assert!(is_protected(
+            &patterns,
+            "com.apple.screensaver",
+            "askForPassword"
+        ));
+        assert!(!is_protected(
+            &patterns,
+            "com.apple.screensaver",
+            "idleTime"
+        ));
+
+        // user entries are prefixed like config domains
+        assert!(is_protected(&patterns, "com.apple.dock", "autohide"));
+        assert!(!is_protected(&patterns, "com.apple.dock", "tilesize"));
+    }
+
+    #[tokio::test]
+    async fn test_protected_one_off() {
+        use clap::Parser;
+        use cutler::cli::Args;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "[safety]\nprotected = [\"com.example.cutlertest.guarded\"]\n",
+        )
+        .unwrap();
+
+        // --set goes through [safety] protected of the config like any other apply
+        let args = Args::try_parse_from([
+            "cutler",
+            "apply",
+            "--no-dom-check",
+            "--set",
+            "com.example.cutlertest.guarded=1",
+        ])
+        .unwrap();
+        let mut config = Config::new(path);
+        let err = args
+            .command
+            .as_runnable()
+            .unwrap()
+            .run(&mut config)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Refusing to change protected settings")
+        );
+    }
+
+    #[test]
+    fn test_counted_changes() {
+        use cutler::domains::plan::{Plan, PlanEntry};
+        use defaults_rs::PrefValue;
+
+        let entry = |key: &str, current: Option<i64>| PlanEntry {
+            domain: "com.apple.dock".into(),
+            key: key.into(),
+            current: current.map(PrefValue::Integer),
+            desired: PrefValue::Integer(48),
+        };
+        let plan = Plan {
+            entries: vec![
+                entry("tilesize", Some(36)),
+                entry("largesize", None),
+                entry("orientation", Some(48)),
+                entry("mod-count", Some(3)),
+            ],
+        };
+        let ignored = HashSet::from([("com.apple.dock".to_string(), "mod-count".to_string())]);
+
+        let counted: Vec<&str> = counted_changes(&plan, &ignored)
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(counted, ["tilesize", "largesize"]);
+    }
+
+    #[test]
+    fn test_import_defaults_dump() {
+        let dump = r#"{
+    "com.apple.dock" = {
+        autohide = 1;
+        tilesize = 48;
+        "persistent-apps" = (
+            { "tile-type" = "file-tile"; }
+        );
+        blob = {length = 256, bytes = 0x62706c69 73743030 ... 00000000 };
+    };
+    "com.googlecode.iterm2" = {
+        Foo = bar;
+    };
+}"#;
+
+        let import = parse_defaults_dump(dump, None).unwrap();
+        let dock = &import.set["dock"];
+
+        // types are guessed from text
+        assert_eq!(dock["autohide"], Value::Integer(1));
+        assert_eq!(dock["tilesize"], Value::Integer(48));
+        assert_eq!(
+            dock["persistent-apps"][0]["tile-type"],
+            Value::String("file-tile".into())
+        );
+
+        // truncated data is flagged, third-party domains are kept as they are
+        assert!(!dock.contains_key("blob"));
+        assert_eq!(import.skipped.len(), 1);
+        assert!(import.skipped.iter().any(|s| s.contains("blob")));
+        assert_eq!(
+            import.set["com.googlecode.iterm2"]["Foo"],
+            Value::String("bar".into())
+        );
+
+        // single-domain dumps need their domain
+        let single = parse_defaults_dump("{ autohide = 0; }", Some("com.apple.dock")).unwrap();
+        assert_eq!(single.set["dock"]["autohide"], Value::Integer(0));
+    }
+
+    #[test]
+    fn test_import_plist() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>autohide</key>
+    <true/>
+    <key>tilesize</key>
+    <integer>48</integer>
+    <key>name</key>
+    <string>48</string>
+    <key>when</key>
+    <date>2024-01-01T10:00:00Z</date>
+</dict>
+</plist>"#;
+
+        let import = parse_plist(xml.as_bytes(), "NSGlobalDomain").unwrap();
+        let global = &import.set["NSGlobalDomain"];
+
+        // exported plists keep their types
+        assert_eq!(global["autohide"], Value::Boolean(true));
+        assert_eq!(global["tilesize"], Value::Integer(48));
+        assert_eq!(global["name"], Value::String("48".into()));
+        assert_eq!(global["when"].to_string(), "2024-01-01T10:00:00Z");
+        assert!(import.skipped.is_empty());
+
+        let toml = import.to_toml().unwrap();
+        assert!(toml.contains("[set.NSGlobalDomain]"));
+    }
+
+    #[test]
+    fn test_managed_keys() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            managed_paths(dir.path(), "com.apple.dock", Some("u")),
+            [
+                dir.path().join("u/com.apple.dock.plist"),
+                dir.path().join("com.apple.dock.plist"),
+            ]
+        );
+        assert_eq!(
+            managed_paths(dir.path(), "NSGlobalDomain", None),
+            [dir.path().join(".GlobalPreferences.plist")]
+        );
+        assert!(managed_paths(dir.path(), "/Library/Preferences/x.plist", Some("u")).is_empty());
+
+        let mut forced = plist::Dictionary::new();
+        forced.insert("autohide".into(), plist::Value::Boolean(true));
+        plist::Value::Dictionary(forced)
+            .to_file_xml(dir.path().join("com.apple.dock.plist"))
+            .unwrap();
+
+        // the user's own file doesn't exist, which is fine
+        let keys = forced_keys(&managed_paths(dir.path(), "com.apple.dock", Some("u")));
+        assert!(keys.contains("autohide"));
+        assert!(!keys.contains("tilesize"));
+    }
+
+    #[test]
+    fn test_full_disk_access_targets() {
+        let home = std::path::Path::new("/Users/u");
+
+        assert!(needs_full_disk_access("com.apple.Safari", home));
+        assert!(needs_full_disk_access("com.apple.mail", home));
+        assert!(!needs_full_disk_access("com.apple.dock", home));
+        assert!(!needs_full_disk_access("NSGlobalDomain", home));
+
+        // plist files inside containers, by home-relative or absolute path
+        assert!(needs_full_disk_access(
+            "~/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari.plist",
+            home
+        ));
+        assert!(needs_full_disk_access(
+            "/Users/u/Library/Group Containers/group.com.apple.notes/x.plist",
+            home
+        ));
+        assert!(!needs_full_disk_access(
+            "~/Library/Preferences/com.apple.finder.plist",
+            home
+        ));
+        assert!(!needs_full_disk_access(
+            "/Users/other/Library/Safari/x.plist",
+            home
+        ));
+    }
 }
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::brew::core::homebrew_env;
+    use cutler::commands::completion::Shell;
+    use cutler::commands::env::render_export;
+
+    #[test]
+    fn test_homebrew_env() {
+        let env = homebrew_env("/usr/bin:/bin", true);
+        assert_eq!(
+            env[0],
+            (
+                "PATH".to_string(),
+                "/opt/homebrew/sbin:/opt/homebrew/bin:/usr/bin:/bin".to_string()
+            )
+        );
+        assert!(env.contains(&("HOMEBREW_NO_AUTO_UPDATE".to_string(), "1".to_string())));
+
+        // $PATH is left alone if it's complete already, or if there's no Homebrew
+        let path = "/opt/homebrew/bin:/opt/homebrew/sbin:/usr/bin";
+        assert!(
+            homebrew_env(path, true)
+                .iter()
+                .all(|(name, _)| name != "PATH")
+        );
+        assert!(
+            homebrew_env("/usr/bin", false)
+                .iter()
+                .all(|(name, _)| name != "PATH")
+        );
+    }
+
+    #[test]
+    fn test_render_export() {
+        assert_eq!(
+            render_export(Shell::Zsh, "X", "it's"),
+            r"export X='it'\''s'"
+        );
+        assert_eq!(
+            render_export(Shell::Fish, "PATH", "/a:/b"),
+            "set -gx PATH '/a' '/b'"
+        );
+        assert_eq!(
+            render_export(Shell::Fish, "X", "it's"),
+            r"set -gx X 'it\'s'"
+        );
+        assert_eq!(
+            render_export(Shell::Elvish, "X", "it's"),
+            "set-env X 'it''s'"
+        );
+        assert_eq!(render_export(Shell::PowerShell, "X", "1"), "$env:X = '1'");
+        assert_eq!(
+            render_export(Shell::Nushell, "PATH", "/a:/b"),
+            r#"$env.PATH = ["/a", "/b"]"#
+        );
+        assert_eq!(render_export(Shell::Nushell, "X", "1"), r#"$env.X = "1""#);
+    }
+}
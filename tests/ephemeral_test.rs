@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::{
+        domains::convert::SerializablePrefValue,
+        ephemeral::{AGENT_LABEL, EphemeralState, agent_plist},
+        snapshot::core::SettingState,
+    };
+    use std::{path::Path, time::Duration};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_ephemeral_state_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+
+        assert!(EphemeralState::load(&config_path).await.is_none());
+
+        let state = EphemeralState::new(
+            Duration::from_secs(2 * 3600),
+            vec![SettingState {
+                domain: "com.apple.dock".into(),
+                key: "tilesize".into(),
+                original_value: Some(SerializablePrefValue::Integer(36)),
+            }],
+        );
+        state.save(&config_path).await.unwrap();
+
+        let loaded = EphemeralState::load(&config_path).await.unwrap();
+        assert_eq!(loaded.revert_at, state.revert_at);
+        assert_eq!(loaded.settings.len(), 1);
+        assert!(loaded.remaining() > Duration::from_secs(3600));
+
+        EphemeralState::delete(&config_path).await.unwrap();
+        assert!(EphemeralState::load(&config_path).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_wait_when_due() {
+        let state = EphemeralState::new(Duration::ZERO, vec![]);
+        assert!(state.remaining().is_zero());
+
+        // returns right away once due
+        state.wait().await;
+    }
+
+    #[test]
+    fn test_agent_plist() {
+        let plist = agent_plist(
+            Path::new("/usr/local/bin/cutler"),
+            Path::new("/Users/me/work.toml"),
+        )
+        .unwrap();
+
+        assert!(plist.contains(&format!("<string>{AGENT_LABEL}</string>")));
+        assert!(plist.contains("<string>/usr/local/bin/cutler</string>"));
+        assert!(plist.contains("<string>--ephemeral</string>"));
+        assert!(plist.contains("<string>--wait</string>"));
+        assert!(plist.contains("<key>RunAtLoad</key>\n\t<true/>"));
+
+        // the agent reverts against the same config, even when overridden
+        assert!(
+            plist.contains("<key>CUTLER_CONFIG</key>\n\t\t<string>/Users/me/work.toml</string>")
+        );
+    }
+}
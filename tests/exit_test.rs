@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, anyhow};
+    use cutler::util::exit::{ExitCode, coded, exit_code_for};
+    use cutler::util::sudo::{is_refused, refused_error};
+
+    #[test]
+    fn test_exit_code_for() {
+        // explicit codes survive added context
+        let err = Err::<(), _>(coded(ExitCode::Locked, "Config is locked."))
+            .context("Could not apply")
+            .unwrap_err();
+        assert_eq!(exit_code_for(&err), ExitCode::Locked);
+        assert_eq!(err.to_string(), "Could not apply");
+
+        // parse errors count as config errors
+        let parse = toml::from_str::<toml::Table>("[set").unwrap_err();
+        let err = anyhow::Error::new(parse).context("Failed to parse config");
+        assert_eq!(exit_code_for(&err), ExitCode::Config);
+
+        assert_eq!(exit_code_for(&anyhow!("Something else")), ExitCode::Failure);
+    }
+
+    #[test]
+    fn test_sudo_refused() {
+        assert_eq!(exit_code_for(&refused_error()), ExitCode::Sudo);
+
+        assert!(is_refused("sudo: 3 incorrect password attempts\n"));
+        assert!(is_refused("sudo: a password is required\n"));
+        assert!(is_refused("me is not in the sudoers file.\n"));
+        assert!(!is_refused("cat: /etc/x: No such file or directory\n"));
+    }
+
+    #[test]
+    fn test_exit_codes_are_stable() {
+        let codes: Vec<(i32, &str)> = ExitCode::ALL.iter().map(|c| (c.code(), c.name())).collect();
+        assert_eq!(
+            codes,
+            vec![
+                (0, "success"),
+                (1, "failure"),
+                (2, "usage"),
+                (3, "config"),
+                (4, "locked"),
+                (5, "drift"),
+                (6, "partial-apply"),
+                (7, "network"),
+                (8, "sudo"),
+            ]
+        );
+    }
+}
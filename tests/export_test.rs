@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::config::core::{Brew, Config, Formula, Tap};
+    use cutler::domains::menubar::{CURRENT_HOST, byhost_domain};
+    use cutler::export::{defaults_args, defaults_write, render_mobileconfig, render_script};
+    use defaults_rs::PrefValue;
+    use std::collections::HashMap;
+    use toml::{Table, Value};
+
+    #[test]
+    fn test_defaults_args() {
+        assert_eq!(
+            defaults_args(&PrefValue::String("left side".into())).unwrap(),
+            "-string 'left side'"
+        );
+        assert_eq!(defaults_args(&PrefValue::Integer(48)).unwrap(), "-int 48");
+        assert_eq!(
+            defaults_args(&PrefValue::Boolean(true)).unwrap(),
+            "-bool true"
+        );
+        assert_eq!(
+            defaults_args(&PrefValue::Data(vec![0xca, 0xfe])).unwrap(),
+            "-data cafe"
+        );
+
+        // collections are passed as plist fragments
+        let array = PrefValue::Array(vec![PrefValue::String("it's".into())]);
+        assert_eq!(
+            defaults_args(&array).unwrap(),
+            "'<array>\n\t<string>it'\\''s</string>\n</array>'"
+        );
+    }
+
+    #[test]
+    fn test_defaults_write() {
+        assert_eq!(
+            defaults_write("com.apple.dock"),
+            "defaults write com.apple.dock"
+        );
+        assert_eq!(
+            defaults_write(&byhost_domain(CURRENT_HOST)),
+            "defaults -currentHost write com.apple.controlcenter"
+        );
+        assert_eq!(
+            defaults_write("~/Library/Preferences/my app.plist"),
+            "defaults write \"$HOME\"/'Library/Preferences/my app.plist'"
+        );
+        assert_eq!(
+            defaults_write("/Library/Preferences/com.apple.loginwindow.plist"),
+            "sudo defaults write /Library/Preferences/com.apple.loginwindow.plist"
+        );
+        assert_eq!(
+            defaults_write("/Users/u/Library/Preferences/x.plist"),
+            "defaults write /Users/u/Library/Preferences/x.plist"
+        );
+    }
+
+    #[test]
+    fn test_render_script() {
+        let mut config = Config::new("config.toml".into());
+        config.brew = Some(Brew {
+            formulae: Some(vec![Formula::Detailed {
+                name: "node@20".into(),
+                pin: Some(true),
+            }]),
+            casks: Some(vec!["firefox".into()]),
+            taps: Some(vec![
+                Tap::from("machlit/tap"),
+                Tap::Detailed {
+                    name: "org/tap".into(),
+                    url: Some("git@github.com:org/homebrew-tap.git".into()),
+                    revision: Some("abc123".into()),
+                },
+            ]),
+            ..Default::default()
+        });
+
+        let mut dock = Table::new();
+        dock.insert("tilesize".into(), Value::Integer(48));
+        dock.insert("autohide".into(), Value::Boolean(true));
+        let domains = HashMap::from([("dock".to_string(), dock)]);
+
+        let script = render_script(&config, &domains).unwrap();
+        let lines: Vec<&str> = script.lines().collect();
+
+        assert_eq!(lines[0], "#!/bin/sh");
+        assert!(lines.contains(&"brew install node@20"));
+        assert!(lines.contains(&"brew pin node@20"));
+        assert!(lines.contains(&"brew install --cask firefox"));
+        assert!(lines.contains(&"brew tap machlit/tap"));
+        assert!(lines.contains(&"brew tap org/tap git@github.com:org/homebrew-tap.git"));
+        assert!(lines.contains(&"git -C \"$(brew --repository org/tap)\" checkout --quiet abc123"));
+
+        // settings are sorted for stable output
+        let autohide = lines
+            .iter()
+            .position(|l| *l == "defaults write com.apple.dock autohide -bool true")
+            .unwrap();
+        let tilesize = lines
+            .iter()
+            .position(|l| *l == "defaults write com.apple.dock tilesize -int 48")
+            .unwrap();
+        assert!(autohide < tilesize);
+
+        // no [mas], no mas section
+        assert!(!script.contains("mas install"));
+    }
+
+    #[test]
+    fn test_render_mobileconfig() {
+        let mut dock = Table::new();
+        dock.insert("tilesize".into(), Value::Integer(48));
+        let mut global = Table::new();
+        global.insert("AppleShowAllExtensions".into(), Value::Boolean(true));
+        let mut file = Table::new();
+        file.insert("GuestEnabled".into(), Value::Boolean(false));
+        let domains = HashMap::from([
+            ("dock".to_string(), dock),
+            ("NSGlobalDomain".to_string(), global),
+            (
+                "/Library/Preferences/com.apple.loginwindow.plist".to_string(),
+                file,
+            ),
+        ]);
+
+        let xml = render_mobileconfig(&domains, "com.example.prefs").unwrap();
+        let profile = plist::Value::from_reader_xml(xml.as_bytes()).unwrap();
+        let profile = profile.as_dictionary().unwrap();
+
+        assert_eq!(profile["PayloadType"].as_string(), Some("Configuration"));
+        assert_eq!(
+            profile["PayloadIdentifier"].as_string(),
+            Some("com.example.prefs")
+        );
+
+        let payloads = profile["PayloadContent"].as_array().unwrap();
+        let kinds: Vec<&str> = payloads
+            .iter()
+            .map(|p| {
+                p.as_dictionary().unwrap()["PayloadType"]
+                    .as_string()
+                    .unwrap()
+            })
+            .collect();
+        // path domains are left out
+        assert_eq!(kinds, [".GlobalPreferences", "com.apple.dock"]);
+
+        let dock = payloads[1].as_dictionary().unwrap();
+        assert_eq!(dock["tilesize"].as_signed_integer(), Some(48));
+
+        // UUIDs are stable across exports
+        assert_eq!(
+            xml,
+            render_mobileconfig(&domains, "com.example.prefs").unwrap()
+        );
+    }
+}
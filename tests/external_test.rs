@@ -4,8 +4,14 @@
 mod tests {
     use cutler::{
         cli::atomic::set_dry_run,
-        config::core::{Command, Config},
-        exec::core::{ExecMode, run_all, run_one},
+        config::{
+            core::{Command, Config, Group, Script, Shell, Var},
+            vars::{is_secret, lookup_var},
+        },
+        exec::core::{ExecMode, extract_cmd, run_all, run_group, run_one, take_line},
+        exec::logs::log_file_name,
+        exec::plan::{Stage, plan_all},
+        util::sudo::pam_has_tid,
     };
     use std::collections::HashMap;
 
@@ -23,10 +29,7 @@ mod tests {
             "foo".into(),
             Command {
                 run: "echo Hello $hostname".into(),
-                ensure_first: None,
-                required: None,
-                flag: None,
-                sudo: None,
+                ..Default::default()
             },
         );
 
@@ -52,10 +55,8 @@ mod tests {
             "whoami".into(),
             Command {
                 run: "echo $USER".into(),
-                ensure_first: None,
-                required: None,
-                flag: None,
                 sudo: Some(true),
+                ..Default::default()
             },
         );
 
@@ -67,4 +68,339 @@ mod tests {
         // Dry‑run single command
         assert!(run_one(config, "whoami").await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_run_group_dry_run() {
+        set_dry_run(true);
+
+        let mut command_map = HashMap::new();
+        for name in ["first", "second"] {
+            command_map.insert(
+                name.to_string(),
+                Command {
+                    run: format!("echo {name}").into(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut config = Config::new(Default::default());
+        config.command = Some(command_map);
+        config.group = Some(HashMap::from([
+            (
+                "setup".to_string(),
+                Group {
+                    commands: vec!["second".into(), "first".into()],
+                },
+            ),
+            (
+                "typo".to_string(),
+                Group {
+                    commands: vec!["first".into(), "thrid".into()],
+                },
+            ),
+        ]));
+
+        assert!(run_group(config.clone(), "setup").await.is_ok());
+
+        // unknown commands are caught before anything runs
+        let err = run_group(config.clone(), "typo").await.unwrap_err();
+        assert!(err.to_string().contains("thrid"), "{err}");
+        assert!(run_group(config, "missing").await.is_err());
+    }
+
+    #[test]
+    fn test_tag_selection() {
+        let mut command_map = HashMap::new();
+        command_map.insert(
+            "tagged".into(),
+            Command {
+                run: "echo tagged".into(),
+                tags: Some(vec!["bootstrap".into()]),
+                ..Default::default()
+            },
+        );
+        command_map.insert(
+            "legacy".into(),
+            Command {
+                run: "echo legacy".into(),
+                flag: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let mut config = Config::new(Default::default());
+        config.command = Some(command_map);
+
+        let tagged = extract_cmd(&config, "tagged").unwrap();
+        let legacy = extract_cmd(&config, "legacy").unwrap();
+
+        let bootstrap = ExecMode::Tagged(vec!["bootstrap".into()]);
+        assert!(bootstrap.selects(&tagged));
+        assert!(!bootstrap.selects(&legacy));
+
+        // `flag = true` maps onto the `flagged` tag
+        assert!(legacy.is_flagged());
+        assert!(ExecMode::Flagged.selects(&legacy));
+        assert!(!ExecMode::Regular.selects(&legacy));
+        assert!(ExecMode::Regular.selects(&tagged));
+        assert!(ExecMode::All.selects(&legacy));
+    }
+
+    #[test]
+    fn test_dynamic_vars() {
+        let mut vars = HashMap::new();
+        vars.insert("static".into(), "plain".into());
+        vars.insert(
+            "dynamic".into(),
+            Var::Dynamic {
+                from_command: "echo '  computed  '".into(),
+            },
+        );
+
+        let mut config = Config::new(Default::default());
+        config.vars = Some(vars);
+
+        assert_eq!(lookup_var(&config, "static").unwrap(), "plain");
+        assert_eq!(lookup_var(&config, "dynamic").unwrap(), "computed");
+        assert_eq!(lookup_var(&config, "unknown"), None);
+    }
+
+    #[test]
+    fn test_vars_resolve_lazily() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("ran");
+
+        let mut vars = HashMap::new();
+        vars.insert(
+            "unused".into(),
+            Var::Dynamic {
+                from_command: format!("touch '{}'", marker.display()),
+            },
+        );
+        vars.insert(
+            "name".into(),
+            Var::Dynamic {
+                from_command: "echo cutler".into(),
+            },
+        );
+
+        let mut commands = HashMap::new();
+        commands.insert(
+            "greet".into(),
+            Command {
+                run: "echo $name".into(),
+                ..Default::default()
+            },
+        );
+
+        let mut config = Config::new(Default::default());
+        config.vars = Some(vars);
+        config.command = Some(commands);
+
+        // only variables the command references run their commands
+        let job = extract_cmd(&config, "greet").unwrap();
+        assert_eq!(job.run, "echo cutler");
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_secret_vars() {
+        let vars: HashMap<String, Var> = toml::from_str(
+            r#"
+            plain = "value"
+            marked = { value = "hunter22", secret = true }
+            unmarked = { value = "visible", secret = false }
+            keychain = { from_command = "security find-generic-password -w -s npm" }
+            GITHUB_TOKEN = "ghp_abc"
+            "#,
+        )
+        .unwrap();
+
+        let secret = |name: &str| is_secret(name, &vars[name]);
+        assert!(!secret("plain"));
+        assert!(secret("marked"));
+        assert!(!secret("unmarked"));
+        assert!(secret("keychain"));
+        assert!(secret("GITHUB_TOKEN"));
+
+        let mut config = Config::new(Default::default());
+        config.vars = Some(vars.into_iter().filter(|(n, _)| n != "keychain").collect());
+        assert_eq!(lookup_var(&config, "marked").unwrap(), "hunter22");
+
+        // a secret without a value is a config error rather than resolving to nothing
+        let err = toml::from_str::<HashMap<String, Var>>("empty = { secret = true }").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("a secret var needs a `value` or `from_command`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_pam_has_tid() {
+        let template = "# sudo_local: local config file\n#auth       sufficient     pam_tid.so\n";
+        assert!(!pam_has_tid(template));
+
+        let enabled =
+            "auth       optional       pam_reattach.so\nauth       sufficient     pam_tid.so\n";
+        assert!(pam_has_tid(enabled));
+
+        assert!(!pam_has_tid(""));
+    }
+
+    #[test]
+    fn test_script_lines() {
+        let script: Command = toml::from_str(
+            r#"
+            run = ["cd /tmp", "touch a"]
+            shell = "zsh"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.shell, Some(Shell::Zsh));
+        assert_eq!(script.run.render(Shell::Zsh), "set -e\ncd /tmp\ntouch a");
+        assert_eq!(
+            script.run.render(Shell::Fish),
+            "cd /tmp\nor exit $status\ntouch a\nor exit $status"
+        );
+
+        // plain strings are left alone
+        assert_eq!(Script::from("echo hi").render(Shell::Sh), "echo hi");
+    }
+
+    #[test]
+    fn test_extract_guards() {
+        let command: Command = toml::from_str(
+            r#"
+            run = "xcodebuild -runFirstLaunch"
+            only_if = "test -d $xcode"
+            skip_if = "false"
+            "#,
+        )
+        .unwrap();
+
+        let mut config = Config::new(Default::default());
+        config.vars = Some(HashMap::from([(
+            "xcode".into(),
+            "/Applications/Xcode.app".into(),
+        )]));
+        config.command = Some(HashMap::from([("xcode".into(), command)]));
+
+        // variables are resolved in guards like in `run`
+        let job = extract_cmd(&config, "xcode").unwrap();
+        assert_eq!(
+            job.only_if.as_deref(),
+            Some("test -d /Applications/Xcode.app")
+        );
+        assert_eq!(job.skip_if.as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn test_plan_all() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("ran");
+
+        let mut vars = HashMap::new();
+        vars.insert("host".into(), "box".into());
+        vars.insert(
+            "token".into(),
+            Var::Dynamic {
+                from_command: format!("touch '{}'; echo hunter2", marker.display()),
+            },
+        );
+
+        let mut command_map = HashMap::new();
+        command_map.insert(
+            "login".into(),
+            Command {
+                run: "login $host $token $HOME".into(),
+                sudo: Some(true),
+                ..Default::default()
+            },
+        );
+        command_map.insert(
+            "setup".into(),
+            Command {
+                run: "echo setup".into(),
+                ensure_first: Some(true),
+                ..Default::default()
+            },
+        );
+        command_map.insert(
+            "other".into(),
+            Command {
+                run: "echo other".into(),
+                ..Default::default()
+            },
+        );
+
+        let mut config = Config::new(Default::default());
+        config.vars = Some(vars);
+        config.command = Some(command_map);
+
+        let plan = plan_all(&config, &ExecMode::Regular);
+        let order: Vec<_> = plan.iter().map(|p| (p.step, p.name.as_str())).collect();
+        assert_eq!(order, [(1, "setup"), (2, "login"), (2, "other")]);
+        assert_eq!(plan[0].stage, Stage::EnsureFirst);
+        assert_eq!(plan[1].stage, Stage::Concurrent);
+
+        // secrets are masked and the environment isn't expanded
+        assert_eq!(plan[1].run, "login box ******** $HOME");
+        assert!(plan[1].sudo);
+
+        // planning runs none of the variable commands
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_take_line() {
+        let mut buf = b"plain\r\n".to_vec();
+        assert_eq!(take_line(&mut buf), "plain");
+        assert!(buf.is_empty());
+
+        // invalid UTF-8 is replaced instead of ending the output
+        let mut buf = b"caf\xe9\n".to_vec();
+        assert_eq!(take_line(&mut buf), "caf\u{fffd}");
+
+        // the last line may lack its line ending
+        let mut buf = b"tail".to_vec();
+        assert_eq!(take_line(&mut buf), "tail");
+    }
+
+    #[test]
+    fn test_log_file_name() {
+        // 2025-03-04 13:37:05 UTC
+        assert_eq!(
+            log_file_name("dotfiles", 1_741_095_425),
+            "dotfiles-20250304-133705.log"
+        );
+        assert_eq!(log_file_name("a/b c", 0), "a_b_c-19700101-000000.log");
+    }
+
+    #[test]
+    fn test_extract_undo() {
+        let mut vars = HashMap::new();
+        vars.insert("hostname".into(), "darkstar".into());
+
+        let mut command_map = HashMap::new();
+        command_map.insert(
+            "hostname".into(),
+            Command {
+                run: "scutil --set HostName $hostname".into(),
+                undo: Some("scutil --set HostName old-$hostname".into()),
+                ..Default::default()
+            },
+        );
+
+        let mut config = Config::new(Default::default());
+        config.vars = Some(vars);
+        config.command = Some(command_map);
+
+        let job = extract_cmd(&config, "hostname").unwrap();
+        assert_eq!(
+            job.undo.as_deref(),
+            Some("scutil --set HostName old-darkstar")
+        );
+    }
 }
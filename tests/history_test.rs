@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::snapshot::crypt::set_encryption;
+    use cutler::util::history::{self, Change, HIDDEN_VALUE, format_timestamp};
+    use cutler::util::summary::Summary;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(1_741_095_420), "2025-03-04 13:37 UTC");
+    }
+
+    #[tokio::test]
+    async fn test_history_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        fs::write(&config_path, "[set.dock]\ntilesize = 48\n").unwrap();
+
+        assert!(history::load(&config_path).await.is_empty());
+
+        let change = Change {
+            domain: "com.apple.dock".into(),
+            key: "tilesize".into(),
+            from: None,
+            to: Some("48".into()),
+        };
+        history::add_change(change.clone());
+        history::record(&config_path, "apply", &Summary::default()).await;
+
+        // changes belong to the run they were recorded in
+        history::record(&config_path, "unapply", &Summary::default()).await;
+
+        let runs = history::load(&config_path).await;
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[0].id, runs[0].command.as_str()), (1, "apply"));
+        assert_eq!(runs[0].changes, vec![change.clone()]);
+        assert_eq!(runs[0].changed, 1);
+        assert!(!runs[0].digest.is_empty());
+        assert_eq!((runs[1].id, runs[1].changed), (2, 0));
+
+        // broken lines don't take the rest of the history with them
+        let path = history::get_history_path(&config_path).unwrap();
+        let mut data = fs::read_to_string(&path).unwrap();
+        data.insert_str(0, "not json\n");
+        fs::write(&path, data).unwrap();
+        assert_eq!(history::load(&config_path).await.len(), 2);
+
+        // with encrypted snapshots, values stay out of the history
+        set_encryption(true);
+        history::add_change(change);
+        history::record(&config_path, "apply", &Summary::default()).await;
+        set_encryption(false);
+
+        let runs = history::load(&config_path).await;
+        assert_eq!(runs[2].changes[0].from, None);
+        assert_eq!(runs[2].changes[0].to.as_deref(), Some(HIDDEN_VALUE));
+    }
+}
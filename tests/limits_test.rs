@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::util::limits::{acquire, cli_jobs, jobs, set_config_jobs, set_jobs};
+
+    #[tokio::test]
+    async fn test_jobs_precedence() {
+        // defaults to the number of CPUs
+        assert!(jobs() >= 1);
+        assert_eq!(cli_jobs(), None);
+
+        set_config_jobs(Some(3));
+        assert_eq!(jobs(), 3);
+
+        // --jobs wins over the config
+        set_jobs(Some(2));
+        assert_eq!(cli_jobs(), Some(2));
+        assert_eq!(jobs(), 2);
+
+        // the shared semaphore is sized on first use
+        let first = acquire().await;
+        let second = acquire().await;
+        let third = tokio::time::timeout(std::time::Duration::from_millis(50), acquire()).await;
+        assert!(third.is_err());
+
+        drop(first);
+        drop(second);
+        drop(acquire().await);
+    }
+}
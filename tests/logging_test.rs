@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::util::logging::{
+        BOLD, LogStyle, MASK, RED, RESET, add_secret, color_code, escape_annotation, redact,
+        set_log_style, should_annotate, strip_ansi,
+    };
+
+    #[test]
+    fn test_strip_ansi() {
+        let text = format!("{RED}ERR  {RESET} {BOLD}com.apple.dock{RESET} \x1b[38;5;208mx");
+        assert_eq!(strip_ansi(&text), "ERR   com.apple.dock x");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn test_color_code() {
+        assert_eq!(color_code("red").as_deref(), Some(RED));
+        assert_eq!(color_code(" Orange ").as_deref(), Some("\x1b[38;5;208m"));
+        assert_eq!(color_code("42").as_deref(), Some("\x1b[38;5;42m"));
+        assert_eq!(color_code("none").as_deref(), Some(""));
+        assert!(color_code("chartreuse").is_none());
+        assert!(color_code("300").is_none());
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        set_log_style(LogStyle::Github);
+        assert!(should_annotate());
+        set_log_style(LogStyle::Plain);
+        assert!(!should_annotate());
+
+        assert_eq!(
+            escape_annotation(&format!("{BOLD}dock{RESET}: 100% off\nnext")),
+            "dock: 100%25 off%0Anext"
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        add_secret("ghp_abc123");
+        add_secret("ghp_abc123xyz");
+        // too short to be masked safely
+        add_secret("ab");
+
+        assert_eq!(
+            redact("token=ghp_abc123 other=ghp_abc123xyz ab"),
+            format!("token={MASK} other={MASK} ab")
+        );
+    }
+}
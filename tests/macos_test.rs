@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::util::macos::{MacosVersion, VersionRange};
+
+    #[test]
+    fn test_parse_versions() {
+        let sonoma = MacosVersion::new(14, 0);
+        assert_eq!("14".parse(), Ok(sonoma));
+        assert_eq!("sonoma".parse(), Ok(sonoma));
+        assert_eq!("14.2.1".parse(), Ok(MacosVersion::new(14, 2)));
+        assert_eq!("Big Sur".parse(), Ok(MacosVersion::new(11, 0)));
+        assert_eq!("high-sierra".parse(), Ok(MacosVersion::new(10, 13)));
+        assert!("fourteen".parse::<MacosVersion>().is_err());
+
+        // minor versions only matter before Big Sur
+        assert!(MacosVersion::new(10, 15) < MacosVersion::new(11, 0));
+        assert!(MacosVersion::new(14, 2) > sonoma);
+        assert_eq!(sonoma.to_string(), "macOS 14 Sonoma");
+        assert_eq!(MacosVersion::new(14, 2).to_string(), "macOS 14.2 Sonoma");
+        assert_eq!(
+            MacosVersion::new(10, 15).to_string(),
+            "macOS 10.15 Catalina"
+        );
+        assert_eq!(MacosVersion::new(99, 0).to_string(), "macOS 99");
+    }
+
+    #[test]
+    fn test_version_ranges() {
+        let range = VersionRange {
+            min: Some("13".parse().unwrap()),
+            max: Some("14".parse().unwrap()),
+        };
+        assert!(range.contains(Some(MacosVersion::new(13, 0))));
+        // a bare major version includes its updates
+        assert!(range.contains(Some(MacosVersion::new(14, 6))));
+        assert!(!range.contains(Some(MacosVersion::new(12, 7))));
+        assert!(!range.contains(Some(MacosVersion::new(15, 0))));
+        // nothing is skipped on an unknown version
+        assert!(range.contains(None));
+
+        let legacy = VersionRange {
+            min: None,
+            max: Some(MacosVersion::new(10, 14)),
+        };
+        assert!(legacy.contains(Some(MacosVersion::new(10, 14))));
+        assert!(!legacy.contains(Some(MacosVersion::new(10, 15))));
+        assert_eq!(legacy.to_string(), "up to macOS 10.14 Mojave");
+    }
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::config::core::Config;
+    use cutler::plist::core::{expand_path, render};
+
+    #[test]
+    fn test_render_plist() {
+        let config: Config = toml::from_str(
+            r#"
+[plist."~/Library/Preferences/com.example.app.plist"]
+Name = "Tom & Jerry"
+Count = 3
+Enabled = true
+Items = [1.5, "two"]
+
+[plist."~/Library/Preferences/com.example.app.plist".Window]
+Width = 800
+"#,
+        )
+        .unwrap();
+
+        let plists = config.plist.unwrap();
+        let (path, table) = plists.iter().next().unwrap();
+
+        assert!(expand_path(path).ends_with("Library/Preferences/com.example.app.plist"));
+        assert!(!expand_path(path).starts_with("~"));
+
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Count</key>
+	<integer>3</integer>
+	<key>Enabled</key>
+	<true/>
+	<key>Items</key>
+	<array>
+		<real>1.5</real>
+		<string>two</string>
+	</array>
+	<key>Name</key>
+	<string>Tom &amp; Jerry</string>
+	<key>Window</key>
+	<dict>
+		<key>Width</key>
+		<integer>800</integer>
+	</dict>
+</dict>
+</plist>
+"#;
+        assert_eq!(render(table).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_render_invalid_values() {
+        let table: toml::Table = toml::from_str(
+            r#"
+utc = 2025-01-01T18:00:00+02:00
+local = 2025-01-01T18:00:00
+nan = nan
+inf = -inf
+"#,
+        )
+        .unwrap();
+        let only = |key: &str| toml::Table::from_iter([(key.to_string(), table[key].clone())]);
+
+        // dates are written in UTC, as plists expect
+        assert!(
+            render(&only("utc"))
+                .unwrap()
+                .contains("<date>2025-01-01T16:00:00Z</date>")
+        );
+        assert!(render(&only("local")).is_err());
+        assert!(render(&only("nan")).is_err());
+        assert!(render(&only("inf")).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::config::core::Config;
+    use cutler::plugin::{plugin_binary, plugin_env};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_plugin_env() {
+        assert_eq!(plugin_binary("asdf"), "cutler-asdf");
+
+        let mut config = Config::new("/tmp/cutler/config.toml".into());
+        let env: HashMap<String, String> = plugin_env("asdf", &config).into_iter().collect();
+        assert_eq!(env["CUTLER_CONFIG"], "/tmp/cutler/config.toml");
+        assert_eq!(env["CUTLER_DRY_RUN"], "0");
+        assert_eq!(env["CUTLER_NO_PROMPTS"], "0");
+        assert_eq!(env["CUTLER_OFFLINE"], "0");
+        assert!(!env.contains_key("CUTLER_PLUGIN_CONFIG"));
+
+        // plugin tables are accepted by the config and passed along as JSON
+        let parsed: Config = toml::from_str(
+            r#"
+            [plugin.asdf]
+            tools = ["nodejs", "python"]
+            "#,
+        )
+        .unwrap();
+        config.plugin = parsed.plugin;
+
+        let env: HashMap<String, String> = plugin_env("asdf", &config).into_iter().collect();
+        assert_eq!(
+            env["CUTLER_PLUGIN_CONFIG"],
+            r#"{"tools":["nodejs","python"]}"#
+        );
+
+        let env: HashMap<String, String> = plugin_env("docker", &config).into_iter().collect();
+        assert!(!env.contains_key("CUTLER_PLUGIN_CONFIG"));
+    }
+}
@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::util::porcelain::{State, record};
+
+    #[test]
+    fn test_record_tab_separated() {
+        assert_eq!(
+            record(
+                "com.apple.dock",
+                "tilesize",
+                State::Drift,
+                "36",
+                "48",
+                false
+            ),
+            "com.apple.dock\ttilesize\tdrift\t36\t48\n"
+        );
+        assert_eq!(
+            record("NSGlobalDomain", "Key", State::Missing, "", "true", false),
+            "NSGlobalDomain\tKey\tmissing\t\ttrue\n"
+        );
+
+        // separators inside values are escaped
+        assert_eq!(
+            record("d", "k", State::Match, "a\tb\nc\\", "x", false),
+            "d\tk\tmatch\ta\\tb\\nc\\\\\tx\n"
+        );
+    }
+
+    #[test]
+    fn test_record_nul_terminated() {
+        assert_eq!(
+            record("d", "k", State::Match, "a\tb", "a\tb", true),
+            "d\0k\0match\0a\tb\0a\tb\0"
+        );
+    }
+}
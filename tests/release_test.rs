@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::commands::self_update::Installer;
+    use cutler::config::core::Channel;
+    use cutler::util::release::{
+        GithubRelease, checksum_asset, find, latest, parse_checksum, verify_checksum,
+    };
+    use self_update::update::{Release, ReleaseAsset};
+    use semver::Version;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const HASH: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            download_url: format!("https://example.com/{name}"),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let name = "cutler-1.0.0-aarch64-apple-darwin.tar.gz";
+
+        // shasum output, including binary mode and paths
+        let sums = format!("{}  other.tar.gz\n{HASH}  {name}\n", "0".repeat(64));
+        assert_eq!(parse_checksum(&sums, name).as_deref(), Some(HASH));
+        let sums = format!("{HASH} *dist/{name}");
+        assert_eq!(parse_checksum(&sums, name).as_deref(), Some(HASH));
+
+        // a bare hash belongs to the asset it's named after
+        assert_eq!(
+            parse_checksum(&format!("{}\n", HASH.to_uppercase()), name).as_deref(),
+            Some(HASH)
+        );
+
+        assert_eq!(parse_checksum(&format!("{HASH}  other.tar.gz"), name), None);
+        assert_eq!(parse_checksum("not a checksum", name), None);
+    }
+
+    #[test]
+    fn test_checksum_asset() {
+        let archive = asset("cutler.tar.gz");
+        let mut release = Release {
+            assets: vec![archive.clone(), asset("SHA256SUMS")],
+            ..Default::default()
+        };
+        assert_eq!(
+            checksum_asset(&release, &archive).unwrap().name,
+            "SHA256SUMS"
+        );
+
+        // the asset's own checksum wins
+        release.assets.push(asset("cutler.tar.gz.sha256"));
+        assert_eq!(
+            checksum_asset(&release, &archive).unwrap().name,
+            "cutler.tar.gz.sha256"
+        );
+
+        release.assets = vec![archive.clone()];
+        assert!(checksum_asset(&release, &archive).is_none());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("archive");
+        fs::write(&path, "hello").unwrap();
+
+        assert!(verify_checksum(&path, HASH).is_ok());
+        assert!(verify_checksum(&path, &HASH.to_uppercase()).is_ok());
+        assert!(verify_checksum(&path, &"0".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_detect_installer() {
+        let cargo_bin = "/Users/me/.cargo/bin/cutler";
+        let cases = [
+            ("/opt/homebrew/bin/cutler", Some(Installer::Homebrew)),
+            (
+                "/opt/homebrew/Cellar/cutler/1.0.0/bin/cutler",
+                Some(Installer::Homebrew),
+            ),
+            (cargo_bin, Some(Installer::Cargo)),
+            (
+                "/Users/me/.local/share/mise/installs/cargo-cutler/1.0.0/bin/cutler",
+                Some(Installer::Mise),
+            ),
+            ("/usr/local/bin/cutler", None),
+        ];
+
+        for (exe, installer) in cases {
+            assert_eq!(Installer::from_exe(exe, cargo_bin), installer, "{exe}");
+        }
+        assert_eq!(Installer::from_exe("/usr/local/bin/cutler", ""), None);
+
+        assert_eq!(
+            Installer::Homebrew.upgrade_command().join(" "),
+            "brew upgrade cutler"
+        );
+    }
+
+    #[test]
+    fn test_release_channels() {
+        let release = |tag: &str, prerelease: bool, draft: bool| GithubRelease {
+            tag_name: tag.into(),
+            prerelease,
+            draft,
+        };
+        let releases = vec![
+            release("v1.3.0", false, true),
+            release("v1.2.0-rc.1", true, false),
+            release("v1.1.1", false, false),
+            release("v1.2.0-beta", false, false),
+            release("not-a-version", false, false),
+            release("v1.1.0", false, false),
+        ];
+
+        let tag = |channel| latest(&releases, channel).map(|r| r.tag_name.as_str());
+        assert_eq!(tag(Channel::Stable), Some("v1.1.1"));
+        assert_eq!(tag(Channel::Prerelease), Some("v1.2.0-rc.1"));
+
+        // rolling back to a specific version
+        let version = Version::parse("1.1.0").unwrap();
+        assert_eq!(find(&releases, &version).unwrap().tag_name, "v1.1.0");
+        assert!(find(&releases, &Version::parse("1.3.0").unwrap()).is_none());
+
+        let config: cutler::config::core::Config =
+            toml::from_str("[update]\nchannel = \"prerelease\"").unwrap();
+        assert_eq!(config.update.unwrap().channel, Some(Channel::Prerelease));
+    }
+}
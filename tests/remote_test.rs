@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::cli::atomic::set_offline;
+    use cutler::config::core::{Config, Lock, Network, Remotes};
+    use cutler::config::remote::{
+        RemoteCache, RemoteCacheEntry, RemoteConfigManager, RemoteLayers, cached_remote_lock,
+        deep_merge, flatten, get_remote_cache_path, keep_local_threshold,
+    };
+    use cutler::util::exit::{ExitCode, exit_code_for};
+    use cutler::util::net::{
+        DEFAULT_RETRIES, DEFAULT_TIMEOUT, client, is_transient_message, proxy, retries, retry,
+        set_network, timeout,
+    };
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_layered_remotes() {
+        let config: Config = toml::from_str(
+            r#"
+[[remote]]
+url = "https://example.com/base.toml"
+autosync = true
+
+[[remote]]
+url = "https://example.com/me.toml"
+"#,
+        )
+        .unwrap();
+
+        let remotes = config.remote.unwrap();
+        assert_eq!(remotes.layers().len(), 2);
+        assert_eq!(remotes.policy().unwrap().autosync, Some(true));
+
+        // layers survive being written back into a merged config
+        let mut merged = toml::Table::new();
+        merged.insert("lock".into(), toml::Value::Boolean(false));
+        merged.insert("remote".into(), toml::Value::try_from(&remotes).unwrap());
+        let reparsed: Config = toml::from_str(&toml::to_string(&merged).unwrap()).unwrap();
+        assert_eq!(reparsed.remote, Some(remotes));
+
+        let config: Config = toml::from_str(
+            r#"
+[remote]
+url = "https://example.com/config.toml"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.remote.unwrap().layers().len(), 1);
+    }
+
+    #[test]
+    fn test_deep_merge_layers() {
+        let mut base: toml::Table = toml::from_str(
+            r#"
+[set.dock]
+tilesize = 46
+autohide = true
+
+[brew]
+formulae = ["git", "wget"]
+"#,
+        )
+        .unwrap();
+        let overlay: toml::Table = toml::from_str(
+            r#"
+[set.dock]
+tilesize = 64
+
+[brew]
+formulae = ["git"]
+"#,
+        )
+        .unwrap();
+
+        deep_merge(&mut base, overlay);
+        let flat = flatten(&base);
+
+        assert_eq!(flat["set.dock.tilesize"], toml::Value::Integer(64));
+        assert_eq!(flat["set.dock.autohide"], toml::Value::Boolean(true));
+        // arrays are replaced, not concatenated
+        assert_eq!(
+            flat["brew.formulae"],
+            toml::Value::Array(vec![toml::Value::String("git".into())])
+        );
+    }
+
+    #[test]
+    fn test_remote_lock() {
+        assert!(RemoteLayers::locks(
+            "lock = true\n[set.dock]\ntilesize = 48\n"
+        ));
+        assert!(!RemoteLayers::locks("lock = false\n"));
+        assert!(!RemoteLayers::locks("[set.dock]\nlock = true\n"));
+        assert!(!RemoteLayers::locks("not toml ="));
+
+        // a lock belongs to the remote only if the remote config sets it
+        let mut config: Config =
+            toml::from_str("lock = true\n[remote]\nurl = \"https://x/c.toml\"\n").unwrap();
+        assert!(!config.is_remote_locked());
+        config.remote_lock = Some(Lock::Plain(false));
+        assert!(!config.is_remote_locked());
+        config.remote_lock = Some(Lock::Plain(true));
+        assert!(config.is_remote_locked());
+    }
+
+    #[test]
+    fn test_keep_local_threshold() {
+        let threshold = |text: &str| {
+            let config: Config = toml::from_str(text).unwrap();
+            config.safety.and_then(|s| s.confirm_over)
+        };
+        let remote = "[set.dock]\ntilesize = 48\n\n[safety]\nconfirm_over = 1000\n";
+
+        // the remote can neither raise nor drop the local threshold
+        let kept = keep_local_threshold(remote, "[safety]\nconfirm_over = 10\n").unwrap();
+        assert_eq!(threshold(&kept), Some(10));
+        let kept = keep_local_threshold(
+            "[set.dock]\ntilesize = 48\n",
+            "[safety]\nconfirm_over = 10\n",
+        )
+        .unwrap();
+        assert_eq!(threshold(&kept), Some(10));
+
+        // nor set one the local config doesn't have
+        let kept = keep_local_threshold(remote, "").unwrap();
+        assert_eq!(threshold(&kept), None);
+        assert!(kept.contains("tilesize = 48"));
+
+        // untouched when both agree
+        let same = "[safety]\nconfirm_over = 10 # keep\n";
+        assert_eq!(keep_local_threshold(same, same).unwrap(), same);
+    }
+
+    #[tokio::test]
+    async fn test_cached_remote_lock() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let cache_path = get_remote_cache_path(&config_path).unwrap();
+        let remotes: Remotes = toml::from_str::<Config>(
+            "[[remote]]\nurl = \"https://x/base.toml\"\n[[remote]]\nurl = \"https://x/team.toml\"\n",
+        )
+        .unwrap()
+        .remote
+        .unwrap();
+        let entry = |body: &str| RemoteCacheEntry {
+            etag: None,
+            last_modified: None,
+            body: body.into(),
+        };
+
+        // never fetched
+        assert_eq!(cached_remote_lock(&config_path, &remotes).await, None);
+
+        RemoteCache::store_at(&cache_path, "https://x/base.toml", entry("lock = true\n"))
+            .await
+            .unwrap();
+        assert_eq!(
+            cached_remote_lock(&config_path, &remotes).await,
+            Some(Lock::Plain(true))
+        );
+
+        // later layers win
+        RemoteCache::store_at(&cache_path, "https://x/team.toml", entry("lock = false\n"))
+            .await
+            .unwrap();
+        assert_eq!(
+            cached_remote_lock(&config_path, &remotes).await,
+            Some(Lock::Plain(false))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_cache_per_layer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("remote_cache.json");
+        let entry = |etag: &str, body: &str| RemoteCacheEntry {
+            etag: Some(etag.into()),
+            last_modified: None,
+            body: body.into(),
+        };
+
+        let base = "https://example.com/base.toml";
+        let team = "https://example.com/team.toml";
+        RemoteCache::store_at(&path, base, entry("\"a\"", "[set.dock]\ntilesize = 48\n"))
+            .await
+            .unwrap();
+        RemoteCache::store_at(&path, team, entry("\"b\"", "[set.dock]\nautohide = true\n"))
+            .await
+            .unwrap();
+
+        // the second layer doesn't overwrite the first one
+        let cache = RemoteCache::read(&path).await;
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(
+            cache.entries[base],
+            entry("\"a\"", "[set.dock]\ntilesize = 48\n")
+        );
+        assert_eq!(cache.entries[team].etag.as_deref(), Some("\"b\""));
+
+        // refetching a layer only replaces its own entry
+        RemoteCache::store_at(&path, base, entry("\"c\"", "lock = true\n"))
+            .await
+            .unwrap();
+        let cache = RemoteCache::read(&path).await;
+        assert_eq!(cache.entries[base].etag.as_deref(), Some("\"c\""));
+        assert_eq!(cache.entries[team].etag.as_deref(), Some("\"b\""));
+
+        // caches of older versions are dropped instead of failing
+        std::fs::write(
+            &path,
+            r#"{"url":"x","etag":null,"last_modified":null,"body":""}"#,
+        )
+        .unwrap();
+        assert!(RemoteCache::read(&path).await.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_offline_fetch_fails_fast() {
+        set_offline(true);
+        let err = RemoteConfigManager::new("https://example.invalid/config.toml".into())
+            .fetch()
+            .await
+            .unwrap_err();
+        set_offline(false);
+
+        assert_eq!(exit_code_for(&err), ExitCode::Network);
+        assert!(err.to_string().contains("offline"));
+    }
+
+    #[tokio::test]
+    async fn test_network_settings() {
+        let config: Config = toml::from_str(
+            r#"
+[network]
+proxy = "http://proxy.corp.example:8080"
+retries = 2
+timeout = "1m"
+"#,
+        )
+        .unwrap();
+        let network = config.network.unwrap();
+        assert_eq!(
+            network.proxy.as_deref(),
+            Some("http://proxy.corp.example:8080")
+        );
+
+        set_network(Some(network.clone()));
+        assert_eq!(retries(), 2);
+        assert_eq!(timeout(), Duration::from_secs(60));
+        assert!(client("cutler-test").is_ok());
+
+        // transient failures are retried until they run out, others aren't
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), &str> = retry(
+            "test",
+            |e| *e == "blip",
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err("blip")
+            },
+        )
+        .await;
+        assert_eq!(result, Err("blip"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        attempts.store(0, Ordering::SeqCst);
+        let result: Result<(), &str> = retry(
+            "test",
+            |e| *e == "blip",
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err("broken")
+            },
+        )
+        .await;
+        assert_eq!(result, Err("broken"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // failures only known by their message: client errors and TLS failures won't go away
+        assert!(is_transient_message(
+            "Download request failed with status: 503"
+        ));
+        assert!(is_transient_message("api request failed with status: 429"));
+        assert!(!is_transient_message(
+            "Download request failed with status: 404"
+        ));
+        assert!(!is_transient_message(
+            "invalid peer certificate: UnknownIssuer"
+        ));
+        assert!(is_transient_message("connection reset by peer"));
+
+        set_network(Some(Network {
+            proxy: Some("not a proxy".into()),
+            ..network
+        }));
+        assert!(client("cutler-test").is_err());
+
+        // empty values fall back to the environment and the defaults
+        set_network(Some(Network {
+            proxy: Some(" ".into()),
+            ..Default::default()
+        }));
+        assert_eq!(proxy(), None);
+        assert_eq!(retries(), DEFAULT_RETRIES);
+        assert_eq!(timeout(), DEFAULT_TIMEOUT);
+        assert!(client("cutler-test").is_ok());
+        set_network(None);
+    }
+}
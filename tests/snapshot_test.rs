@@ -7,8 +7,10 @@ mod tests {
         domains::convert::SerializablePrefValue,
         exec::core::ExecJob,
         snapshot::{
-            core::{SettingState, Snapshot},
+            core::{BrewChanges, CommandUndo, SettingState, Snapshot},
+            crypt::{self, KEY_LEN},
             get_snapshot_path,
+            path::resolve_snapshot_path,
         },
     };
     use std::{collections::HashMap, env, path::PathBuf};
@@ -30,6 +32,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_snapshot_path() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("config.toml");
+
+        assert_eq!(
+            resolve_snapshot_path(&config, "state/snapshot.json"),
+            dir.path().join("state/snapshot.json")
+        );
+        assert_eq!(
+            resolve_snapshot_path(&config, "/var/cutler/snapshot.json"),
+            PathBuf::from("/var/cutler/snapshot.json")
+        );
+
+        // config directories resolve against themselves
+        assert_eq!(
+            resolve_snapshot_path(dir.path(), "snapshot.json"),
+            dir.path().join("snapshot.json")
+        );
+
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            resolve_snapshot_path(&config, "~/snapshot.json"),
+            home.join("snapshot.json")
+        );
+    }
+
     #[tokio::test]
     async fn test_snapshot_basic() {
         // Test creation
@@ -57,8 +86,8 @@ mod tests {
             run: "echo Hello World".to_string(),
             sudo: false,
             ensure_first: false,
-            flag: false,
             required: vec!["echo".to_string()],
+            ..Default::default()
         };
         assert_eq!(command.run, "echo Hello World");
         assert!(!command.sudo);
@@ -233,4 +262,98 @@ mod tests {
             _ => panic!("Expected dictionary type"),
         }
     }
+
+    #[tokio::test]
+    async fn test_snapshot_undo() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snapshot.json");
+
+        // snapshots from before undo scripts still load
+        fs::write(
+            &path,
+            r#"{"settings": [], "exec_run_count": 2, "version": "0.1.0", "digest": ""}"#,
+        )
+        .await
+        .unwrap();
+        let snap = Snapshot::load(&path).await.unwrap();
+        assert!(snap.undo.is_empty());
+        assert_eq!(snap.exec_run_count, 2);
+
+        let mut snap = snap;
+        snap.undo.push(CommandUndo {
+            name: "hostname".into(),
+            run: "scutil --set HostName old".into(),
+            shell: Default::default(),
+            sudo: true,
+        });
+        snap.save().await.unwrap();
+
+        let loaded = Snapshot::load(&path).await.unwrap();
+        assert_eq!(loaded.undo, snap.undo);
+    }
+
+    #[test]
+    fn test_brew_changes_merge() {
+        let mut changes = BrewChanges {
+            formulae: vec!["jq".into()],
+            ..Default::default()
+        };
+        assert!(!changes.is_empty());
+
+        changes.merge(BrewChanges {
+            formulae: vec!["jq".into(), "ripgrep".into()],
+            casks: vec!["zed".into()],
+            taps: vec!["homebrew/cask-fonts".into()],
+        });
+
+        assert_eq!(changes.formulae, vec!["jq", "ripgrep"]);
+        assert_eq!(changes.casks, vec!["zed"]);
+        assert_eq!(changes.taps, vec!["homebrew/cask-fonts"]);
+        assert!(BrewChanges::default().is_empty());
+    }
+
+    #[test]
+    fn test_add_key_command() {
+        let command = crypt::add_key_command("jane doe", "c2VjcmV0+/==");
+
+        // a single line for `security -i`, with the secret as the value of `-w`
+        assert!(command.ends_with('\n'));
+        assert_eq!(command.lines().count(), 1);
+        let args = shell_words::split(&command).unwrap();
+        assert_eq!(args[0], "add-generic-password");
+        assert!(args.windows(2).any(|w| w == ["-a", "jane doe"]));
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["-s", crypt::KEYCHAIN_SERVICE])
+        );
+        assert_eq!(args[args.len() - 2..], ["-w", "c2VjcmV0+/=="]);
+    }
+
+    #[test]
+    fn test_snapshot_encryption() {
+        let key = [7u8; KEY_LEN];
+        let json = r#"{"settings":[],"exec_run_count":0,"version":"1.0.0","digest":""}"#;
+
+        let sealed = crypt::encrypt(json, &key).unwrap();
+        assert!(crypt::is_encrypted(&sealed));
+        assert!(!sealed.contains("settings"));
+        assert!(!crypt::is_encrypted(json));
+        assert_eq!(crypt::decrypt(&sealed, &key).unwrap(), json);
+
+        // every save uses a new nonce
+        assert_ne!(crypt::encrypt(json, &key).unwrap(), sealed);
+
+        // wrong keys and tampering are caught
+        assert!(crypt::decrypt(&sealed, &[8u8; KEY_LEN]).is_err());
+        let mut tampered = sealed.trim().to_string();
+        let at = tampered.len() - 3;
+        let flipped = if &tampered[at..at + 1] == "A" {
+            "B"
+        } else {
+            "A"
+        };
+        tampered.replace_range(at..at + 1, flipped);
+        assert!(crypt::decrypt(&tampered, &key).is_err());
+        assert!(crypt::decrypt(json, &key).is_err());
+    }
 }
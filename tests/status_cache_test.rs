@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::config::core::Prompt;
+    use cutler::util::status_cache::{StatusCache, format_age, render_prompt};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(42), "42s");
+        assert_eq!(format_age(5 * 60 + 3), "5m");
+        assert_eq!(format_age(3 * 3600), "3h");
+        assert_eq!(format_age(2 * 86400 + 5), "2d");
+    }
+
+    #[tokio::test]
+    async fn test_status_cache_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        fs::write(&config_path, "[set.dock]\ntilesize = 48\n").unwrap();
+
+        assert!(StatusCache::load(&config_path).await.is_none());
+
+        StatusCache::record(&config_path, vec!["com.apple.dock | tilesize".into()]).await;
+        let cache = StatusCache::load(&config_path).await.unwrap();
+        assert_eq!(cache.drifted, vec!["com.apple.dock | tilesize"]);
+        assert!(!cache.is_stale(&config_path));
+        assert!(cache.describe(false).starts_with("drifted: 1 (checked "));
+
+        // editing the config invalidates the result
+        fs::write(&config_path, "[set.dock]\ntilesize = 36\n").unwrap();
+        assert!(cache.is_stale(&config_path));
+        assert!(cache.describe(true).starts_with("unknown"));
+
+        StatusCache::record(&config_path, Vec::new()).await;
+        let cache = StatusCache::load(&config_path).await.unwrap();
+        assert!(cache.describe(false).starts_with("in sync"));
+
+        // so does adding or editing the local overlay
+        let overlay = dir.path().join("config.local.toml");
+        fs::write(&overlay, "[set.dock]\nautohide = true\n").unwrap();
+        assert!(cache.is_stale(&config_path));
+
+        StatusCache::record(&config_path, Vec::new()).await;
+        let cache = StatusCache::load(&config_path).await.unwrap();
+        assert!(!cache.is_stale(&config_path));
+        fs::write(&overlay, "[set.dock]\nautohide = false\n").unwrap();
+        assert!(cache.is_stale(&config_path));
+    }
+
+    #[tokio::test]
+    async fn test_record_prefs_keeps_software() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("config.toml");
+        fs::write(&config_path, "[brew]\nformulae = [\"git\"]\n").unwrap();
+
+        // without a full check, apply can't tell about Homebrew
+        StatusCache::record_prefs(&config_path, Vec::new(), true).await;
+        assert!(StatusCache::load(&config_path).await.is_none());
+
+        StatusCache::record(&config_path, vec!["a | b".into(), "brew".into()]).await;
+        StatusCache::record_prefs(&config_path, Vec::new(), true).await;
+        let cache = StatusCache::load(&config_path).await.unwrap();
+        assert_eq!(cache.drifted, vec!["brew"]);
+
+        // without software in the config, preferences are everything
+        StatusCache::record_prefs(&config_path, vec!["a | b".into()], false).await;
+        let cache = StatusCache::load(&config_path).await.unwrap();
+        assert_eq!(cache.drifted, vec!["a | b"]);
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let mut cache = StatusCache {
+            checked_at: 0,
+            drifted: vec!["com.apple.dock | tilesize".into(), "brew".into()],
+            digest: String::new(),
+        };
+        let default = Prompt::default();
+
+        assert_eq!(render_prompt(Some(&cache), false, &default), "±2");
+        assert_eq!(render_prompt(Some(&cache), true, &default), "✗");
+        assert_eq!(render_prompt(None, true, &default), "✗");
+
+        let custom = Prompt {
+            format: Some("cutler {symbol}".into()),
+            clean: Some(String::new()),
+            drifted: Some("{count} off".into()),
+            unknown: None,
+        };
+        assert_eq!(render_prompt(Some(&cache), false, &custom), "cutler 2 off");
+
+        cache.drifted.clear();
+        assert_eq!(render_prompt(Some(&cache), false, &default), "✓");
+        assert_eq!(render_prompt(Some(&cache), false, &custom), "cutler ");
+    }
+}
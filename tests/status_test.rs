@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::{commands::status::domain_matches, util::io::truncate};
+
+    #[test]
+    fn test_domain_matches() {
+        assert!(domain_matches("dock", "com.apple.dock"));
+        assert!(domain_matches("com.apple.dock", "com.apple.dock"));
+        assert!(domain_matches("com.apple.*", "com.apple.finder"));
+        assert!(domain_matches("*Global*", "NSGlobalDomain"));
+        assert!(domain_matches("NSGlobalDomain", "NSGlobalDomain"));
+
+        // whole domains only, and dots are literal
+        assert!(!domain_matches("dock", "com.apple.dock.extra"));
+        assert!(!domain_matches("com.apple.*", "org.example.app"));
+        assert!(!domain_matches("com.apple.doc.", "com.apple.dock"));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 16), "short");
+        assert_eq!(truncate("exactly", 7), "exactly");
+        assert_eq!(truncate("{ a = 1, b = 2 }", 8), "{ a = 1…");
+        assert_eq!(truncate("multi\nline", 16), "multi\\nline");
+        assert_eq!(truncate("äöü äöü", 4), "äöü…");
+    }
+}
@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::util::summary;
+
+    #[test]
+    fn test_nested_summary() {
+        summary::begin();
+        summary::add_applied(2);
+        summary::add_skipped(1);
+
+        // e.g. `apply --brew` running brew install inside apply
+        summary::begin();
+        summary::add_applied(1);
+        summary::add_failure("install wget");
+        assert!(summary::end().is_none());
+
+        summary::add_commands(3);
+        summary::add_verified(2);
+        summary::add_unverified("com.apple.dock | tilesize");
+
+        let result = summary::end().unwrap();
+        assert_eq!(result.applied, 3);
+        assert_eq!(result.skipped, 1);
+        assert_eq!(result.commands_run, 3);
+        assert_eq!(result.failed, vec!["install wget"]);
+        assert_eq!(result.verified, 2);
+        assert_eq!(result.unverified, vec!["com.apple.dock | tilesize"]);
+
+        // a new run starts from scratch
+        summary::begin();
+        assert_eq!(summary::end().unwrap().applied, 0);
+    }
+}
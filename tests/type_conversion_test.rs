@@ -5,10 +5,12 @@ mod tests {
     use std::f64::consts::PI;
 
     use cutler::domains::convert::{
-        prefvalue_to_toml, toml_edit_to_prefvalue, toml_edit_to_toml, toml_to_prefvalue,
+        SerializablePrefValue, prefvalue_to_serializable, prefvalue_to_toml,
+        serializable_to_prefvalue, toml_edit_to_prefvalue, toml_edit_to_toml, toml_to_prefvalue,
     };
     use defaults_rs::PrefValue;
     use toml::Value;
+    use toml_edit::Value as EditValue;
 
     #[test]
     fn test_toml_to_prefvalue_basic_types() {
@@ -158,4 +160,66 @@ test = { Preview = false, MetaData = true }
             panic!("Expected Value");
         }
     }
+
+    #[test]
+    fn test_date_roundtrip() {
+        let doc: toml::Table = toml::from_str("d = 2001-01-02T00:00:00Z").unwrap();
+        let pref = toml_to_prefvalue(&doc["d"]).unwrap();
+        assert_eq!(pref, PrefValue::Date(86_400.0));
+
+        // offsets are folded into UTC
+        let doc: toml::Table = toml::from_str("d = 2024-05-01T12:30:00+02:00").unwrap();
+        let pref = toml_to_prefvalue(&doc["d"]).unwrap();
+        assert_eq!(
+            prefvalue_to_toml(&pref).to_string(),
+            "2024-05-01T10:30:00Z".to_string()
+        );
+
+        // a bare time can't be a plist date
+        let doc: toml::Table = toml::from_str("d = 12:00:00").unwrap();
+        assert!(toml_to_prefvalue(&doc["d"]).is_err());
+    }
+
+    #[test]
+    fn test_data_roundtrip() {
+        let doc: toml::Table = toml::from_str(r#"d = { base64 = "AAEC/w==" }"#).unwrap();
+        let pref = toml_to_prefvalue(&doc["d"]).unwrap();
+        assert_eq!(pref, PrefValue::Data(vec![0, 1, 2, 255]));
+        assert_eq!(prefvalue_to_toml(&pref), doc["d"]);
+
+        // other single-key tables stay dictionaries
+        let doc: toml::Table = toml::from_str(r#"d = { base = "x" }"#).unwrap();
+        assert!(matches!(
+            toml_to_prefvalue(&doc["d"]).unwrap(),
+            PrefValue::Dictionary(_)
+        ));
+
+        let doc: toml::Table = toml::from_str(r#"d = { base64 = "not base64!" }"#).unwrap();
+        assert!(toml_to_prefvalue(&doc["d"]).is_err());
+    }
+
+    #[test]
+    fn test_toml_edit_date_and_data() {
+        let date: EditValue = "2001-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(toml_edit_to_prefvalue(&date).unwrap(), PrefValue::Date(0.0));
+
+        let data: EditValue = r#"{ base64 = "aGk=" }"#.parse().unwrap();
+        assert_eq!(
+            toml_edit_to_prefvalue(&data).unwrap(),
+            PrefValue::Data(b"hi".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_serializable_date_and_data() {
+        for pref in [PrefValue::Data(b"hi".to_vec()), PrefValue::Date(12.5)] {
+            let json = serde_json::to_string(&prefvalue_to_serializable(&pref)).unwrap();
+            let back: SerializablePrefValue = serde_json::from_str(&json).unwrap();
+            assert_eq!(serializable_to_prefvalue(&back), pref);
+        }
+
+        // plain dictionaries must not be mistaken for the wrappers
+        let back: SerializablePrefValue = serde_json::from_str(r#"{"base64": "aGk="}"#).unwrap();
+        assert!(matches!(back, SerializablePrefValue::Dictionary(_)));
+    }
 }
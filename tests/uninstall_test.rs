@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#[cfg(test)]
+mod tests {
+    use cutler::commands::uninstall::state_files;
+    use std::path::Path;
+
+    #[test]
+    fn test_state_files() {
+        let files = state_files(
+            Path::new("/home/u/.config/cutler/config.toml"),
+            Path::new("/home/u/dotfiles/snapshot.json"),
+        )
+        .unwrap();
+
+        let names: Vec<&str> = files.iter().map(|p| p.to_str().unwrap()).collect();
+        assert_eq!(
+            names[..names.len() - 1],
+            [
+                "/home/u/dotfiles/snapshot.json",
+                "/home/u/.config/cutler/ephemeral.json",
+                "/home/u/.config/cutler/bootstrap.json",
+                "/home/u/.config/cutler/last_status.json",
+                "/home/u/.config/cutler/history.jsonl",
+                "/home/u/.config/cutler/remote_cache.json",
+                "/home/u/.config/cutler/last_sync",
+            ]
+        );
+        assert!(names.last().unwrap().ends_with("cutler/cmd-logs"));
+
+        // backups are removed one by one through the snapshot, never as a whole directory
+        assert!(!names.contains(&"/home/u/dotfiles/backups"));
+
+        // the config itself is kept
+        assert!(!names.contains(&"/home/u/.config/cutler/config.toml"));
+    }
+}